@@ -23,11 +23,14 @@ pub fn run_tests(test_fn: fn() -> Result<(), ()>) {
 
 static BIN_PATH_CPP: &str = "./build/bin/twsearch";
 static BIN_PATH_RUST: &str = "./target/release/twsearch-cpp-wrapper";
+static BIN_PATH_RUST_LIB: &str = "./target/release/twsearch";
 
 #[allow(dead_code)] // Workaround for how we are hacking together our tests.
 pub enum CliCommand {
     Cpp(),
     Rust(),
+    /// The CLI of the Rust port (`src/rs`), rather than the C++ wrapper.
+    RustLib(),
 }
 
 impl CliCommand {
@@ -35,6 +38,7 @@ impl CliCommand {
         match self {
             CliCommand::Cpp() => BIN_PATH_CPP,
             CliCommand::Rust() => BIN_PATH_RUST,
+            CliCommand::RustLib() => BIN_PATH_RUST_LIB,
         }
     }
     fn prefix_args(&self) -> &[&str] {
@@ -46,6 +50,7 @@ impl CliCommand {
                 "--num-threads",
                 "1",
             ],
+            CliCommand::RustLib() => &["search"],
         }
     }
 }
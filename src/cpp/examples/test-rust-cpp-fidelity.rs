@@ -0,0 +1,107 @@
+#[path = "./lib/common.rs"]
+mod common;
+
+use std::{fs::read_to_string, time::Duration};
+
+use common::{run_search_command, run_tests, CliCommand};
+use cubing::alg::Alg;
+use serde::Deserialize;
+
+// Each fixture records the solutions that the C++ implementation printed for
+// some inputs (`cpp_args`, run with the `recorded_with` binary), along with
+// the equivalent inputs for the Rust CLI. To add a fixture, run the C++ binary
+// yourself and paste its solution lines into `recorded_solutions`.
+static FIXTURES_PATH: &str = "src/cpp/examples/test-cases/fidelity.json";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RecordedWith {
+    Cpp,
+    CppWrapper,
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    name: String,
+    #[allow(dead_code)] // Documents how `recorded_solutions` was produced.
+    recorded_with: RecordedWith,
+    #[allow(dead_code)] // Documents how `recorded_solutions` was produced.
+    cpp_args: Vec<String>,
+    recorded_solutions: Vec<String>,
+    rust_args: Vec<String>,
+}
+
+fn solution_length(solution: &str) -> Result<usize, ()> {
+    match solution.trim().parse::<Alg>() {
+        Ok(alg) => Ok(alg.nodes.len()),
+        Err(_) => {
+            eprintln!("Could not parse solution: {}", solution);
+            Err(())
+        }
+    }
+}
+
+// The Rust CLI prints each solution as `<alg> // solution #<n> (…)`.
+fn rust_solution_lengths(stdout: &str) -> Result<Vec<usize>, ()> {
+    stdout
+        .lines()
+        .filter_map(|line| line.split_once(" // solution #"))
+        .map(|(solution, _)| solution_length(solution))
+        .collect()
+}
+
+// TODO: Support `#[test]`.
+fn fidelity_tests() -> Result<(), ()> {
+    let fixtures: Vec<Fixture> =
+        serde_json::from_str(&read_to_string(FIXTURES_PATH).map_err(|e| {
+            eprintln!("Could not read fixtures: {}", e);
+        })?)
+        .map_err(|e| {
+            eprintln!("Could not parse fixtures: {}", e);
+        })?;
+
+    let mut all_passed = true;
+    for fixture in fixtures {
+        println!("Fixture: {}", fixture.name);
+        let expected_lengths = fixture
+            .recorded_solutions
+            .iter()
+            .map(|solution| solution_length(solution))
+            .collect::<Result<Vec<usize>, ()>>()?;
+        let rust_args: Vec<&str> = fixture.rust_args.iter().map(|arg| arg.as_str()).collect();
+        let rust_lengths = match run_search_command(
+            CliCommand::RustLib(),
+            &rust_args,
+            None,
+            Some(Duration::from_secs(10)),
+        ) {
+            Ok(stdout) => rust_solution_lengths(&stdout)?,
+            Err(stderr) => {
+                println!("❌");
+                eprintln!("twsearch failed with stderr:\n{}\n", stderr);
+                all_passed = false;
+                continue;
+            }
+        };
+        if rust_lengths == expected_lengths {
+            println!("✅");
+        } else {
+            println!("❌");
+            eprintln!(
+                "Expected solution lengths (recorded from C++): {:?}\nActual solution lengths: {:?}\n",
+                expected_lengths, rust_lengths
+            );
+            all_passed = false;
+        }
+    }
+
+    if all_passed {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+fn main() {
+    run_tests(fidelity_tests)
+}
@@ -5,11 +5,11 @@ use std::{
     ptr::null_mut,
 };
 
-use twsearch::scramble::{random_scramble_for_event, Event};
+use twsearch::api::{random_scramble_for_event, Event};
 
 /// # Safety
 ///
-/// This function can panic. If you are working in pure Rust, use [`twsearch::scramble::random_scramble_for_event`] instead.
+/// This function can panic. If you are working in pure Rust, use [`twsearch::api::random_scramble_for_event`] instead.
 ///
 /// Returns:
 /// - A null pointer for *any* error.
@@ -0,0 +1,155 @@
+// Conversions from `cubing` types. Unlike the rest of the crate, these need `std`.
+
+use std::fmt::Display;
+
+use cubing::kpuzzle::{KPattern, KPuzzle, KTransformation, OrientationWithMod};
+
+use super::{PackedOrbit, PackedPattern, PackedPuzzle, PackedTransformation};
+
+/// A definition (or pattern) that can't be represented using packed patterns.
+#[derive(Debug)]
+pub struct PackingError {
+    pub orbit_name: String,
+    pub description: String,
+}
+
+impl Display for PackingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Orbit {} can't be packed: {}",
+            self.orbit_name, self.description
+        )
+    }
+}
+
+impl std::error::Error for PackingError {}
+
+// Orientations are added before taking the remainder, so their sum must fit in a `u8`.
+const MAX_PACKED_NUM_ORIENTATIONS: u8 = 128;
+
+impl PackedPuzzle {
+    pub fn try_from_kpuzzle(kpuzzle: &KPuzzle) -> Result<Self, PackingError> {
+        let mut orbits = Vec::<PackedOrbit>::new();
+        for orbit_info in kpuzzle.orbit_info_iter() {
+            if orbit_info.num_orientations > MAX_PACKED_NUM_ORIENTATIONS {
+                return Err(PackingError {
+                    orbit_name: orbit_info.name.0.clone(),
+                    description: format!(
+                        "{} orientations (at most {} are supported)",
+                        orbit_info.num_orientations, MAX_PACKED_NUM_ORIENTATIONS
+                    ),
+                });
+            }
+            orbits.push(PackedOrbit {
+                num_pieces: orbit_info.num_pieces as usize,
+                num_orientations: orbit_info.num_orientations,
+            });
+        }
+        Ok(Self::new(orbits))
+    }
+}
+
+impl TryFrom<&KPattern> for PackedPattern {
+    type Error = PackingError;
+
+    fn try_from(pattern: &KPattern) -> Result<Self, PackingError> {
+        let mut bytes = Vec::<u8>::new();
+        for orbit_info in pattern.kpuzzle().orbit_info_iter() {
+            for i in 0..orbit_info.num_pieces {
+                bytes.push(pattern.get_piece(orbit_info, i));
+            }
+            for i in 0..orbit_info.num_pieces {
+                let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+                if orientation_with_mod.orientation_mod != 0 {
+                    return Err(PackingError {
+                        orbit_name: orbit_info.name.0.clone(),
+                        description: "orientation mods are not supported".to_owned(),
+                    });
+                }
+                bytes.push(orientation_with_mod.orientation);
+            }
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl PackedPattern {
+    /// The inverse of `PackedPattern::try_from(…)`, for a pattern of a puzzle
+    /// packed using `PackedPuzzle::try_from_kpuzzle(kpuzzle)`.
+    pub fn to_kpattern(&self, kpuzzle: &KPuzzle) -> KPattern {
+        let mut pattern = kpuzzle.default_pattern();
+        let mut offset = 0;
+        for orbit_info in kpuzzle.orbit_info_iter() {
+            let num_pieces = orbit_info.num_pieces as usize;
+            for i in 0..orbit_info.num_pieces {
+                let position = offset + i as usize;
+                pattern.set_piece(orbit_info, i, self.0[position]);
+                pattern.set_orientation_with_mod(
+                    orbit_info,
+                    i,
+                    &OrientationWithMod {
+                        orientation: self.0[position + num_pieces],
+                        orientation_mod: 0,
+                    },
+                );
+            }
+            offset += 2 * num_pieces;
+        }
+        pattern
+    }
+}
+
+// A pattern with distinct pieces (each at its own position) and no orientation.
+fn normalized_pattern(kpuzzle: &KPuzzle) -> KPattern {
+    let mut pattern = kpuzzle.default_pattern();
+    for orbit_info in kpuzzle.orbit_info_iter() {
+        for i in 0..orbit_info.num_pieces {
+            pattern.set_piece(orbit_info, i, i);
+            pattern.set_orientation_with_mod(
+                orbit_info,
+                i,
+                &OrientationWithMod {
+                    orientation: 0,
+                    orientation_mod: 0,
+                },
+            );
+        }
+    }
+    pattern
+}
+
+impl PackedTransformation {
+    pub fn from_ktransformation(kpuzzle: &KPuzzle, transformation: &KTransformation) -> Self {
+        // Applying the transformation to distinct, unoriented pieces gives its permutation and orientation delta.
+        let applied = normalized_pattern(kpuzzle).apply_transformation(transformation);
+        Self(
+            PackedPattern::try_from(&applied)
+                .expect("Normalized patterns don't use orientation mods.")
+                .0,
+        )
+    }
+}
+
+#[test]
+fn packed_pattern_round_trip_test() -> Result<(), PackingError> {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let packed_puzzle = PackedPuzzle::try_from_kpuzzle(kpuzzle)?;
+    let transformation = kpuzzle
+        .transformation_from_alg(&"R U F' D2 L".parse().unwrap())
+        .unwrap();
+    let pattern = kpuzzle
+        .default_pattern()
+        .apply_transformation(&transformation);
+
+    let packed_pattern = PackedPattern::try_from(&kpuzzle.default_pattern())?;
+    let packed_pattern = packed_puzzle.apply(
+        &packed_pattern,
+        &PackedTransformation::from_ktransformation(kpuzzle, &transformation),
+    );
+    assert_eq!(packed_pattern, PackedPattern::try_from(&pattern)?);
+    assert!(packed_pattern.to_kpattern(kpuzzle) == pattern);
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use alloc::vec::Vec;
+
+use super::{PackedPattern, PackedPuzzle, PackedTransformation};
+
+pub struct PackedMove {
+    pub transformation: PackedTransformation,
+    /// Consecutive moves with the same class (e.g. `U` and `U2`) are never searched.
+    pub move_class: usize,
+}
+
+struct IdaStarSearch<'a, Heuristic: Fn(&PackedPattern) -> usize> {
+    puzzle: &'a PackedPuzzle,
+    target_pattern: &'a PackedPattern,
+    moves: &'a [PackedMove],
+    heuristic: Heuristic,
+    // One pattern per depth, so that the search doesn't allocate.
+    patterns: Vec<PackedPattern>,
+    move_indices: Vec<usize>,
+}
+
+impl<Heuristic: Fn(&PackedPattern) -> usize> IdaStarSearch<'_, Heuristic> {
+    fn recurse(&mut self, depth: usize, remaining_depth: usize) -> bool {
+        if remaining_depth == 0 {
+            return &self.patterns[depth] == self.target_pattern;
+        }
+        if (self.heuristic)(&self.patterns[depth]) > remaining_depth {
+            return false;
+        }
+        for (move_index, r#move) in self.moves.iter().enumerate() {
+            if let Some(previous_move_index) = self.move_indices.last() {
+                if self.moves[*previous_move_index].move_class == r#move.move_class {
+                    continue;
+                }
+            }
+            let (current, next) = self.patterns.split_at_mut(depth + 1);
+            self.puzzle
+                .apply_into(&current[depth], &r#move.transformation, &mut next[0]);
+            self.move_indices.push(move_index);
+            if self.recurse(depth + 1, remaining_depth - 1) {
+                return true;
+            }
+            self.move_indices.pop();
+        }
+        false
+    }
+}
+
+/// Returns the indices (into `moves`) of an optimal solution from
+/// `start_pattern` to `target_pattern`, if there is one within `max_depth`
+/// moves. `heuristic` must never overestimate the distance to the target
+/// (`|_| 0` is always valid).
+pub fn ida_star(
+    puzzle: &PackedPuzzle,
+    start_pattern: &PackedPattern,
+    target_pattern: &PackedPattern,
+    moves: &[PackedMove],
+    max_depth: usize,
+    heuristic: impl Fn(&PackedPattern) -> usize,
+) -> Option<Vec<usize>> {
+    let mut patterns = Vec::<PackedPattern>::with_capacity(max_depth + 1);
+    patterns.push(start_pattern.clone());
+    for _ in 0..max_depth {
+        patterns.push(PackedPattern(Vec::with_capacity(puzzle.num_bytes())));
+    }
+    let mut search = IdaStarSearch {
+        puzzle,
+        target_pattern,
+        moves,
+        heuristic,
+        patterns,
+        move_indices: Vec::with_capacity(max_depth),
+    };
+    for depth in 0..=max_depth {
+        if search.recurse(0, depth) {
+            return Some(search.move_indices);
+        }
+    }
+    None
+}
@@ -0,0 +1,23 @@
+//! A small search kernel (packed patterns, transformation application, and
+//! IDA*) that only depends on `core` and `alloc`, so that it can run on
+//! targets without `std` (e.g. embedded scramble displays). The conversions
+//! from `cubing` types (in `from_kpuzzle`) need `std`, and are only built
+//! with the `std` feature (enabled by default).
+//!
+//! `twsearch` re-exports this crate as `twsearch::search_core` (with the
+//! `search-core` feature).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod packed;
+pub use packed::*;
+
+mod ida_star;
+pub use ida_star::*;
+
+#[cfg(feature = "std")]
+mod from_kpuzzle;
+#[cfg(feature = "std")]
+pub use from_kpuzzle::*;
@@ -0,0 +1,102 @@
+use alloc::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedOrbit {
+    pub num_pieces: usize,
+    pub num_orientations: u8,
+}
+
+/// The layout of packed patterns and transformations for a puzzle. Each orbit
+/// takes `2 * num_pieces` bytes: one per position, followed by one orientation
+/// per position. Orientation mods are not supported.
+#[derive(Clone, Debug)]
+pub struct PackedPuzzle {
+    orbits: Vec<PackedOrbit>,
+    offsets: Vec<usize>,
+    num_bytes: usize,
+}
+
+/// For each position: the piece at that position (followed by orientations).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PackedPattern(pub Vec<u8>);
+
+/// For each position: the position that the piece comes from (followed by
+/// the orientation added to it).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PackedTransformation(pub Vec<u8>);
+
+impl PackedPuzzle {
+    pub fn new(orbits: Vec<PackedOrbit>) -> Self {
+        let mut offsets = Vec::<usize>::with_capacity(orbits.len());
+        let mut num_bytes = 0;
+        for orbit in &orbits {
+            offsets.push(num_bytes);
+            num_bytes += 2 * orbit.num_pieces;
+        }
+        Self {
+            orbits,
+            offsets,
+            num_bytes,
+        }
+    }
+
+    pub fn orbits(&self) -> &[PackedOrbit] {
+        &self.orbits
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// The piece at each position of an orbit of the pattern.
+    pub fn orbit_pieces<'a>(&self, pattern: &'a PackedPattern, orbit_index: usize) -> &'a [u8] {
+        let offset = self.offsets[orbit_index];
+        &pattern.0[offset..offset + self.orbits[orbit_index].num_pieces]
+    }
+
+    /// Writes `pattern` with `transformation` applied into `into_pattern`,
+    /// reusing its storage.
+    pub fn apply_into(
+        &self,
+        pattern: &PackedPattern,
+        transformation: &PackedTransformation,
+        into_pattern: &mut PackedPattern,
+    ) {
+        into_pattern.0.resize(self.num_bytes, 0);
+        for (orbit, offset) in self.orbits.iter().zip(&self.offsets) {
+            let orientation_offset = offset + orbit.num_pieces;
+            for i in 0..orbit.num_pieces {
+                let source = transformation.0[offset + i] as usize;
+                into_pattern.0[offset + i] = pattern.0[offset + source];
+                into_pattern.0[orientation_offset + i] = (pattern.0[orientation_offset + source]
+                    + transformation.0[orientation_offset + i])
+                    % orbit.num_orientations;
+            }
+        }
+    }
+
+    pub fn invert(&self, transformation: &PackedTransformation) -> PackedTransformation {
+        let mut inverse = PackedTransformation(alloc::vec![0; self.num_bytes]);
+        for (orbit, offset) in self.orbits.iter().zip(&self.offsets) {
+            let orientation_offset = offset + orbit.num_pieces;
+            for i in 0..orbit.num_pieces {
+                let source = transformation.0[offset + i] as usize;
+                inverse.0[offset + source] = i as u8;
+                inverse.0[orientation_offset + source] = (orbit.num_orientations
+                    - transformation.0[orientation_offset + i])
+                    % orbit.num_orientations;
+            }
+        }
+        inverse
+    }
+
+    pub fn apply(
+        &self,
+        pattern: &PackedPattern,
+        transformation: &PackedTransformation,
+    ) -> PackedPattern {
+        let mut into_pattern = PackedPattern(Vec::with_capacity(self.num_bytes));
+        self.apply_into(pattern, transformation, &mut into_pattern);
+        into_pattern
+    }
+}
@@ -8,7 +8,9 @@ use wasm_bindgen::prelude::*;
 
 use twsearch::scramble::{random_scramble_for_event, Event};
 
-use twsearch::_internal::{IDFSearch, IndividualSearchOptions, SearchLogger};
+use twsearch::_internal::{
+    set_table_memory_mode, IDFSearch, IndividualSearchOptions, SearchLogger, TableMemoryMode,
+};
 
 pub fn internal_init() {
     console_error_panic_hook::set_once();
@@ -75,7 +77,7 @@ pub fn wasmTwsearch(
         true,
         None,
     );
-    let mut idfs = idfs.map_err(|e| e.description)?;
+    let mut idfs = idfs.map_err(|e| e.to_string())?;
 
     match idfs
         .search(&search_pattern, options.inidividual_search_options)
@@ -97,3 +99,16 @@ pub fn wasmRandomScrambleForEvent(event_str: String) -> Result<String, String> {
         Err(e) => Err(e.description),
     }
 }
+
+/// Overrides the table memory mode (`"full"`, `"reduced"`, or `"minimal"`), which is `"reduced"` by default for WASM.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn wasmSetTableMemoryMode(table_memory_mode_str: String) -> Result<(), String> {
+    internal_init();
+
+    let table_memory_mode: TableMemoryMode =
+        serde_json::from_value(serde_json::Value::String(table_memory_mode_str))
+            .map_err(|e| e.to_string())?;
+    set_table_memory_mode(table_memory_mode);
+    Ok(())
+}
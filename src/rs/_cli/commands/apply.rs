@@ -0,0 +1,89 @@
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPatternData, KPuzzle, KPuzzleDefinition},
+};
+use serde_json::{json, Map, Value};
+use twsearch::_internal::{
+    cli::options::{ApplyArgs, PatternFormat},
+    read_to_json, ArgumentError, CommandError, KPatternDisplay, NotationDialect,
+};
+
+fn orbit_vectors(pattern: &KPattern) -> Vec<(String, Vec<u8>, Vec<u8>, Vec<u8>)> {
+    pattern
+        .kpuzzle()
+        .orbit_info_iter()
+        .map(|orbit_info| {
+            let mut pieces = vec![];
+            let mut orientation = vec![];
+            let mut orientation_mod = vec![];
+            for i in 0..orbit_info.num_pieces {
+                pieces.push(pattern.get_piece(orbit_info, i));
+                let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+                orientation.push(orientation_with_mod.orientation);
+                orientation_mod.push(orientation_with_mod.orientation_mod);
+            }
+            (
+                orbit_info.name.0.clone(),
+                pieces,
+                orientation,
+                orientation_mod,
+            )
+        })
+        .collect()
+}
+
+fn pattern_to_json(pattern: &KPattern) -> Value {
+    let mut orbits = Map::new();
+    for (orbit_name, pieces, orientation, orientation_mod) in orbit_vectors(pattern) {
+        let mut orbit = json!({
+            "pieces": pieces,
+            "orientation": orientation,
+        });
+        // Only include `orientationMod` when it's non-trivial, to match hand-written pattern files.
+        if orientation_mod.iter().any(|m| *m != 0) {
+            orbit["orientationMod"] = json!(orientation_mod);
+        }
+        orbits.insert(orbit_name, orbit);
+    }
+    Value::Object(orbits)
+}
+
+pub fn apply(args: &ApplyArgs) -> Result<(), CommandError> {
+    let def: KPuzzleDefinition = read_to_json(&args.input_args.def_file)?;
+    let kpuzzle = KPuzzle::try_new(def).map_err(|e| ArgumentError {
+        description: format!("Invalid definition: {}", e),
+    })?;
+
+    let start_pattern = match &args.start_pattern_args.start_pattern {
+        Some(start_pattern_file) => {
+            let kpattern_data: KPatternData = read_to_json(start_pattern_file)?;
+            KPattern::try_from_data(&kpuzzle, &kpattern_data).map_err(|e| ArgumentError {
+                description: e.to_string(),
+            })?
+        }
+        None => kpuzzle.default_pattern(),
+    };
+
+    let alg = args.alg.parse::<Alg>().map_err(|e| ArgumentError {
+        description: format!("Could not parse alg: {:?}", e),
+    })?;
+    let alg = match args.notation_args.notation {
+        Some(_) => NotationDialect::Wca.convert_alg(&alg),
+        None => alg,
+    };
+    let transformation = kpuzzle
+        .transformation_from_alg(&alg)
+        .map_err(|e| ArgumentError {
+            description: format!("Could not apply alg: {:?}", e),
+        })?;
+    let pattern = start_pattern.apply_transformation(&transformation);
+
+    match args.format {
+        PatternFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&pattern_to_json(&pattern)).unwrap()
+        ),
+        PatternFormat::Text => println!("{}", KPatternDisplay(&pattern)),
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use cubing::kpuzzle::{KPattern, KPatternData, KPuzzle, KPuzzleDefinition};
+use thousands::Separable;
+use twsearch::_internal::{
+    cli::options::EstimateSearchSpaceArgs, estimate_search_space as estimate, read_to_json,
+    ArgumentError, CommandError, SearchSpaceEstimateOptions,
+};
+
+pub fn estimate_search_space(args: &EstimateSearchSpaceArgs) -> Result<(), CommandError> {
+    let def: KPuzzleDefinition = read_to_json(&args.input_args.def_file)?;
+    let kpuzzle = KPuzzle::try_new(def).map_err(|e| ArgumentError {
+        description: format!("Invalid definition: {}", e),
+    })?;
+
+    let start_pattern = match &args.start_pattern_args.start_pattern {
+        Some(start_pattern_file) => {
+            let kpattern_data: KPatternData = read_to_json(start_pattern_file)?;
+            Some(
+                KPattern::try_from_data(&kpuzzle, &kpattern_data).map_err(|e| ArgumentError {
+                    description: e.to_string(),
+                })?,
+            )
+        }
+        None => None,
+    };
+
+    let estimate = estimate(
+        &kpuzzle,
+        start_pattern,
+        &args.generator_args.parse(),
+        &args.metric_args.metric,
+        &SearchSpaceEstimateOptions {
+            max_depth: args.max_depth,
+            num_samples: args.num_samples,
+        },
+    )?;
+    println!("Estimated number of canonical move sequences:");
+    for (depth, num_canonical_sequences) in
+        estimate.num_canonical_sequences_by_depth.iter().enumerate()
+    {
+        println!(
+            "{:>4} {:>24}",
+            depth,
+            (num_canonical_sequences.round() as u128).separate_with_underscores()
+        );
+    }
+    match estimate.estimated_num_patterns {
+        Some(estimated_num_patterns) => println!(
+            "Estimated number of patterns at depth {}: {}",
+            args.max_depth,
+            (estimated_num_patterns.round() as u128).separate_with_underscores()
+        ),
+        None => println!(
+            "Too many patterns to estimate using {} samples.",
+            args.num_samples
+        ),
+    }
+    if let Some(estimated_solution_depth) = estimate.estimated_solution_depth {
+        println!(
+            "Estimated typical solution depth: at least {}",
+            estimated_solution_depth
+        );
+    }
+    Ok(())
+}
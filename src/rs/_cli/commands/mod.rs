@@ -1,5 +1,14 @@
+mod apply;
+pub use apply::*;
+
 mod benchmark;
 pub use benchmark::*;
 
 mod canonical_algs;
 pub use canonical_algs::*;
+
+mod estimate_search_space;
+pub use estimate_search_space::*;
+
+mod pretty_patterns;
+pub use pretty_patterns::*;
@@ -0,0 +1,67 @@
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPatternData, KPuzzle, KPuzzleDefinition},
+};
+use twsearch::_internal::{
+    cli::options::PrettyPatternsArgs, read_to_json, ArgumentError, CommandError, PatternSymmetries,
+    PrettyPatternSearch, PrettyPatternSearchOptions,
+};
+
+pub fn pretty_patterns(args: &PrettyPatternsArgs) -> Result<(), CommandError> {
+    let def: KPuzzleDefinition = read_to_json(&args.input_args.def_file)?;
+    let kpuzzle = KPuzzle::try_new(def).map_err(|e| ArgumentError {
+        description: format!("Invalid definition: {}", e),
+    })?;
+
+    let start_pattern = match &args.start_pattern_args.start_pattern {
+        Some(start_pattern_file) => {
+            let kpattern_data: KPatternData = read_to_json(start_pattern_file)?;
+            Some(
+                KPattern::try_from_data(&kpuzzle, &kpattern_data).map_err(|e| ArgumentError {
+                    description: e.to_string(),
+                })?,
+            )
+        }
+        None => None,
+    };
+
+    let symmetries = match &args.symmetries {
+        Some(symmetries) => {
+            let symmetries = symmetries
+                .split(',')
+                .map(|s| {
+                    s.parse::<Alg>().map_err(|e| ArgumentError {
+                        description: format!("Could not parse symmetry: {:?}", e),
+                    })
+                })
+                .collect::<Result<Vec<Alg>, ArgumentError>>()?;
+            PatternSymmetries::try_new(&kpuzzle, &symmetries)?
+        }
+        None => PatternSymmetries::try_new_cube_rotations(&kpuzzle)?,
+    };
+
+    let pretty_pattern_search = PrettyPatternSearch::try_new(
+        &kpuzzle,
+        start_pattern,
+        &args.generator_args.parse(),
+        &args.metric_args.metric,
+    )?;
+    let results = pretty_pattern_search.search(
+        &PrettyPatternSearchOptions {
+            // The start pattern is usually symmetric, so skip it by default.
+            min_depth: Some(args.min_depth.unwrap_or(1)),
+            max_depth: args.max_depth,
+            max_num_results: args.max_num_results,
+        },
+        Some(&symmetries),
+        |pattern| symmetries.stabilizer(pattern).len() >= args.min_num_symmetries,
+    );
+    for result in results {
+        println!(
+            "{} // {} symmetries",
+            result.alg,
+            symmetries.stabilizer(&result.pattern).len()
+        );
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
 mod commands;
 mod serve;
+mod solution_writer;
 
 use std::{
     path::{Path, PathBuf},
@@ -7,19 +8,24 @@ use std::{
     sync::Arc,
 };
 
-use commands::{benchmark, canonical_algs};
+use commands::{apply, benchmark, canonical_algs, estimate_search_space, pretty_patterns};
 use cubing::{
     alg::Alg,
     kpuzzle::{KPattern, KPatternData, KPuzzle, KPuzzleDefinition},
 };
 use serve::serve;
+use solution_writer::SolutionWriter;
 use twsearch::_internal::{
     cli::options::{get_options, CliCommand, GodsAlgorithmArgs, SearchCommandArgs},
     options::VerbosityLevel,
-    read_to_json, ArgumentError, CommandError, GodsAlgorithmSearch, IDFSearch,
-    IndividualSearchOptions, SearchLogger,
+    read_to_json, search_generators_and_canonical_fsm_with_cache, set_table_memory_mode,
+    AlgMoveCounts, ArgumentError, CommandError, GodsAlgorithmSearch, IDFSearch, NotationDialect,
+    SearchLogger,
 };
 
+// Solutions that have been found but not written yet, before the search waits for them.
+const MAX_BUFFERED_SOLUTIONS: usize = 1024;
+
 fn main() -> Result<(), CommandError> {
     let args = get_options();
 
@@ -34,6 +40,9 @@ fn main() -> Result<(), CommandError> {
         CliCommand::GodsAlgorithm(gods_algorithm_args) => gods_algorithm(gods_algorithm_args),
         CliCommand::TimingTest(_args) => todo!(),
         CliCommand::CanonicalAlgs(args) => canonical_algs(&args),
+        CliCommand::Apply(args) => apply(&args),
+        CliCommand::PrettyPatterns(args) => pretty_patterns(&args),
+        CliCommand::EstimateSearchSpace(args) => estimate_search_space(&args),
         CliCommand::Benchmark(benchmark_args) => benchmark(&benchmark_args),
     }
 }
@@ -82,6 +91,15 @@ fn gods_algorithm(gods_algorithm_args: GodsAlgorithmArgs) -> Result<(), CommandE
 }
 
 fn search(search_command_args: SearchCommandArgs) -> Result<(), CommandError> {
+    let individual_search_options = search_command_args.individual_search_options()?;
+    if let Some(table_memory_mode) = search_command_args
+        .search_args
+        .performance_args
+        .memory_args
+        .table_memory_mode
+    {
+        set_table_memory_mode(table_memory_mode);
+    }
     let (kpuzzle, target_pattern) = common(
         &search_command_args
             .input_def_and_optional_scramble_file_args
@@ -129,6 +147,10 @@ fn search(search_command_args: SearchCommandArgs) -> Result<(), CommandError> {
                     exit(1)
                 }
             };
+            let alg = match search_command_args.notation_args.notation {
+                Some(_) => NotationDialect::Wca.convert_alg(&alg),
+                None => alg,
+            };
             // TODO: add a way for `KPuzzle` to construct a KTransformation from serialized data directly.
             let transformation = match kpuzzle.transformation_from_alg(&alg) {
                 Ok(transformation) => transformation,
@@ -145,41 +167,65 @@ fn search(search_command_args: SearchCommandArgs) -> Result<(), CommandError> {
         }
     };
 
-    let mut idf_search = IDFSearch::try_new(
-        kpuzzle,
-        target_pattern,
-        search_command_args.generator_args.parse(),
-        Arc::new(SearchLogger {
-            verbosity: search_command_args
-                .verbosity_args
-                .verbosity
-                .unwrap_or(VerbosityLevel::Error),
-        }),
+    let search_logger = Arc::new(SearchLogger {
+        verbosity: search_command_args
+            .verbosity_args
+            .verbosity
+            .unwrap_or(VerbosityLevel::Error),
+    });
+    let (search_generators, canonical_fsm) = search_generators_and_canonical_fsm_with_cache(
+        &kpuzzle,
+        &target_pattern,
+        &search_command_args.generator_args.parse(),
         &search_command_args.metric_args.metric,
         search_command_args.search_args.random_start,
-        None,
+        search_command_args
+            .search_persistence_args
+            .cache_dir
+            .as_deref(),
+        &search_logger,
     )?;
+    let idf_search = IDFSearch::new_from_search_generators(
+        kpuzzle,
+        target_pattern,
+        search_generators,
+        canonical_fsm,
+        search_logger,
+        None,
+    );
 
     let search_start_time = instant::Instant::now();
-    let solutions = idf_search.search(
-        &scramble_pattern,
-        IndividualSearchOptions {
-            min_num_solutions: search_command_args.min_num_solutions,
-            min_depth: search_command_args.search_args.min_depth,
-            max_depth: search_command_args.search_args.max_depth,
-            disallowed_initial_quanta: None,
-            disallowed_final_quanta: None,
-        },
+    let mut solution_writer = SolutionWriter::try_new(&search_command_args.solution_output_args)?;
+    // Each solution is printed (or written) as soon as it's found, while the search continues.
+    let solutions = idf_search.search_in_background(
+        scramble_pattern,
+        individual_search_options,
+        MAX_BUFFERED_SOLUTIONS,
     );
     let mut solution_index = 0;
     for solution in solutions {
+        let solution = match search_command_args.notation_args.notation {
+            Some(notation) => notation.convert_alg(&solution),
+            None => solution,
+        };
         solution_index += 1;
+        match &mut solution_writer {
+            Some(solution_writer) => solution_writer.write(&solution, solution_index)?,
+            None => println!(
+                "{} // solution #{} ({} nodes; {})",
+                solution,
+                solution_index,
+                solution.nodes.len(),
+                AlgMoveCounts::from_alg(&solution)
+            ),
+        }
+    }
+    if let Some(solution_writer) = solution_writer {
+        solution_writer.finish()?;
         println!(
-            "{} // solution #{} ({} nodes)",
-            solution,
-            solution_index,
-            solution.nodes.len()
-        )
+            "// Found {} solutions (see the output file)",
+            solution_index
+        );
     }
     println!(
         "// Entire search duration: {:?}",
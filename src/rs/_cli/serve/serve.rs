@@ -1,3 +1,4 @@
+use cubing::alg::Move;
 use cubing::kpuzzle::KPattern;
 use cubing::kpuzzle::KPatternData;
 use cubing::kpuzzle::KPuzzle;
@@ -9,12 +10,14 @@ use rouille::Request;
 use rouille::Response;
 use serde::Deserialize;
 use serde::Serialize;
+use twsearch::_internal::search_generators_and_canonical_fsm_with_cache;
 use twsearch::_internal::SearchLogger;
 
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use twsearch::_internal::cli::options::CustomGenerators;
+use twsearch::_internal::cli::options::EnableAutoAlwaysNeverValueEnum;
 use twsearch::_internal::CommandError;
 use twsearch::_internal::IDFSearch;
 use twsearch::_internal::IndividualSearchOptions;
@@ -81,30 +84,46 @@ fn solve_pattern(
         Some(client_args) => client_args.generator_moves.as_ref().cloned(),
         None => None,
     };
-    let move_list =
-        move_subset.unwrap_or_else(|| kpuzzle.definition().moves.keys().cloned().collect());
+    let move_list = move_subset.unwrap_or_else(|| {
+        // Sorted so that the search (and the generators cache key) doesn't depend on hash map order.
+        let mut moves: Vec<Move> = kpuzzle.definition().moves.keys().cloned().collect();
+        moves.sort_by_cached_key(|r#move| r#move.to_string());
+        moves
+    });
     let search_pattern = match KPattern::try_from_data(&kpuzzle, &kpattern_solve.pattern) {
         Ok(search_pattern) => search_pattern,
         Err(e) => return Response::text(e.to_string()).with_status_code(400),
     };
-    let mut search = match IDFSearch::try_new(
-        kpuzzle,
-        target_pattern,
-        Generators::Custom(CustomGenerators {
+    let (search_generators, canonical_fsm) = match search_generators_and_canonical_fsm_with_cache(
+        &kpuzzle,
+        &target_pattern,
+        &Generators::Custom(CustomGenerators {
             moves: move_list.clone(),
             algs: vec![],
         }),
-        search_logger,
         &MetricEnum::Hand, // TODO
         match args_for_individual_search.client_args {
             Some(client_args) => client_args.random_start == Some(true),
             None => false,
         },
-        None,
+        args_for_individual_search
+            .commandline_args
+            .search_persistence_args
+            .cache_dir
+            .as_deref(),
+        &search_logger,
     ) {
-        Ok(search) => search,
+        Ok(search_generators_and_canonical_fsm) => search_generators_and_canonical_fsm,
         Err(e) => return Response::text(e.description).with_status_code(400),
     };
+    let mut search = IDFSearch::new_from_search_generators(
+        kpuzzle,
+        target_pattern,
+        search_generators,
+        canonical_fsm,
+        search_logger,
+        None,
+    );
     if let Some(solution) = search
         .search(
             &search_pattern,
@@ -120,6 +139,18 @@ fn solve_pattern(
                     .and_then(|client_args| client_args.max_depth),
                 disallowed_initial_quanta: None,
                 disallowed_final_quanta: None,
+                // Solves from clients are requested directly, like CLI solves.
+                check_before_solve: Some(EnableAutoAlwaysNeverValueEnum::or_always(
+                    args_for_individual_search
+                        .client_args
+                        .as_ref()
+                        .and_then(|client_args| client_args.check_before_solve.clone()),
+                )),
+                direction: None,
+                timeout_milliseconds: None,
+                finish_solution_depth: None,
+                tie_break: None,
+                solution_order: None,
             },
         )
         .next()
@@ -0,0 +1,97 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Read, Write},
+    time::{Duration, Instant},
+};
+
+use cubing::alg::Alg;
+use serde_json::json;
+use twsearch::_internal::{
+    cli::options::{SolutionOutputArgs, SolutionOutputFormat},
+    ArgumentError, CommandError,
+};
+
+// Limits how much is lost if the search is interrupted.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn io_error(e: std::io::Error) -> CommandError {
+    ArgumentError {
+        description: format!("Could not write solution output file: {}", e),
+    }
+    .into()
+}
+
+/// Streams solutions to a file, so that they don't need to be kept in memory.
+pub struct SolutionWriter {
+    writer: BufWriter<File>,
+    format: SolutionOutputFormat,
+    // Solutions that are already in the file (when resuming).
+    num_solutions_to_skip: usize,
+    last_flush: Instant,
+}
+
+impl SolutionWriter {
+    pub fn try_new(args: &SolutionOutputArgs) -> Result<Option<Self>, CommandError> {
+        let Some(path) = &args.solution_output_file else {
+            return Ok(None);
+        };
+        let mut num_solutions_to_skip = 0;
+        let file = if args.resume && path.exists() {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .map_err(io_error)?;
+            let mut contents = Vec::<u8>::new();
+            file.read_to_end(&mut contents).map_err(io_error)?;
+            // Drop a partial line from an interrupted run, so that it's written again in full.
+            let complete_len = contents
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |newline_index| newline_index + 1);
+            num_solutions_to_skip = contents[..complete_len]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count();
+            file.set_len(complete_len as u64).map_err(io_error)?;
+            drop(file);
+            OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(io_error)?
+        } else {
+            File::create(path).map_err(io_error)?
+        };
+        Ok(Some(Self {
+            writer: BufWriter::new(file),
+            format: args.solution_output_format,
+            num_solutions_to_skip,
+            last_flush: Instant::now(),
+        }))
+    }
+
+    /// `solution_index` starts at 1.
+    pub fn write(&mut self, solution: &Alg, solution_index: usize) -> Result<(), CommandError> {
+        if solution_index <= self.num_solutions_to_skip {
+            return Ok(());
+        }
+        match self.format {
+            SolutionOutputFormat::Algs => writeln!(self.writer, "{}", solution),
+            SolutionOutputFormat::Jsonl => writeln!(
+                self.writer,
+                "{}",
+                json!({ "index": solution_index, "alg": solution.to_string() })
+            ),
+        }
+        .map_err(io_error)?;
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.writer.flush().map_err(io_error)?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), CommandError> {
+        self.writer.flush().map_err(io_error)
+    }
+}
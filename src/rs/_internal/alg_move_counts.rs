@@ -0,0 +1,92 @@
+use std::fmt::Display;
+
+use cubing::alg::{Alg, AlgNode, Move};
+
+use crate::_internal::options::MetricEnum;
+
+/// The length of an alg in common metrics. Rotations and slice moves are
+/// recognized using cube notation (`x`, `Rv`, `M`, `2R`, …). Other moves
+/// (including for other puzzles) count as face turns.
+///
+/// Moves nested inside other nodes (e.g. groupings) are not counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlgMoveCounts {
+    /// Half turn metric: face turns (of any amount) count 1, slice turns count 2.
+    pub htm: usize,
+    /// Quarter turn metric: like HTM, but each quarter turn counts.
+    pub qtm: usize,
+    /// Slice turn metric: face and slice turns (of any amount) count 1.
+    pub stm: usize,
+    /// Execution turn metric: every move (including rotations) counts 1.
+    pub etm: usize,
+}
+
+enum MoveKind {
+    Rotation,
+    Slice,
+    Face,
+}
+
+fn move_kind(r#move: &Move) -> MoveKind {
+    let family = r#move.quantum.family.as_str();
+    if matches!(family, "x" | "y" | "z") || family.ends_with('v') {
+        return MoveKind::Rotation;
+    }
+    if matches!(family, "M" | "E" | "S" | "m" | "e" | "s") {
+        return MoveKind::Slice;
+    }
+    // A single inner layer (e.g. `2R`), as opposed to a wide move (e.g. `3Rw` or `3r`).
+    let quantum_str = r#move.quantum.to_string();
+    if quantum_str.starts_with(|c: char| c.is_ascii_digit())
+        && !quantum_str.contains('-')
+        && family.chars().all(|c| c.is_ascii_uppercase())
+    {
+        return MoveKind::Slice;
+    }
+    MoveKind::Face
+}
+
+impl AlgMoveCounts {
+    pub fn from_alg(alg: &Alg) -> Self {
+        let mut counts = Self::default();
+        for node in &alg.nodes {
+            let AlgNode::MoveNode(r#move) = node else {
+                continue;
+            };
+            let num_quarter_turns = r#move.amount.unsigned_abs() as usize;
+            counts.etm += 1;
+            match move_kind(r#move) {
+                MoveKind::Rotation => {}
+                MoveKind::Slice => {
+                    counts.htm += 2;
+                    counts.qtm += 2 * num_quarter_turns;
+                    counts.stm += 1;
+                }
+                MoveKind::Face => {
+                    counts.htm += 1;
+                    counts.qtm += num_quarter_turns;
+                    counts.stm += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// The count that corresponds to the depth of a search using the metric.
+    pub fn for_metric(&self, metric: &MetricEnum) -> usize {
+        match metric {
+            MetricEnum::Hand => self.htm,
+            MetricEnum::Quantum => self.qtm,
+        }
+    }
+}
+
+impl Display for AlgMoveCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} HTM, {} QTM, {} STM, {} ETM",
+            self.htm, self.qtm, self.stm, self.etm
+        )
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use cubing::{
+    alg::{Alg, AlgNode, Move, QuantumMove},
+    kpuzzle::{KPuzzle, KTransformation},
+};
+
+use crate::_internal::{definition_moves, do_transformations_commute, PuzzleError};
+
+fn for_each_move(
+    alg: &Alg,
+    mut f: impl FnMut(&Move) -> Result<Move, PuzzleError>,
+) -> Result<Alg, PuzzleError> {
+    let mut nodes = Vec::<AlgNode>::new();
+    for node in &alg.nodes {
+        match node {
+            AlgNode::MoveNode(r#move) => nodes.push(f(r#move)?.into()),
+            _ => {
+                return Err(PuzzleError {
+                    description: "Only algs consisting of moves are supported.".to_owned(),
+                })
+            }
+        }
+    }
+    Ok(Alg { nodes })
+}
+
+fn transformation_for_move(
+    kpuzzle: &KPuzzle,
+    r#move: &Move,
+) -> Result<KTransformation, PuzzleError> {
+    kpuzzle
+        .transformation_from_move(r#move)
+        .map_err(|_| PuzzleError {
+            description: format!("Move does not exist in the puzzle definition: {}", r#move),
+        })
+}
+
+fn check_moves_exist(kpuzzle: &KPuzzle, alg: &Alg) -> Result<(), PuzzleError> {
+    for_each_move(alg, |r#move| {
+        transformation_for_move(kpuzzle, r#move)?;
+        Ok(r#move.clone())
+    })?;
+    Ok(())
+}
+
+/// Inverts an alg, checking that all the resulting moves exist for the puzzle.
+pub fn invert_alg(kpuzzle: &KPuzzle, alg: &Alg) -> Result<Alg, PuzzleError> {
+    let inverted = alg.invert();
+    check_moves_exist(kpuzzle, &inverted)?;
+    Ok(inverted)
+}
+
+/// A reflection of the puzzle, declared by how move quanta map across the
+/// mirror plane. Quanta that are swapped (e.g. `L` and `R`) and quanta that
+/// aren't listed (e.g. `U`) have their direction reversed, while quanta listed
+/// as unchanged (turns about the axis perpendicular to the plane, e.g. `x` and
+/// `M`) keep it.
+#[derive(Clone, Debug)]
+pub struct MirrorPlane {
+    // The mirrored quantum, and whether the amount is negated.
+    quantum_map: HashMap<QuantumMove, (QuantumMove, bool)>,
+}
+
+impl MirrorPlane {
+    pub fn try_new(
+        swapped_quanta: &[(&str, &str)],
+        unchanged_quanta: &[&str],
+    ) -> Result<Self, PuzzleError> {
+        let parse = |s: &str| {
+            s.parse::<QuantumMove>().map_err(|_| PuzzleError {
+                description: format!("Invalid quantum move: {}", s),
+            })
+        };
+        let mut quantum_map = HashMap::<QuantumMove, (QuantumMove, bool)>::new();
+        for (quantum1, quantum2) in swapped_quanta {
+            let quantum1 = parse(quantum1)?;
+            let quantum2 = parse(quantum2)?;
+            quantum_map.insert(quantum1.clone(), (quantum2.clone(), true));
+            quantum_map.insert(quantum2, (quantum1, true));
+        }
+        for quantum in unchanged_quanta {
+            let quantum = parse(quantum)?;
+            quantum_map.insert(quantum.clone(), (quantum, false));
+        }
+        Ok(Self { quantum_map })
+    }
+
+    /// The plane between the `L` and `R` faces of a cube.
+    pub fn cube_l_r() -> Self {
+        Self::try_new(&[("L", "R"), ("Lw", "Rw"), ("l", "r")], &["x", "M"]).unwrap()
+    }
+
+    fn mirror_move(&self, r#move: &Move) -> Move {
+        match self.quantum_map.get(r#move.quantum.as_ref()) {
+            Some((quantum, negate)) => Move {
+                quantum: Arc::new(quantum.clone()),
+                amount: if *negate {
+                    -r#move.amount
+                } else {
+                    r#move.amount
+                },
+            },
+            None => Move {
+                quantum: r#move.quantum.clone(),
+                amount: -r#move.amount,
+            },
+        }
+    }
+}
+
+/// Mirrors an alg across the given plane, checking that all the resulting moves exist for the puzzle.
+pub fn mirror_alg(
+    kpuzzle: &KPuzzle,
+    alg: &Alg,
+    mirror_plane: &MirrorPlane,
+) -> Result<Alg, PuzzleError> {
+    for_each_move(alg, |r#move| {
+        let mirrored = mirror_plane.mirror_move(r#move);
+        transformation_for_move(kpuzzle, &mirrored)?;
+        Ok(mirrored)
+    })
+}
+
+/// Rewrites an alg as it would be performed after holding the puzzle rotated
+/// by `rotation` (i.e. each move `M` becomes the move equivalent to
+/// `rotation M rotation'`). The rotated moves are found by comparing
+/// transformations, so this works for any notation in the puzzle definition.
+pub fn conjugate_alg(kpuzzle: &KPuzzle, alg: &Alg, rotation: &Alg) -> Result<Alg, PuzzleError> {
+    let rotation_transformation =
+        kpuzzle
+            .transformation_from_alg(rotation)
+            .map_err(|_| PuzzleError {
+                description: format!("Invalid rotation for the puzzle: {}", rotation),
+            })?;
+    let rotation_inverse_transformation = rotation_transformation.invert();
+
+    let candidate_quanta: Vec<&QuantumMove> = definition_moves(kpuzzle)
+        .into_iter()
+        .map(|r#move| r#move.quantum.as_ref())
+        .collect();
+
+    // Keyed by the move string, since the same move usually appears many times.
+    let mut cache = HashMap::<String, Move>::new();
+    for_each_move(alg, |r#move| {
+        if let Some(conjugated) = cache.get(&r#move.to_string()) {
+            return Ok(conjugated.clone());
+        }
+        let target_transformation = rotation_transformation
+            .apply_transformation(&transformation_for_move(kpuzzle, r#move)?)
+            .apply_transformation(&rotation_inverse_transformation);
+        for quantum in &candidate_quanta {
+            let candidate = Move {
+                quantum: Arc::new((*quantum).clone()),
+                amount: r#move.amount,
+            };
+            if let Ok(transformation) = kpuzzle.transformation_from_move(&candidate) {
+                if transformation == target_transformation {
+                    cache.insert(r#move.to_string(), candidate.clone());
+                    return Ok(candidate);
+                }
+            }
+        }
+        Err(PuzzleError {
+            description: format!(
+                "No move in the puzzle definition corresponds to {} after rotating by: {}",
+                r#move, rotation
+            ),
+        })
+    })
+}
+
+/// Reorders commuting moves into a fixed order, so that algs that only differ
+/// by the order of commuting moves (e.g. `U D R` and `D U R`) have the same
+/// canonical form. Of all the ways to reorder the alg like this, this returns
+/// the one that is smallest when comparing moves by their string form.
+pub fn canonicalize_commuting_moves(kpuzzle: &KPuzzle, alg: &Alg) -> Result<Alg, PuzzleError> {
+    let mut remaining = Vec::<(String, Move, KTransformation)>::new();
+    for_each_move(alg, |r#move| {
+        remaining.push((
+            r#move.to_string(),
+            r#move.clone(),
+            transformation_for_move(kpuzzle, r#move)?,
+        ));
+        Ok(r#move.clone())
+    })?;
+    let mut nodes = Vec::<AlgNode>::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        // A move can go next if it commutes with every move before it.
+        let mut next_index = 0;
+        for (index, (move_string, _, transformation)) in remaining.iter().enumerate().skip(1) {
+            if move_string < &remaining[next_index].0
+                && remaining[..index]
+                    .iter()
+                    .all(|(_, _, earlier)| do_transformations_commute(transformation, earlier))
+            {
+                next_index = index;
+            }
+        }
+        nodes.push(remaining.remove(next_index).1.into());
+    }
+    Ok(Alg { nodes })
+}
+
+/// Removes solutions that are the same as an earlier one up to the order of
+/// commuting moves (see `canonicalize_commuting_moves(…)`), keeping the
+/// earlier one as-is.
+pub fn dedup_commuting_solutions(
+    kpuzzle: &KPuzzle,
+    solutions: impl IntoIterator<Item = Alg>,
+) -> Result<Vec<Alg>, PuzzleError> {
+    let mut seen = HashSet::<String>::new();
+    let mut deduped = Vec::<Alg>::new();
+    for solution in solutions {
+        if seen.insert(canonicalize_commuting_moves(kpuzzle, &solution)?.to_string()) {
+            deduped.push(solution);
+        }
+    }
+    Ok(deduped)
+}
+
+/// A rotation for each of the 24 orientations of a cube: each of the 6 faces
+/// on top, with each of 4 faces in front.
+pub const CUBE_ORIENTATION_ROTATIONS: [&str; 24] = [
+    "", "y", "y2", "y'", //
+    "z2", "z2 y", "z2 y2", "z2 y'", //
+    "z'", "z' y", "z' y2", "z' y'", //
+    "z", "z y", "z y2", "z y'", //
+    "x'", "x' y", "x' y2", "x' y'", //
+    "x", "x y", "x y2", "x y'", //
+];
+
+/// Rewrites algs into a target move vocabulary (e.g. outer-layer moves only),
+/// absorbing rotations (and the rotation part of moves like wide moves) into
+/// how the following moves are expressed. The result is equivalent to the
+/// original alg up to a final rotation of the whole puzzle.
+pub struct AlgNormalizer {
+    kpuzzle: KPuzzle,
+    // All non-identity multiples of the vocabulary moves.
+    vocabulary: Vec<(Move, KTransformation)>,
+    rotations: Vec<KTransformation>,
+}
+
+impl AlgNormalizer {
+    pub fn try_new(
+        kpuzzle: &KPuzzle,
+        vocabulary_moves: &[Move],
+        rotations: &[Alg],
+    ) -> Result<Self, PuzzleError> {
+        let identity_transformation = kpuzzle.identity_transformation();
+
+        let mut vocabulary = Vec::<(Move, KTransformation)>::new();
+        for r#move in vocabulary_moves {
+            let quantum_move = Move {
+                quantum: r#move.quantum.clone(),
+                amount: 1,
+            };
+            let quantum_transformation = transformation_for_move(kpuzzle, &quantum_move)?;
+            let mut multiples = vec![];
+            let mut transformation = quantum_transformation.clone();
+            while transformation != identity_transformation {
+                multiples.push(transformation.clone());
+                transformation = transformation.apply_transformation(&quantum_transformation);
+            }
+            let order = multiples.len() as i32 + 1;
+            for (i, transformation) in multiples.into_iter().enumerate() {
+                // Prefer the smallest absolute amount (e.g. `U'` instead of `U3`).
+                let amount = i as i32 + 1;
+                let amount = if 2 * amount > order {
+                    amount - order
+                } else {
+                    amount
+                };
+                vocabulary.push((
+                    Move {
+                        quantum: r#move.quantum.clone(),
+                        amount,
+                    },
+                    transformation,
+                ));
+            }
+        }
+
+        let rotations = rotations
+            .iter()
+            .map(|rotation| {
+                kpuzzle
+                    .transformation_from_alg(rotation)
+                    .map_err(|_| PuzzleError {
+                        description: format!("Invalid rotation for the puzzle: {}", rotation),
+                    })
+            })
+            .collect::<Result<Vec<KTransformation>, PuzzleError>>()?;
+
+        Ok(Self {
+            kpuzzle: kpuzzle.clone(),
+            vocabulary,
+            rotations,
+        })
+    }
+
+    /// Normalizes to outer-layer moves (`U`, `L`, `F`, `R`, `B`, `D`) for a cube definition that includes `x`, `y`, and `z`.
+    pub fn try_new_cube_outer_layers(kpuzzle: &KPuzzle) -> Result<Self, PuzzleError> {
+        let parse_all = |strings: &[&str]| {
+            strings
+                .iter()
+                .map(|s| {
+                    s.parse::<Move>().map_err(|_| PuzzleError {
+                        description: format!("Invalid move: {}", s),
+                    })
+                })
+                .collect::<Result<Vec<Move>, PuzzleError>>()
+        };
+        let rotations = CUBE_ORIENTATION_ROTATIONS
+            .iter()
+            .map(|s| {
+                s.parse::<Alg>().map_err(|_| PuzzleError {
+                    description: format!("Invalid rotation: {}", s),
+                })
+            })
+            .collect::<Result<Vec<Alg>, PuzzleError>>()?;
+        Self::try_new(
+            kpuzzle,
+            &parse_all(&["U", "L", "F", "R", "B", "D"])?,
+            &rotations,
+        )
+    }
+
+    fn vocabulary_move_for(&self, transformation: &KTransformation) -> Option<&Move> {
+        self.vocabulary
+            .iter()
+            .find(|(_, vocabulary_transformation)| vocabulary_transformation == transformation)
+            .map(|(r#move, _)| r#move)
+    }
+
+    pub fn normalize(&self, alg: &Alg) -> Result<Alg, PuzzleError> {
+        let identity_transformation = self.kpuzzle.identity_transformation();
+        // The rotation accumulated so far, so that `alg` (up to the current move) is equivalent to the output followed by `frame`.
+        let mut frame = identity_transformation.clone();
+        let mut nodes = Vec::<AlgNode>::new();
+        for_each_move(alg, |r#move| {
+            let move_transformation = transformation_for_move(&self.kpuzzle, r#move)?;
+            // Decompose the move into a vocabulary move (or nothing) followed by a rotation.
+            for rotation in &self.rotations {
+                let remainder = move_transformation.apply_transformation(&rotation.invert());
+                let vocabulary_transformation = if remainder == identity_transformation {
+                    None
+                } else {
+                    match self.vocabulary_move_for(&remainder) {
+                        Some(_) => Some(remainder),
+                        None => continue,
+                    }
+                };
+                if let Some(vocabulary_transformation) = vocabulary_transformation {
+                    let conjugated = frame
+                        .apply_transformation(&vocabulary_transformation)
+                        .apply_transformation(&frame.invert());
+                    let output_move = self.vocabulary_move_for(&conjugated).ok_or_else(|| {
+                        PuzzleError {
+                            description: format!(
+                                "The move vocabulary is not closed under the given rotations (needed for: {})",
+                                r#move
+                            ),
+                        }
+                    })?;
+                    nodes.push(output_move.clone().into());
+                }
+                frame = frame.apply_transformation(rotation);
+                return Ok(r#move.clone());
+            }
+            Err(PuzzleError {
+                description: format!(
+                    "Could not express the move using the move vocabulary and rotations: {}",
+                    r#move
+                ),
+            })
+        })?;
+        Ok(Alg { nodes })
+    }
+}
+
+#[test]
+fn canonicalize_commuting_moves_test() {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let canonicalize = |alg: &str| {
+        canonicalize_commuting_moves(kpuzzle, &alg.parse().unwrap())
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(canonicalize("U D R"), "D U R");
+    assert_eq!(canonicalize("D U R"), "D U R");
+    assert_eq!(canonicalize("R2 L' U"), "L' R2 U");
+    // `U` and `D` don't commute with `R`, so they stay on either side of it.
+    assert_eq!(canonicalize("U R D"), "U R D");
+    // `D` can move past `U2 D2` but not past `R`.
+    assert_eq!(canonicalize("R U2 D2 D"), "R D D2 U2");
+
+    let deduped = dedup_commuting_solutions(
+        kpuzzle,
+        ["U D R", "D U R", "R U D", "R D U"]
+            .iter()
+            .map(|alg| alg.parse::<Alg>().unwrap()),
+    )
+    .unwrap();
+    assert_eq!(deduped.len(), 2);
+    assert_eq!(deduped[0].to_string(), "U D R");
+    assert_eq!(deduped[1].to_string(), "R U D");
+}
@@ -0,0 +1,64 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+};
+
+/// What a breadth-first search should do after visiting a state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreadthFirstSearchControl {
+    /// Keep searching, including the neighbors of this state.
+    Continue,
+    /// Keep searching, but don't expand this state.
+    SkipNeighbors,
+    /// Stop the whole search.
+    Stop,
+}
+
+/// A breadth-first search from `start`, for building tables (move tables,
+/// pruning tables, …) without writing the queue handling each time.
+///
+/// States are deduplicated by `key(…)`: `visit(…)` is called exactly once
+/// for each key, with its depth, as soon as it is found (so in order of
+/// increasing depth). `neighbors(…)` is called once for each state that is
+/// expanded, and may also be used to record every edge (e.g. for a move table).
+///
+/// Returns the number of states visited.
+pub fn breadth_first_search<State, Key, Neighbors>(
+    start: State,
+    mut key: impl FnMut(&State) -> Key,
+    mut neighbors: impl FnMut(&State) -> Neighbors,
+    mut visit: impl FnMut(&State, &Key, usize) -> BreadthFirstSearchControl,
+) -> usize
+where
+    Key: Eq + Hash,
+    Neighbors: IntoIterator<Item = State>,
+{
+    let mut visited = HashSet::<Key>::new();
+    let mut queue = VecDeque::<(State, usize)>::new();
+
+    let start_key = key(&start);
+    let control = visit(&start, &start_key, 0);
+    visited.insert(start_key);
+    match control {
+        BreadthFirstSearchControl::Continue => queue.push_back((start, 0)),
+        BreadthFirstSearchControl::SkipNeighbors => {}
+        BreadthFirstSearchControl::Stop => return visited.len(),
+    }
+
+    while let Some((state, depth)) = queue.pop_front() {
+        for next_state in neighbors(&state) {
+            let next_key = key(&next_state);
+            if visited.contains(&next_key) {
+                continue;
+            }
+            let control = visit(&next_state, &next_key, depth + 1);
+            visited.insert(next_key);
+            match control {
+                BreadthFirstSearchControl::Continue => queue.push_back((next_state, depth + 1)),
+                BreadthFirstSearchControl::SkipNeighbors => {}
+                BreadthFirstSearchControl::Stop => return visited.len(),
+            }
+        }
+    }
+    visited.len()
+}
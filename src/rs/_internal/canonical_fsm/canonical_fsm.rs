@@ -9,8 +9,8 @@ use crate::_internal::{PuzzleError, SearchGenerators};
 
 const MAX_NUM_MOVE_CLASSES: usize = usize::BITS as usize;
 
-#[derive(Clone, Copy, Debug)]
-pub(crate) struct MoveClassIndex(pub usize);
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MoveClassIndex(pub usize);
 
 // Bit N is indexed by a `MoveClass` value of N.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -22,7 +22,7 @@ impl BitAndAssign for MoveClassMask {
     }
 }
 
-fn do_transformations_commute(t1: &KTransformation, t2: &KTransformation) -> bool {
+pub(crate) fn do_transformations_commute(t1: &KTransformation, t2: &KTransformation) -> bool {
     t1.apply_transformation(t2) == t2.apply_transformation(t1)
 }
 
@@ -4,3 +4,6 @@ pub use canonical_fsm::*;
 
 mod search_generators;
 pub use search_generators::*;
+
+mod search_generators_cache;
+pub use search_generators_cache::*;
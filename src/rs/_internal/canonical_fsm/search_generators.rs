@@ -1,24 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use cubing::{
     alg::{Move, QuantumMove},
-    kpuzzle::{KPuzzle, KTransformation, KTransformationBuffer},
+    kpuzzle::{KPattern, KPuzzle, KTransformation, KTransformationBuffer},
 };
 use rand::{seq::SliceRandom, thread_rng};
 
 use crate::_internal::{
     cli::options::{Generators, MetricEnum},
-    PuzzleError,
+    do_transformations_commute, normalized_pattern, MoveClassIndex, PuzzleError,
 };
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MoveAxisIndex(pub usize);
+
 #[derive(Clone, Debug)]
 pub struct MoveTransformationInfo {
     #[allow(dead_code)] // TODO
     pub r#move: Move,
     // move_class: MoveClass, // TODO: do we need this?
+    pub axis: MoveAxisIndex,
     // pub metric_turns: i32,
     pub transformation: KTransformation,
-    #[allow(dead_code)] // TODO
+    /// Used for backward search: expanding from the target, where each step
+    /// applies the inverse of the move that a solution will use.
     pub inverse_transformation: KTransformation,
 }
 
@@ -29,6 +34,8 @@ pub struct SearchGenerators {
     // TODO: figure out the most reusable abstraction
     pub grouped: Vec<MoveTransformationMultiples>,
     pub flat: Vec<MoveTransformationInfo>, // TODO: avoid duplicate data
+    // Indexed by `MoveAxisIndex`. Each entry lists the move classes on that axis, in increasing order.
+    pub axes: Vec<Vec<MoveClassIndex>>,
 }
 
 fn transformation_order(
@@ -50,6 +57,97 @@ fn canonicalize_center_amount(order: i32, amount: i32) -> i32 {
     (amount + offset).rem_euclid(order) - offset
 }
 
+/// All moves in the puzzle definition (including derived moves), sorted by
+/// their string representation.
+///
+/// The definition stores moves in hash maps, so iterating over them directly
+/// would give a different order on every run (and hence a different move
+/// order during search, which can change which solutions are found first).
+pub fn definition_moves(kpuzzle: &KPuzzle) -> Vec<&Move> {
+    let def = kpuzzle.definition();
+    let mut moves: Vec<&Move> = def.moves.keys().collect();
+    if let Some(derived_moves) = &def.derived_moves {
+        moves.extend(derived_moves.keys());
+    }
+    moves.sort_by_cached_key(|r#move| r#move.to_string());
+    moves
+}
+
+// Move classes are on the same axis if they are connected by a chain of
+// commuting move classes. For example, `U`, `Uw`, and `D` on a 4x4x4 all share
+// an axis, while every move class of a Megaminx is on its own axis.
+fn calculate_axes(grouped: &[MoveTransformationMultiples]) -> Vec<Vec<MoveClassIndex>> {
+    let mut assigned = vec![false; grouped.len()];
+    let mut axes = Vec::<Vec<MoveClassIndex>>::default();
+    for i in 0..grouped.len() {
+        if assigned[i] {
+            continue;
+        }
+        assigned[i] = true;
+        let mut axis = Vec::<MoveClassIndex>::default();
+        let mut pending = vec![i];
+        while let Some(j) = pending.pop() {
+            axis.push(MoveClassIndex(j));
+            for (k, other_multiples) in grouped.iter().enumerate() {
+                if !assigned[k]
+                    && do_transformations_commute(
+                        &grouped[j][0].transformation,
+                        &other_multiples[0].transformation,
+                    )
+                {
+                    assigned[k] = true;
+                    pending.push(k);
+                }
+            }
+        }
+        axis.sort_by_key(|move_class_index| move_class_index.0);
+        axes.push(axis);
+    }
+    axes
+}
+
+// For each orbit: whether every piece of the target pattern is identical and
+// has an ignored orientation (i.e. an orientation mod of 1, or an orbit
+// without orientations). Moves can't change such an orbit in any pattern that
+// the search compares to the target.
+pub(crate) fn fully_masked_orbits(kpuzzle: &KPuzzle, target_pattern: &KPattern) -> Vec<bool> {
+    kpuzzle
+        .orbit_info_iter()
+        .map(|orbit_info| {
+            let first_piece = target_pattern.get_piece(orbit_info, 0);
+            (0..orbit_info.num_pieces).all(|i| {
+                target_pattern.get_piece(orbit_info, i) == first_piece
+                    && (orbit_info.num_orientations == 1
+                        || target_pattern
+                            .get_orientation_with_mod(orbit_info, i)
+                            .orientation_mod
+                            == 1)
+            })
+        })
+        .collect()
+}
+
+// The permutation and orientation deltas of `transformation`, except for the
+// fully masked orbits.
+fn unmasked_transformation_key(
+    kpuzzle: &KPuzzle,
+    transformation: &KTransformation,
+    fully_masked_orbits: &[bool],
+) -> Vec<u8> {
+    let applied = normalized_pattern(kpuzzle).apply_transformation(transformation);
+    let mut key = Vec::<u8>::new();
+    for (orbit_info, is_fully_masked) in kpuzzle.orbit_info_iter().zip(fully_masked_orbits) {
+        if *is_fully_masked {
+            continue;
+        }
+        for i in 0..orbit_info.num_pieces {
+            key.push(applied.get_piece(orbit_info, i));
+            key.push(applied.get_orientation_with_mod(orbit_info, i).orientation);
+        }
+    }
+    key
+}
+
 impl SearchGenerators {
     pub fn try_new(
         kpuzzle: &KPuzzle,
@@ -62,15 +160,7 @@ impl SearchGenerators {
         let mut seen_quantum_moves = HashMap::<QuantumMove, Move>::new();
 
         let moves: Vec<&Move> = match generators {
-            Generators::Default => {
-                let def = kpuzzle.definition();
-                let moves = def.moves.keys();
-                if let Some(derived_moves) = &def.derived_moves {
-                    moves.chain(derived_moves.keys()).collect()
-                } else {
-                    moves.collect()
-                }
-            }
+            Generators::Default => definition_moves(kpuzzle),
             Generators::Custom(generators) => generators.moves.iter().collect(),
         };
         if let Generators::Custom(custom_generators) = generators {
@@ -81,7 +171,6 @@ impl SearchGenerators {
 
         // TODO: actually calculate GCDs
         let mut grouped = Vec::<MoveTransformationMultiples>::default();
-        let mut flat = Vec::<MoveTransformationInfo>::default();
         for r#move in moves {
             if let Some(existing) = seen_quantum_moves.get(&r#move.quantum) {
                 // TODO: deduplicate by quantum move.
@@ -123,12 +212,12 @@ impl SearchGenerators {
                         move_multiple.amount = canonicalize_center_amount(order, amount);
                         let info = MoveTransformationInfo {
                             r#move: move_multiple,
+                            axis: MoveAxisIndex(0), // Assigned once all move classes are known.
                             // metric_turns: 1, // TODO
                             transformation: move_multiple_transformation.current().clone(),
                             inverse_transformation: move_multiple_transformation.current().invert(),
                         };
-                        multiples.push(info.clone());
-                        flat.push(info);
+                        multiples.push(info);
 
                         amount += r#move.amount;
                         move_multiple_transformation.apply_transformation(&move_transformation);
@@ -137,22 +226,22 @@ impl SearchGenerators {
                 MetricEnum::Quantum => {
                     let info = MoveTransformationInfo {
                         r#move: r#move.clone(),
+                        axis: MoveAxisIndex(0), // Assigned once all move classes are known.
                         // metric_turns: 1, // TODO
                         transformation: move_multiple_transformation.current().clone(),
                         inverse_transformation: move_multiple_transformation.current().invert(),
                     };
                     let is_self_inverse = info.transformation == info.inverse_transformation;
-                    multiples.push(info.clone());
-                    flat.push(info);
+                    multiples.push(info);
                     if !is_self_inverse {
                         let info = MoveTransformationInfo {
                             r#move: r#move.invert(),
+                            axis: MoveAxisIndex(0), // Assigned once all move classes are known.
                             // metric_turns: 1, // TODO
                             transformation: move_multiple_transformation.current().invert(),
                             inverse_transformation: move_multiple_transformation.current().clone(),
                         };
-                        multiples.push(info.clone());
-                        flat.push(info);
+                        multiples.push(info);
                     }
                 }
             }
@@ -161,9 +250,200 @@ impl SearchGenerators {
         let mut rng = thread_rng();
         if random_start {
             grouped.shuffle(&mut rng);
+        }
+
+        let axes = calculate_axes(&grouped);
+        for (axis_index, axis) in axes.iter().enumerate() {
+            for move_class_index in axis {
+                for info in &mut grouped[move_class_index.0] {
+                    info.axis = MoveAxisIndex(axis_index);
+                }
+            }
+        }
+
+        let mut flat: Vec<MoveTransformationInfo> = grouped.iter().flatten().cloned().collect();
+        if random_start {
             flat.shuffle(&mut rng);
         }
 
-        Ok(Self { grouped, flat })
+        Ok(Self {
+            grouped,
+            flat,
+            axes,
+        })
+    }
+
+    /// Removes move classes that can't affect `target_pattern`: those that only
+    /// move orbits where the target has identical pieces with ignored
+    /// orientations (e.g. corners during an edge-only phase), and those that
+    /// act the same as an earlier move class on every other orbit. Neither
+    /// kind can shorten a solution, so this only reduces branching.
+    ///
+    /// This must be called before building the canonical FSM for the
+    /// generators. If every move class would be removed, the generators are
+    /// returned unchanged.
+    pub fn without_moves_ignored_by_target(
+        &self,
+        kpuzzle: &KPuzzle,
+        target_pattern: &KPattern,
+    ) -> SearchGenerators {
+        let fully_masked_orbits = fully_masked_orbits(kpuzzle, target_pattern);
+        if !fully_masked_orbits.contains(&true) {
+            return self.clone();
+        }
+        let identity_key = unmasked_transformation_key(
+            kpuzzle,
+            &kpuzzle.identity_transformation(),
+            &fully_masked_orbits,
+        );
+
+        let mut seen_keys = HashSet::<Vec<Vec<u8>>>::new();
+        let mut grouped: Vec<MoveTransformationMultiples> = self
+            .grouped
+            .iter()
+            .filter(|multiples| {
+                let mut keys: Vec<Vec<u8>> = multiples
+                    .iter()
+                    .map(|info| {
+                        unmasked_transformation_key(
+                            kpuzzle,
+                            &info.transformation,
+                            &fully_masked_orbits,
+                        )
+                    })
+                    .collect();
+                if keys[0] == identity_key {
+                    return false;
+                }
+                // Multiples may be listed in a different order (e.g. `r` acts like `M'`).
+                keys.sort();
+                seen_keys.insert(keys)
+            })
+            .cloned()
+            .collect();
+        if grouped.is_empty() || grouped.len() == self.grouped.len() {
+            return self.clone();
+        }
+
+        let axes = calculate_axes(&grouped);
+        for (axis_index, axis) in axes.iter().enumerate() {
+            for move_class_index in axis {
+                for info in &mut grouped[move_class_index.0] {
+                    info.axis = MoveAxisIndex(axis_index);
+                }
+            }
+        }
+
+        // Keep the existing order (which may have been shuffled), but with the new axes.
+        let flat: Vec<MoveTransformationInfo> = self
+            .flat
+            .iter()
+            .filter_map(|info| {
+                grouped
+                    .iter()
+                    .flatten()
+                    .find(|kept_info| kept_info.r#move == info.r#move)
+                    .cloned()
+            })
+            .collect();
+
+        Self {
+            grouped,
+            flat,
+            axes,
+        }
+    }
+
+    pub fn axis_for_move_class(&self, move_class_index: MoveClassIndex) -> MoveAxisIndex {
+        self.grouped[move_class_index.0][0].axis
+    }
+
+    pub fn axis_for_move(&self, r#move: &Move) -> Option<MoveAxisIndex> {
+        self.flat
+            .iter()
+            .find(|info| info.r#move.quantum == r#move.quantum)
+            .map(|info| info.axis)
+    }
+}
+
+#[test]
+fn search_generators_deterministic_order_test() {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let move_strings = || -> Vec<String> {
+        SearchGenerators::try_new(kpuzzle, &Generators::Default, &MetricEnum::Hand, false)
+            .unwrap()
+            .flat
+            .iter()
+            .map(|info| info.r#move.to_string())
+            .collect()
+    };
+    let expected = move_strings();
+    // Each `HashMap` gets its own random seed, so repeating this in the same process is enough to catch order differences.
+    for _ in 0..10 {
+        assert_eq!(move_strings(), expected);
+    }
+}
+
+#[test]
+fn without_moves_ignored_by_target_test() {
+    use cubing::{kpuzzle::OrientationWithMod, puzzles::cube3x3x3_kpuzzle};
+
+    use crate::_internal::cli::options::CustomGenerators;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let search_generators = SearchGenerators::try_new(
+        kpuzzle,
+        &Generators::Custom(CustomGenerators {
+            moves: vec![
+                "R".parse().unwrap(),
+                "M".parse().unwrap(),
+                "U".parse().unwrap(),
+                "r".parse().unwrap(),
+            ],
+            algs: vec![],
+        }),
+        &MetricEnum::Hand,
+        false,
+    )
+    .unwrap();
+    let move_strings = |search_generators: &SearchGenerators| -> Vec<String> {
+        search_generators
+            .flat
+            .iter()
+            .map(|info| info.r#move.to_string())
+            .collect()
+    };
+
+    // Nothing is masked, so nothing is removed.
+    assert_eq!(
+        move_strings(
+            &search_generators.without_moves_ignored_by_target(kpuzzle, &kpuzzle.default_pattern())
+        ),
+        move_strings(&search_generators)
+    );
+
+    // Only centers matter: `R` and `U` can't affect them, and `r` moves them the same way as `M'`.
+    let mut centers_only_target_pattern = kpuzzle.default_pattern();
+    for orbit_info in kpuzzle.orbit_info_iter() {
+        if orbit_info.name.0 == "CENTERS" {
+            continue;
+        }
+        for i in 0..orbit_info.num_pieces {
+            centers_only_target_pattern.set_piece(orbit_info, i, 0);
+            centers_only_target_pattern.set_orientation_with_mod(
+                orbit_info,
+                i,
+                &OrientationWithMod {
+                    orientation: 0,
+                    orientation_mod: 1,
+                },
+            );
+        }
     }
+    let restricted =
+        search_generators.without_moves_ignored_by_target(kpuzzle, &centers_only_target_pattern);
+    assert_eq!(move_strings(&restricted), vec!["M", "M2", "M'"]);
+    assert_eq!(restricted.axes.len(), 1);
 }
@@ -0,0 +1,252 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    hash::BuildHasher,
+    path::Path,
+};
+
+use cubing::{
+    alg::Move,
+    kpuzzle::{KPattern, KPuzzle},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::_internal::{
+    cli::options::{Generators, MetricEnum},
+    CanonicalFSM, CanonicalFSMState, MoveAxisIndex, MoveClassIndex, MoveTransformationInfo,
+    MoveTransformationMultiples, PuzzleError, SearchGenerators, SearchLogger,
+};
+
+use super::search_generators::fully_masked_orbits;
+
+// Bump this whenever the serialized format (or the way the cached structures are derived) changes.
+const CACHE_FORMAT_VERSION: u32 = 2;
+
+// `KTransformation` can't be constructed from serialized data directly, so we
+// store the moves and recompute their transformations on load. This is cheap
+// compared to calculating move orders and building the canonical FSM.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedSearchGenerators {
+    cache_key: String,
+    grouped_moves: Vec<Vec<Move>>,
+    axes: Vec<Vec<usize>>,
+    next_state_lookup: Vec<Vec<usize>>,
+}
+
+fn cache_key(
+    kpuzzle: &KPuzzle,
+    target_pattern: &KPattern,
+    generators: &Generators,
+    metric: &MetricEnum,
+) -> Result<String, PuzzleError> {
+    // Converting to a `serde_json::Value` sorts the keys of the definition's
+    // maps, which keeps the key stable across processes.
+    let definition_json = serde_json::to_value(kpuzzle.definition())
+        .map_err(|e| PuzzleError {
+            description: e.to_string(),
+        })?
+        .to_string();
+    let generators_str = match generators {
+        Generators::Default => "default".to_owned(),
+        Generators::Custom(custom_generators) => format!(
+            "moves: {} / algs: {}",
+            custom_generators
+                .moves
+                .iter()
+                .map(|r#move| r#move.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            custom_generators
+                .algs
+                .iter()
+                .map(|alg| alg.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
+    };
+    // Only the fully masked orbits of the target pattern affect which moves are kept.
+    let fully_masked_orbits_str: String = fully_masked_orbits(kpuzzle, target_pattern)
+        .iter()
+        .map(|is_fully_masked| if *is_fully_masked { '1' } else { '0' })
+        .collect();
+    let hash = cityhasher::CityHasher::new().hash_one(format!(
+        "{}\n{}\n{}\n{}\n{}",
+        CACHE_FORMAT_VERSION, definition_json, generators_str, metric, fully_masked_orbits_str
+    ));
+    Ok(format!("v{}-{:016x}", CACHE_FORMAT_VERSION, hash))
+}
+
+fn serialize(
+    cache_key: String,
+    search_generators: &SearchGenerators,
+    canonical_fsm: &CanonicalFSM,
+) -> SerializedSearchGenerators {
+    SerializedSearchGenerators {
+        cache_key,
+        grouped_moves: search_generators
+            .grouped
+            .iter()
+            .map(|multiples| multiples.iter().map(|info| info.r#move.clone()).collect())
+            .collect(),
+        axes: search_generators
+            .axes
+            .iter()
+            .map(|axis| {
+                axis.iter()
+                    .map(|move_class_index| move_class_index.0)
+                    .collect()
+            })
+            .collect(),
+        next_state_lookup: canonical_fsm
+            .next_state_lookup
+            .iter()
+            .map(|next_states| next_states.iter().map(|state| state.0).collect())
+            .collect(),
+    }
+}
+
+fn deserialize(
+    kpuzzle: &KPuzzle,
+    serialized: SerializedSearchGenerators,
+) -> Result<(SearchGenerators, CanonicalFSM), PuzzleError> {
+    let num_move_classes = serialized.grouped_moves.len();
+
+    let mut axis_for_move_class = vec![MoveAxisIndex(0); num_move_classes];
+    for (axis_index, axis) in serialized.axes.iter().enumerate() {
+        for move_class_index in axis {
+            *axis_for_move_class
+                .get_mut(*move_class_index)
+                .ok_or("Cached axis refers to an invalid move class.")? = MoveAxisIndex(axis_index);
+        }
+    }
+
+    let mut grouped = Vec::<MoveTransformationMultiples>::default();
+    for (moves, axis) in serialized.grouped_moves.iter().zip(axis_for_move_class) {
+        let mut multiples = MoveTransformationMultiples::default();
+        for r#move in moves {
+            let transformation =
+                kpuzzle
+                    .transformation_from_move(r#move)
+                    .map_err(|e| PuzzleError {
+                        description: e.to_string(),
+                    })?;
+            multiples.push(MoveTransformationInfo {
+                r#move: r#move.clone(),
+                axis,
+                inverse_transformation: transformation.invert(),
+                transformation,
+            });
+        }
+        grouped.push(multiples);
+    }
+    let flat = grouped.iter().flatten().cloned().collect();
+    let axes = serialized
+        .axes
+        .into_iter()
+        .map(|axis| axis.into_iter().map(MoveClassIndex).collect())
+        .collect();
+
+    if serialized
+        .next_state_lookup
+        .iter()
+        .any(|next_states| next_states.len() != num_move_classes)
+    {
+        return Err("Cached canonical FSM does not match the cached move classes.".into());
+    }
+    let canonical_fsm = CanonicalFSM {
+        next_state_lookup: serialized
+            .next_state_lookup
+            .into_iter()
+            .map(|next_states| next_states.into_iter().map(CanonicalFSMState).collect())
+            .collect(),
+        move_class_indices: (0..num_move_classes).map(MoveClassIndex).collect(),
+    };
+
+    Ok((
+        SearchGenerators {
+            grouped,
+            flat,
+            axes,
+        },
+        canonical_fsm,
+    ))
+}
+
+/// Builds the search generators and canonical FSM for the given puzzle, reusing
+/// the serialized structures in `cache_dir` if they were previously written for
+/// the same definition, generators, metric, and target pattern masking. Like
+/// `IDFSearch::try_new(…)`, this drops moves that can't affect the target
+/// pattern (see `SearchGenerators::without_moves_ignored_by_target(…)`).
+///
+/// Caching is skipped when `random_start` is set, since the canonical FSM
+/// depends on the (randomized) order of the move classes.
+pub fn search_generators_and_canonical_fsm_with_cache(
+    kpuzzle: &KPuzzle,
+    target_pattern: &KPattern,
+    generators: &Generators,
+    metric: &MetricEnum,
+    random_start: bool,
+    cache_dir: Option<&Path>,
+    search_logger: &SearchLogger,
+) -> Result<(SearchGenerators, CanonicalFSM), PuzzleError> {
+    let cache_dir = match (cache_dir, random_start) {
+        (Some(cache_dir), false) => cache_dir,
+        _ => {
+            let search_generators =
+                SearchGenerators::try_new(kpuzzle, generators, metric, random_start)?
+                    .without_moves_ignored_by_target(kpuzzle, target_pattern);
+            let canonical_fsm = CanonicalFSM::try_new(search_generators.clone())?;
+            return Ok((search_generators, canonical_fsm));
+        }
+    };
+
+    let cache_key = cache_key(kpuzzle, target_pattern, generators, metric)?;
+    let cache_file = cache_dir.join(format!("search-generators-{}.json", cache_key));
+    if let Ok(contents) = read_to_string(&cache_file) {
+        match serde_json::from_str::<SerializedSearchGenerators>(&contents) {
+            Ok(serialized) if serialized.cache_key == cache_key => {
+                match deserialize(kpuzzle, serialized) {
+                    Ok(deserialized) => {
+                        search_logger.write_info(&format!(
+                            "[Search generators cache] Loaded: {}",
+                            cache_file.display()
+                        ));
+                        return Ok(deserialized);
+                    }
+                    Err(e) => search_logger.write_warning(&format!(
+                        "[Search generators cache] Ignoring invalid cache file ({}): {}",
+                        e.description,
+                        cache_file.display()
+                    )),
+                }
+            }
+            _ => search_logger.write_warning(&format!(
+                "[Search generators cache] Ignoring stale or unreadable cache file: {}",
+                cache_file.display()
+            )),
+        }
+    }
+
+    let search_generators = SearchGenerators::try_new(kpuzzle, generators, metric, false)?
+        .without_moves_ignored_by_target(kpuzzle, target_pattern);
+    let canonical_fsm = CanonicalFSM::try_new(search_generators.clone())?;
+
+    let serialized = serialize(cache_key, &search_generators, &canonical_fsm);
+    let write_result = create_dir_all(cache_dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string(&serialized).map_err(|e| e.to_string()))
+        .and_then(|json| write(&cache_file, json).map_err(|e| e.to_string()));
+    match write_result {
+        Ok(()) => search_logger.write_info(&format!(
+            "[Search generators cache] Wrote: {}",
+            cache_file.display()
+        )),
+        Err(e) => search_logger.write_warning(&format!(
+            "[Search generators cache] Could not write cache file ({}): {}",
+            e,
+            cache_file.display()
+        )),
+    }
+
+    Ok((search_generators, canonical_fsm))
+}
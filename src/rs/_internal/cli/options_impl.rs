@@ -9,6 +9,13 @@ use std::path::PathBuf;
 use std::process::exit;
 use std::str::FromStr;
 
+use cubing::alg::QuantumMove;
+
+use crate::_internal::{
+    ArgumentError, IndividualSearchOptions, NotationDialect, SearchDirection, SolutionOrder,
+    SolutionTieBreak, TableMemoryMode,
+};
+
 /// twsearch-cpp-wrapper — a native Rust wrapper for `twsearch` functionality.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -46,6 +53,14 @@ pub enum CliCommand {
     TimingTest(TimingTestArgs),
     // Enumerate canonical algs (move sequences) at iterative depths.
     CanonicalAlgs(CanonicalAlgsArgs),
+    /// Apply an alg to a pattern and print the result.
+    Apply(ApplyArgs),
+    /// Search for short algs that produce symmetric ("pretty") patterns.
+    PrettyPatterns(PrettyPatternsArgs),
+    /// Estimate the number of patterns and the typical solution depth for a
+    /// generator set by sampling random move sequences (without building any
+    /// tables).
+    EstimateSearchSpace(EstimateSearchSpaceArgs),
 
     /// Run an internal benchmark suite.
     Benchmark(BenchmarkArgs),
@@ -56,7 +71,7 @@ pub enum CliCommand {
 
 #[derive(Args, Debug)]
 pub struct CommonSearchArgs {
-    /// Check that a position is valid before attempting to solve it. This may take extra time or memory for large puzzles.
+    /// Check that a position is valid before attempting to solve it. This may take extra time or memory for large puzzles. `auto` enables the check for the CLI and the server.
     #[clap(long/*, visible_alias = "checkbeforesolve" */)]
     pub check_before_solve: Option<EnableAutoAlwaysNeverValueEnum>,
 
@@ -88,6 +103,17 @@ pub struct SearchCommandArgs {
     #[clap(long/* , visible_short_alias = 't' */)]
     pub min_num_solutions: Option<usize>,
 
+    /// After finding `--min-num-solutions` solutions, keep going to print all
+    /// the other solutions of the same length.
+    #[clap(long)]
+    pub finish_solution_depth: bool,
+
+    /// Print solutions as soon as they are found, or hold back the solutions
+    /// of each length to print them sorted (which also implies
+    /// `--finish-solution-depth`).
+    #[clap(long)]
+    pub solution_order: Option<SolutionOrderEnum>,
+
     #[command(flatten)]
     pub generator_args: GeneratorArgs,
     #[command(flatten)]
@@ -98,12 +124,240 @@ pub struct SearchCommandArgs {
     pub metric_args: MetricArgs,
     #[command(flatten)]
     pub verbosity_args: VerbosityArgs,
+    #[command(flatten)]
+    pub notation_args: NotationArgs,
+    #[command(flatten)]
+    pub solution_output_args: SolutionOutputArgs,
+    #[command(flatten)]
+    pub tie_break_args: TieBreakArgs,
+    #[command(flatten)]
+    pub individual_search_args: IndividualSearchArgs,
 
     // We place this last show it shows at the end of `--help` (and therefore just above the next shell prompt).
     #[command(flatten)]
     pub input_def_and_optional_scramble_file_args: InputDefAndOptionalScrambleFileArgs,
 }
 
+impl SearchCommandArgs {
+    /// Collects the options for the search itself, checking that they are consistent.
+    pub fn individual_search_options(&self) -> Result<IndividualSearchOptions, ArgumentError> {
+        if let (Some(min_depth), Some(max_depth)) =
+            (self.search_args.min_depth, self.search_args.max_depth)
+        {
+            if min_depth > max_depth {
+                return Err(ArgumentError {
+                    description: format!(
+                        "`--min-depth` ({}) must not be greater than `--max-depth` ({}).",
+                        min_depth, max_depth
+                    ),
+                });
+            }
+        }
+        if self.min_num_solutions == Some(0) {
+            return Err("`--min-num-solutions` must be at least 1.".into());
+        }
+        if self.individual_search_args.timeout_milliseconds == Some(0) {
+            return Err("`--timeout-milliseconds` must be at least 1.".into());
+        }
+        Ok(IndividualSearchOptions {
+            min_num_solutions: self.min_num_solutions,
+            min_depth: self.search_args.min_depth,
+            max_depth: self.search_args.max_depth,
+            disallowed_initial_quanta: parse_quanta(
+                "--disallowed-initial-quanta",
+                &self.individual_search_args.disallowed_initial_quanta,
+            )?,
+            disallowed_final_quanta: parse_quanta(
+                "--disallowed-final-quanta",
+                &self.individual_search_args.disallowed_final_quanta,
+            )?,
+            // A helpful error for an unreachable pattern is worth the cost of
+            // the check for a solve that was requested directly.
+            check_before_solve: Some(EnableAutoAlwaysNeverValueEnum::or_always(
+                self.search_args.check_before_solve,
+            )),
+            direction: self
+                .individual_search_args
+                .direction
+                .map(|direction| match direction {
+                    SearchDirectionEnum::Solve => SearchDirection::SearchPatternToTarget,
+                    SearchDirectionEnum::Scramble => SearchDirection::TargetToSearchPattern,
+                }),
+            timeout_milliseconds: self.individual_search_args.timeout_milliseconds,
+            finish_solution_depth: Some(self.finish_solution_depth),
+            tie_break: self.tie_break_args.parse(),
+            solution_order: self
+                .solution_order
+                .map(|solution_order| match solution_order {
+                    SolutionOrderEnum::FirstFound => SolutionOrder::FirstFound,
+                    SolutionOrderEnum::ShortestFirst => SolutionOrder::ShortestFirst,
+                }),
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct IndividualSearchArgs {
+    /// A comma-separated list of quanta (e.g. `U,R`) that solutions may not
+    /// start with, in any amount.
+    #[clap(long)]
+    pub disallowed_initial_quanta: Option<String>,
+
+    /// A comma-separated list of quanta (e.g. `U,R`) that solutions may not
+    /// end with, in any amount.
+    #[clap(long)]
+    pub disallowed_final_quanta: Option<String>,
+
+    /// Whether to print solutions for the input pattern, or scrambles that
+    /// produce it from the target pattern.
+    #[clap(long)]
+    pub direction: Option<SearchDirectionEnum>,
+
+    /// Stop searching after this long. Any solutions found so far are still
+    /// printed.
+    #[clap(long)]
+    pub timeout_milliseconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SearchDirectionEnum {
+    /// Each solution takes the input pattern to the target pattern.
+    Solve,
+    /// Each solution takes the target pattern to the input pattern.
+    Scramble,
+}
+
+impl Display for SearchDirectionEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SearchDirectionEnum::Solve => "solve",
+            SearchDirectionEnum::Scramble => "scramble",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn parse_quanta(
+    flag_name: &str,
+    input: &Option<String>,
+) -> Result<Option<Vec<QuantumMove>>, ArgumentError> {
+    input
+        .as_ref()
+        .map(|quanta| {
+            quanta
+                .split(',')
+                .map(|quantum_str| {
+                    quantum_str
+                        .parse::<QuantumMove>()
+                        .map_err(|_| ArgumentError {
+                            description: format!(
+                                "Invalid quantum for `{}`: {}",
+                                flag_name, quantum_str
+                            ),
+                        })
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+#[derive(Args, Debug)]
+pub struct SolutionOutputArgs {
+    /// Write solutions to this file as they are found (instead of printing
+    /// them), e.g. when enumerating too many solutions to keep in memory.
+    #[clap(long)]
+    pub solution_output_file: Option<PathBuf>,
+
+    #[clap(long, default_value_t = SolutionOutputFormat::Algs)]
+    pub solution_output_format: SolutionOutputFormat,
+
+    /// Skip as many solutions as the output file already has, and append the
+    /// rest. This only makes sense for searches with a deterministic order
+    /// (i.e. without `--random-start`).
+    #[clap(long, requires = "solution_output_file")]
+    pub resume: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SolutionOutputFormat {
+    /// One alg per line.
+    Algs,
+    /// One JSON object per line, with the solution index and alg.
+    Jsonl,
+}
+
+impl Display for SolutionOutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SolutionOutputFormat::Algs => "algs",
+            SolutionOutputFormat::Jsonl => "jsonl",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SolutionOrderEnum {
+    /// As soon as each solution is found.
+    FirstFound,
+    /// Sorted within each length (shortest first).
+    ShortestFirst,
+}
+
+impl Display for SolutionOrderEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SolutionOrderEnum::FirstFound => "first-found",
+            SolutionOrderEnum::ShortestFirst => "shortest-first",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct TieBreakArgs {
+    /// Which solution to print first when there are several of the same length.
+    #[clap(long)]
+    pub tie_break: Option<TieBreakEnum>,
+
+    /// The seed for `--tie-break random`.
+    #[clap(long, default_value_t = 0)]
+    pub tie_break_seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TieBreakEnum {
+    /// Lexicographic by the order of the generators.
+    Lexicographic,
+    /// Random, using `--tie-break-seed`.
+    Random,
+    /// Prefer solutions with fewer wide moves.
+    FewerWideMoves,
+}
+
+impl Display for TieBreakEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TieBreakEnum::Lexicographic => "lexicographic",
+            TieBreakEnum::Random => "random",
+            TieBreakEnum::FewerWideMoves => "fewer-wide-moves",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl TieBreakArgs {
+    pub fn parse(&self) -> Option<SolutionTieBreak> {
+        self.tie_break.map(|tie_break| match tie_break {
+            TieBreakEnum::Lexicographic => SolutionTieBreak::Lexicographic,
+            TieBreakEnum::Random => SolutionTieBreak::Random {
+                seed: self.tie_break_seed,
+            },
+            TieBreakEnum::FewerWideMoves => SolutionTieBreak::FewerWideMoves,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
 pub enum VerbosityLevel {
     Silent,
@@ -124,6 +378,15 @@ pub struct VerbosityArgs {
     pub verbosity: Option<VerbosityLevel>,
 }
 
+#[derive(Args, Debug)]
+pub struct NotationArgs {
+    /// Accept wide moves in any notation dialect for input algs, and use this
+    /// dialect for wide moves in output algs. If not specified, algs are used
+    /// exactly as written.
+    #[clap(long)]
+    pub notation: Option<NotationDialect>,
+}
+
 #[derive(Args, Debug)]
 pub struct GeneratorArgs {
     /// A comma-separated list of moves to use. All multiples of these
@@ -192,7 +455,7 @@ pub struct SearchPersistenceArgs {
     pub cache_dir: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, ValueEnum, Serialize, Deserialize)]
 pub enum EnableAutoAlwaysNeverValueEnum {
     Auto,
     Never,
@@ -207,6 +470,14 @@ impl EnableAutoAlwaysNeverValueEnum {
             EnableAutoAlwaysNeverValueEnum::Always => true,
         }
     }
+
+    /// Resolves `auto` (or a missing value) to `always`.
+    pub fn or_always(value: Option<Self>) -> Self {
+        match value {
+            Some(EnableAutoAlwaysNeverValueEnum::Never) => EnableAutoAlwaysNeverValueEnum::Never,
+            _ => EnableAutoAlwaysNeverValueEnum::Always,
+        }
+    }
 }
 
 impl Display for EnableAutoAlwaysNeverValueEnum {
@@ -235,6 +506,11 @@ pub struct MemoryArgs {
     /// Memory to use in MiB. See `README.md` for advice on how to tune memory usage.
     #[clap(long = "memory-MiB", help_heading = "Performance"/* , visible_short_alias = 'm' */, id = "MEBIBYTES")]
     pub memory_mebibytes: Option<usize>,
+
+    /// Limit prune tables to a fixed size (at the cost of slower deep
+    /// searches). Defaults to `reduced` on 32-bit targets and `full` otherwise.
+    #[clap(long, help_heading = "Performance")]
+    pub table_memory_mode: Option<TableMemoryMode>,
 }
 
 #[derive(Args, Debug)]
@@ -315,6 +591,101 @@ pub struct CanonicalAlgsArgs {
     pub performance_args: PerformanceArgs,
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+pub enum PatternFormat {
+    /// Pattern JSON (the same format as scramble files).
+    Json,
+    /// One line per orbit, listing the pieces and orientations.
+    Text,
+}
+
+impl Display for PatternFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            PatternFormat::Json => "json",
+            PatternFormat::Text => "text",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// The alg to apply.
+    #[clap(long)]
+    pub alg: String,
+
+    #[clap(long, default_value_t = PatternFormat::Json)]
+    pub format: PatternFormat,
+
+    #[command(flatten)]
+    pub notation_args: NotationArgs,
+
+    #[command(flatten)]
+    pub start_pattern_args: StartPatternArgs,
+
+    #[command(flatten)]
+    pub input_args: InputDefFileOnlyArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct PrettyPatternsArgs {
+    #[clap(long)]
+    pub min_depth: Option<usize>,
+
+    /// There is no pruning, so keep this small.
+    #[clap(long)]
+    pub max_depth: usize,
+
+    /// Only report patterns that are unchanged by at least this many of the
+    /// symmetries (including the identity, if present).
+    #[clap(long, default_value_t = 2)]
+    pub min_num_symmetries: usize,
+
+    /// A comma-separated list of symmetries, as algs for the puzzle. Defaults
+    /// to the 24 rotations of a cube (which requires `x`, `y`, and `z` moves).
+    #[clap(long)]
+    pub symmetries: Option<String>,
+
+    #[clap(long)]
+    pub max_num_results: Option<usize>,
+
+    #[command(flatten)]
+    pub generator_args: GeneratorArgs,
+
+    #[command(flatten)]
+    pub metric_args: MetricArgs,
+
+    #[command(flatten)]
+    pub start_pattern_args: StartPatternArgs,
+
+    #[command(flatten)]
+    pub input_args: InputDefFileOnlyArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct EstimateSearchSpaceArgs {
+    /// The length of the sampled move sequences. This should be long enough
+    /// for random sequences to reach most patterns.
+    #[clap(long)]
+    pub max_depth: usize,
+
+    #[clap(long, default_value_t = 100000)]
+    pub num_samples: usize,
+
+    #[command(flatten)]
+    pub generator_args: GeneratorArgs,
+
+    #[command(flatten)]
+    pub metric_args: MetricArgs,
+
+    #[command(flatten)]
+    pub start_pattern_args: StartPatternArgs,
+
+    #[command(flatten)]
+    pub input_args: InputDefFileOnlyArgs,
+}
+
 #[derive(Clone, Args, Debug)]
 pub struct MetricArgs {
     #[clap(long, default_value_t = MetricEnum::Hand)]
@@ -428,6 +799,8 @@ pub struct ServeCommandArgs {
     #[command(flatten)]
     pub performance_args: PerformanceArgs,
     #[command(flatten)]
+    pub search_persistence_args: SearchPersistenceArgs,
+    #[command(flatten)]
     pub verbosity_args: VerbosityArgs,
 }
 
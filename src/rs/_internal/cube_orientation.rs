@@ -0,0 +1,139 @@
+use std::hash::BuildHasher;
+
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle, KTransformation},
+};
+
+use crate::_internal::{PuzzleError, CUBE_ORIENTATION_ROTATIONS};
+
+const NUM_CUBE_FACES: u8 = 6;
+
+/// Rotates NxNxN patterns into a canonical orientation, so that patterns
+/// which only differ by a whole-puzzle rotation have the same representative
+/// (e.g. for database keys, or recognizing cases in any orientation).
+///
+/// For odd cubes (with a fixed center on each face), this is the orientation
+/// with the centers where they are in the default pattern (e.g. white top,
+/// green front). Otherwise (e.g. for even cubes, or a centerless 3x3x3), this
+/// is the orientation that gives the lexicographically smallest pattern.
+pub struct CubeOrientationCanonicalizer {
+    kpuzzle: KPuzzle,
+    rotations: Vec<(Alg, KTransformation)>,
+    fixed_centers_orbit_index: Option<usize>,
+}
+
+impl CubeOrientationCanonicalizer {
+    /// The definition must include `x`, `y`, and `z`.
+    pub fn try_new(kpuzzle: &KPuzzle) -> Result<Self, PuzzleError> {
+        let rotations = CUBE_ORIENTATION_ROTATIONS
+            .iter()
+            .map(|rotation| {
+                let rotation = rotation.parse::<Alg>().map_err(|_| PuzzleError {
+                    description: format!("Invalid rotation: {}", rotation),
+                })?;
+                let transformation =
+                    kpuzzle
+                        .transformation_from_alg(&rotation)
+                        .map_err(|_| PuzzleError {
+                            description: format!("Invalid rotation for the puzzle: {}", rotation),
+                        })?;
+                Ok((rotation, transformation))
+            })
+            .collect::<Result<Vec<(Alg, KTransformation)>, PuzzleError>>()?;
+        // The fixed centers are the only orbit of an NxNxN with one distinct piece per face.
+        let default_pattern = kpuzzle.default_pattern();
+        let fixed_centers_orbit_index = kpuzzle.orbit_info_iter().position(|orbit_info| {
+            orbit_info.num_pieces == NUM_CUBE_FACES
+                && (0..NUM_CUBE_FACES).all(|i| default_pattern.get_piece(orbit_info, i) == i)
+        });
+        Ok(Self {
+            kpuzzle: kpuzzle.clone(),
+            rotations,
+            fixed_centers_orbit_index,
+        })
+    }
+
+    fn lexicographic_key(&self, pattern: &KPattern) -> Vec<u8> {
+        let mut key = Vec::<u8>::new();
+        for orbit_info in self.kpuzzle.orbit_info_iter() {
+            for i in 0..orbit_info.num_pieces {
+                let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+                key.push(pattern.get_piece(orbit_info, i));
+                key.push(orientation_with_mod.orientation);
+                key.push(orientation_with_mod.orientation_mod);
+            }
+        }
+        key
+    }
+
+    /// Returns the rotation (one of `CUBE_ORIENTATION_ROTATIONS`) that takes
+    /// the pattern to its canonical orientation, along with the rotated
+    /// pattern.
+    pub fn canonicalize(&self, pattern: &KPattern) -> (Alg, KPattern) {
+        let rotated_patterns = self.rotations.iter().map(|(rotation, transformation)| {
+            (rotation, pattern.apply_transformation(transformation))
+        });
+        if let Some(orbit_index) = self.fixed_centers_orbit_index {
+            let orbit_info = &self.kpuzzle.data.ordered_orbit_info[orbit_index];
+            let default_pattern = self.kpuzzle.default_pattern();
+            if let Some((rotation, rotated_pattern)) =
+                rotated_patterns.clone().find(|(_, rotated_pattern)| {
+                    (0..orbit_info.num_pieces).all(|i| {
+                        rotated_pattern.get_piece(orbit_info, i)
+                            == default_pattern.get_piece(orbit_info, i)
+                    })
+                })
+            {
+                return (rotation.clone(), rotated_pattern);
+            }
+            // The centers can't be rotated into place (e.g. if some of them
+            // are indistinguishable), so we fall back to the general case.
+        }
+        let (rotation, rotated_pattern) = rotated_patterns
+            .min_by_key(|(_, rotated_pattern)| self.lexicographic_key(rotated_pattern))
+            .unwrap();
+        (rotation.clone(), rotated_pattern)
+    }
+
+    /// A hash of the pattern that is the same for all whole-puzzle rotations
+    /// of it (e.g. for pattern databases, or detecting duplicate scrambles).
+    pub fn rotation_invariant_hash(&self, pattern: &KPattern) -> u64 {
+        let (_, canonical_pattern) = self.canonicalize(pattern);
+        cityhasher::CityHasher::new().hash_one(unsafe { canonical_pattern.byte_slice() })
+    }
+
+    pub fn are_equivalent_up_to_rotation(&self, pattern1: &KPattern, pattern2: &KPattern) -> bool {
+        self.canonicalize(pattern1).1 == self.canonicalize(pattern2).1
+    }
+}
+
+#[test]
+fn cube_orientation_canonicalizer_test() {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let canonicalizer = CubeOrientationCanonicalizer::try_new(kpuzzle).unwrap();
+    let pattern_from_alg = |alg: &str| {
+        kpuzzle.default_pattern().apply_transformation(
+            &kpuzzle
+                .transformation_from_alg(&alg.parse().unwrap())
+                .unwrap(),
+        )
+    };
+    let (rotation, canonical_pattern) = canonicalizer.canonicalize(&pattern_from_alg("R U x y"));
+    assert!(canonical_pattern == pattern_from_alg("R U"));
+    assert!(pattern_from_alg(&format!("R U x y {}", rotation)) == canonical_pattern);
+    // Already in the canonical orientation.
+    let (rotation, _) = canonicalizer.canonicalize(&pattern_from_alg("R U"));
+    assert_eq!(rotation.to_string(), "");
+
+    assert_eq!(
+        canonicalizer.rotation_invariant_hash(&pattern_from_alg("R U z'")),
+        canonicalizer.rotation_invariant_hash(&pattern_from_alg("R U"))
+    );
+    assert!(canonicalizer
+        .are_equivalent_up_to_rotation(&pattern_from_alg("y2 R U"), &pattern_from_alg("L U")));
+    assert!(!canonicalizer
+        .are_equivalent_up_to_rotation(&pattern_from_alg("R U"), &pattern_from_alg("U R")));
+}
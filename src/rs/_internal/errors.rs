@@ -1,7 +1,11 @@
+use std::fmt::Display;
+
 #[derive(derive_more::From, Debug)]
 pub enum CommandError {
-    SearchError(PuzzleError),
+    PuzzleError(PuzzleError),
+    SearchError(SearchError),
     ArgumentError(ArgumentError),
+    TwsearchError(TwsearchError),
 }
 
 #[derive(Debug)]
@@ -29,3 +33,98 @@ impl From<&str> for PuzzleError {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct SearchError {
+    pub description: String,
+}
+
+impl From<&str> for SearchError {
+    fn from(description: &str) -> Self {
+        Self {
+            description: description.to_owned(),
+        }
+    }
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// An error from the search API, categorized so that callers can handle
+/// different kinds of failure without parsing the description.
+#[derive(Debug)]
+pub enum TwsearchError {
+    /// The puzzle definition can't be used (e.g. a move is missing).
+    InvalidDefinition { description: String },
+    /// The generators can't be used with the puzzle (e.g. an unknown move).
+    InvalidGenerators { description: String },
+    /// A search (or target) pattern can't be used, e.g. because it can't be
+    /// reached from the target pattern.
+    InvalidPattern { description: String },
+    /// The search stopped before finding any solutions (e.g. it reached the
+    /// maximum depth or timed out).
+    SearchLimitReached { description: String },
+}
+
+impl TwsearchError {
+    pub fn description(&self) -> &str {
+        match self {
+            TwsearchError::InvalidDefinition { description } => description,
+            TwsearchError::InvalidGenerators { description } => description,
+            TwsearchError::InvalidPattern { description } => description,
+            TwsearchError::SearchLimitReached { description } => description,
+        }
+    }
+}
+
+impl Display for TwsearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let category = match self {
+            TwsearchError::InvalidDefinition { .. } => "Invalid definition",
+            TwsearchError::InvalidGenerators { .. } => "Invalid generators",
+            TwsearchError::InvalidPattern { .. } => "Invalid pattern",
+            TwsearchError::SearchLimitReached { .. } => "Search limit reached",
+        };
+        write!(f, "{}: {}", category, self.description())
+    }
+}
+
+impl std::error::Error for TwsearchError {}
+
+// These allow code that uses the older error types to propagate categorized errors with `?`.
+impl From<TwsearchError> for PuzzleError {
+    fn from(error: TwsearchError) -> Self {
+        Self {
+            description: error.to_string(),
+        }
+    }
+}
+
+impl From<TwsearchError> for SearchError {
+    fn from(error: TwsearchError) -> Self {
+        Self {
+            description: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use cubing::kpuzzle::{KPattern, KPuzzle, KTransformation};
+
+/// A puzzle that can be searched using `generic_ida_star(…)`. This is
+/// implemented for `KPuzzle`, and can be implemented for puzzles that use
+/// their own representation of patterns (e.g. coordinates for a phase of a
+/// multi-phase solver).
+///
+/// See `examples/generic_puzzle.rs` for an implementation.
+pub trait GenericPuzzle {
+    type Pattern: Clone + PartialEq;
+    type Transformation: Clone;
+
+    /// Returns the pattern with the transformation applied.
+    fn pattern_apply_transformation(
+        &self,
+        pattern: &Self::Pattern,
+        transformation: &Self::Transformation,
+    ) -> Self::Pattern;
+
+    /// Returns the inverse of the transformation. This is used to search
+    /// backwards from the target pattern (see `generic_ida_star_backward(…)`).
+    fn transformation_invert(&self, transformation: &Self::Transformation) -> Self::Transformation;
+}
+
+impl GenericPuzzle for KPuzzle {
+    type Pattern = KPattern;
+    type Transformation = KTransformation;
+
+    fn pattern_apply_transformation(
+        &self,
+        pattern: &KPattern,
+        transformation: &KTransformation,
+    ) -> KPattern {
+        pattern.apply_transformation(transformation)
+    }
+
+    fn transformation_invert(&self, transformation: &KTransformation) -> KTransformation {
+        transformation.invert()
+    }
+}
+
+fn generic_ida_star_recurse<P: GenericPuzzle>(
+    puzzle: &P,
+    pattern: &P::Pattern,
+    target_pattern: &P::Pattern,
+    moves: &[P::Transformation],
+    remaining_depth: usize,
+    heuristic: &impl Fn(&P::Pattern) -> usize,
+    move_indices: &mut Vec<usize>,
+) -> bool {
+    if remaining_depth == 0 {
+        return pattern == target_pattern;
+    }
+    if heuristic(pattern) > remaining_depth {
+        return false;
+    }
+    for (move_index, r#move) in moves.iter().enumerate() {
+        move_indices.push(move_index);
+        if generic_ida_star_recurse(
+            puzzle,
+            &puzzle.pattern_apply_transformation(pattern, r#move),
+            target_pattern,
+            moves,
+            remaining_depth - 1,
+            heuristic,
+            move_indices,
+        ) {
+            return true;
+        }
+        move_indices.pop();
+    }
+    false
+}
+
+/// Returns the indices (into `moves`) of an optimal solution from
+/// `start_pattern` to `target_pattern`, if there is one within `max_depth`
+/// moves. `heuristic` must never overestimate the distance to the target
+/// (`|_| 0` is always valid, but slow).
+///
+/// Unlike `IDFSearch`, this does not use a canonical FSM or a prune table, so
+/// it is only suitable for small searches (or with a strong heuristic).
+pub fn generic_ida_star<P: GenericPuzzle>(
+    puzzle: &P,
+    start_pattern: &P::Pattern,
+    target_pattern: &P::Pattern,
+    moves: &[P::Transformation],
+    max_depth: usize,
+    heuristic: impl Fn(&P::Pattern) -> usize,
+) -> Option<Vec<usize>> {
+    let mut move_indices = Vec::<usize>::new();
+    for depth in 0..=max_depth {
+        if generic_ida_star_recurse(
+            puzzle,
+            start_pattern,
+            target_pattern,
+            moves,
+            depth,
+            &heuristic,
+            &mut move_indices,
+        ) {
+            return Some(move_indices);
+        }
+    }
+    None
+}
+
+/// Like `generic_ida_star(…)`, but expands backwards from `target_pattern`
+/// (applying the inverse of each move) until it reaches `start_pattern`.
+/// `heuristic` must never overestimate the distance *from* `start_pattern`
+/// (e.g. a pruning table built outwards from a solved start pattern).
+///
+/// The solution is returned in the same form as for `generic_ida_star(…)`:
+/// the indices (into `moves`) of the moves from `start_pattern` to
+/// `target_pattern`.
+pub fn generic_ida_star_backward<P: GenericPuzzle>(
+    puzzle: &P,
+    start_pattern: &P::Pattern,
+    target_pattern: &P::Pattern,
+    moves: &[P::Transformation],
+    max_depth: usize,
+    heuristic: impl Fn(&P::Pattern) -> usize,
+) -> Option<Vec<usize>> {
+    let inverse_moves: Vec<P::Transformation> = moves
+        .iter()
+        .map(|r#move| puzzle.transformation_invert(r#move))
+        .collect();
+    let mut move_indices = generic_ida_star(
+        puzzle,
+        target_pattern,
+        start_pattern,
+        &inverse_moves,
+        max_depth,
+        heuristic,
+    )?;
+    // Undoing the moves from the target, last move first.
+    move_indices.reverse();
+    Some(move_indices)
+}
+
+#[test]
+fn generic_ida_star_backward_test() {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let moves: Vec<KTransformation> = ["R", "U", "F"]
+        .iter()
+        .map(|r#move| {
+            kpuzzle
+                .transformation_from_move(&r#move.parse().unwrap())
+                .unwrap()
+        })
+        .collect();
+    let start_pattern = kpuzzle.default_pattern();
+    // Quarter turns in one direction only, so that the inverse moves aren't among the moves.
+    let target_pattern = start_pattern.apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&"R U2 F".parse().unwrap())
+            .unwrap(),
+    );
+    let apply_move_indices = |move_indices: &[usize]| {
+        move_indices
+            .iter()
+            .fold(start_pattern.clone(), |pattern, move_index| {
+                pattern.apply_transformation(&moves[*move_index])
+            })
+    };
+
+    let forward =
+        generic_ida_star(kpuzzle, &start_pattern, &target_pattern, &moves, 4, |_| 0).unwrap();
+    let backward =
+        generic_ida_star_backward(kpuzzle, &start_pattern, &target_pattern, &moves, 4, |_| 0)
+            .unwrap();
+    assert_eq!(backward.len(), forward.len());
+    assert!(apply_move_indices(&forward) == target_pattern);
+    assert!(apply_move_indices(&backward) == target_pattern);
+}
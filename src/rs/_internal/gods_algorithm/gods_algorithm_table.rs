@@ -121,9 +121,10 @@ impl GodsAlgorithmSearch {
             progress_bar.set_style(progress_bar_style);
             progress_bar.set_prefix(current_depth.to_string());
 
-            let num_to_test_at_current_depth: usize =
-                num_last_depth_patterns * self.search_moves.flat.len();
-            let mut num_tested_at_current_depth = 0;
+            // These can exceed `u32::MAX` (e.g. for a 32-bit `usize` in WASM).
+            let num_to_test_at_current_depth: u64 = (num_last_depth_patterns as u64)
+                .saturating_mul(self.search_moves.flat.len() as u64);
+            let mut num_tested_at_current_depth: u64 = 0;
             let mut patterns_at_current_depth = BulkQueue::new(None);
             for queue_item in last_depth_patterns.into_iter() {
                 for move_class_index in &self.canonical_fsm.move_class_indices {
@@ -134,12 +135,13 @@ impl GodsAlgorithmSearch {
                     let next_state = match next_state {
                         Some(next_state) => next_state,
                         None => {
-                            num_tested_at_current_depth += moves_in_class.len();
+                            num_tested_at_current_depth = num_tested_at_current_depth
+                                .saturating_add(moves_in_class.len() as u64);
                             continue;
                         }
                     };
                     for move_info in moves_in_class {
-                        num_tested_at_current_depth += 1;
+                        num_tested_at_current_depth = num_tested_at_current_depth.saturating_add(1);
                         let new_pattern = queue_item
                             .pattern
                             .apply_transformation(&move_info.inverse_transformation);
@@ -157,20 +159,14 @@ impl GodsAlgorithmSearch {
                             .insert(new_pattern, current_depth);
 
                         if num_tested_at_current_depth % 1000 == 0 {
-                            progress_bar
-                                .set_length(num_to_test_at_current_depth.try_into().unwrap());
-                            progress_bar.set_position(num_tested_at_current_depth as u64);
+                            progress_bar.set_length(num_to_test_at_current_depth);
+                            progress_bar.set_position(num_tested_at_current_depth);
                             progress_bar.set_message(format!(
                                 "{} patterns ({} cumulative) — {} remaining candidates",
                                 format_num!(patterns_at_current_depth.size()),
                                 format_num!(num_patterns_total + patterns_at_current_depth.size()), // TODO: increment before
-                                format_num!(
-                                    num_to_test_at_current_depth
-                                        - std::convert::TryInto::<usize>::try_into(
-                                            num_tested_at_current_depth
-                                        )
-                                        .unwrap()
-                                )
+                                format_num!(num_to_test_at_current_depth
+                                    .saturating_sub(num_tested_at_current_depth))
                             ))
                         }
                     }
@@ -10,5 +10,50 @@ pub use gods_algorithm::*;
 mod errors;
 pub use errors::*;
 
+mod alg_move_counts;
+pub use alg_move_counts::*;
+
+mod alg_transformations;
+pub use alg_transformations::*;
+
+mod cube_orientation;
+pub use cube_orientation::*;
+
+mod breadth_first_search;
+pub use breadth_first_search::*;
+
+mod generic_puzzle;
+pub use generic_puzzle::*;
+
+mod notation_dialect;
+pub use notation_dialect::*;
+
+mod orbit_info_by_name;
+pub use orbit_info_by_name::*;
+
+mod parallel_options;
+pub use parallel_options::*;
+
+mod parity;
+pub use parity::*;
+
+mod pattern_algebra;
+pub use pattern_algebra::*;
+
+mod pattern_orbit;
+pub use pattern_orbit::*;
+
+mod pattern_display;
+pub use pattern_display::*;
+
+mod pattern_symmetry;
+pub use pattern_symmetry::*;
+
 mod search;
 pub use search::*;
+
+mod table_memory_mode;
+pub use table_memory_mode::*;
+
+mod trace_recorder;
+pub use trace_recorder::*;
@@ -0,0 +1,104 @@
+use std::{fmt::Display, sync::Arc};
+
+use clap::ValueEnum;
+use cubing::alg::{Alg, AlgNode, Move, QuantumMove};
+use serde::{Deserialize, Serialize};
+
+const CUBE_FACES: &str = "URFDLB";
+
+/// How wide moves on cubes are written. Twsearch uses `Wca` internally (and
+/// in its puzzle definitions), so algs in other dialects should be converted
+/// to it before being applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotationDialect {
+    /// `Rw` for 2 layers, `3Rw` for 3 layers.
+    Wca,
+    /// `r` for 2 layers, `3r` for 3 layers.
+    Sign,
+    /// `2Rw` for 2 layers, `3Rw` for 3 layers.
+    NumberedWca,
+}
+
+impl Default for NotationDialect {
+    fn default() -> Self {
+        Self::Wca
+    }
+}
+
+impl Display for NotationDialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            NotationDialect::Wca => "wca",
+            NotationDialect::Sign => "sign",
+            NotationDialect::NumberedWca => "numbered-wca",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Returns the face and number of layers if the quantum is a wide move in any dialect.
+fn parse_wide_quantum(quantum_str: &str) -> Option<(char, usize)> {
+    let num_digits = quantum_str
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    let (depth_str, rest) = quantum_str.split_at(num_digits);
+    let depth = if depth_str.is_empty() {
+        2
+    } else {
+        depth_str.parse::<usize>().ok()?
+    };
+    let mut chars = rest.chars();
+    let face = match (chars.next(), chars.next(), chars.next()) {
+        (Some(c), None, None) if c.is_ascii_lowercase() => c.to_ascii_uppercase(),
+        (Some(c), Some('w'), None) if c.is_ascii_uppercase() => c,
+        _ => return None,
+    };
+    if !CUBE_FACES.contains(face) {
+        return None;
+    }
+    Some((face, depth))
+}
+
+impl NotationDialect {
+    fn format_wide_quantum(&self, face: char, depth: usize) -> String {
+        match (self, depth) {
+            (NotationDialect::Wca, 2) => format!("{}w", face),
+            (NotationDialect::Wca, depth) => format!("{}{}w", depth, face),
+            (NotationDialect::Sign, 2) => face.to_ascii_lowercase().to_string(),
+            (NotationDialect::Sign, depth) => format!("{}{}", depth, face.to_ascii_lowercase()),
+            (NotationDialect::NumberedWca, depth) => format!("{}{}w", depth, face),
+        }
+    }
+
+    /// Writes a wide move in this dialect. Other moves are returned unchanged.
+    pub fn convert_move(&self, r#move: &Move) -> Move {
+        let (face, depth) = match parse_wide_quantum(&r#move.quantum.to_string()) {
+            Some(face_and_depth) => face_and_depth,
+            None => return r#move.clone(),
+        };
+        match self.format_wide_quantum(face, depth).parse::<QuantumMove>() {
+            Ok(quantum) => Move {
+                quantum: Arc::new(quantum),
+                amount: r#move.amount,
+            },
+            Err(_) => r#move.clone(),
+        }
+    }
+
+    /// Writes all the wide moves at the top level of the alg in this dialect.
+    /// Moves nested inside other nodes (e.g. groupings) are left unchanged.
+    pub fn convert_alg(&self, alg: &Alg) -> Alg {
+        Alg {
+            nodes: alg
+                .nodes
+                .iter()
+                .map(|node| match node {
+                    AlgNode::MoveNode(r#move) => self.convert_move(r#move).into(),
+                    node => node.clone(),
+                })
+                .collect(),
+        }
+    }
+}
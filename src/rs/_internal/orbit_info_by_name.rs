@@ -0,0 +1,25 @@
+use cubing::kpuzzle::{KPuzzle, KPuzzleOrbitInfo};
+
+use crate::_internal::PuzzleError;
+
+/// Looks up orbits by name, so that code doesn't depend on the order of the
+/// orbits in a definition.
+pub trait OrbitInfoByName {
+    fn orbit_info_by_name(&self, orbit_name: &str) -> Result<&KPuzzleOrbitInfo, PuzzleError>;
+}
+
+impl OrbitInfoByName for KPuzzle {
+    // Puzzles only have a handful of orbits, so a linear scan is as fast as a map (and doesn't need to be kept in sync with the definition).
+    fn orbit_info_by_name(&self, orbit_name: &str) -> Result<&KPuzzleOrbitInfo, PuzzleError> {
+        self.data
+            .ordered_orbit_info
+            .iter()
+            .find(|orbit_info| orbit_info.name.0 == orbit_name)
+            .ok_or_else(|| PuzzleError {
+                description: format!(
+                    "Orbit does not exist in the puzzle definition: {}",
+                    orbit_name
+                ),
+            })
+    }
+}
@@ -0,0 +1,33 @@
+/// Options for work that can be split across threads when the `parallel`
+/// feature is enabled (e.g. batch scrambles and building coordinate pruning
+/// tables). Without the feature (e.g. for WASM builds), everything runs on the
+/// calling thread and these options have no effect.
+#[derive(Clone, Debug, Default)]
+pub struct ParallelOptions {
+    /// Defaults to the global thread pool (one thread per core).
+    pub num_threads: Option<usize>,
+}
+
+impl ParallelOptions {
+    /// Runs `f`, such that any parallel work inside it uses the configured
+    /// number of threads.
+    #[cfg(feature = "parallel")]
+    pub fn install<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        let Some(num_threads) = self.num_threads else {
+            return f();
+        };
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+        {
+            Ok(thread_pool) => thread_pool.install(f),
+            // Fall back to the global thread pool rather than failing the work itself.
+            Err(_) => f(),
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub fn install<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        f()
+    }
+}
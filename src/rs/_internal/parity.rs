@@ -0,0 +1,118 @@
+use cubing::kpuzzle::{KPattern, KPuzzleOrbitInfo};
+
+#[cfg(feature = "search-core")]
+use crate::search_core::{PackedPattern, PackedPuzzle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicParity {
+    Even,
+    Odd,
+}
+
+impl BasicParity {
+    pub fn flip(&mut self) {
+        let new_value = match self {
+            BasicParity::Even => BasicParity::Odd,
+            BasicParity::Odd => BasicParity::Even,
+        };
+        *self = new_value
+    }
+
+    pub fn is_odd(&self) -> bool {
+        *self == BasicParity::Odd
+    }
+}
+
+/// The parity of a permutation, given as the values at each position (e.g.
+/// the pieces in an orbit). This counts inversions, so the values only need
+/// to be distinct (not `0..n`).
+pub fn basic_parity<T: PartialOrd>(permutation: &[T]) -> BasicParity {
+    let mut parity = BasicParity::Even;
+    // TODO: we can save a tiny bit of speed by avoid iterating over the last element for `p1`.
+    for (i, p2) in permutation.iter().enumerate().skip(1) {
+        for p1 in &permutation[0..i] {
+            if p1 > p2 {
+                parity.flip();
+            }
+        }
+    }
+    parity
+}
+
+/// Swaps the first two values if needed, so that the permutation has the
+/// given parity.
+pub fn set_parity<T: PartialOrd>(permutation: &mut [T], target_parity: BasicParity) {
+    let parity = basic_parity(permutation);
+    if parity != target_parity {
+        // Since odd parity is only possible with more than 1 element in the permutation, we can safely swap the first two elements.
+        permutation.swap(0, 1);
+    };
+}
+
+/// The permutation parity of the pieces in an orbit of the pattern. This is
+/// only meaningful if the pieces in the orbit are distinct.
+pub fn orbit_parity(pattern: &KPattern, orbit_info: &KPuzzleOrbitInfo) -> BasicParity {
+    let pieces: Vec<u8> = (0..orbit_info.num_pieces)
+        .map(|i| pattern.get_piece(orbit_info, i))
+        .collect();
+    basic_parity(&pieces)
+}
+
+/// Like `orbit_parity(…)`, for an orbit of a packed pattern.
+#[cfg(feature = "search-core")]
+pub fn packed_orbit_parity(
+    packed_puzzle: &PackedPuzzle,
+    packed_pattern: &PackedPattern,
+    orbit_index: usize,
+) -> BasicParity {
+    basic_parity(packed_puzzle.orbit_pieces(packed_pattern, orbit_index))
+}
+
+#[test]
+fn parity_test() {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    use crate::_internal::OrbitInfoByName;
+
+    assert_eq!(basic_parity(&[0, 1, 2, 3]), BasicParity::Even);
+    assert_eq!(basic_parity(&[1, 0, 2, 3]), BasicParity::Odd);
+    assert_eq!(basic_parity(&[2, 0, 1]), BasicParity::Even);
+    // Values don't need to be `0..n`.
+    assert_eq!(basic_parity(&['c', 'a', 'z']), BasicParity::Odd);
+
+    let mut permutation = [0, 1, 2, 3];
+    set_parity(&mut permutation, BasicParity::Odd);
+    assert_eq!(basic_parity(&permutation), BasicParity::Odd);
+
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let pattern = kpuzzle.default_pattern().apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&"R".parse().unwrap())
+            .unwrap(),
+    );
+    // A quarter turn is a 4-cycle of corners.
+    let orbit_info = kpuzzle.orbit_info_by_name("CORNERS").unwrap();
+    assert_eq!(orbit_parity(&pattern, orbit_info), BasicParity::Odd);
+}
+
+#[cfg(feature = "search-core")]
+#[test]
+fn packed_orbit_parity_test() {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    // 2x2x2 patterns can be packed (unlike 3x3x3 patterns, which have orientation mods for centers).
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let pattern = kpuzzle.default_pattern().apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&"R".parse().unwrap())
+            .unwrap(),
+    );
+    let packed_puzzle = PackedPuzzle::try_from_kpuzzle(kpuzzle).unwrap();
+    let packed_pattern = PackedPattern::try_from(&pattern).unwrap();
+    for (orbit_index, orbit_info) in kpuzzle.orbit_info_iter().enumerate() {
+        assert_eq!(
+            packed_orbit_parity(&packed_puzzle, &packed_pattern, orbit_index),
+            orbit_parity(&pattern, orbit_info),
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle, KTransformation, OrientationWithMod},
+};
+
+use crate::_internal::{normalized_pattern, PuzzleError};
+
+fn transformation_for_alg(kpuzzle: &KPuzzle, alg: &Alg) -> Result<KTransformation, PuzzleError> {
+    kpuzzle
+        .transformation_from_alg(alg)
+        .map_err(|_| PuzzleError {
+            description: format!("Invalid alg for the puzzle: {}", alg),
+        })
+}
+
+// Applies the effect of `transformation_pattern` (relative to the default
+// pattern) to `pattern`. This requires the default pattern to have distinct,
+// unoriented pieces, so that a pattern fully describes how it was reached.
+pub(crate) fn apply_pattern_as_transformation(
+    pattern: &KPattern,
+    transformation_pattern: &KPattern,
+) -> KPattern {
+    let mut applied = pattern.clone();
+    for orbit_info in pattern.kpuzzle().orbit_info_iter() {
+        for i in 0..orbit_info.num_pieces {
+            let source = transformation_pattern.get_piece(orbit_info, i);
+            let orientation_delta = transformation_pattern
+                .get_orientation_with_mod(orbit_info, i)
+                .orientation;
+            applied.set_piece(orbit_info, i, pattern.get_piece(orbit_info, source));
+            let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, source);
+            applied.set_orientation_with_mod(
+                orbit_info,
+                i,
+                &OrientationWithMod {
+                    orientation: (orientation_with_mod.orientation + orientation_delta)
+                        % orbit_info.num_orientations,
+                    orientation_mod: 0,
+                },
+            );
+        }
+    }
+    applied
+}
+
+/// Common ways of applying algs to a pattern, so that solvers and analysis
+/// tools don't need to look up, invert, and apply transformations by hand.
+pub trait PatternAlgebra {
+    /// Applies the inverse of `alg` (i.e. undoes `alg`).
+    fn apply_inverse_alg(&self, alg: &Alg) -> Result<KPattern, PuzzleError>;
+
+    /// For a pattern reached by `S` from the default pattern, returns the
+    /// pattern reached by `A' S A` (e.g. to view a scramble from another
+    /// orientation, with `A` as a rotation). This requires the default
+    /// pattern to have distinct, unoriented pieces.
+    fn conjugate_by_alg(&self, alg: &Alg) -> Result<KPattern, PuzzleError>;
+
+    /// Applies the commutator `[A, B]` (i.e. `A B A' B'`).
+    fn apply_commutator(&self, a: &Alg, b: &Alg) -> Result<KPattern, PuzzleError>;
+}
+
+impl PatternAlgebra for KPattern {
+    fn apply_inverse_alg(&self, alg: &Alg) -> Result<KPattern, PuzzleError> {
+        Ok(self.apply_transformation(&transformation_for_alg(self.kpuzzle(), alg)?.invert()))
+    }
+
+    fn conjugate_by_alg(&self, alg: &Alg) -> Result<KPattern, PuzzleError> {
+        let kpuzzle = self.kpuzzle();
+        if kpuzzle.default_pattern() != normalized_pattern(kpuzzle) {
+            return Err(
+                "Conjugating a pattern requires the default pattern to have distinct, unoriented pieces.".into(),
+            );
+        }
+        let transformation = transformation_for_alg(kpuzzle, alg)?;
+        let conjugated = kpuzzle
+            .default_pattern()
+            .apply_transformation(&transformation.invert());
+        Ok(
+            apply_pattern_as_transformation(&conjugated, self)
+                .apply_transformation(&transformation),
+        )
+    }
+
+    fn apply_commutator(&self, a: &Alg, b: &Alg) -> Result<KPattern, PuzzleError> {
+        let a_transformation = transformation_for_alg(self.kpuzzle(), a)?;
+        let b_transformation = transformation_for_alg(self.kpuzzle(), b)?;
+        Ok(self
+            .apply_transformation(&a_transformation)
+            .apply_transformation(&b_transformation)
+            .apply_transformation(&a_transformation.invert())
+            .apply_transformation(&b_transformation.invert()))
+    }
+}
+
+#[test]
+fn pattern_algebra_test() -> Result<(), PuzzleError> {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let pattern_for = |alg: &str| {
+        kpuzzle
+            .default_pattern()
+            .apply_transformation(&transformation_for_alg(kpuzzle, &alg.parse().unwrap()).unwrap())
+    };
+    let alg = |alg: &str| alg.parse::<Alg>().unwrap();
+
+    assert!(pattern_for("R U").apply_inverse_alg(&alg("R U"))? == kpuzzle.default_pattern());
+    assert!(
+        kpuzzle
+            .default_pattern()
+            .apply_commutator(&alg("R"), &alg("U"))?
+            == pattern_for("R U R' U'")
+    );
+    Ok(())
+}
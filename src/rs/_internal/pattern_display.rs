@@ -0,0 +1,107 @@
+use std::fmt::Display;
+
+use cubing::kpuzzle::{KPattern, KPuzzle, KTransformation};
+
+use crate::_internal::normalized_pattern;
+
+fn join(values: impl Iterator<Item = u8>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Displays a pattern with one line per orbit, listing the piece and
+/// orientation at each position (and the orientation mod, if it is used).
+/// For example:
+///
+/// ```text
+/// EDGES: pieces 0 1 2 3 4 5 6 7 8 9 10 11 / orientation 0 0 0 0 0 0 0 0 0 0 0 0
+/// CORNERS: pieces 0 1 2 3 4 5 6 7 / orientation 0 0 0 0 0 0 0 0
+/// ```
+pub struct KPatternDisplay<'a>(pub &'a KPattern);
+
+impl Display for KPatternDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pattern = self.0;
+        for (orbit_index, orbit_info) in pattern.kpuzzle().orbit_info_iter().enumerate() {
+            if orbit_index > 0 {
+                writeln!(f)?;
+            }
+            let positions = 0..orbit_info.num_pieces;
+            write!(
+                f,
+                "{}: pieces {} / orientation {}",
+                orbit_info.name.0,
+                join(positions.clone().map(|i| pattern.get_piece(orbit_info, i))),
+                join(
+                    positions
+                        .clone()
+                        .map(|i| pattern.get_orientation_with_mod(orbit_info, i).orientation)
+                )
+            )?;
+            if positions.clone().any(|i| {
+                pattern
+                    .get_orientation_with_mod(orbit_info, i)
+                    .orientation_mod
+                    != 0
+            }) {
+                write!(
+                    f,
+                    " / orientation mod {}",
+                    join(positions.map(|i| {
+                        pattern
+                            .get_orientation_with_mod(orbit_info, i)
+                            .orientation_mod
+                    }))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Displays a transformation with one line per orbit, listing the position
+/// that each piece comes from and the orientation added to it. For example
+/// (for `U` on a 3x3x3):
+///
+/// ```text
+/// EDGES: permutation 1 2 3 0 4 5 6 7 8 9 10 11 / orientation delta 0 0 0 0 0 0 0 0 0 0 0 0
+/// CORNERS: permutation 1 2 3 0 4 5 6 7 / orientation delta 0 0 0 0 0 0 0 0
+/// ```
+pub struct KTransformationDisplay<'a> {
+    kpuzzle: &'a KPuzzle,
+    transformation: &'a KTransformation,
+}
+
+impl<'a> KTransformationDisplay<'a> {
+    pub fn new(kpuzzle: &'a KPuzzle, transformation: &'a KTransformation) -> Self {
+        Self {
+            kpuzzle,
+            transformation,
+        }
+    }
+}
+
+impl Display for KTransformationDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Applying the transformation to distinct, unoriented pieces shows where each piece comes from.
+        let applied = normalized_pattern(self.kpuzzle).apply_transformation(self.transformation);
+        for (orbit_index, orbit_info) in self.kpuzzle.orbit_info_iter().enumerate() {
+            if orbit_index > 0 {
+                writeln!(f)?;
+            }
+            let positions = 0..orbit_info.num_pieces;
+            write!(
+                f,
+                "{}: permutation {} / orientation delta {}",
+                orbit_info.name.0,
+                join(positions.clone().map(|i| applied.get_piece(orbit_info, i))),
+                join(
+                    positions.map(|i| applied.get_orientation_with_mod(orbit_info, i).orientation)
+                )
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use cubing::kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo, OrientationWithMod};
+
+use crate::_internal::{orbit_parity, BasicParity, OrbitInfoByName, PuzzleError};
+
+/// Access to a single orbit of a pattern by name (e.g.
+/// `pattern.orbit("EDGES")?.piece(3)`), instead of passing an orbit info and
+/// a raw index to every accessor. Indices are bounds-checked in debug builds.
+pub trait PatternOrbits {
+    fn orbit(&self, orbit_name: &str) -> Result<PatternOrbit<'_>, PuzzleError>;
+    fn orbit_mut(&mut self, orbit_name: &str) -> Result<PatternOrbitMut<'_>, PuzzleError>;
+}
+
+impl PatternOrbits for KPattern {
+    fn orbit(&self, orbit_name: &str) -> Result<PatternOrbit<'_>, PuzzleError> {
+        let orbit_info = self.kpuzzle().orbit_info_by_name(orbit_name)?;
+        Ok(PatternOrbit {
+            pattern: self,
+            orbit_info,
+        })
+    }
+
+    fn orbit_mut(&mut self, orbit_name: &str) -> Result<PatternOrbitMut<'_>, PuzzleError> {
+        // The orbit info is borrowed from the `KPuzzle`, so we keep our own
+        // (cheap) handle to it rather than borrowing it from the pattern.
+        let kpuzzle = self.kpuzzle().clone();
+        let orbit_index = kpuzzle
+            .data
+            .ordered_orbit_info
+            .iter()
+            .position(|orbit_info| orbit_info.name.0 == orbit_name)
+            .ok_or_else(|| PuzzleError {
+                description: format!(
+                    "Orbit does not exist in the puzzle definition: {}",
+                    orbit_name
+                ),
+            })?;
+        Ok(PatternOrbitMut {
+            pattern: self,
+            kpuzzle,
+            orbit_index,
+        })
+    }
+}
+
+fn debug_assert_in_orbit(orbit_info: &KPuzzleOrbitInfo, i: u8) {
+    debug_assert!(
+        i < orbit_info.num_pieces,
+        "Index {} is out of range for orbit {} ({} pieces).",
+        i,
+        orbit_info.name.0,
+        orbit_info.num_pieces
+    );
+}
+
+pub struct PatternOrbit<'a> {
+    pattern: &'a KPattern,
+    orbit_info: &'a KPuzzleOrbitInfo,
+}
+
+impl PatternOrbit<'_> {
+    pub fn num_pieces(&self) -> u8 {
+        self.orbit_info.num_pieces
+    }
+
+    pub fn num_orientations(&self) -> u8 {
+        self.orbit_info.num_orientations
+    }
+
+    pub fn piece(&self, i: u8) -> u8 {
+        debug_assert_in_orbit(self.orbit_info, i);
+        self.pattern.get_piece(self.orbit_info, i)
+    }
+
+    pub fn orientation(&self, i: u8) -> u8 {
+        self.orientation_with_mod(i).orientation
+    }
+
+    pub fn orientation_with_mod(&self, i: u8) -> OrientationWithMod {
+        debug_assert_in_orbit(self.orbit_info, i);
+        self.pattern.get_orientation_with_mod(self.orbit_info, i)
+    }
+
+    /// See `orbit_parity(…)`.
+    pub fn parity(&self) -> BasicParity {
+        orbit_parity(self.pattern, self.orbit_info)
+    }
+}
+
+pub struct PatternOrbitMut<'a> {
+    pattern: &'a mut KPattern,
+    kpuzzle: KPuzzle,
+    orbit_index: usize,
+}
+
+impl PatternOrbitMut<'_> {
+    fn orbit_info(&self) -> &KPuzzleOrbitInfo {
+        &self.kpuzzle.data.ordered_orbit_info[self.orbit_index]
+    }
+
+    pub fn num_pieces(&self) -> u8 {
+        self.orbit_info().num_pieces
+    }
+
+    pub fn num_orientations(&self) -> u8 {
+        self.orbit_info().num_orientations
+    }
+
+    pub fn piece(&self, i: u8) -> u8 {
+        debug_assert_in_orbit(self.orbit_info(), i);
+        self.pattern.get_piece(self.orbit_info(), i)
+    }
+
+    pub fn orientation(&self, i: u8) -> u8 {
+        self.orientation_with_mod(i).orientation
+    }
+
+    pub fn orientation_with_mod(&self, i: u8) -> OrientationWithMod {
+        debug_assert_in_orbit(self.orbit_info(), i);
+        self.pattern.get_orientation_with_mod(self.orbit_info(), i)
+    }
+
+    /// See `orbit_parity(…)`.
+    pub fn parity(&self) -> BasicParity {
+        orbit_parity(self.pattern, self.orbit_info())
+    }
+
+    pub fn set_piece(&mut self, i: u8, piece: u8) {
+        let orbit_info = &self.kpuzzle.data.ordered_orbit_info[self.orbit_index];
+        debug_assert_in_orbit(orbit_info, i);
+        debug_assert!(
+            piece < orbit_info.num_pieces,
+            "Piece {} is out of range for orbit {}.",
+            piece,
+            orbit_info.name.0
+        );
+        self.pattern.set_piece(orbit_info, i, piece);
+    }
+
+    /// Sets the orientation, keeping the current orientation mod.
+    pub fn set_orientation(&mut self, i: u8, orientation: u8) {
+        let orientation_mod = self.orientation_with_mod(i).orientation_mod;
+        self.set_orientation_with_mod(
+            i,
+            &OrientationWithMod {
+                orientation,
+                orientation_mod,
+            },
+        );
+    }
+
+    pub fn set_orientation_with_mod(&mut self, i: u8, orientation_with_mod: &OrientationWithMod) {
+        let orbit_info = &self.kpuzzle.data.ordered_orbit_info[self.orbit_index];
+        debug_assert_in_orbit(orbit_info, i);
+        debug_assert!(
+            orientation_with_mod.orientation < orbit_info.num_orientations,
+            "Orientation {} is out of range for orbit {}.",
+            orientation_with_mod.orientation,
+            orbit_info.name.0
+        );
+        self.pattern
+            .set_orientation_with_mod(orbit_info, i, orientation_with_mod);
+    }
+}
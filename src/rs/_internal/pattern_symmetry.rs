@@ -0,0 +1,101 @@
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle, KTransformation},
+};
+
+use crate::_internal::{
+    apply_pattern_as_transformation, normalized_pattern, PuzzleError, CUBE_ORIENTATION_ROTATIONS,
+};
+
+/// A set of symmetries of a puzzle (e.g. whole-puzzle rotations), given as algs
+/// in its definition. Reflections are supported if the definition includes
+/// moves for them.
+pub struct PatternSymmetries {
+    kpuzzle: KPuzzle,
+    symmetries: Vec<(Alg, KTransformation)>,
+}
+
+impl PatternSymmetries {
+    pub fn try_new(kpuzzle: &KPuzzle, symmetries: &[Alg]) -> Result<Self, PuzzleError> {
+        if kpuzzle.default_pattern() != normalized_pattern(kpuzzle) {
+            return Err(
+                "Symmetry detection requires the default pattern to have distinct, unoriented pieces.".into(),
+            );
+        }
+        let symmetries = symmetries
+            .iter()
+            .map(|symmetry| {
+                let transformation =
+                    kpuzzle
+                        .transformation_from_alg(symmetry)
+                        .map_err(|_| PuzzleError {
+                            description: format!("Invalid symmetry for the puzzle: {}", symmetry),
+                        })?;
+                Ok((symmetry.clone(), transformation))
+            })
+            .collect::<Result<Vec<(Alg, KTransformation)>, PuzzleError>>()?;
+        Ok(Self {
+            kpuzzle: kpuzzle.clone(),
+            symmetries,
+        })
+    }
+
+    /// The 24 rotations of a cube definition that includes `x`, `y`, and `z`.
+    pub fn try_new_cube_rotations(kpuzzle: &KPuzzle) -> Result<Self, PuzzleError> {
+        let rotations = CUBE_ORIENTATION_ROTATIONS
+            .iter()
+            .map(|s| s.parse::<Alg>().unwrap())
+            .collect::<Vec<Alg>>();
+        Self::try_new(kpuzzle, &rotations)
+    }
+
+    // For a pattern reached by `S` from the default pattern, returns the pattern reached by `s' S s`.
+    fn conjugate(&self, pattern: &KPattern, symmetry_transformation: &KTransformation) -> KPattern {
+        let conjugated = self
+            .kpuzzle
+            .default_pattern()
+            .apply_transformation(&symmetry_transformation.invert());
+        apply_pattern_as_transformation(&conjugated, pattern)
+            .apply_transformation(symmetry_transformation)
+    }
+
+    /// The symmetries that leave the pattern unchanged (when applied as a
+    /// conjugation). This always includes the identity, if it is one of the
+    /// symmetries.
+    pub fn stabilizer(&self, pattern: &KPattern) -> Vec<Alg> {
+        self.symmetries
+            .iter()
+            .filter(|(_, transformation)| &self.conjugate(pattern, transformation) == pattern)
+            .map(|(symmetry, _)| symmetry.clone())
+            .collect()
+    }
+
+    /// The pattern conjugated by each of the symmetries (in order). This may
+    /// contain duplicates.
+    pub fn symmetric_patterns(&self, pattern: &KPattern) -> Vec<KPattern> {
+        self.symmetries
+            .iter()
+            .map(|(_, transformation)| self.conjugate(pattern, transformation))
+            .collect()
+    }
+
+    /// Groups patterns that are equivalent under the symmetries, returning the
+    /// index of a representative for each pattern. This can be used to avoid
+    /// searching from symmetric copies of the same pattern.
+    pub fn equivalence_class_representatives(&self, patterns: &[KPattern]) -> Vec<usize> {
+        let mut representatives = Vec::<usize>::with_capacity(patterns.len());
+        for (i, pattern) in patterns.iter().enumerate() {
+            let conjugates = self.symmetric_patterns(pattern);
+            let representative = (0..i)
+                .find(|j| {
+                    representatives[*j] == *j
+                        && conjugates
+                            .iter()
+                            .any(|conjugate| conjugate == &patterns[*j])
+                })
+                .unwrap_or(i);
+            representatives.push(representative);
+        }
+        representatives
+    }
+}
@@ -0,0 +1,69 @@
+use cubing::alg::Alg;
+
+/// A requirement for solutions in addition to reaching the target pattern
+/// (e.g. a constraint that a later phase depends on). Use
+/// `IDFSearch::search_with_additional_checks(…)` to search with several of
+/// these at once, instead of merging them into one condition.
+pub trait AdditionalSolutionCondition: Send {
+    /// Used to identify the condition in statistics and logs.
+    fn name(&self) -> &str;
+
+    /// Called for each candidate solution (in the direction of the returned
+    /// algs). The search continues as if the candidate wasn't a solution if
+    /// this returns `false`.
+    fn accepts_solution(&mut self, candidate_solution: &Alg) -> bool;
+}
+
+/// How often an additional solution condition was checked during a search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AdditionalSolutionConditionStats {
+    pub name: String,
+    /// Conditions are checked in order, and a condition is only checked if
+    /// all the previous ones accepted the candidate.
+    pub num_checked: u64,
+    pub num_rejected: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct AdditionalSolutionConditions {
+    conditions: Vec<(
+        Box<dyn AdditionalSolutionCondition>,
+        AdditionalSolutionConditionStats,
+    )>,
+}
+
+impl AdditionalSolutionConditions {
+    pub fn new(conditions: Vec<Box<dyn AdditionalSolutionCondition>>) -> Self {
+        Self {
+            conditions: conditions
+                .into_iter()
+                .map(|condition| {
+                    let stats = AdditionalSolutionConditionStats {
+                        name: condition.name().to_owned(),
+                        ..Default::default()
+                    };
+                    (condition, stats)
+                })
+                .collect(),
+        }
+    }
+
+    // Stops at the first condition that rejects the candidate.
+    pub fn accept_solution(&mut self, candidate_solution: &Alg) -> bool {
+        for (condition, stats) in &mut self.conditions {
+            stats.num_checked = stats.num_checked.saturating_add(1);
+            if !condition.accepts_solution(candidate_solution) {
+                stats.num_rejected = stats.num_rejected.saturating_add(1);
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn into_stats(self) -> Vec<AdditionalSolutionConditionStats> {
+        self.conditions
+            .into_iter()
+            .map(|(_, stats)| stats)
+            .collect()
+    }
+}
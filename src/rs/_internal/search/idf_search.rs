@@ -1,22 +1,31 @@
-use std::sync::{
-    mpsc::{channel, Receiver, Sender},
-    Arc,
+use std::{
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, Sender, SyncSender},
+        Arc, OnceLock,
+    },
+    time::Duration,
 };
 
 use cubing::{
     alg::{Alg, AlgNode, Move, QuantumMove},
     kpuzzle::{KPattern, KPuzzle},
 };
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 use crate::_internal::{
-    cli::options::{Generators, MetricEnum},
-    CanonicalFSM, CanonicalFSMState, MoveClassIndex, PruneTable, PuzzleError, RecursiveWorkTracker,
-    SearchGenerators, SearchLogger, CANONICAL_FSM_START_STATE,
+    cli::options::{EnableAutoAlwaysNeverValueEnum, Generators, MetricEnum},
+    normalized_pattern, AdditionalSolutionCondition, AdditionalSolutionConditionStats,
+    AdditionalSolutionConditions, CanonicalFSM, CanonicalFSMState, MoveClassIndex,
+    PatternInvariants, PruneTable, RecursiveWorkTracker, SearchGenerators, SearchLogger,
+    TraceCategory, TraceSpan, TwsearchError, CANONICAL_FSM_START_STATE,
 };
 
 const MAX_SUPPORTED_SEARCH_DEPTH: usize = 500; // TODO: increase
 
+// Checking the clock on every recursive call is expensive, so we only check it this often.
+const NUM_RECURSIVE_CALLS_PER_TIMEOUT_CHECK: usize = 1 << 12;
+
 #[allow(clippy::enum_variant_names)]
 enum SearchRecursionResult {
     DoneSearching(),
@@ -53,15 +62,44 @@ impl<'a> SolutionMoves<'a> {
     }
 }
 
+/// The result of `IDFSearch::check_no_solutions_shorter_than(…)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShortSolutionCheck {
+    /// Every depth below this one was searched without finding a solution.
+    NoneShorterThan(usize),
+    /// A shortest solution (below the requested depth).
+    Found(Alg),
+}
+
+// Each solution is sent along with the index of the search pattern it solves.
+pub type SearchPatternIndexAndAlg = (usize, Alg);
+
+// Searches on the caller's thread send every solution before returning, so
+// they need an unbounded channel. Background searches use a bounded channel,
+// so that they wait for solutions to be consumed instead of buffering them.
+enum SolutionSender {
+    Unbounded(Sender<Option<SearchPatternIndexAndAlg>>),
+    Bounded(SyncSender<Option<SearchPatternIndexAndAlg>>),
+}
+
+impl SolutionSender {
+    // Returns `false` if the solutions are no longer being received.
+    fn send(&self, indexed_alg: Option<SearchPatternIndexAndAlg>) -> bool {
+        match self {
+            Self::Unbounded(sender) => sender.send(indexed_alg).is_ok(),
+            Self::Bounded(sender) => sender.send(indexed_alg).is_ok(),
+        }
+    }
+}
+
 pub struct SearchSolutions {
-    receiver: Receiver<Option<Alg>>,
+    receiver: Receiver<Option<SearchPatternIndexAndAlg>>,
     done: bool,
 }
 
 impl SearchSolutions {
-    pub fn construct() -> (Sender<Option<Alg>>, Self) {
-        // TODO: use `sync_channel` to control resumption?
-        let (sender, receiver) = channel::<Option<Alg>>();
+    pub fn construct() -> (Sender<Option<SearchPatternIndexAndAlg>>, Self) {
+        let (sender, receiver) = channel::<Option<SearchPatternIndexAndAlg>>();
         (
             sender,
             Self {
@@ -70,12 +108,34 @@ impl SearchSolutions {
             },
         )
     }
-}
 
-impl Iterator for SearchSolutions {
-    type Item = Alg;
+    fn construct_bounded(
+        max_buffered_solutions: usize,
+    ) -> (SyncSender<Option<SearchPatternIndexAndAlg>>, Self) {
+        let (sender, receiver) =
+            sync_channel::<Option<SearchPatternIndexAndAlg>>(max_buffered_solutions);
+        (
+            sender,
+            Self {
+                receiver,
+                done: false,
+            },
+        )
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    // An iterator that yields no solutions.
+    pub fn empty() -> Self {
+        let (_, search_solutions) = Self::construct();
+        search_solutions
+    }
+
+    // Yields each solution along with the index of the search pattern it
+    // solves (see `IDFSearch::search_from_multiple_patterns(…)`).
+    pub fn with_search_pattern_indices(self) -> IndexedSearchSolutions {
+        IndexedSearchSolutions(self)
+    }
+
+    fn next_indexed(&mut self) -> Option<SearchPatternIndexAndAlg> {
         if self.done {
             None
         } else {
@@ -88,7 +148,7 @@ impl Iterator for SearchSolutions {
                 }
             };
             match received {
-                Some(alg) => Some(alg),
+                Some(indexed_alg) => Some(indexed_alg),
                 None => {
                     self.done = true;
                     None
@@ -98,6 +158,154 @@ impl Iterator for SearchSolutions {
     }
 }
 
+impl Iterator for SearchSolutions {
+    type Item = Alg;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_indexed().map(|(_, alg)| alg)
+    }
+}
+
+pub struct IndexedSearchSolutions(SearchSolutions);
+
+impl Iterator for IndexedSearchSolutions {
+    type Item = SearchPatternIndexAndAlg;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_indexed()
+    }
+}
+
+/// Which way the returned algs go between the search pattern and the target pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchDirection {
+    /// Each alg takes the search pattern to the target pattern (i.e. it solves the search pattern).
+    SearchPatternToTarget,
+    /// Each alg takes the target pattern to the search pattern (e.g. a scramble for the search pattern when the target is solved).
+    TargetToSearchPattern,
+}
+
+impl Default for SearchDirection {
+    fn default() -> Self {
+        Self::SearchPatternToTarget
+    }
+}
+
+/// Which solution is returned first when there are several of the same length.
+///
+/// This only changes the order in which move classes are expanded at each
+/// step of the search, so every policy finds the same set of solutions at each
+/// depth (and the same number of nodes are visited for an exhaustive depth).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SolutionTieBreak {
+    /// Solutions are in lexicographic order by the order of the generators.
+    Lexicographic,
+    /// Move classes are expanded in a random order at each step. The same
+    /// seed gives the same solutions for the same search.
+    Random { seed: u64 },
+    /// Move classes without wide moves (e.g. `Rw` or `r`) are expanded first,
+    /// so wide moves are only used as late as possible in the first
+    /// solutions found. A move only counts as wide if it affects more pieces
+    /// than its outer move (so Pyraminx tips aren't wide). This is a
+    /// preference: it does not guarantee the minimum number of wide moves.
+    FewerWideMoves,
+}
+
+impl Default for SolutionTieBreak {
+    fn default() -> Self {
+        Self::Lexicographic
+    }
+}
+
+/// The order in which solutions are yielded (when more than one is requested).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SolutionOrder {
+    /// Each solution is yielded as soon as it is found. Solutions are in
+    /// non-decreasing length (since each depth is searched completely before
+    /// the next one), and solutions of the same length are in search order
+    /// (see `SolutionTieBreak`).
+    FirstFound,
+    /// The solutions of each length are held back until that depth has been
+    /// searched completely, and then yielded sorted by their notation (and
+    /// then by search pattern index). This order doesn't depend on the
+    /// generator order or `SolutionTieBreak`.
+    ///
+    /// The search always finishes the depth at which `min_num_solutions` is
+    /// reached (as with `finish_solution_depth`). If the search times out,
+    /// the solutions of the last depth are still sorted, but may not include
+    /// every solution of that length.
+    ShortestFirst,
+}
+
+impl Default for SolutionOrder {
+    fn default() -> Self {
+        Self::FirstFound
+    }
+}
+
+// The number of pieces that the move moves or reorients (or `None` if the
+// puzzle doesn't have the move).
+fn num_affected_pieces(
+    kpuzzle: &KPuzzle,
+    normalized_pattern: &KPattern,
+    r#move: &Move,
+) -> Option<usize> {
+    let transformation = kpuzzle.transformation_from_move(r#move).ok()?;
+    let applied = normalized_pattern.apply_transformation(&transformation);
+    Some(
+        kpuzzle
+            .data
+            .ordered_orbit_info
+            .iter()
+            .map(|orbit_info| {
+                (0..orbit_info.num_pieces)
+                    .filter(|i| {
+                        applied.get_piece(orbit_info, *i)
+                            != normalized_pattern.get_piece(orbit_info, *i)
+                            || applied.get_orientation_with_mod(orbit_info, *i).orientation
+                                != normalized_pattern
+                                    .get_orientation_with_mod(orbit_info, *i)
+                                    .orientation
+                    })
+                    .count()
+            })
+            .sum(),
+    )
+}
+
+// A move is wide if it's the lowercase or `w` version of a move (e.g. `r` or
+// `Rw` for `R`) and affects more pieces than that move does. This depends on
+// the puzzle definition: on Pyraminx, `u` only turns a tip, so it isn't wide.
+fn is_wide_move(kpuzzle: &KPuzzle, normalized_pattern: &KPattern, r#move: &Move) -> bool {
+    let family = r#move.quantum.family.as_str();
+    let outer_family = match family.strip_suffix('w') {
+        Some(outer_family) => outer_family.to_owned(),
+        None => family.to_uppercase(),
+    };
+    if outer_family == family {
+        return false;
+    }
+    // Keeps any prefix (e.g. `3Rw` is compared with `3R`).
+    let quantum_str = r#move.quantum.to_string();
+    let prefix = &quantum_str[..quantum_str.len() - family.len()];
+    let (Ok(single_move), Ok(outer_move)) = (
+        quantum_str.parse::<Move>(),
+        format!("{}{}", prefix, outer_family).parse::<Move>(),
+    ) else {
+        return false;
+    };
+    match (
+        num_affected_pieces(kpuzzle, normalized_pattern, &single_move),
+        num_affected_pieces(kpuzzle, normalized_pattern, &outer_move),
+    ) {
+        (Some(num_pieces), Some(outer_num_pieces)) => num_pieces > outer_num_pieces,
+        _ => false,
+    }
+}
+
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IndividualSearchOptions {
@@ -106,6 +314,17 @@ pub struct IndividualSearchOptions {
     pub max_depth: Option<usize>,
     pub disallowed_initial_quanta: Option<Vec<QuantumMove>>, // TODO: Change this to `fsm_pre_moves` so we can compute disallowed initial FSM states.
     pub disallowed_final_quanta: Option<Vec<QuantumMove>>, // TODO: Find a way to represent this using disallowed final FSM states?
+    pub check_before_solve: Option<EnableAutoAlwaysNeverValueEnum>,
+    // Initial and final quanta always refer to the returned algs, regardless of direction.
+    pub direction: Option<SearchDirection>,
+    /// Stop searching after this long. Any solutions found so far are still returned.
+    pub timeout_milliseconds: Option<u64>,
+    /// Once `min_num_solutions` have been found, keep searching until the end
+    /// of the current depth (returning every solution of that length) instead
+    /// of stopping immediately.
+    pub finish_solution_depth: Option<bool>,
+    pub tie_break: Option<SolutionTieBreak>,
+    pub solution_order: Option<SolutionOrder>,
 }
 
 fn is_move_disallowed(r#move: &Move, disallowed_quanta: &Option<Vec<QuantumMove>>) -> bool {
@@ -130,13 +349,134 @@ impl IndividualSearchOptions {
     pub fn get_max_depth(&self) -> usize {
         self.max_depth.unwrap_or(MAX_SUPPORTED_SEARCH_DEPTH)
     }
+    // The check runs for every search pattern (e.g. each random pattern when
+    // filtering scrambles), so it's only on when requested. The CLI and the
+    // server request it for `auto`.
+    pub fn get_check_before_solve(&self) -> bool {
+        match &self.check_before_solve {
+            Some(check_before_solve) => check_before_solve.enabled(|| false),
+            None => false,
+        }
+    }
+    pub fn get_direction(&self) -> SearchDirection {
+        self.direction.unwrap_or_default()
+    }
+    pub fn get_finish_solution_depth(&self) -> bool {
+        self.finish_solution_depth.unwrap_or(false)
+    }
+    pub fn get_tie_break(&self) -> SolutionTieBreak {
+        self.tie_break.unwrap_or_default()
+    }
+    pub fn get_solution_order(&self) -> SolutionOrder {
+        self.solution_order.unwrap_or_default()
+    }
+    // Whether to keep searching until the end of the depth with the last requested solution.
+    fn finishes_solution_depth(&self) -> bool {
+        self.get_finish_solution_depth()
+            || self.get_solution_order() == SolutionOrder::ShortestFirst
+    }
+}
+
+/// The state of the search at a sampled node.
+#[derive(Clone, Debug)]
+pub struct SearchNodeSample {
+    /// The moves from the search pattern to this node.
+    pub moves: Alg,
+    /// The depth of the whole search iteration that this node is part of.
+    pub search_depth: usize,
+    /// The index of the search pattern (see `IDFSearch::search_from_multiple_patterns(…)`).
+    pub search_pattern_index: usize,
+}
+
+/// Calls a callback for every `n`th node of a search (e.g. to animate the
+/// search tree in a visualization). Building a sample is much more expensive
+/// than visiting a node, so `n` should usually be large.
+pub struct SearchNodeHook {
+    sample_every_num_nodes: u64,
+    num_nodes_until_sample: u64,
+    callback: Box<dyn FnMut(SearchNodeSample) + Send>,
+}
+
+impl SearchNodeHook {
+    pub fn new(
+        sample_every_num_nodes: u64,
+        callback: impl FnMut(SearchNodeSample) + Send + 'static,
+    ) -> Self {
+        let sample_every_num_nodes = sample_every_num_nodes.max(1);
+        Self {
+            sample_every_num_nodes,
+            num_nodes_until_sample: sample_every_num_nodes,
+            callback: Box::new(callback),
+        }
+    }
 }
 
 struct IndividualSearchData {
     individual_search_options: IndividualSearchOptions,
     recursive_work_tracker: RecursiveWorkTracker,
     num_solutions_sofar: usize,
-    solution_sender: Sender<Option<Alg>>,
+    search_pattern_index: usize,
+    solution_sender: SolutionSender,
+    deadline: Option<instant::Instant>,
+    num_recursive_calls_until_timeout_check: usize,
+    timed_out: bool,
+    // Indices into `SearchGenerators::grouped`, in the order they are expanded.
+    move_class_order: Arc<Vec<usize>>,
+    // Only set for `SolutionTieBreak::Random`, to reshuffle the order at each step.
+    tie_break_rng: Option<StdRng>,
+    // Scratch buffers for `SolutionTieBreak::Random`, indexed by remaining
+    // depth. These are allocated once per search (rather than at every node),
+    // and each one is reshuffled in place whenever it is used.
+    shuffled_move_class_orders: Vec<Vec<usize>>,
+    search_depth: usize,
+    search_node_hook: Option<SearchNodeHook>,
+    additional_solution_conditions: AdditionalSolutionConditions,
+    // Solutions of the current depth for `SolutionOrder::ShortestFirst`.
+    current_depth_solutions: Vec<SearchPatternIndexAndAlg>,
+}
+
+impl IndividualSearchData {
+    fn allocate_shuffled_move_class_orders(&mut self, max_remaining_depth: usize) {
+        if self.tie_break_rng.is_none() {
+            return;
+        }
+        while self.shuffled_move_class_orders.len() <= max_remaining_depth {
+            self.shuffled_move_class_orders
+                .push(self.move_class_order.as_ref().clone());
+        }
+    }
+
+    // Returns `false` if the solutions are no longer being received (so the
+    // search can stop).
+    fn send_solution(&mut self, indexed_alg: SearchPatternIndexAndAlg) -> bool {
+        self.num_solutions_sofar += 1;
+        match self.individual_search_options.get_solution_order() {
+            SolutionOrder::FirstFound => self.solution_sender.send(Some(indexed_alg)),
+            SolutionOrder::ShortestFirst => {
+                self.current_depth_solutions.push(indexed_alg);
+                true
+            }
+        }
+    }
+
+    // Sends any solutions that were held back for the current depth.
+    fn finish_depth_solutions(&mut self) {
+        let mut solutions = std::mem::take(&mut self.current_depth_solutions);
+        // Sorting by notation (rather than search order) makes ties deterministic.
+        solutions.sort_by_cached_key(|(search_pattern_index, alg)| {
+            (alg.to_string(), *search_pattern_index)
+        });
+        for indexed_alg in solutions {
+            if !self.solution_sender.send(Some(indexed_alg)) {
+                return;
+            }
+        }
+    }
+
+    fn send_end_of_search(&mut self) {
+        self.finish_depth_solutions();
+        self.solution_sender.send(None);
+    }
 }
 
 pub struct IDFSearchAPIData {
@@ -150,6 +490,9 @@ pub struct IDFSearchAPIData {
 pub struct IDFSearch {
     api_data: Arc<IDFSearchAPIData>,
     prune_table: PruneTable,
+    // Only built once a search needs them (see `pattern_invariants(…)`).
+    pattern_invariants: OnceLock<PatternInvariants>,
+    search_node_hook: Option<SearchNodeHook>,
 }
 
 impl IDFSearch {
@@ -161,10 +504,37 @@ impl IDFSearch {
         metric: &MetricEnum,
         random_start: bool,
         min_prune_table_size: Option<usize>,
-    ) -> Result<Self, PuzzleError> {
+    ) -> Result<Self, TwsearchError> {
         let search_generators =
-            SearchGenerators::try_new(&kpuzzle, &generators, metric, random_start)?;
-        let canonical_fsm = CanonicalFSM::try_new(search_generators.clone())?; // TODO: avoid a clone
+            SearchGenerators::try_new(&kpuzzle, &generators, metric, random_start)
+                .map_err(|e| TwsearchError::InvalidGenerators {
+                    description: e.description,
+                })?
+                .without_moves_ignored_by_target(&kpuzzle, &target_pattern);
+        let canonical_fsm = CanonicalFSM::try_new(search_generators.clone()).map_err(|e| {
+            TwsearchError::InvalidGenerators {
+                description: e.description,
+            }
+        })?; // TODO: avoid a clone
+        Ok(Self::new_from_search_generators(
+            kpuzzle,
+            target_pattern,
+            search_generators,
+            canonical_fsm,
+            search_logger,
+            min_prune_table_size,
+        ))
+    }
+
+    // Useful for reusing generators and a canonical FSM that were computed (or cached) ahead of time.
+    pub fn new_from_search_generators(
+        kpuzzle: KPuzzle,
+        target_pattern: KPattern,
+        search_generators: SearchGenerators,
+        canonical_fsm: CanonicalFSM,
+        search_logger: Arc<SearchLogger>,
+        min_prune_table_size: Option<usize>,
+    ) -> Self {
         let api_data = Arc::new(IDFSearchAPIData {
             search_generators,
             canonical_fsm,
@@ -174,17 +544,217 @@ impl IDFSearch {
         });
 
         let prune_table = PruneTable::new(api_data.clone(), search_logger, min_prune_table_size); // TODO: make the prune table reusable across searches.
-        Ok(Self {
+        Self {
             api_data,
             prune_table,
+            pattern_invariants: OnceLock::new(),
+            search_node_hook: None,
+        }
+    }
+
+    pub fn kpuzzle(&self) -> &KPuzzle {
+        &self.api_data.kpuzzle
+    }
+
+    /// The approximate memory used by the prune table, which grows as deeper searches are run.
+    pub fn approximate_prune_table_num_bytes(&self) -> usize {
+        self.prune_table.approximate_num_bytes()
+    }
+
+    pub fn target_pattern(&self) -> &KPattern {
+        &self.api_data.target_pattern
+    }
+
+    /// Sets (or, with `None`, removes) a hook that samples nodes of later
+    /// searches. Without a hook, searches don't do any extra work per node.
+    pub fn set_search_node_hook(&mut self, search_node_hook: Option<SearchNodeHook>) {
+        self.search_node_hook = search_node_hook;
+    }
+
+    fn pattern_invariants(&self) -> &PatternInvariants {
+        self.pattern_invariants.get_or_init(|| {
+            PatternInvariants::new(&self.api_data.kpuzzle, &self.api_data.search_generators)
         })
     }
 
+    // Returns an error if the search pattern can't be reached from the target pattern with any number of moves.
+    pub fn check_reachability(&self, search_pattern: &KPattern) -> Result<(), TwsearchError> {
+        self.pattern_invariants()
+            .check_reachability(&self.api_data.target_pattern, search_pattern)
+            .map_err(|e| TwsearchError::InvalidPattern {
+                description: e.description,
+            })
+    }
+
+    // Logs any search error and returns an empty iterator in that case. Use
+    // `try_search(…)` to handle errors directly.
     pub fn search(
         &mut self,
         search_pattern: &KPattern,
-        mut individual_search_options: IndividualSearchOptions,
+        individual_search_options: IndividualSearchOptions,
+    ) -> SearchSolutions {
+        match self.try_search(search_pattern, individual_search_options) {
+            Ok(search_solutions) => search_solutions,
+            Err(e) => {
+                self.api_data.search_logger.write_error(&e.to_string());
+                SearchSolutions::empty()
+            }
+        }
+    }
+
+    pub fn try_search(
+        &mut self,
+        search_pattern: &KPattern,
+        individual_search_options: IndividualSearchOptions,
+    ) -> Result<SearchSolutions, TwsearchError> {
+        self.search_from_multiple_patterns(
+            std::slice::from_ref(search_pattern),
+            individual_search_options,
+        )
+    }
+
+    /// Proves that `search_pattern` has no solution shorter than
+    /// `min_depth` by searching every shorter depth exhaustively. This is
+    /// what scramble filtering needs, without a separate set of options.
+    ///
+    /// Returns the proven depth, or the shortest solution if there is one.
+    /// Unreachable patterns have no solutions at all, so they count as
+    /// proven.
+    pub fn check_no_solutions_shorter_than(
+        &mut self,
+        search_pattern: &KPattern,
+        min_depth: usize,
+    ) -> ShortSolutionCheck {
+        if min_depth == 0 {
+            return ShortSolutionCheck::NoneShorterThan(0);
+        }
+        match self
+            .search(
+                search_pattern,
+                IndividualSearchOptions {
+                    min_num_solutions: Some(1),
+                    min_depth: Some(0),
+                    // The max depth is exclusive.
+                    max_depth: Some(min_depth),
+                    ..Default::default()
+                },
+            )
+            .next()
+        {
+            Some(solution) => ShortSolutionCheck::Found(solution),
+            None => ShortSolutionCheck::NoneShorterThan(min_depth),
+        }
+    }
+
+    /// Returns the distance from `search_pattern` to the target pattern if it
+    /// is at most `max_distance`, or `None` if it is farther. The prune table
+    /// is checked first, so patterns that are clearly farther don't need a
+    /// search at all.
+    pub fn distance_at_most(
+        &mut self,
+        search_pattern: &KPattern,
+        max_distance: usize,
+    ) -> Option<u8> {
+        let max_distance = max_distance.min(u8::MAX as usize);
+        if self.prune_table.lookup(search_pattern) > max_distance {
+            return None;
+        }
+        match self.check_no_solutions_shorter_than(search_pattern, max_distance + 1) {
+            ShortSolutionCheck::Found(solution) => Some(solution.nodes.len() as u8),
+            ShortSolutionCheck::NoneShorterThan(_) => None,
+        }
+    }
+
+    /// Searches from all the given patterns at once, sharing the target
+    /// pattern and options. All patterns are searched at each depth before
+    /// moving on to the next one, so solutions are still returned in order of
+    /// increasing length. Use `.with_search_pattern_indices()` on the result to
+    /// find out which pattern each solution is for.
+    ///
+    /// Patterns that fail the reachability check are skipped (with a warning),
+    /// unless none of them are reachable.
+    pub fn search_from_multiple_patterns(
+        &mut self,
+        search_patterns: &[KPattern],
+        individual_search_options: IndividualSearchOptions,
+    ) -> Result<SearchSolutions, TwsearchError> {
+        self.search_from_multiple_patterns_with_conditions(
+            search_patterns,
+            individual_search_options,
+            AdditionalSolutionConditions::default(),
+        )
+        .map(|(search_solutions, _)| search_solutions)
+    }
+
+    /// Like `try_search(…)`, but only returns solutions that every condition
+    /// accepts. The conditions are checked in order for each candidate
+    /// solution, stopping at the first one that rejects it, so cheaper or more
+    /// selective conditions should go first. Also returns statistics for each
+    /// condition (in the same order).
+    pub fn search_with_additional_checks(
+        &mut self,
+        search_pattern: &KPattern,
+        individual_search_options: IndividualSearchOptions,
+        additional_solution_conditions: Vec<Box<dyn AdditionalSolutionCondition>>,
+    ) -> Result<(SearchSolutions, Vec<AdditionalSolutionConditionStats>), TwsearchError> {
+        self.search_from_multiple_patterns_with_conditions(
+            std::slice::from_ref(search_pattern),
+            individual_search_options,
+            AdditionalSolutionConditions::new(additional_solution_conditions),
+        )
+    }
+
+    /// Like `search(…)`, but runs the search on its own thread and returns
+    /// right away, so that solutions can be handled (e.g. written to a file)
+    /// while the search continues. The search waits whenever
+    /// `max_buffered_solutions` solutions have not been consumed yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn search_in_background(
+        mut self,
+        search_pattern: KPattern,
+        individual_search_options: IndividualSearchOptions,
+        max_buffered_solutions: usize,
     ) -> SearchSolutions {
+        let (solution_sender, search_solutions) =
+            SearchSolutions::construct_bounded(max_buffered_solutions);
+        std::thread::spawn(move || {
+            if let Err(e) = self.send_solutions_from_multiple_patterns(
+                std::slice::from_ref(&search_pattern),
+                individual_search_options,
+                AdditionalSolutionConditions::default(),
+                SolutionSender::Bounded(solution_sender),
+            ) {
+                self.api_data.search_logger.write_error(&e.to_string());
+            }
+        });
+        search_solutions
+    }
+
+    fn search_from_multiple_patterns_with_conditions(
+        &mut self,
+        search_patterns: &[KPattern],
+        individual_search_options: IndividualSearchOptions,
+        additional_solution_conditions: AdditionalSolutionConditions,
+    ) -> Result<(SearchSolutions, Vec<AdditionalSolutionConditionStats>), TwsearchError> {
+        let (solution_sender, search_solutions) = SearchSolutions::construct();
+        let additional_solution_condition_stats = self.send_solutions_from_multiple_patterns(
+            search_patterns,
+            individual_search_options,
+            additional_solution_conditions,
+            SolutionSender::Unbounded(solution_sender),
+        )?;
+        Ok((search_solutions, additional_solution_condition_stats))
+    }
+
+    // Returns the stats for each additional solution condition.
+    fn send_solutions_from_multiple_patterns(
+        &mut self,
+        search_patterns: &[KPattern],
+        mut individual_search_options: IndividualSearchOptions,
+        additional_solution_conditions: AdditionalSolutionConditions,
+        solution_sender: SolutionSender,
+    ) -> Result<Vec<AdditionalSolutionConditionStats>, TwsearchError> {
+        let _trace_span = TraceSpan::start(TraceCategory::SearchPhase, || "Search".to_owned());
         // TODO: do validation more consistently.
         if let Some(min_depth) = individual_search_options.min_depth {
             if min_depth > MAX_SUPPORTED_SEARCH_DEPTH {
@@ -203,7 +773,81 @@ impl IDFSearch {
             }
         }
 
-        let (solution_sender, search_solutions) = SearchSolutions::construct();
+        // The search itself always proceeds from the search pattern to the
+        // target, so the ends of the alg are swapped when we invert it.
+        if individual_search_options.get_direction() == SearchDirection::TargetToSearchPattern {
+            std::mem::swap(
+                &mut individual_search_options.disallowed_initial_quanta,
+                &mut individual_search_options.disallowed_final_quanta,
+            );
+        }
+
+        let mut reachable_search_pattern_indices = Vec::<usize>::new();
+        let mut first_reachability_error: Option<TwsearchError> = None;
+        for (search_pattern_index, search_pattern) in search_patterns.iter().enumerate() {
+            if !individual_search_options.get_check_before_solve() {
+                reachable_search_pattern_indices.push(search_pattern_index);
+                continue;
+            }
+            match self.check_reachability(search_pattern) {
+                Ok(()) => reachable_search_pattern_indices.push(search_pattern_index),
+                Err(e) => {
+                    if search_patterns.len() > 1 {
+                        self.api_data.search_logger.write_warning(&format!(
+                            "Skipping search pattern #{}: {}",
+                            search_pattern_index,
+                            e.description()
+                        ));
+                    }
+                    first_reachability_error.get_or_insert(e);
+                }
+            }
+        }
+        if reachable_search_pattern_indices.is_empty() {
+            return Err(first_reachability_error.unwrap_or_else(|| {
+                TwsearchError::InvalidPattern {
+                    description: "No search patterns.".to_owned(),
+                }
+            }));
+        }
+
+        // For e.g. quarter-turn metrics, every solution for a given pattern has
+        // the same length parity, so we can skip the other depths entirely.
+        let solution_depth_parities: Vec<Option<bool>> = search_patterns
+            .iter()
+            .map(|search_pattern| {
+                self.pattern_invariants()
+                    .solution_depth_parity(&self.api_data.target_pattern, search_pattern)
+            })
+            .collect();
+        let may_have_solutions_at_depth = |search_pattern_index: usize, depth: usize| {
+            solution_depth_parities[search_pattern_index]
+                .map_or(true, |is_odd| is_odd == (depth % 2 == 1))
+        };
+
+        let deadline = individual_search_options
+            .timeout_milliseconds
+            .map(|timeout| instant::Instant::now() + Duration::from_millis(timeout));
+        let grouped = &self.api_data.search_generators.grouped;
+        let mut move_class_order: Vec<usize> = (0..grouped.len()).collect();
+        let mut tie_break_rng = None;
+        match individual_search_options.get_tie_break() {
+            SolutionTieBreak::Lexicographic => {}
+            SolutionTieBreak::Random { seed } => {
+                tie_break_rng = Some(StdRng::seed_from_u64(seed));
+            }
+            SolutionTieBreak::FewerWideMoves => {
+                let normalized_pattern = normalized_pattern(&self.api_data.kpuzzle);
+                // A stable sort, so that the generator order is kept otherwise.
+                move_class_order.sort_by_cached_key(|move_class_index| {
+                    is_wide_move(
+                        &self.api_data.kpuzzle,
+                        &normalized_pattern,
+                        &grouped[*move_class_index][0].r#move,
+                    )
+                });
+            }
+        }
         let mut individual_search_data = IndividualSearchData {
             individual_search_options,
             recursive_work_tracker: RecursiveWorkTracker::new(
@@ -211,11 +855,20 @@ impl IDFSearch {
                 self.api_data.search_logger.clone(),
             ),
             num_solutions_sofar: 0,
+            search_pattern_index: 0,
             solution_sender,
+            deadline,
+            num_recursive_calls_until_timeout_check: NUM_RECURSIVE_CALLS_PER_TIMEOUT_CHECK,
+            timed_out: false,
+            move_class_order: Arc::new(move_class_order),
+            tie_break_rng,
+            shuffled_move_class_orders: vec![],
+            search_depth: 0,
+            search_node_hook: self.search_node_hook.take(),
+            additional_solution_conditions,
+            current_depth_solutions: vec![],
         };
 
-        let search_pattern = search_pattern.clone();
-
         for remaining_depth in individual_search_data
             .individual_search_options
             .get_min_depth()
@@ -223,6 +876,16 @@ impl IDFSearch {
                 .individual_search_options
                 .get_max_depth()
         {
+            if !reachable_search_pattern_indices
+                .iter()
+                .any(|search_pattern_index| {
+                    may_have_solutions_at_depth(*search_pattern_index, remaining_depth)
+                })
+            {
+                continue;
+            }
+            individual_search_data.search_depth = remaining_depth;
+            individual_search_data.allocate_shuffled_move_class_orders(remaining_depth);
             self.api_data.search_logger.write_info("----------------");
             self.prune_table.extend_for_search_depth(
                 remaining_depth,
@@ -233,21 +896,68 @@ impl IDFSearch {
             individual_search_data
                 .recursive_work_tracker
                 .start_depth(remaining_depth, Some("Starting search…"));
-            let recursion_result = self.recurse(
-                &mut individual_search_data,
-                &search_pattern,
-                CANONICAL_FSM_START_STATE,
-                remaining_depth,
-                SolutionMoves(None),
-            );
+            let mut done_searching = false;
+            for search_pattern_index in &reachable_search_pattern_indices {
+                if !may_have_solutions_at_depth(*search_pattern_index, remaining_depth) {
+                    continue;
+                }
+                individual_search_data.search_pattern_index = *search_pattern_index;
+                let recursion_result = self.recurse(
+                    &mut individual_search_data,
+                    &search_patterns[*search_pattern_index],
+                    CANONICAL_FSM_START_STATE,
+                    remaining_depth,
+                    SolutionMoves(None),
+                );
+                if let SearchRecursionResult::DoneSearching() = recursion_result {
+                    done_searching = true;
+                    break;
+                }
+            }
             individual_search_data
                 .recursive_work_tracker
                 .finish_latest_depth();
-            if let SearchRecursionResult::DoneSearching() = recursion_result {
+            if done_searching {
+                break;
+            }
+            individual_search_data.finish_depth_solutions();
+            if individual_search_data
+                .individual_search_options
+                .finishes_solution_depth()
+                && individual_search_data.num_solutions_sofar
+                    >= individual_search_data
+                        .individual_search_options
+                        .get_min_num_solutions()
+            {
+                individual_search_data.send_end_of_search();
                 break;
             }
         }
-        search_solutions
+        self.search_node_hook = individual_search_data.search_node_hook.take();
+        let additional_solution_condition_stats =
+            std::mem::take(&mut individual_search_data.additional_solution_conditions).into_stats();
+        if individual_search_data.timed_out {
+            if individual_search_data.num_solutions_sofar == 0 {
+                return Err(TwsearchError::SearchLimitReached {
+                    description: "Search timed out before finding a solution.".to_owned(),
+                });
+            }
+            return Ok(additional_solution_condition_stats);
+        }
+        if individual_search_data.num_solutions_sofar == 0
+            && individual_search_data
+                .individual_search_options
+                .get_max_depth()
+                == MAX_SUPPORTED_SEARCH_DEPTH
+        {
+            return Err(TwsearchError::SearchLimitReached {
+                description: format!(
+                    "No solution found up to the maximum supported search depth ({}). The search pattern may not be reachable using the given generators.",
+                    MAX_SUPPORTED_SEARCH_DEPTH
+                ),
+            });
+        }
+        Ok(additional_solution_condition_stats)
     }
 
     fn recurse(
@@ -261,6 +971,31 @@ impl IDFSearch {
         individual_search_data
             .recursive_work_tracker
             .record_recursive_call();
+        if let Some(deadline) = individual_search_data.deadline {
+            individual_search_data.num_recursive_calls_until_timeout_check -= 1;
+            if individual_search_data.num_recursive_calls_until_timeout_check == 0 {
+                individual_search_data.num_recursive_calls_until_timeout_check =
+                    NUM_RECURSIVE_CALLS_PER_TIMEOUT_CHECK;
+                if instant::Instant::now() >= deadline {
+                    individual_search_data.timed_out = true;
+                    individual_search_data.send_end_of_search();
+                    return SearchRecursionResult::DoneSearching();
+                }
+            }
+        }
+        if let Some(search_node_hook) = &mut individual_search_data.search_node_hook {
+            search_node_hook.num_nodes_until_sample -= 1;
+            if search_node_hook.num_nodes_until_sample == 0 {
+                search_node_hook.num_nodes_until_sample = search_node_hook.sample_every_num_nodes;
+                (search_node_hook.callback)(SearchNodeSample {
+                    moves: Alg {
+                        nodes: solution_moves.get_alg_nodes(),
+                    },
+                    search_depth: individual_search_data.search_depth,
+                    search_pattern_index: individual_search_data.search_pattern_index,
+                });
+            }
+        }
         if remaining_depth == 0 {
             if let Some(previous_moves) = solution_moves.0 {
                 if is_move_disallowed(
@@ -273,21 +1008,32 @@ impl IDFSearch {
                 }
             }
             return if current_pattern == &self.api_data.target_pattern {
-                individual_search_data.num_solutions_sofar += 1;
-                let alg = Alg::from(solution_moves);
-                individual_search_data
-                    .solution_sender
-                    .send(Some(alg))
-                    .expect("Internal error: could not send solution");
+                let alg = match individual_search_data
+                    .individual_search_options
+                    .get_direction()
+                {
+                    SearchDirection::SearchPatternToTarget => Alg::from(solution_moves),
+                    SearchDirection::TargetToSearchPattern => Alg::from(solution_moves).invert(),
+                };
+                if !individual_search_data
+                    .additional_solution_conditions
+                    .accept_solution(&alg)
+                {
+                    return SearchRecursionResult::ContinueSearchingDefault();
+                }
+                let search_pattern_index = individual_search_data.search_pattern_index;
+                if !individual_search_data.send_solution((search_pattern_index, alg)) {
+                    return SearchRecursionResult::DoneSearching();
+                }
                 if individual_search_data.num_solutions_sofar
                     >= individual_search_data
                         .individual_search_options
                         .get_min_num_solutions()
+                    && !individual_search_data
+                        .individual_search_options
+                        .finishes_solution_depth()
                 {
-                    individual_search_data
-                        .solution_sender
-                        .send(None)
-                        .expect("Internal error: could not send end of search");
+                    individual_search_data.send_end_of_search();
                     SearchRecursionResult::DoneSearching()
                 } else {
                     SearchRecursionResult::ContinueSearchingDefault()
@@ -303,9 +1049,47 @@ impl IDFSearch {
         if prune_table_depth > remaining_depth {
             return SearchRecursionResult::ContinueSearchingDefault();
         }
-        for (move_class_index, move_transformation_multiples) in
-            self.api_data.search_generators.grouped.iter().enumerate()
-        {
+        let move_class_order = individual_search_data.move_class_order.clone();
+        // Taken out of `individual_search_data` while recursing, and put back afterwards.
+        let shuffled_move_class_order = match &mut individual_search_data.tie_break_rng {
+            Some(tie_break_rng) => {
+                let mut shuffled_move_class_order = std::mem::take(
+                    &mut individual_search_data.shuffled_move_class_orders[remaining_depth],
+                );
+                shuffled_move_class_order.shuffle(tie_break_rng);
+                Some(shuffled_move_class_order)
+            }
+            None => None,
+        };
+        let recursion_result = self.recurse_move_classes(
+            individual_search_data,
+            current_pattern,
+            current_state,
+            remaining_depth,
+            solution_moves,
+            shuffled_move_class_order
+                .as_deref()
+                .unwrap_or(&move_class_order),
+        );
+        if let Some(shuffled_move_class_order) = shuffled_move_class_order {
+            individual_search_data.shuffled_move_class_orders[remaining_depth] =
+                shuffled_move_class_order;
+        }
+        recursion_result
+    }
+
+    fn recurse_move_classes(
+        &self,
+        individual_search_data: &mut IndividualSearchData,
+        current_pattern: &KPattern,
+        current_state: CanonicalFSMState,
+        remaining_depth: usize,
+        solution_moves: SolutionMoves,
+        move_class_order: &[usize],
+    ) -> SearchRecursionResult {
+        for move_class_index in move_class_order.iter().copied() {
+            let move_transformation_multiples =
+                &self.api_data.search_generators.grouped[move_class_index];
             let next_state = match self
                 .api_data
                 .canonical_fsm
@@ -352,3 +1136,110 @@ impl IDFSearch {
         SearchRecursionResult::ContinueSearchingDefault()
     }
 }
+
+#[test]
+fn shortest_first_solution_order_test() -> Result<(), TwsearchError> {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    use crate::_internal::cli::options::VerbosityLevel;
+
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let pattern = kpuzzle.default_pattern().apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&"R U".parse().unwrap())
+            .unwrap(),
+    );
+    let solutions = |tie_break: SolutionTieBreak| -> Result<Vec<String>, TwsearchError> {
+        let mut idfs = IDFSearch::try_new(
+            kpuzzle.clone(),
+            kpuzzle.default_pattern(),
+            Generators::Default,
+            Arc::new(SearchLogger {
+                verbosity: VerbosityLevel::Silent,
+            }),
+            &MetricEnum::Hand,
+            false,
+            None,
+        )?;
+        Ok(idfs
+            .try_search(
+                &pattern,
+                IndividualSearchOptions {
+                    min_num_solutions: Some(3),
+                    tie_break: Some(tie_break),
+                    solution_order: Some(SolutionOrder::ShortestFirst),
+                    ..Default::default()
+                },
+            )?
+            .map(|alg| alg.to_string())
+            .collect())
+    };
+    let lexicographic_solutions = solutions(SolutionTieBreak::Lexicographic)?;
+    assert!(lexicographic_solutions.len() >= 3);
+    // Ties are sorted by notation within each length, regardless of the tie break.
+    assert_eq!(
+        solutions(SolutionTieBreak::Random { seed: 3 })?,
+        lexicographic_solutions
+    );
+    let lengths: Vec<usize> = lexicographic_solutions
+        .iter()
+        .map(|solution| solution.parse::<Alg>().unwrap().nodes.len())
+        .collect();
+    assert!(lengths.windows(2).all(|pair| pair[0] <= pair[1]));
+    Ok(())
+}
+
+#[test]
+fn is_wide_move_test() {
+    use crate::scramble::puzzles::definitions::cube4x4x4_kpuzzle;
+
+    let kpuzzle = cube4x4x4_kpuzzle();
+    let cube4x4x4_pattern = normalized_pattern(kpuzzle);
+    let is_wide =
+        |r#move: &str| is_wide_move(kpuzzle, &cube4x4x4_pattern, &r#move.parse().unwrap());
+    assert!(is_wide("Rw"));
+    assert!(is_wide("Uw2'"));
+    assert!(!is_wide("R"));
+    assert!(!is_wide("2R"));
+    assert!(!is_wide("x"));
+
+    // Like Pyraminx, where `u` only turns the tip of the `U` corner.
+    let tip_kpuzzle = KPuzzle::try_new(
+        serde_json::from_str::<cubing::kpuzzle::KPuzzleDefinition>(
+            r#"{
+                "name": "tip",
+                "orbits": [
+                    { "orbitName": "CORNERS", "numPieces": 1, "numOrientations": 3 },
+                    { "orbitName": "TIPS", "numPieces": 1, "numOrientations": 3 }
+                ],
+                "defaultPattern": {
+                    "CORNERS": { "pieces": [0], "orientation": [0] },
+                    "TIPS": { "pieces": [0], "orientation": [0] }
+                },
+                "moves": {
+                    "U": {
+                        "CORNERS": { "permutation": [0], "orientationDelta": [1] },
+                        "TIPS": { "permutation": [0], "orientationDelta": [1] }
+                    },
+                    "u": {
+                        "CORNERS": { "permutation": [0], "orientationDelta": [0] },
+                        "TIPS": { "permutation": [0], "orientationDelta": [1] }
+                    }
+                }
+            }"#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let tip_pattern = normalized_pattern(&tip_kpuzzle);
+    assert!(!is_wide_move(
+        &tip_kpuzzle,
+        &tip_pattern,
+        &"u".parse().unwrap()
+    ));
+    assert!(!is_wide_move(
+        &tip_kpuzzle,
+        &tip_pattern,
+        &"U".parse().unwrap()
+    ));
+}
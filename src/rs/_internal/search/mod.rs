@@ -2,11 +2,35 @@
 mod idf_search;
 pub use idf_search::*;
 
+mod additional_solution_condition;
+pub use additional_solution_condition::*;
+
+mod solution_adapters;
+pub use solution_adapters::*;
+
 mod prune_table;
 pub(crate) use prune_table::*;
 
+mod orbit_prune_tables;
+pub(crate) use orbit_prune_tables::*;
+
 mod recursive_work_tracker;
 pub(crate) use recursive_work_tracker::*;
 
 mod search_logger;
 pub use search_logger::*;
+
+mod pattern_invariants;
+pub(crate) use pattern_invariants::*;
+
+mod pattern_view;
+pub use pattern_view::*;
+
+mod niss;
+pub use niss::*;
+
+mod pretty_pattern_search;
+pub use pretty_pattern_search::*;
+
+mod search_space_estimate;
+pub use search_space_estimate::*;
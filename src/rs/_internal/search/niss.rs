@@ -0,0 +1,132 @@
+use std::fmt::Display;
+
+use cubing::{
+    alg::{Alg, AlgNode},
+    kpuzzle::{KPattern, KPuzzle},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::_internal::{IDFSearch, IndividualSearchOptions, SearchError};
+
+/// Which scramble a NISS search step continues on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NissSide {
+    Normal,
+    Inverse,
+}
+
+impl Default for NissSide {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+fn concat_algs(alg1: &Alg, alg2: &Alg) -> Alg {
+    let mut nodes: Vec<AlgNode> = alg1.nodes.clone();
+    nodes.extend(alg2.nodes.iter().cloned());
+    Alg { nodes }
+}
+
+/// A (partial) solution found using NISS ("Normal-Inverse Scramble Switch"):
+/// moves performed on the normal scramble, and moves performed on the inverse
+/// scramble (which act as premoves for the normal scramble).
+#[derive(Clone, Debug)]
+pub struct NissSolution {
+    pub normal: Alg,
+    pub inverse: Alg,
+}
+
+impl Default for NissSolution {
+    fn default() -> Self {
+        Self {
+            normal: Alg { nodes: vec![] },
+            inverse: Alg { nodes: vec![] },
+        }
+    }
+}
+
+impl NissSolution {
+    /// The equivalent solution for the normal scramble: `normal` followed by the inverse of `inverse`.
+    pub fn to_linear_alg(&self) -> Alg {
+        concat_algs(&self.normal, &self.inverse.invert())
+    }
+
+    // For a scramble `S`, the full solution is `S N I'` (with `N` = `normal` and `I` = `inverse`). To continue with moves `X` on:
+    //
+    // - the normal scramble, we need `S N X I'` to be solved, i.e. `I' S N X` (conjugating by `I'`).
+    // - the inverse scramble, we need `S N X' I'` to be solved, i.e. `N' S' I X` (inverting and conjugating by `N'`).
+    fn search_pattern(
+        &self,
+        kpuzzle: &KPuzzle,
+        scramble: &Alg,
+        side: NissSide,
+    ) -> Result<KPattern, SearchError> {
+        let alg = match side {
+            NissSide::Normal => {
+                concat_algs(&concat_algs(&self.inverse.invert(), scramble), &self.normal)
+            }
+            NissSide::Inverse => concat_algs(
+                &concat_algs(&self.normal.invert(), &scramble.invert()),
+                &self.inverse,
+            ),
+        };
+        let transformation = kpuzzle
+            .transformation_from_alg(&alg)
+            .map_err(|e| SearchError {
+                description: e.to_string(),
+            })?;
+        Ok(kpuzzle
+            .default_pattern()
+            .apply_transformation(&transformation))
+    }
+
+    fn extend(&self, side: NissSide, alg: &Alg) -> Self {
+        match side {
+            NissSide::Normal => Self {
+                normal: concat_algs(&self.normal, alg),
+                inverse: self.inverse.clone(),
+            },
+            NissSide::Inverse => Self {
+                normal: self.normal.clone(),
+                inverse: concat_algs(&self.inverse, alg),
+            },
+        }
+    }
+}
+
+/// Uses the usual FMC notation, with inverse moves in parentheses (e.g. `R U (F' D)`).
+impl Display for NissSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.normal.nodes.is_empty(), self.inverse.nodes.is_empty()) {
+            (_, true) => write!(f, "{}", self.normal),
+            (true, false) => write!(f, "({})", self.inverse),
+            (false, false) => write!(f, "{} ({})", self.normal, self.inverse),
+        }
+    }
+}
+
+impl IDFSearch {
+    /// Continues `partial_solution` for `scramble` with moves on the given
+    /// side, returning the extended solutions. The search must be for the
+    /// default pattern of the puzzle, and `options` apply to the moves found in
+    /// this step.
+    pub fn search_niss(
+        &mut self,
+        scramble: &Alg,
+        partial_solution: &NissSolution,
+        side: NissSide,
+        options: IndividualSearchOptions,
+    ) -> Result<impl Iterator<Item = NissSolution>, SearchError> {
+        if self.target_pattern() != &self.kpuzzle().default_pattern() {
+            return Err(
+                "NISS search requires the target pattern to be the default pattern.".into(),
+            );
+        }
+        let search_pattern = partial_solution.search_pattern(self.kpuzzle(), scramble, side)?;
+        let partial_solution = partial_solution.clone();
+        Ok(self
+            .try_search(&search_pattern, options)?
+            .map(move |alg| partial_solution.extend(side, &alg)))
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use cubing::kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo};
+
+use crate::_internal::{
+    breadth_first_search, BreadthFirstSearchControl, SearchGenerators, SearchLogger,
+};
+
+// Keeps the time to build the tables negligible compared to most searches.
+// Orbits that have more reachable states than this only get a partial table.
+const MAX_ORBIT_PRUNE_TABLE_SIZE: usize = 1 << 16;
+
+fn orbit_key(pattern: &KPattern, orbit_info: &KPuzzleOrbitInfo) -> Vec<u8> {
+    let mut key = Vec::<u8>::with_capacity(3 * orbit_info.num_pieces as usize);
+    for i in 0..orbit_info.num_pieces {
+        let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+        key.push(pattern.get_piece(orbit_info, i));
+        key.push(orientation_with_mod.orientation);
+        key.push(orientation_with_mod.orientation_mod);
+    }
+    key
+}
+
+// The exact distance to the target pattern when only the pieces of a single
+// orbit are considered. Moves act on each orbit independently, so this is a
+// lower bound for the distance of the full pattern.
+struct OrbitPruneTable {
+    orbit_index: usize,
+    orbit_key_to_depth: HashMap<Vec<u8>, u8>,
+    // All orbit states up to (and including) this depth are in the table.
+    complete_depth: u8,
+}
+
+impl OrbitPruneTable {
+    fn new(
+        kpuzzle: &KPuzzle,
+        orbit_index: usize,
+        target_pattern: &KPattern,
+        search_generators: &SearchGenerators,
+    ) -> Self {
+        let orbit_info = &kpuzzle.data.ordered_orbit_info[orbit_index];
+        let mut orbit_key_to_depth = HashMap::<Vec<u8>, u8>::new();
+        let mut complete_depth = u8::MAX;
+        breadth_first_search(
+            target_pattern.clone(),
+            |pattern| orbit_key(pattern, orbit_info),
+            |pattern| {
+                // The inverse transformation takes us one move further from
+                // the target, for any choice of generators.
+                search_generators
+                    .flat
+                    .iter()
+                    .map(|move_transformation_info| {
+                        pattern
+                            .apply_transformation(&move_transformation_info.inverse_transformation)
+                    })
+                    .collect::<Vec<KPattern>>()
+            },
+            |_, key, depth| {
+                if depth > (u8::MAX - 1) as usize
+                    || orbit_key_to_depth.len() >= MAX_ORBIT_PRUNE_TABLE_SIZE
+                {
+                    // States at `depth` may be missing from now on.
+                    complete_depth = (depth - 1) as u8;
+                    return BreadthFirstSearchControl::Stop;
+                }
+                orbit_key_to_depth.insert(key.clone(), depth as u8);
+                BreadthFirstSearchControl::Continue
+            },
+        );
+        Self {
+            orbit_index,
+            orbit_key_to_depth,
+            complete_depth,
+        }
+    }
+
+    fn lookup(&self, kpuzzle: &KPuzzle, pattern: &KPattern) -> usize {
+        let orbit_info = &kpuzzle.data.ordered_orbit_info[self.orbit_index];
+        match self.orbit_key_to_depth.get(&orbit_key(pattern, orbit_info)) {
+            Some(depth) => *depth as usize,
+            None => (self.complete_depth as usize) + 1,
+        }
+    }
+}
+
+/// Pruning heuristics generated automatically for any puzzle: the distance
+/// to the target for each orbit on its own, with the max taken across
+/// orbits.
+pub(crate) struct OrbitPruneTables {
+    kpuzzle: KPuzzle,
+    tables: Vec<OrbitPruneTable>,
+}
+
+impl OrbitPruneTables {
+    pub fn new(
+        kpuzzle: &KPuzzle,
+        target_pattern: &KPattern,
+        search_generators: &SearchGenerators,
+        search_logger: &SearchLogger,
+    ) -> Self {
+        let mut tables = Vec::<OrbitPruneTable>::new();
+        // With a single orbit, this would duplicate the main prune table.
+        if kpuzzle.data.ordered_orbit_info.len() > 1 {
+            for orbit_index in 0..kpuzzle.data.ordered_orbit_info.len() {
+                let table =
+                    OrbitPruneTable::new(kpuzzle, orbit_index, target_pattern, search_generators);
+                search_logger.write_info(&format!(
+                    "[Orbit prune tables] {}: {} entries (complete up to depth {})",
+                    kpuzzle.data.ordered_orbit_info[orbit_index].name.0,
+                    table.orbit_key_to_depth.len(),
+                    table.complete_depth
+                ));
+                // A table that only knows the target gives no information.
+                if table.complete_depth > 0 {
+                    tables.push(table);
+                }
+            }
+        }
+        Self {
+            kpuzzle: kpuzzle.clone(),
+            tables,
+        }
+    }
+
+    // Returns a heuristic depth for the given pattern.
+    pub fn lookup(&self, pattern: &KPattern) -> usize {
+        self.tables
+            .iter()
+            .map(|table| table.lookup(&self.kpuzzle, pattern))
+            .max()
+            .unwrap_or(0)
+    }
+}
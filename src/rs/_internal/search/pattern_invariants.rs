@@ -0,0 +1,234 @@
+use cubing::kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo};
+
+use crate::_internal::{
+    basic_parity, normalized_pattern, orbit_parity, SearchError, SearchGenerators,
+};
+
+// Parity vectors are stored as bitmasks, so we only check parity for puzzles with at most this many orbits.
+const MAX_NUM_ORBITS_FOR_PARITY_CHECK: usize = u64::BITS as usize;
+
+fn orbit_pieces(pattern: &KPattern, orbit_info: &KPuzzleOrbitInfo) -> Vec<u8> {
+    (0..orbit_info.num_pieces)
+        .map(|i| pattern.get_piece(orbit_info, i))
+        .collect()
+}
+
+fn has_distinct_pieces(pieces: &[u8]) -> bool {
+    let mut seen = vec![false; pieces.len()];
+    for piece in pieces {
+        let piece = *piece as usize;
+        if piece >= seen.len() || seen[piece] {
+            return false;
+        }
+        seen[piece] = true;
+    }
+    true
+}
+
+// Returns `None` if any orientation in the orbit is ignored (i.e. uses a non-trivial orientation mod).
+fn orientation_sum(pattern: &KPattern, orbit_info: &KPuzzleOrbitInfo) -> Option<u32> {
+    let mut sum: u32 = 0;
+    for i in 0..orbit_info.num_pieces {
+        let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+        if orientation_with_mod.orientation_mod != 0 {
+            return None;
+        }
+        sum += orientation_with_mod.orientation as u32;
+    }
+    Some(sum % (orbit_info.num_orientations as u32))
+}
+
+// Reduces `vectors` to a basis (over GF(2)) and reports whether `target` lies in their span.
+fn is_in_span(vectors: &[u64], target: u64) -> bool {
+    let mut basis = Vec::<u64>::new();
+    for vector in vectors {
+        let mut reduced = *vector;
+        for basis_vector in &basis {
+            reduced = reduced.min(reduced ^ basis_vector);
+        }
+        if reduced != 0 {
+            basis.push(reduced);
+            basis.sort_by(|a, b| b.cmp(a));
+        }
+    }
+    let mut reduced = target;
+    for basis_vector in &basis {
+        reduced = reduced.min(reduced ^ basis_vector);
+    }
+    reduced == 0
+}
+
+/// Properties of patterns that none of the search generators can change. If a
+/// search pattern differs from the target pattern in any of these, no amount
+/// of search depth will find a solution.
+pub(crate) struct PatternInvariants {
+    kpuzzle: KPuzzle,
+    // Indexed by orbit.
+    orientation_sum_is_invariant: Vec<bool>,
+    // One bitmask per generator, with bit N set if the generator has odd permutation parity on orbit N.
+    generator_parity_vectors: Vec<u64>,
+    // Sets of orbits (as bitmasks) whose total parity is flipped by every
+    // generator (e.g. the corners in the quarter-turn metric for 3x3x3).
+    depth_parity_orbit_masks: Vec<u64>,
+}
+
+impl PatternInvariants {
+    pub fn new(kpuzzle: &KPuzzle, search_generators: &SearchGenerators) -> Self {
+        let normalized_pattern = normalized_pattern(kpuzzle);
+        let num_orbits = kpuzzle.data.ordered_orbit_info.len();
+        let mut orientation_sum_is_invariant = vec![true; num_orbits];
+        let mut generator_parity_vectors = Vec::<u64>::new();
+        for move_transformation_info in &search_generators.flat {
+            let applied =
+                normalized_pattern.apply_transformation(&move_transformation_info.transformation);
+            let mut parity_vector: u64 = 0;
+            for (orbit_index, orbit_info) in kpuzzle.data.ordered_orbit_info.iter().enumerate() {
+                if orientation_sum(&applied, orbit_info) != Some(0) {
+                    orientation_sum_is_invariant[orbit_index] = false;
+                }
+                if orbit_index < MAX_NUM_ORBITS_FOR_PARITY_CHECK
+                    && orbit_parity(&applied, orbit_info).is_odd()
+                {
+                    parity_vector |= 1 << orbit_index;
+                }
+            }
+            generator_parity_vectors.push(parity_vector);
+        }
+        let flips_total_parity = |orbit_mask: u64| {
+            !generator_parity_vectors.is_empty()
+                && generator_parity_vectors
+                    .iter()
+                    .all(|parity_vector| (parity_vector & orbit_mask).count_ones() % 2 == 1)
+        };
+        let num_parity_orbits = num_orbits.min(MAX_NUM_ORBITS_FOR_PARITY_CHECK);
+        // We only try single orbits and all orbits together, which covers the common metrics.
+        let depth_parity_orbit_masks = (0..num_parity_orbits)
+            .map(|orbit_index| 1 << orbit_index)
+            .chain(std::iter::once(
+                (0..num_parity_orbits)
+                    .fold(0, |orbit_mask, orbit_index| orbit_mask | 1 << orbit_index),
+            ))
+            .filter(|orbit_mask| flips_total_parity(*orbit_mask))
+            .collect();
+        Self {
+            kpuzzle: kpuzzle.clone(),
+            orientation_sum_is_invariant,
+            generator_parity_vectors,
+            depth_parity_orbit_masks,
+        }
+    }
+
+    /// If every generator flips the same parity (e.g. for quarter-turn
+    /// metrics), every solution for the search pattern has the same length
+    /// parity. Returns whether that length is odd, or `None` if solutions
+    /// could have either parity.
+    pub fn solution_depth_parity(
+        &self,
+        target_pattern: &KPattern,
+        search_pattern: &KPattern,
+    ) -> Option<bool> {
+        for orbit_mask in &self.depth_parity_orbit_masks {
+            let mut is_odd = false;
+            let mut is_known = true;
+            for (orbit_index, orbit_info) in self.kpuzzle.data.ordered_orbit_info.iter().enumerate()
+            {
+                if orbit_index >= MAX_NUM_ORBITS_FOR_PARITY_CHECK
+                    || (orbit_mask >> orbit_index) & 1 == 0
+                {
+                    continue;
+                }
+                let target_pieces = orbit_pieces(target_pattern, orbit_info);
+                let search_pieces = orbit_pieces(search_pattern, orbit_info);
+                // The parity of a permutation is only well-defined for distinct pieces.
+                if !has_distinct_pieces(&target_pieces) || !has_distinct_pieces(&search_pieces) {
+                    is_known = false;
+                    break;
+                }
+                if basic_parity(&target_pieces) != basic_parity(&search_pieces) {
+                    is_odd = !is_odd;
+                }
+            }
+            if is_known {
+                return Some(is_odd);
+            }
+        }
+        None
+    }
+
+    pub fn check_reachability(
+        &self,
+        target_pattern: &KPattern,
+        search_pattern: &KPattern,
+    ) -> Result<(), SearchError> {
+        let mut parity_mask: u64 = 0;
+        let mut relative_parity: u64 = 0;
+        for (orbit_index, orbit_info) in self.kpuzzle.data.ordered_orbit_info.iter().enumerate() {
+            let target_pieces = orbit_pieces(target_pattern, orbit_info);
+            let search_pieces = orbit_pieces(search_pattern, orbit_info);
+
+            let mut sorted_target_pieces = target_pieces.clone();
+            sorted_target_pieces.sort();
+            let mut sorted_search_pieces = search_pieces.clone();
+            sorted_search_pieces.sort();
+            if sorted_target_pieces != sorted_search_pieces {
+                return Err(SearchError {
+                    description: format!(
+                        "The pieces in orbit {} do not match the target pattern, so it cannot be reached using any moves.",
+                        orbit_info.name.0
+                    ),
+                });
+            }
+
+            if self.orientation_sum_is_invariant[orbit_index] {
+                if let (Some(target_sum), Some(search_sum)) = (
+                    orientation_sum(target_pattern, orbit_info),
+                    orientation_sum(search_pattern, orbit_info),
+                ) {
+                    if target_sum != search_sum {
+                        return Err(SearchError {
+                            description: format!(
+                                "The orientations in orbit {} sum to {} (mod {}), but none of the generators change this sum and the target pattern sums to {}.",
+                                orbit_info.name.0, search_sum, orbit_info.num_orientations, target_sum
+                            ),
+                        });
+                    }
+                }
+            }
+
+            if orbit_index < MAX_NUM_ORBITS_FOR_PARITY_CHECK
+                && has_distinct_pieces(&target_pieces)
+                && has_distinct_pieces(&search_pieces)
+            {
+                parity_mask |= 1 << orbit_index;
+                if basic_parity(&target_pieces) != basic_parity(&search_pieces) {
+                    relative_parity |= 1 << orbit_index;
+                }
+            }
+        }
+
+        let masked_generator_parity_vectors: Vec<u64> = self
+            .generator_parity_vectors
+            .iter()
+            .map(|parity_vector| parity_vector & parity_mask)
+            .collect();
+        if !is_in_span(&masked_generator_parity_vectors, relative_parity) {
+            let orbit_names: Vec<&str> = self
+                .kpuzzle
+                .data
+                .ordered_orbit_info
+                .iter()
+                .enumerate()
+                .filter(|(orbit_index, _)| (relative_parity >> *orbit_index) & 1 != 0)
+                .map(|(_, orbit_info)| orbit_info.name.0.as_str())
+                .collect();
+            return Err(SearchError {
+                description: format!(
+                    "The permutation parity of the search pattern (which differs from the target pattern for: {}) cannot be reached using the given generators.",
+                    orbit_names.join(", ")
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
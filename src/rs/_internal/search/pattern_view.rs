@@ -0,0 +1,144 @@
+use cubing::kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo, KTransformation, OrientationWithMod};
+
+// A pattern where every piece is distinct and unoriented, so that applying a
+// transformation to it exposes the transformation's permutation and orientation deltas.
+pub(crate) fn normalized_pattern(kpuzzle: &KPuzzle) -> KPattern {
+    let mut pattern = kpuzzle.default_pattern();
+    for orbit_info in kpuzzle.orbit_info_iter() {
+        for i in 0..orbit_info.num_pieces {
+            pattern.set_piece(orbit_info, i, i);
+            pattern.set_orientation_with_mod(
+                orbit_info,
+                i,
+                &OrientationWithMod {
+                    orientation: 0,
+                    orientation_mod: 0,
+                },
+            );
+        }
+    }
+    pattern
+}
+
+fn add_orientation(
+    orientation_with_mod: &OrientationWithMod,
+    orientation_delta: u8,
+    orbit_info: &KPuzzleOrbitInfo,
+) -> OrientationWithMod {
+    let modulus = match orientation_with_mod.orientation_mod {
+        0 => orbit_info.num_orientations,
+        orientation_mod => orientation_mod,
+    };
+    OrientationWithMod {
+        orientation: (orientation_with_mod.orientation + orientation_delta) % modulus,
+        orientation_mod: orientation_with_mod.orientation_mod,
+    }
+}
+
+/// A transformation in a form that can be applied piece by piece, without
+/// allocating a new pattern.
+#[derive(Clone, Debug)]
+pub struct IndexedTransformation {
+    // Indexed by orbit, then by position: the position that the piece comes from, and the orientation added to it.
+    sources: Vec<Vec<(u8, u8)>>,
+}
+
+impl IndexedTransformation {
+    pub fn new(kpuzzle: &KPuzzle, transformation: &KTransformation) -> Self {
+        let applied = normalized_pattern(kpuzzle).apply_transformation(transformation);
+        let sources = kpuzzle
+            .orbit_info_iter()
+            .map(|orbit_info| {
+                (0..orbit_info.num_pieces)
+                    .map(|i| {
+                        (
+                            applied.get_piece(orbit_info, i),
+                            applied.get_orientation_with_mod(orbit_info, i).orientation,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { sources }
+    }
+
+    /// Writes `pattern` with this transformation applied into `into_pattern`,
+    /// reusing its storage. `into_pattern` must be for the same puzzle and must
+    /// not be `pattern` itself.
+    pub fn apply_into(&self, pattern: &KPattern, into_pattern: &mut KPattern) {
+        for (orbit_info, orbit_sources) in pattern.kpuzzle().orbit_info_iter().zip(&self.sources) {
+            for (i, (source, orientation_delta)) in orbit_sources.iter().enumerate() {
+                let i = i as u8;
+                into_pattern.set_piece(orbit_info, i, pattern.get_piece(orbit_info, *source));
+                into_pattern.set_orientation_with_mod(
+                    orbit_info,
+                    i,
+                    &add_orientation(
+                        pattern.get_orientation_with_mod(orbit_info, *source),
+                        *orientation_delta,
+                        orbit_info,
+                    ),
+                );
+            }
+        }
+    }
+
+    /// A read-only view of `pattern` with this transformation applied.
+    pub fn view<'a>(&'a self, pattern: &'a KPattern) -> TransformedKPatternView<'a> {
+        TransformedKPatternView {
+            pattern,
+            transformation: self,
+        }
+    }
+}
+
+/// A borrowed, read-only view of a pattern with a transformation applied,
+/// which is computed lazily for each piece that is read. This allows cheap
+/// checks on the result of a move (e.g. from multiple threads) without
+/// cloning the pattern.
+#[derive(Clone, Copy)]
+pub struct TransformedKPatternView<'a> {
+    pattern: &'a KPattern,
+    transformation: &'a IndexedTransformation,
+}
+
+impl TransformedKPatternView<'_> {
+    pub fn kpuzzle(&self) -> &KPuzzle {
+        self.pattern.kpuzzle()
+    }
+
+    fn source(&self, orbit_info: &KPuzzleOrbitInfo, i: u8) -> (u8, u8) {
+        let orbit_index = self
+            .kpuzzle()
+            .data
+            .ordered_orbit_info
+            .iter()
+            .position(|other| other.name == orbit_info.name)
+            .unwrap();
+        self.transformation.sources[orbit_index][i as usize]
+    }
+
+    pub fn get_piece(&self, orbit_info: &KPuzzleOrbitInfo, i: u8) -> u8 {
+        let (source, _) = self.source(orbit_info, i);
+        self.pattern.get_piece(orbit_info, source)
+    }
+
+    pub fn get_orientation_with_mod(
+        &self,
+        orbit_info: &KPuzzleOrbitInfo,
+        i: u8,
+    ) -> OrientationWithMod {
+        let (source, orientation_delta) = self.source(orbit_info, i);
+        add_orientation(
+            self.pattern.get_orientation_with_mod(orbit_info, source),
+            orientation_delta,
+            orbit_info,
+        )
+    }
+
+    pub fn to_pattern(&self) -> KPattern {
+        let mut pattern = self.pattern.clone();
+        self.transformation.apply_into(self.pattern, &mut pattern);
+        pattern
+    }
+}
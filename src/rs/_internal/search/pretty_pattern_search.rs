@@ -0,0 +1,141 @@
+use cubing::{
+    alg::{Alg, Move},
+    kpuzzle::{KPattern, KPuzzle},
+};
+
+use crate::_internal::{
+    cli::options::{Generators, MetricEnum},
+    CanonicalFSM, CanonicalFSMState, MoveClassIndex, PatternSymmetries, PuzzleError,
+    SearchGenerators, CANONICAL_FSM_START_STATE,
+};
+
+pub struct PrettyPatternSearchOptions {
+    pub min_depth: Option<usize>,
+    // There is no pruning, so the number of algs grows exponentially with this.
+    pub max_depth: usize,
+    pub max_num_results: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct PrettyPattern {
+    pub alg: Alg,
+    pub pattern: KPattern,
+}
+
+/// Enumerates canonical algs from a start pattern and reports the resulting
+/// patterns that satisfy a predicate (e.g. patterns with many symmetries, see
+/// `PatternSymmetries`).
+pub struct PrettyPatternSearch {
+    start_pattern: KPattern,
+    search_generators: SearchGenerators,
+    canonical_fsm: CanonicalFSM,
+}
+
+impl PrettyPatternSearch {
+    pub fn try_new(
+        kpuzzle: &KPuzzle,
+        start_pattern: Option<KPattern>,
+        generators: &Generators,
+        metric: &MetricEnum,
+    ) -> Result<Self, PuzzleError> {
+        let search_generators = SearchGenerators::try_new(kpuzzle, generators, metric, false)?;
+        let canonical_fsm = CanonicalFSM::try_new(search_generators.clone())?; // TODO: avoid a clone
+        Ok(Self {
+            start_pattern: start_pattern.unwrap_or_else(|| kpuzzle.default_pattern()),
+            search_generators,
+            canonical_fsm,
+        })
+    }
+
+    /// Returns the algs (in order of increasing length) whose patterns satisfy
+    /// `predicate`. Each pattern is only reported once, for the shortest alg
+    /// that reaches it. If `symmetries` are given, patterns that are
+    /// symmetric to an earlier result are also skipped.
+    pub fn search(
+        &self,
+        options: &PrettyPatternSearchOptions,
+        symmetries: Option<&PatternSymmetries>,
+        mut predicate: impl FnMut(&KPattern) -> bool,
+    ) -> Vec<PrettyPattern> {
+        let mut results = Vec::<PrettyPattern>::new();
+        let mut visit = |pattern: &KPattern, moves: &[Move]| -> bool {
+            if !predicate(pattern) {
+                return false;
+            }
+            let mut equivalent_patterns = match symmetries {
+                Some(symmetries) => symmetries.symmetric_patterns(pattern),
+                None => vec![],
+            };
+            equivalent_patterns.push(pattern.clone());
+            if results
+                .iter()
+                .any(|result| equivalent_patterns.contains(&result.pattern))
+            {
+                return false;
+            }
+            results.push(PrettyPattern {
+                alg: Alg {
+                    nodes: moves.iter().map(|r#move| r#move.clone().into()).collect(),
+                },
+                pattern: pattern.clone(),
+            });
+            options
+                .max_num_results
+                .map_or(false, |max_num_results| results.len() >= max_num_results)
+        };
+        for depth in options.min_depth.unwrap_or(0)..=options.max_depth {
+            if self.recurse(
+                &self.start_pattern,
+                CANONICAL_FSM_START_STATE,
+                depth,
+                &mut vec![],
+                &mut visit,
+            ) {
+                break;
+            }
+        }
+        results
+    }
+
+    // Returns `true` when the search is done.
+    fn recurse(
+        &self,
+        current_pattern: &KPattern,
+        current_state: CanonicalFSMState,
+        remaining_depth: usize,
+        moves: &mut Vec<Move>,
+        visit: &mut dyn FnMut(&KPattern, &[Move]) -> bool,
+    ) -> bool {
+        if remaining_depth == 0 {
+            return visit(current_pattern, moves);
+        }
+        for (move_class_index, move_transformation_multiples) in
+            self.search_generators.grouped.iter().enumerate()
+        {
+            let next_state = match self
+                .canonical_fsm
+                .next_state(current_state, MoveClassIndex(move_class_index))
+            {
+                Some(next_state) => next_state,
+                None => {
+                    continue;
+                }
+            };
+            for move_transformation_info in move_transformation_multiples {
+                moves.push(move_transformation_info.r#move.clone());
+                let done = self.recurse(
+                    &current_pattern.apply_transformation(&move_transformation_info.transformation),
+                    next_state,
+                    remaining_depth - 1,
+                    moves,
+                    visit,
+                );
+                moves.pop();
+                if done {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
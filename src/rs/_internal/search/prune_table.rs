@@ -4,8 +4,8 @@ use cubing::kpuzzle::KPattern;
 use thousands::Separable;
 
 use crate::_internal::{
-    CanonicalFSMState, MoveClassIndex, RecursiveWorkTracker, SearchLogger,
-    CANONICAL_FSM_START_STATE,
+    table_memory_mode, CanonicalFSMState, MoveClassIndex, OrbitPruneTables, RecursiveWorkTracker,
+    SearchLogger, TraceCategory, TraceSpan, CANONICAL_FSM_START_STATE,
 };
 
 use super::idf_search::IDFSearchAPIData;
@@ -20,6 +20,7 @@ const DEFAULT_MIN_PRUNE_TABLE_SIZE: usize = 1 << 20;
 
 struct PruneTableImmutableData {
     search_api_data: Arc<IDFSearchAPIData>,
+    orbit_prune_tables: OrbitPruneTables,
 }
 struct PruneTableMutableData {
     min_size: usize,               // power of 2
@@ -72,8 +73,17 @@ impl PruneTable {
             Some(min_size) => min_size.next_power_of_two(),
             None => DEFAULT_MIN_PRUNE_TABLE_SIZE,
         };
+        let orbit_prune_tables = OrbitPruneTables::new(
+            &search_api_data.kpuzzle,
+            &search_api_data.target_pattern,
+            &search_api_data.search_generators,
+            &search_logger,
+        );
         let mut prune_table = Self {
-            immutable: PruneTableImmutableData { search_api_data },
+            immutable: PruneTableImmutableData {
+                search_api_data,
+                orbit_prune_tables,
+            },
             mutable: PruneTableMutableData {
                 min_size,
                 prune_table_size: min_size,
@@ -105,10 +115,24 @@ impl PruneTable {
             new_pruning_depth = MAX_PRUNE_TABLE_DEPTH;
         }
 
-        let new_prune_table_size = usize::max(
-            usize::next_power_of_two(approximate_num_entries),
+        // The estimate can be huge for deep searches (especially with a 32-bit `usize`).
+        let max_prune_table_size = table_memory_mode().max_prune_table_size();
+        let mut new_prune_table_size = usize::max(
+            approximate_num_entries
+                .checked_next_power_of_two()
+                .unwrap_or(max_prune_table_size),
             self.mutable.min_size,
         );
+        if new_prune_table_size > max_prune_table_size
+            && self.mutable.prune_table_size < max_prune_table_size
+        {
+            self.mutable.search_logger.write_warning(&format!(
+                "[Prune table] Limiting size to {} entries for the current table memory mode.",
+                max_prune_table_size.separate_with_underscores()
+            ));
+        }
+        new_prune_table_size =
+            new_prune_table_size.min(max_prune_table_size.max(self.mutable.min_size));
         match new_prune_table_size.cmp(&self.mutable.prune_table_size) {
             std::cmp::Ordering::Less => {
                 // Don't shrink the prune table.
@@ -131,6 +155,12 @@ impl PruneTable {
             }
         }
 
+        let mut trace_span = TraceSpan::start(TraceCategory::TableBuild, || {
+            format!("Prune table to depth {}", new_pruning_depth)
+        });
+        if let Some(trace_span) = &mut trace_span {
+            trace_span.set_arg("num_entries", self.mutable.prune_table_size);
+        }
         for depth in (self.mutable.current_pruning_depth + 1)..(new_pruning_depth + 1) {
             self.mutable
                 .recursive_work_tracker
@@ -180,10 +210,14 @@ impl PruneTable {
             };
 
             for move_transformation_info in move_transformation_multiples {
+                // We expand backwards from the target, so that the stored depths
+                // are distances *to* the target even if the generators aren't
+                // closed under inverses.
                 Self::recurse(
                     immutable_data,
                     mutable_data,
-                    &current_pattern.apply_transformation(&move_transformation_info.transformation),
+                    &current_pattern
+                        .apply_transformation(&move_transformation_info.inverse_transformation),
                     next_state,
                     remaining_depth - 1,
                 )
@@ -191,8 +225,17 @@ impl PruneTable {
         }
     }
 
+    /// The approximate memory used by the main table (the orbit tables are
+    /// comparatively tiny).
+    pub fn approximate_num_bytes(&self) -> usize {
+        self.mutable.pattern_hash_to_depth.len() * std::mem::size_of::<PruneTableEntryType>()
+    }
+
     // Returns a heurstic depth for the given pattern.
     pub fn lookup(&self, pattern: &KPattern) -> usize {
-        self.mutable.lookup(pattern)
+        usize::max(
+            self.mutable.lookup(pattern),
+            self.immutable.orbit_prune_tables.lookup(pattern),
+        )
     }
 }
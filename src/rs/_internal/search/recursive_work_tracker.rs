@@ -2,18 +2,21 @@ use std::{sync::Arc, time::Duration};
 
 use thousands::Separable;
 
-use crate::_internal::SearchLogger;
+use crate::_internal::{SearchLogger, TraceCategory, TraceSpan};
 
 pub(crate) struct RecursiveWorkTracker {
     work_name: String,
     // TODO: support custom writes intead of sending to stdout/stderr
     latest_depth: usize,
-    latest_depth_num_recursive_calls: usize,
+    // Node counts use `u64` (rather than `usize`) so that deep searches don't
+    // overflow on 32-bit targets (e.g. WASM).
+    latest_depth_num_recursive_calls: u64,
     latest_depth_start_time: instant::Instant,
     latest_depth_duration: Duration,
     latest_depth_finished: bool,
+    latest_depth_trace_span: Option<TraceSpan>,
 
-    previous_depth_num_recursive_calls: usize,
+    previous_depth_num_recursive_calls: u64,
 
     search_logger: Arc<SearchLogger>,
 }
@@ -28,6 +31,7 @@ impl RecursiveWorkTracker {
             latest_depth_start_time: instant::Instant::now(),
             latest_depth_duration: Duration::ZERO,
             latest_depth_finished: true,
+            latest_depth_trace_span: None,
             latest_depth_num_recursive_calls: 0,
             search_logger,
         }
@@ -40,6 +44,9 @@ impl RecursiveWorkTracker {
 
     // Pass `None` as the message to avoid printing anything.
     pub fn start_depth(&mut self, depth: usize, message: Option<&str>) {
+        self.latest_depth_trace_span = TraceSpan::start(TraceCategory::SearchDepth, || {
+            format!("{} depth {}", self.work_name, depth)
+        });
         self.latest_depth_start_time = instant::Instant::now();
 
         self.latest_depth = depth;
@@ -66,7 +73,7 @@ impl RecursiveWorkTracker {
         }
         self.latest_depth_duration = instant::Instant::now() - self.latest_depth_start_time;
         let rate = (self.latest_depth_num_recursive_calls as f64
-            / (self.latest_depth_duration).as_secs_f64()) as usize;
+            / (self.latest_depth_duration).as_secs_f64()) as u64;
         self.search_logger.write_info(&format!(
             "[{}][Depth {}] {} recursive calls ({:?}) ({} calls/s)",
             self.work_name,
@@ -77,17 +84,25 @@ impl RecursiveWorkTracker {
             rate.separate_with_underscores()
         ));
         self.latest_depth_finished = true;
+        if let Some(mut trace_span) = self.latest_depth_trace_span.take() {
+            trace_span.set_arg("recursive_calls", self.latest_depth_num_recursive_calls);
+        }
     }
 
     pub fn record_recursive_call(&mut self) {
-        self.latest_depth_num_recursive_calls += 1;
+        self.latest_depth_num_recursive_calls =
+            self.latest_depth_num_recursive_calls.saturating_add(1);
     }
 
+    // Saturates at `usize::MAX`, since this is used to size tables.
     pub fn estimate_next_level_num_recursive_calls(&self) -> usize {
-        if self.previous_depth_num_recursive_calls == 0 {
-            return self.latest_depth_num_recursive_calls;
-        }
-        self.latest_depth_num_recursive_calls * self.latest_depth_num_recursive_calls
-            / self.previous_depth_num_recursive_calls
+        let estimate = if self.previous_depth_num_recursive_calls == 0 {
+            self.latest_depth_num_recursive_calls
+        } else {
+            self.latest_depth_num_recursive_calls
+                .saturating_mul(self.latest_depth_num_recursive_calls)
+                / self.previous_depth_num_recursive_calls
+        };
+        usize::try_from(estimate).unwrap_or(usize::MAX)
     }
 }
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use cubing::kpuzzle::{KPattern, KPuzzle};
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::_internal::{
+    options::{Generators, MetricEnum},
+    CanonicalFSM, MoveClassIndex, MoveTransformationInfo, PuzzleError, SearchGenerators,
+    CANONICAL_FSM_START_STATE,
+};
+
+pub struct SearchSpaceEstimateOptions {
+    pub max_depth: usize,
+    pub num_samples: usize,
+}
+
+pub struct SearchSpaceEstimate {
+    /// The estimated number of canonical move sequences of each length
+    /// (indexed by depth).
+    pub num_canonical_sequences_by_depth: Vec<f64>,
+    /// The estimated number of distinct patterns at the max depth, based on
+    /// how often the samples end at the same pattern. This is `None` if there
+    /// were no repeats (i.e. the search space is too large to estimate using
+    /// this many samples).
+    pub estimated_num_patterns: Option<f64>,
+    /// The first depth at which there are at least as many canonical move
+    /// sequences (in total) as estimated patterns. Typical optimal solutions
+    /// are at least this long.
+    pub estimated_solution_depth: Option<usize>,
+}
+
+/// Estimates the size of the search space for the generators by sampling
+/// random canonical move sequences from the start pattern (or the default
+/// pattern), so that new phases can be evaluated before building any tables.
+///
+/// The number of canonical sequences uses Knuth's estimator (the product of
+/// the branching factors along each random sequence), which is unbiased. The
+/// number of patterns assumes that random sequences of the max depth are
+/// close to uniformly distributed, so the max depth should be large enough
+/// for the sequences to mix well. Otherwise, it is an underestimate.
+pub fn estimate_search_space(
+    kpuzzle: &KPuzzle,
+    start_pattern: Option<KPattern>,
+    generators: &Generators,
+    metric: &MetricEnum,
+    options: &SearchSpaceEstimateOptions,
+) -> Result<SearchSpaceEstimate, PuzzleError> {
+    let search_generators = SearchGenerators::try_new(kpuzzle, generators, metric, false)?;
+    let canonical_fsm = CanonicalFSM::try_new(search_generators.clone())?; // TODO: avoid a clone
+    let start_pattern = start_pattern.unwrap_or_else(|| kpuzzle.default_pattern());
+
+    let mut rng = thread_rng();
+    let mut weight_sums = vec![0.0; options.max_depth + 1];
+    let mut final_pattern_counts = HashMap::<KPattern, usize>::new();
+    for _ in 0..options.num_samples {
+        let mut pattern = start_pattern.clone();
+        let mut current_state = CANONICAL_FSM_START_STATE;
+        let mut weight = 1.0;
+        weight_sums[0] += weight;
+        for weight_sum in weight_sums.iter_mut().skip(1) {
+            let mut candidates = Vec::<(_, &MoveTransformationInfo)>::new();
+            for (move_class_index, move_transformation_multiples) in
+                search_generators.grouped.iter().enumerate()
+            {
+                if let Some(next_state) =
+                    canonical_fsm.next_state(current_state, MoveClassIndex(move_class_index))
+                {
+                    for move_transformation_info in move_transformation_multiples {
+                        candidates.push((next_state, move_transformation_info));
+                    }
+                }
+            }
+            let Some((next_state, move_transformation_info)) = candidates.choose(&mut rng) else {
+                break;
+            };
+            weight *= candidates.len() as f64;
+            *weight_sum += weight;
+            pattern = pattern.apply_transformation(&move_transformation_info.transformation);
+            current_state = *next_state;
+        }
+        *final_pattern_counts.entry(pattern).or_insert(0) += 1;
+    }
+
+    let num_canonical_sequences_by_depth: Vec<f64> = weight_sums
+        .into_iter()
+        .map(|weight_sum| weight_sum / (options.num_samples as f64))
+        .collect();
+
+    // For `n` uniform samples from `N` patterns, we expect `n(n-1)/(2N)` pairs of equal samples.
+    let num_equal_pairs: usize = final_pattern_counts
+        .values()
+        .map(|count| count * (count - 1) / 2)
+        .sum();
+    let estimated_num_patterns = (num_equal_pairs > 0).then(|| {
+        let n = options.num_samples as f64;
+        n * (n - 1.0) / (2.0 * num_equal_pairs as f64)
+    });
+
+    let estimated_solution_depth = estimated_num_patterns.and_then(|estimated_num_patterns| {
+        let mut total = 0.0;
+        num_canonical_sequences_by_depth
+            .iter()
+            .position(|num_canonical_sequences| {
+                total += num_canonical_sequences;
+                total >= estimated_num_patterns
+            })
+    });
+
+    Ok(SearchSpaceEstimate {
+        num_canonical_sequences_by_depth,
+        estimated_num_patterns,
+        estimated_solution_depth,
+    })
+}
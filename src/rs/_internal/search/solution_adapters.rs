@@ -0,0 +1,50 @@
+use cubing::alg::Alg;
+
+use crate::_internal::{AlgMoveCounts, SearchPatternIndexAndAlg};
+
+/// A solution along with details that are useful when collecting many
+/// solutions (e.g. for trainers that sort or bucket them).
+#[derive(Clone, Debug)]
+pub struct AnnotatedSolution {
+    pub alg: Alg,
+    /// See `IDFSearch::search_from_multiple_patterns(…)`.
+    pub search_pattern_index: usize,
+    /// The number of moves (in the search metric).
+    pub depth: usize,
+    pub move_counts: AlgMoveCounts,
+}
+
+/// Lazy adapters for indexed solutions (see
+/// `SearchSolutions::with_search_pattern_indices()`).
+///
+/// The filters only look at the alg, so applying them before `.annotated()`
+/// avoids building annotations for solutions that are rejected anyway.
+pub trait IndexedSolutionsAdapters: Iterator<Item = SearchPatternIndexAndAlg> + Sized {
+    /// Only keeps solutions whose alg matches the predicate.
+    fn filter_solutions(
+        self,
+        mut predicate: impl FnMut(&Alg) -> bool,
+    ) -> impl Iterator<Item = SearchPatternIndexAndAlg> {
+        self.filter(move |(_, alg)| predicate(alg))
+    }
+
+    /// Stops at the first solution longer than `max_depth`. Solutions are
+    /// found in order of increasing length, so this never skips a shorter one.
+    fn take_while_depth_at_most(
+        self,
+        max_depth: usize,
+    ) -> impl Iterator<Item = SearchPatternIndexAndAlg> {
+        self.take_while(move |(_, alg)| alg.nodes.len() <= max_depth)
+    }
+
+    fn annotated(self) -> impl Iterator<Item = AnnotatedSolution> {
+        self.map(|(search_pattern_index, alg)| AnnotatedSolution {
+            search_pattern_index,
+            depth: alg.nodes.len(),
+            move_counts: AlgMoveCounts::from_alg(&alg),
+            alg,
+        })
+    }
+}
+
+impl<T: Iterator<Item = SearchPatternIndexAndAlg>> IndexedSolutionsAdapters for T {}
@@ -0,0 +1,89 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// Prune table entries are a single byte, so these are also the sizes in bytes.
+const MINIMAL_MAX_PRUNE_TABLE_SIZE: usize = 1 << 20;
+const REDUCED_MAX_PRUNE_TABLE_SIZE: usize = 1 << 28;
+const FULL_MAX_PRUNE_TABLE_SIZE: usize = 1 << (usize::BITS - 1);
+
+/// How much memory search tables may use. Prune tables normally grow with the
+/// search depth, which can exceed the memory available to 32-bit targets
+/// (e.g. WASM in a browser, which is capped at 2–4 GiB). In reduced mode, they
+/// stop growing at a fixed size, so deep searches are slower but don't run out
+/// of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TableMemoryMode {
+    Full,
+    /// Caps each prune table at 256 MiB.
+    Reduced,
+    /// Caps each prune table at 1 MiB, for devices with very little memory.
+    /// Coordinate pruning tables that don't fit are stored sparsely (only
+    /// the pairs closest to the target), which makes searches much slower.
+    Minimal,
+}
+
+impl TableMemoryMode {
+    /// `Reduced` for 32-bit targets (e.g. WASM), and `Full` otherwise.
+    pub fn default_for_target() -> Self {
+        if cfg!(target_pointer_width = "32") {
+            Self::Reduced
+        } else {
+            Self::Full
+        }
+    }
+
+    /// The max number of entries in a prune table (always a power of 2).
+    pub fn max_prune_table_size(&self) -> usize {
+        match self {
+            TableMemoryMode::Full => FULL_MAX_PRUNE_TABLE_SIZE,
+            TableMemoryMode::Reduced => REDUCED_MAX_PRUNE_TABLE_SIZE,
+            TableMemoryMode::Minimal => MINIMAL_MAX_PRUNE_TABLE_SIZE,
+        }
+    }
+}
+
+impl Default for TableMemoryMode {
+    fn default() -> Self {
+        Self::default_for_target()
+    }
+}
+
+impl Display for TableMemoryMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TableMemoryMode::Full => "full",
+            TableMemoryMode::Reduced => "reduced",
+            TableMemoryMode::Minimal => "minimal",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// 0 means that no mode has been set, so we use the default for the target.
+static TABLE_MEMORY_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the table memory mode for all tables created (or extended) after this
+/// call, in place of `TableMemoryMode::default_for_target()`.
+pub fn set_table_memory_mode(table_memory_mode: TableMemoryMode) {
+    let value = match table_memory_mode {
+        TableMemoryMode::Full => 1,
+        TableMemoryMode::Reduced => 2,
+        TableMemoryMode::Minimal => 3,
+    };
+    TABLE_MEMORY_MODE.store(value, Ordering::Relaxed);
+}
+
+pub fn table_memory_mode() -> TableMemoryMode {
+    match TABLE_MEMORY_MODE.load(Ordering::Relaxed) {
+        1 => TableMemoryMode::Full,
+        2 => TableMemoryMode::Reduced,
+        3 => TableMemoryMode::Minimal,
+        _ => TableMemoryMode::default_for_target(),
+    }
+}
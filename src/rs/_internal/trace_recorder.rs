@@ -0,0 +1,197 @@
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Records search phases, table builds, and per-depth search iterations (from
+/// all threads) while it is active, for profiling. The recording can be
+/// exported in the Chrome trace format, which can be opened in
+/// `chrome://tracing` or <https://ui.perfetto.dev>.
+///
+/// Recording is off unless a recorder is passed to `set_trace_recorder(…)`.
+pub struct TraceRecorder {
+    start_time: instant::Instant,
+    events: Mutex<Vec<ChromeTraceEvent>>,
+}
+
+// A "complete" event (with a start and a duration) in the Chrome trace format.
+#[derive(Serialize)]
+struct ChromeTraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    // In microseconds since the recorder was created.
+    ts: f64,
+    dur: f64,
+    pid: u64,
+    tid: u64,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    args: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace<'a> {
+    trace_events: &'a [ChromeTraceEvent],
+    display_time_unit: &'static str,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self {
+            start_time: instant::Instant::now(),
+            events: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn num_events(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    /// The events recorded so far, as Chrome trace JSON.
+    pub fn to_chrome_trace_json(&self) -> String {
+        let events = self.events.lock().unwrap();
+        serde_json::to_string(&ChromeTrace {
+            trace_events: &events,
+            display_time_unit: "ms",
+        })
+        .expect("Could not serialize trace events.")
+    }
+
+    fn microseconds_since_start(&self, instant: instant::Instant) -> f64 {
+        (instant - self.start_time).as_secs_f64() * 1_000_000.0
+    }
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static ACTIVE_TRACE_RECORDER: RwLock<Option<Arc<TraceRecorder>>> = RwLock::new(None);
+
+/// Starts recording trace events to `trace_recorder` (replacing any previous
+/// recorder), or stops recording if it is `None`. Spans that are already in
+/// progress are still recorded to the recorder they started with.
+pub fn set_trace_recorder(trace_recorder: Option<Arc<TraceRecorder>>) {
+    *ACTIVE_TRACE_RECORDER.write().unwrap() = trace_recorder;
+}
+
+fn active_trace_recorder() -> Option<Arc<TraceRecorder>> {
+    ACTIVE_TRACE_RECORDER.read().unwrap().clone()
+}
+
+// `std::thread::ThreadId` has no stable numeric value, so we number the
+// threads ourselves (in the order that they first record something).
+static NEXT_TRACE_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+thread_local! {
+    static TRACE_THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+fn trace_thread_id() -> u64 {
+    TRACE_THREAD_ID.with(|thread_id| {
+        if thread_id.get() == 0 {
+            thread_id.set(NEXT_TRACE_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        thread_id.get()
+    })
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum TraceCategory {
+    Scramble,
+    SearchPhase,
+    TableBuild,
+    SearchDepth,
+}
+
+impl TraceCategory {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TraceCategory::Scramble => "scramble",
+            TraceCategory::SearchPhase => "search_phase",
+            TraceCategory::TableBuild => "table_build",
+            TraceCategory::SearchDepth => "search_depth",
+        }
+    }
+}
+
+/// Records the time from its creation until it is dropped (if a recorder was
+/// active when it was created).
+pub(crate) struct TraceSpan {
+    trace_recorder: Arc<TraceRecorder>,
+    name: String,
+    category: TraceCategory,
+    start_time: instant::Instant,
+    args: Map<String, Value>,
+}
+
+impl TraceSpan {
+    /// Returns `None` (without formatting the name) if no recorder is active.
+    pub fn start(category: TraceCategory, name: impl FnOnce() -> String) -> Option<Self> {
+        let trace_recorder = active_trace_recorder()?;
+        Some(Self {
+            trace_recorder,
+            name: name(),
+            category,
+            start_time: instant::Instant::now(),
+            args: Map::new(),
+        })
+    }
+
+    pub fn set_arg(&mut self, name: &str, value: impl Into<Value>) {
+        self.args.insert(name.to_owned(), value.into());
+    }
+}
+
+impl Drop for TraceSpan {
+    fn drop(&mut self) {
+        let end_time = instant::Instant::now();
+        let ts = self
+            .trace_recorder
+            .microseconds_since_start(self.start_time);
+        let event = ChromeTraceEvent {
+            name: std::mem::take(&mut self.name),
+            cat: self.category.as_str(),
+            ph: "X",
+            ts,
+            dur: self.trace_recorder.microseconds_since_start(end_time) - ts,
+            pid: 1,
+            tid: trace_thread_id(),
+            args: std::mem::take(&mut self.args),
+        };
+        self.trace_recorder.events.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn trace_recorder_test() {
+    let trace_recorder = Arc::new(TraceRecorder::new());
+    set_trace_recorder(Some(trace_recorder.clone()));
+    {
+        let mut span = TraceSpan::start(TraceCategory::SearchDepth, || "Search depth 3".to_owned());
+        if let Some(span) = &mut span {
+            span.set_arg("recursive_calls", 12);
+        }
+    }
+    set_trace_recorder(None);
+    assert!(TraceSpan::start(TraceCategory::SearchDepth, || unreachable!()).is_none());
+
+    // Other tests may record events at the same time, so we only look for ours.
+    let trace: Value = serde_json::from_str(&trace_recorder.to_chrome_trace_json()).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let event = events
+        .iter()
+        .find(|event| event["name"] == "Search depth 3")
+        .unwrap();
+    assert_eq!(event["cat"], "search_depth");
+    assert_eq!(event["ph"], "X");
+    assert_eq!(event["args"]["recursive_calls"], 12);
+}
@@ -0,0 +1,68 @@
+//! The supported public API of `twsearch`.
+//!
+//! Everything here is re-exported from elsewhere in the crate, and is kept
+//! stable across internal changes (e.g. to the solvers for individual
+//! puzzles). Items that are only reachable through `_internal` (or the
+//! individual puzzle modules of `scramble`) may change or disappear without
+//! notice, so downstream code should import from this module instead.
+
+// Puzzle definitions, patterns, and algs.
+pub use cubing::{
+    alg::{Alg, Move, QuantumMove},
+    kpuzzle::{KPattern, KPuzzle, KTransformation},
+};
+
+// Scrambles.
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::scramble::ScramblePrefetcher;
+pub use crate::scramble::{
+    event_capabilities, random_scramble_for_event, random_scramble_for_event_with_audit_log,
+    random_scramble_for_event_with_compressed_notation, random_scramble_for_event_with_length,
+    random_scramble_for_event_with_notation, random_scramble_for_event_with_options,
+    random_scramble_for_event_with_random_source, random_scramble_for_event_with_time_budget,
+    random_scrambles_for_event, supported_events, Event, EventCapabilities, EventError,
+    ExpectedLatency, Puzzle, RandomMoveScrambleLength, ScrambleAuditLog, ScrambleAuditRecord,
+    ScrambleDistanceRange, ScrambleMethod, ScrambleOptions, ScrambleRandomSource, ScrambleSeed,
+    ScrambleSession, SeededScramble, TimeBoundedScramble,
+};
+
+// 3x3x3 solvers and analysis.
+pub use crate::scramble::{
+    analyze_3x3x3_difficulty, analyze_3x3x3_neutrality, recognize_3x3x3_last_layer,
+    solve_3x3x3_cross, solve_3x3x3_f2l_pairs, solve_3x3x3_last_layer,
+    solve_3x3x3_with_max_total_length, solve_3x3x3_within_time_budget, Cube3x3x3DifficultyMetrics,
+    Cube3x3x3PhaseGenerators, Scramble3x3x3TwoPhase,
+};
+
+// Health checks (e.g. for scramble servers).
+pub use crate::scramble::{self_test, SelfTestReport};
+#[cfg(feature = "randomizer-verification")]
+pub use crate::scramble::{verify_randomizer_for_event, RandomizerVerificationReport};
+
+// Solving with whichever solver is fastest for the request.
+pub use crate::scramble::{
+    choose_solve_strategy, solve_pattern, PatternSolution, SolveConstraints, SolveStrategy,
+};
+
+// Tracking a physical puzzle (e.g. a smart cube).
+pub use crate::scramble::{
+    verify_scramble_application, PatternTracker, ScrambleApplication, ScrambleDeviation,
+};
+
+// General search.
+pub use crate::_internal::{
+    options::{CustomGenerators, Generators, MetricEnum},
+    IDFSearch, IndividualSearchOptions, SearchDirection, SearchLogger, SearchSolutions,
+    SolutionOrder, SolutionTieBreak,
+};
+
+// Options.
+pub use crate::_internal::{
+    set_table_memory_mode, table_memory_mode, NotationDialect, ParallelOptions, TableMemoryMode,
+};
+
+// Profiling.
+pub use crate::_internal::{set_trace_recorder, TraceRecorder};
+
+// Errors.
+pub use crate::_internal::{PuzzleError, SearchError, TwsearchError};
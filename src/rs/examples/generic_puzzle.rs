@@ -0,0 +1,82 @@
+//! Implements `GenericPuzzle` for a small puzzle that doesn't have a `KPuzzle`
+//! definition: a row of 5 tiles, where each move swaps two adjacent tiles.
+//!
+//! Run using: `cargo run --example generic_puzzle`
+
+use twsearch::{generic_ida_star, GenericPuzzle};
+
+const NUM_TILES: usize = 5;
+
+// The tile at each position.
+type Tiles = [u8; NUM_TILES];
+
+// The position that each tile comes from.
+type Permutation = [u8; NUM_TILES];
+
+struct TileRow;
+
+impl GenericPuzzle for TileRow {
+    type Pattern = Tiles;
+    type Transformation = Permutation;
+
+    fn pattern_apply_transformation(&self, pattern: &Tiles, transformation: &Permutation) -> Tiles {
+        transformation.map(|from| pattern[from as usize])
+    }
+
+    fn transformation_invert(&self, transformation: &Permutation) -> Permutation {
+        let mut inverse = [0; NUM_TILES];
+        for (to, from) in transformation.iter().enumerate() {
+            inverse[*from as usize] = to as u8;
+        }
+        inverse
+    }
+}
+
+fn adjacent_swap(position: usize) -> Permutation {
+    let mut permutation: Permutation = [0, 1, 2, 3, 4];
+    permutation.swap(position, position + 1);
+    permutation
+}
+
+// Each swap fixes at most one inversion, so this never overestimates.
+fn num_inversions(tiles: &Tiles) -> usize {
+    (0..NUM_TILES)
+        .flat_map(|i| ((i + 1)..NUM_TILES).map(move |j| (i, j)))
+        .filter(|(i, j)| tiles[*i] > tiles[*j])
+        .count()
+}
+
+fn main() {
+    let moves: Vec<Permutation> = (0..(NUM_TILES - 1)).map(adjacent_swap).collect();
+    let start_pattern: Tiles = [4, 3, 2, 1, 0];
+    let target_pattern: Tiles = [0, 1, 2, 3, 4];
+
+    let solution = generic_ida_star(
+        &TileRow,
+        &start_pattern,
+        &target_pattern,
+        &moves,
+        10,
+        num_inversions,
+    )
+    .expect("No solution found");
+    println!(
+        "Solution ({} swaps): {}",
+        solution.len(),
+        solution
+            .iter()
+            .map(|move_index| format!("swap({}, {})", move_index, move_index + 1))
+            .collect::<Vec<String>>()
+            .join(" ")
+    );
+
+    // Undoing the solution (backwards, using inverse moves) returns to the start.
+    let mut pattern = target_pattern;
+    for move_index in solution.iter().rev() {
+        pattern = TileRow.pattern_apply_transformation(
+            &pattern,
+            &TileRow.transformation_invert(&moves[*move_index]),
+        );
+    }
+    assert_eq!(pattern, start_pattern);
+}
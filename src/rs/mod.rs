@@ -1,3 +1,12 @@
+pub mod api;
+
+// Not part of the supported API (see `api`), but public for the CLI and the
+// bindings in this repository.
+#[doc(hidden)]
 pub mod _internal;
+pub use _internal::{generic_ida_star, generic_ida_star_backward, GenericPuzzle};
 
 pub mod scramble;
+
+#[cfg(feature = "search-core")]
+pub mod search_core;
@@ -0,0 +1,143 @@
+use super::{scramble_search::filtering_profile_for_event, Event};
+
+const ALL_EVENTS: [Event; 21] = [
+    Event::Cube3x3x3Speedsolving,
+    Event::Cube2x2x2Speedsolving,
+    Event::Cube4x4x4Speedsolving,
+    Event::Cube5x5x5Speedsolving,
+    Event::Cube6x6x6Speedsolving,
+    Event::Cube7x7x7Speedsolving,
+    Event::Cube3x3x3Blindfolded,
+    Event::Cube3x3x3FewestMoves,
+    Event::Cube3x3x3OneHanded,
+    Event::ClockSpeedsolving,
+    Event::MegaminxSpeedsolving,
+    Event::PyraminxSpeedsolving,
+    Event::SkewbSpeedsolving,
+    Event::Square1Speedsolving,
+    Event::Cube4x4x4Blindfolded,
+    Event::Cube5x5x5Blindfolded,
+    Event::Cube3x3x3MultiBlind,
+    Event::FTOSpeedsolving,
+    Event::MasterTetraminxSpeedsolving,
+    Event::KilominxSpeedsolving,
+    Event::RediCubeSpeedsolving,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrambleMethod {
+    /// A uniformly random pattern, solved by search.
+    RandomState,
+    /// A sequence of random moves.
+    RandomMoves,
+}
+
+/// Roughly how long the first scramble for an event takes (including building
+/// any tables). Later scrambles are usually much faster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedLatency {
+    /// Less than a few milliseconds.
+    Instant,
+    /// Less than about a second.
+    Fast,
+    /// Several seconds (or more on slow devices).
+    Slow,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventCapabilities {
+    pub event: Event,
+    pub scramble_method: ScrambleMethod,
+    /// Whether scrambles that are too easy (e.g. a short optimal solution) are rejected.
+    pub is_filtered: bool,
+    pub first_scramble_latency: ExpectedLatency,
+    /// An estimate of the memory used by the tables for the event (which are
+    /// kept for the rest of the process), in bytes.
+    pub approximate_table_memory_bytes: usize,
+    /// Whether `random_scramble_for_event_with_length(…)` is supported.
+    pub supports_custom_length: bool,
+    /// Whether `random_scramble_for_event_with_time_budget(…)` can fall back to random moves.
+    pub has_random_move_fallback: bool,
+}
+
+const MEGABYTE: usize = 1 << 20;
+
+fn random_state(
+    event: Event,
+    first_scramble_latency: ExpectedLatency,
+    approximate_table_memory_bytes: usize,
+) -> EventCapabilities {
+    EventCapabilities {
+        event,
+        scramble_method: ScrambleMethod::RandomState,
+        is_filtered: filtering_profile_for_event(event).is_some(),
+        first_scramble_latency,
+        approximate_table_memory_bytes,
+        supports_custom_length: false,
+        has_random_move_fallback: true,
+    }
+}
+
+fn random_moves(event: Event, supports_custom_length: bool) -> EventCapabilities {
+    EventCapabilities {
+        event,
+        scramble_method: ScrambleMethod::RandomMoves,
+        is_filtered: false,
+        first_scramble_latency: ExpectedLatency::Instant,
+        approximate_table_memory_bytes: 0,
+        supports_custom_length,
+        has_random_move_fallback: false,
+    }
+}
+
+/// Returns the capabilities of the event, or `None` if it can't be scrambled yet.
+pub fn event_capabilities(event: Event) -> Option<EventCapabilities> {
+    // Keep this in sync with `random_scramble_for_event(…)`.
+    Some(match event {
+        // Two-phase coordinate tables, plus a small filtering search.
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3MultiBlind => random_state(event, ExpectedLatency::Slow, 8 * MEGABYTE),
+        Event::Cube3x3x3FewestMoves => EventCapabilities {
+            has_random_move_fallback: false,
+            ..random_state(event, ExpectedLatency::Slow, 8 * MEGABYTE)
+        },
+        // The default prune table size for a search.
+        Event::Cube2x2x2Speedsolving | Event::PyraminxSpeedsolving => {
+            random_state(event, ExpectedLatency::Fast, MEGABYTE)
+        }
+        Event::Cube5x5x5Speedsolving
+        | Event::Cube6x6x6Speedsolving
+        | Event::Cube7x7x7Speedsolving
+        | Event::MegaminxSpeedsolving => random_moves(event, true),
+        Event::Cube5x5x5Blindfolded => random_moves(event, false),
+        // Random pin and dial settings, which don't need a search.
+        Event::ClockSpeedsolving => EventCapabilities {
+            event,
+            scramble_method: ScrambleMethod::RandomState,
+            is_filtered: false,
+            first_scramble_latency: ExpectedLatency::Instant,
+            approximate_table_memory_bytes: 0,
+            supports_custom_length: false,
+            has_random_move_fallback: false,
+        },
+        Event::Cube4x4x4Speedsolving
+        | Event::SkewbSpeedsolving
+        | Event::Square1Speedsolving
+        | Event::Cube4x4x4Blindfolded
+        | Event::FTOSpeedsolving
+        | Event::MasterTetraminxSpeedsolving
+        | Event::KilominxSpeedsolving
+        | Event::RediCubeSpeedsolving => return None,
+    })
+}
+
+/// Returns the capabilities of all the events that can be scrambled, e.g. for
+/// building menus and warning before a slow first scramble.
+pub fn supported_events() -> Vec<EventCapabilities> {
+    ALL_EVENTS
+        .into_iter()
+        .filter_map(event_capabilities)
+        .collect()
+}
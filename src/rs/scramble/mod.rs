@@ -1,7 +1,19 @@
 mod collapse;
 mod puzzles;
 mod randomize;
+pub use randomize::{ScrambleRandomSource, ScrambleSeed};
+mod rejection_tracker;
+mod scramble_length;
+pub use scramble_length::RandomMoveScrambleLength;
+mod scramble_options;
+pub use scramble_options::{ScrambleDistanceRange, ScrambleOptions};
+mod scramble_audit_log;
+pub use scramble_audit_log::{
+    random_scramble_for_event_with_audit_log, ScrambleAuditLog, ScrambleAuditRecord,
+};
 mod scramble_search;
+mod wide_move_compression;
+pub use wide_move_compression::compress_wide_moves;
 
 mod puzzle;
 pub use puzzle::{Puzzle, PuzzleError};
@@ -9,5 +21,66 @@ pub use puzzle::{Puzzle, PuzzleError};
 mod event;
 pub use event::{Event, EventError};
 
+mod event_capabilities;
+pub use event_capabilities::{
+    event_capabilities, supported_events, EventCapabilities, ExpectedLatency, ScrambleMethod,
+};
+
 mod random_scramble_for_event;
-pub use random_scramble_for_event::random_scramble_for_event;
+pub use random_scramble_for_event::{
+    random_scramble_for_event, random_scramble_for_event_with_compressed_notation,
+    random_scramble_for_event_with_length, random_scramble_for_event_with_notation,
+    random_scramble_for_event_with_options, random_scramble_for_event_with_random_source,
+    random_scramble_for_event_with_time_budget, random_scrambles_for_event, SeededScramble,
+    TimeBoundedScramble,
+};
+
+mod pattern_tracker;
+pub use pattern_tracker::{
+    verify_scramble_application, PatternTracker, ScrambleApplication, ScrambleDeviation,
+};
+
+// WASM has no background threads to prefetch with.
+#[cfg(not(target_arch = "wasm32"))]
+mod scramble_prefetcher;
+#[cfg(not(target_arch = "wasm32"))]
+pub use scramble_prefetcher::ScramblePrefetcher;
+
+mod scramble_session;
+pub use scramble_session::ScrambleSession;
+
+#[cfg(feature = "randomizer-verification")]
+mod randomizer_verification;
+#[cfg(feature = "randomizer-verification")]
+pub use randomizer_verification::{verify_randomizer_for_event, RandomizerVerificationReport};
+
+mod self_test;
+pub use self_test::{self_test, SelfTestReport};
+
+mod solve_pattern;
+pub use solve_pattern::{
+    choose_solve_strategy, solve_pattern, PatternSolution, SolveConstraints, SolveStrategy,
+};
+
+pub use puzzles::cube3x3x3_difficulty::{analyze_3x3x3_difficulty, Cube3x3x3DifficultyMetrics};
+
+pub use puzzles::cube3x3x3_trainer::{
+    solve_3x3x3_cross, solve_3x3x3_f2l_pairs, Cube3x3x3F2LSlot, Cube3x3x3Face,
+    Cube3x3x3TrainerSolutions, Cube3x3x3TrainerSolver,
+};
+
+pub use puzzles::cube3x3x3_last_layer::{
+    recognize_3x3x3_last_layer, solve_3x3x3_last_layer, Cube3x3x3LastLayerCase,
+    Cube3x3x3LastLayerSolutions, Cube3x3x3LastLayerSolver,
+};
+
+pub use puzzles::cube3x3x3_neutrality::{
+    analyze_3x3x3_neutrality, Cube3x3x3NeutralityAnalyzer, Cube3x3x3OrientationAnalysis,
+};
+
+pub use puzzles::cube4x4x4_reduction::{cube4x4x4_pattern_from_reduction, Cube4x4x4Reduction};
+
+pub use puzzles::cube3x3x3::{
+    solve_3x3x3_with_max_total_length, solve_3x3x3_within_time_budget, Cube3x3x3PhaseGenerators,
+    Scramble3x3x3TwoPhase,
+};
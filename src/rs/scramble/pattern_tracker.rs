@@ -0,0 +1,349 @@
+use cubing::{
+    alg::{Alg, AlgNode, Move},
+    kpuzzle::{KPattern, KPuzzle},
+};
+
+use crate::_internal::{CubeOrientationCanonicalizer, PuzzleError};
+
+use super::solve_pattern::{solve_pattern, PatternSolution, SolveConstraints};
+
+// The order of a single move is small for any real puzzle.
+const MAX_QUANTUM_ORDER: usize = 12;
+
+/// Tracks the pattern of a physical puzzle from the moves that are done on it
+/// (e.g. a stream of moves from a smart cube), so that the current pattern is
+/// always available without replaying the whole stream.
+#[derive(Clone, Debug)]
+pub struct PatternTracker {
+    pattern: KPattern,
+    num_moves: usize,
+}
+
+impl PatternTracker {
+    /// Starts tracking from the default (solved) pattern of the puzzle.
+    pub fn new(kpuzzle: &KPuzzle) -> Self {
+        Self::from_pattern(kpuzzle.default_pattern())
+    }
+
+    /// Starts tracking from the given pattern (e.g. the state that a smart
+    /// cube reports when it connects).
+    pub fn from_pattern(pattern: KPattern) -> Self {
+        Self {
+            pattern,
+            num_moves: 0,
+        }
+    }
+
+    pub fn apply_move(&mut self, r#move: &Move) -> Result<(), PuzzleError> {
+        let transformation = self
+            .pattern
+            .kpuzzle()
+            .transformation_from_move(r#move)
+            .map_err(|e| PuzzleError {
+                description: format!("Invalid move {}: {}", r#move, e),
+            })?;
+        self.pattern = self.pattern.apply_transformation(&transformation);
+        self.num_moves += 1;
+        Ok(())
+    }
+
+    /// Applies each move in order. If a move is invalid, the moves before it
+    /// are still applied.
+    pub fn apply_moves<'a>(
+        &mut self,
+        moves: impl IntoIterator<Item = &'a Move>,
+    ) -> Result<(), PuzzleError> {
+        for r#move in moves {
+            self.apply_move(r#move)?;
+        }
+        Ok(())
+    }
+
+    pub fn pattern(&self) -> &KPattern {
+        &self.pattern
+    }
+
+    /// The number of moves applied since tracking started (or the last `reset(…)`).
+    pub fn num_moves(&self) -> usize {
+        self.num_moves
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.pattern == self.pattern.kpuzzle().default_pattern()
+    }
+
+    /// Starts tracking again from the given pattern (e.g. after the puzzle has
+    /// been solved and reset by hand).
+    pub fn reset(&mut self, pattern: KPattern) {
+        *self = Self::from_pattern(pattern);
+    }
+
+    /// Whether the current pattern is the one that `alg` produces from the
+    /// default pattern (e.g. to check that a scramble was applied correctly).
+    pub fn matches_alg(&self, alg: &Alg) -> Result<bool, PuzzleError> {
+        let kpuzzle = self.pattern.kpuzzle();
+        let transformation = kpuzzle
+            .transformation_from_alg(alg)
+            .map_err(|e| PuzzleError {
+                description: format!("Invalid alg {}: {}", alg, e),
+            })?;
+        Ok(self.pattern
+            == kpuzzle
+                .default_pattern()
+                .apply_transformation(&transformation))
+    }
+
+    /// Solves the current pattern using `solve_pattern(…)`. For the 3x3x3,
+    /// this only uses the two-phase solver if the pattern uses the centerless
+    /// 3x3x3 orbits.
+    pub fn solve(&self, constraints: &SolveConstraints) -> Result<PatternSolution, PuzzleError> {
+        solve_pattern(&self.pattern, constraints)
+    }
+}
+
+/// Where the observed moves deviated from a scramble (see
+/// `verify_scramble_application(…)`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrambleDeviation {
+    /// The index of the first observed move that left the scramble (and
+    /// wasn't undone afterwards).
+    pub observed_move_index: usize,
+    pub observed_move: Move,
+    /// The number of scramble moves that had been applied before the deviation.
+    pub num_scramble_moves_applied: usize,
+    /// The scramble move that was expected next, or `None` if the scramble
+    /// had already been applied.
+    pub expected_move: Option<Move>,
+}
+
+/// The result of `verify_scramble_application(…)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrambleApplication {
+    /// The observed moves produce the scramble's pattern.
+    Applied,
+    /// The observed moves follow the scramble so far, but haven't finished it.
+    InProgress {
+        num_scramble_moves_applied: usize,
+    },
+    Deviated(ScrambleDeviation),
+}
+
+// The patterns after each prefix of a scramble, compared up to whole-puzzle
+// rotations for cubes.
+struct ScramblePrefixes {
+    canonicalizer: Option<CubeOrientationCanonicalizer>,
+    moves: Vec<Move>,
+    patterns: Vec<KPattern>,
+    canonical_patterns: Vec<KPattern>,
+}
+
+impl ScramblePrefixes {
+    fn try_new(kpuzzle: &KPuzzle, scramble: &Alg) -> Result<Self, PuzzleError> {
+        let mut tracker = PatternTracker::new(kpuzzle);
+        let mut moves = Vec::<Move>::new();
+        let mut patterns = vec![tracker.pattern().clone()];
+        for node in &scramble.nodes {
+            let AlgNode::MoveNode(r#move) = node else {
+                let node_alg = Alg {
+                    nodes: vec![node.clone()],
+                };
+                // Line breaks (e.g. in big cube scrambles) don't change the pattern.
+                if PatternTracker::new(kpuzzle).matches_alg(&node_alg)? {
+                    continue;
+                }
+                return Err(PuzzleError {
+                    description: format!(
+                        "Scrambles can only contain moves, but found: {}",
+                        node_alg
+                    ),
+                });
+            };
+            tracker.apply_move(r#move)?;
+            moves.push(r#move.clone());
+            patterns.push(tracker.pattern().clone());
+        }
+        let canonicalizer = CubeOrientationCanonicalizer::try_new(kpuzzle).ok();
+        let canonical_patterns = patterns
+            .iter()
+            .map(|pattern| Self::canonicalize(&canonicalizer, pattern))
+            .collect();
+        Ok(Self {
+            canonicalizer,
+            moves,
+            patterns,
+            canonical_patterns,
+        })
+    }
+
+    fn canonicalize(
+        canonicalizer: &Option<CubeOrientationCanonicalizer>,
+        pattern: &KPattern,
+    ) -> KPattern {
+        match canonicalizer {
+            Some(canonicalizer) => canonicalizer.canonicalize(pattern).1,
+            None => pattern.clone(),
+        }
+    }
+
+    // Whether the pattern is partway through scramble move `index` (e.g. `R`
+    // for `R2`, or after `R'` for `R`), i.e. a turn of the same quantum from
+    // the pattern before it.
+    fn is_partway_through_move(&self, index: usize, canonical_pattern: &KPattern) -> bool {
+        let Some(r#move) = self.moves.get(index) else {
+            return false;
+        };
+        let start_pattern = &self.patterns[index];
+        let canonical_start_pattern = &self.canonical_patterns[index];
+        let quantum_transformation = start_pattern
+            .kpuzzle()
+            .transformation_from_move(&Move {
+                quantum: r#move.quantum.clone(),
+                amount: 1,
+            })
+            .unwrap();
+        let mut pattern = start_pattern.apply_transformation(&quantum_transformation);
+        for _ in 0..MAX_QUANTUM_ORDER {
+            let canonical_partial_pattern = Self::canonicalize(&self.canonicalizer, &pattern);
+            if canonical_partial_pattern == *canonical_start_pattern {
+                return false;
+            }
+            if canonical_partial_pattern == *canonical_pattern {
+                return true;
+            }
+            pattern = pattern.apply_transformation(&quantum_transformation);
+        }
+        false
+    }
+}
+
+/// Checks whether `observed_moves` (e.g. from a smart cube) apply `scramble`
+/// to the puzzle, starting from the default pattern.
+///
+/// The moves don't need to match the scramble exactly: a move can be done in
+/// either direction or in parts (e.g. `R R` or `R' R'` for `R2`), cube
+/// patterns are compared up to whole-puzzle rotations (so a scramble with
+/// rotations matches the face turns that a smart cube reports), and mistakes
+/// that are undone (e.g. `F F'`) are ignored.
+pub fn verify_scramble_application(
+    kpuzzle: &KPuzzle,
+    scramble: &Alg,
+    observed_moves: &[Move],
+) -> Result<ScrambleApplication, PuzzleError> {
+    let prefixes = ScramblePrefixes::try_new(kpuzzle, scramble)?;
+    let num_scramble_moves = prefixes.moves.len();
+    let mut tracker = PatternTracker::new(kpuzzle);
+    let mut num_scramble_moves_applied = 0;
+    let mut deviation: Option<ScrambleDeviation> = None;
+    for (observed_move_index, observed_move) in observed_moves.iter().enumerate() {
+        tracker.apply_move(observed_move)?;
+        let canonical_pattern =
+            ScramblePrefixes::canonicalize(&prefixes.canonicalizer, tracker.pattern());
+        // Rotations in the scramble don't need an observed move, so we skip past them.
+        let mut is_on_track = false;
+        for index in (num_scramble_moves_applied..=num_scramble_moves).rev() {
+            if prefixes.canonical_patterns[index] == canonical_pattern {
+                num_scramble_moves_applied = index;
+                is_on_track = true;
+                break;
+            }
+        }
+        if !is_on_track {
+            is_on_track =
+                prefixes.is_partway_through_move(num_scramble_moves_applied, &canonical_pattern);
+        }
+        if is_on_track {
+            deviation = None;
+        } else if deviation.is_none() {
+            deviation = Some(ScrambleDeviation {
+                observed_move_index,
+                observed_move: observed_move.clone(),
+                num_scramble_moves_applied,
+                expected_move: prefixes.moves.get(num_scramble_moves_applied).cloned(),
+            });
+        }
+    }
+    Ok(match deviation {
+        Some(deviation) => ScrambleApplication::Deviated(deviation),
+        None if prefixes.canonical_patterns[num_scramble_moves_applied]
+            == prefixes.canonical_patterns[num_scramble_moves] =>
+        {
+            ScrambleApplication::Applied
+        }
+        None => ScrambleApplication::InProgress {
+            num_scramble_moves_applied,
+        },
+    })
+}
+
+#[test]
+fn pattern_tracker_test() -> Result<(), PuzzleError> {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    let mut pattern_tracker = PatternTracker::new(cube2x2x2_kpuzzle());
+    let scramble: Alg = "R U' F2".parse().unwrap();
+    for node in &scramble.nodes {
+        let cubing::alg::AlgNode::MoveNode(r#move) = node else {
+            panic!("The scramble should only have moves.");
+        };
+        pattern_tracker.apply_move(r#move)?;
+    }
+    assert_eq!(pattern_tracker.num_moves(), 3);
+    assert!(pattern_tracker.matches_alg(&scramble)?);
+    assert!(!pattern_tracker.is_solved());
+
+    let solution = pattern_tracker.solve(&SolveConstraints::default())?;
+    assert_eq!(solution.alg.nodes.len(), 3);
+    for node in &solution.alg.nodes {
+        let cubing::alg::AlgNode::MoveNode(r#move) = node else {
+            panic!("The solution should only have moves.");
+        };
+        pattern_tracker.apply_move(r#move)?;
+    }
+    assert!(pattern_tracker.is_solved());
+
+    assert!(pattern_tracker
+        .apply_move(&"Q".parse::<Move>().unwrap())
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn verify_scramble_application_test() -> Result<(), PuzzleError> {
+    use cubing::puzzles::cube3x3x3_kpuzzle;
+
+    let kpuzzle = cube3x3x3_kpuzzle();
+    let scramble: Alg = "R2 U x F".parse().unwrap();
+    let verify = |observed_moves: &str| {
+        let observed_moves: Vec<Move> = observed_moves
+            .split_whitespace()
+            .map(|r#move| r#move.parse().unwrap())
+            .collect();
+        verify_scramble_application(kpuzzle, &scramble, &observed_moves)
+    };
+    // `F` after `x` turns the face that started on `D`.
+    assert_eq!(verify("R R U D")?, ScrambleApplication::Applied);
+    assert_eq!(verify("R' R' U D")?, ScrambleApplication::Applied);
+    assert_eq!(
+        verify("R R U")?,
+        ScrambleApplication::InProgress {
+            num_scramble_moves_applied: 3
+        }
+    );
+    // Undone mistakes are ignored.
+    assert_eq!(
+        verify("R F F'")?,
+        ScrambleApplication::InProgress {
+            num_scramble_moves_applied: 0
+        }
+    );
+    assert_eq!(
+        verify("R F")?,
+        ScrambleApplication::Deviated(ScrambleDeviation {
+            observed_move_index: 1,
+            observed_move: "F".parse().unwrap(),
+            num_scramble_moves_applied: 0,
+            expected_move: Some("R2".parse().unwrap()),
+        })
+    );
+    Ok(())
+}
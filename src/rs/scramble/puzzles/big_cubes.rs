@@ -1,10 +1,10 @@
 use std::sync::OnceLock;
 
 use cubing::{
-    alg::{Alg, AlgNode, Move},
+    alg::{Alg, AlgNode, Move, Newline},
     kpuzzle::KPuzzle,
 };
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use rand::{seq::SliceRandom, Rng};
 
 use crate::_internal::{
     options::CustomGenerators, CanonicalFSM, MoveClassIndex, SearchGenerators,
@@ -12,15 +12,12 @@ use crate::_internal::{
 };
 
 use super::{
+    super::{randomize::ScrambleRng, scramble_length::RandomMoveScrambleLength, Event},
     definitions::{cube5x5x5_kpuzzle, cube6x6x6_kpuzzle, cube7x7x7_kpuzzle},
     static_move_list::{add_random_suffixes_from, static_parsed_list, static_parsed_opt_list},
 };
 
-const NUM_5X5X5_RANDOM_MOVES: usize = 60;
-const NUM_6X6X6_RANDOM_MOVES: usize = 80;
-const NUM_7X7X7_RANDOM_MOVES: usize = 100;
-
-struct ScrambleInfo {
+pub(crate) struct ScrambleInfo {
     generators: SearchGenerators,
     canonical_fsm: CanonicalFSM,
 }
@@ -47,6 +44,12 @@ impl ScrambleInfo {
 
 static CUBE5X5X5_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
 pub fn scramble_5x5x5() -> Alg {
+    scramble_5x5x5_with_length(
+        &RandomMoveScrambleLength::default_for_event(Event::Cube5x5x5Speedsolving).unwrap(),
+    )
+}
+
+pub fn scramble_5x5x5_with_length(length: &RandomMoveScrambleLength) -> Alg {
     let scramble_info = CUBE5X5X5_SCRAMBLE_INFO_CELL.get_or_init(|| {
         ScrambleInfo::new(
             cube5x5x5_kpuzzle(),
@@ -60,7 +63,7 @@ pub fn scramble_5x5x5() -> Alg {
             ]),
         )
     });
-    scramble_big_cube(scramble_info, NUM_5X5X5_RANDOM_MOVES)
+    scramble_with_random_moves(scramble_info, length)
 }
 
 pub fn scramble_5x5x5_bld() -> Alg {
@@ -71,6 +74,12 @@ pub fn scramble_5x5x5_bld() -> Alg {
 
 static CUBE6X6X6_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
 pub fn scramble_6x6x6() -> Alg {
+    scramble_6x6x6_with_length(
+        &RandomMoveScrambleLength::default_for_event(Event::Cube6x6x6Speedsolving).unwrap(),
+    )
+}
+
+pub fn scramble_6x6x6_with_length(length: &RandomMoveScrambleLength) -> Alg {
     let scramble_info = CUBE6X6X6_SCRAMBLE_INFO_CELL.get_or_init(|| {
         ScrambleInfo::new(
             cube6x6x6_kpuzzle(),
@@ -84,11 +93,17 @@ pub fn scramble_6x6x6() -> Alg {
             ]),
         )
     });
-    scramble_big_cube(scramble_info, NUM_6X6X6_RANDOM_MOVES)
+    scramble_with_random_moves(scramble_info, length)
 }
 
 static CUBE7X7X7_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
 pub fn scramble_7x7x7() -> Alg {
+    scramble_7x7x7_with_length(
+        &RandomMoveScrambleLength::default_for_event(Event::Cube7x7x7Speedsolving).unwrap(),
+    )
+}
+
+pub fn scramble_7x7x7_with_length(length: &RandomMoveScrambleLength) -> Alg {
     let scramble_info = CUBE7X7X7_SCRAMBLE_INFO_CELL.get_or_init(|| {
         ScrambleInfo::new(
             cube7x7x7_kpuzzle(),
@@ -102,15 +117,31 @@ pub fn scramble_7x7x7() -> Alg {
             ]),
         )
     });
-    scramble_big_cube(scramble_info, NUM_7X7X7_RANDOM_MOVES)
+    scramble_with_random_moves(scramble_info, length)
 }
 
-fn scramble_big_cube(scramble_info: &ScrambleInfo, num_random_moves: usize) -> Alg {
+/// The puzzle for big cube events, for post-processing their scrambles.
+pub(crate) fn big_cube_kpuzzle_for_event(event: Event) -> Option<&'static KPuzzle> {
+    match event {
+        Event::Cube5x5x5Speedsolving | Event::Cube5x5x5Blindfolded => Some(cube5x5x5_kpuzzle()),
+        Event::Cube6x6x6Speedsolving => Some(cube6x6x6_kpuzzle()),
+        Event::Cube7x7x7Speedsolving => Some(cube7x7x7_kpuzzle()),
+        _ => None,
+    }
+}
+
+pub(crate) fn scramble_with_random_moves(
+    scramble_info: &ScrambleInfo,
+    length: &RandomMoveScrambleLength,
+) -> Alg {
     // TODO: globally cache generators and `canonical_fsm` for each puzzle.
     let mut current_fsm_state = CANONICAL_FSM_START_STATE;
-    let mut rng = thread_rng();
+    let mut rng = ScrambleRng;
     let mut nodes = Vec::<AlgNode>::default();
-    for _ in 0..num_random_moves {
+    for i in 0..(length.num_blocks * length.num_random_moves_per_block) {
+        if i > 0 && i % length.num_random_moves_per_block == 0 {
+            nodes.push(Newline::default().into());
+        }
         // TODO: we can forward-cache the valid move classes for each state instead of rejection sampling.
         loop {
             let move_class_index =
@@ -1,8 +1,10 @@
 use cubing::alg::{parse_move, Alg, AlgNode, Move};
-use rand::{thread_rng, Rng};
+use rand::Rng;
+
+use super::super::randomize::ScrambleRng;
 
 pub fn scramble_clock() -> Alg {
-    let mut rng = thread_rng();
+    let mut rng = ScrambleRng;
     let mut alg_nodes = Vec::<AlgNode>::new();
 
     // TODO: implement `parse_quantum_move!(…)`?
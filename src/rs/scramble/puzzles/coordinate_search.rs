@@ -0,0 +1,1583 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock},
+};
+
+use cubing::{
+    alg::{Alg, Move, QuantumMove},
+    kpuzzle::{KPattern, KPuzzle, KTransformation},
+};
+
+use crate::_internal::{
+    breadth_first_search, do_transformations_commute, table_memory_mode, BreadthFirstSearchControl,
+    PuzzleError, TraceCategory, TraceSpan,
+};
+
+// Coordinate searches are used for phases with a known (small) diameter.
+const MAX_COORDINATE_SEARCH_DEPTH: usize = 30;
+
+// Checking the clock at every node is expensive, so we only check the
+// deadline at nodes with at least this much depth left (whose subtrees are
+// large enough that the check is negligible).
+const MIN_REMAINING_DEPTH_FOR_DEADLINE_CHECK: usize = 4;
+
+/// Maps each pattern in a subgroup (e.g. a phase of a multi-phase solver) to
+/// a value in `0..size`, such that the value after a move only depends on the
+/// value before it.
+pub(crate) struct Coordinate {
+    pub encode: fn(&KPattern) -> usize,
+    pub size: usize,
+}
+
+/// Checks that every move of a phase keeps the target of the previous phase
+/// (as seen by `previous_phase_coordinates`), so that a mistake in the phase
+/// definitions is reported up front instead of giving wrong solutions.
+pub(crate) fn check_phase_moves_preserve_target(
+    kpuzzle: &KPuzzle,
+    phase_name: &str,
+    moves: &[Move],
+    previous_phase_name: &str,
+    previous_phase_coordinates: &[Coordinate],
+) -> Result<(), PuzzleError> {
+    let default_pattern = kpuzzle.default_pattern();
+    for r#move in moves {
+        let transformation = kpuzzle
+            .transformation_from_move(r#move)
+            .map_err(|e| PuzzleError {
+                description: format!("Invalid {} move {}: {}", phase_name, r#move, e),
+            })?;
+        let pattern = default_pattern.apply_transformation(&transformation);
+        if let Some(coordinate_index) = previous_phase_coordinates
+            .iter()
+            .position(|coordinate| coordinate.value(&pattern) != coordinate.value(&default_pattern))
+        {
+            return Err(PuzzleError {
+                description: format!(
+                    "{} move {} does not preserve the {} target (it changes coordinate {}).",
+                    phase_name, r#move, previous_phase_name, coordinate_index
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that each move can be undone by a move in the same list. Pruning
+/// tables are built by searching outwards from the target, so their depths
+/// are only distances *to* the target if the moves are closed under inverses.
+pub(crate) fn check_phase_moves_closed_under_inverses(
+    kpuzzle: &KPuzzle,
+    phase_name: &str,
+    moves: &[Move],
+) -> Result<(), PuzzleError> {
+    let transformations = moves
+        .iter()
+        .map(|r#move| {
+            kpuzzle
+                .transformation_from_move(r#move)
+                .map_err(|e| PuzzleError {
+                    description: format!("Invalid {} move {}: {}", phase_name, r#move, e),
+                })
+        })
+        .collect::<Result<Vec<KTransformation>, PuzzleError>>()?;
+    for (r#move, transformation) in moves.iter().zip(&transformations) {
+        let inverse_transformation = transformation.invert();
+        if !transformations.contains(&inverse_transformation) {
+            return Err(PuzzleError {
+                description: format!(
+                    "{} moves must include the inverse of each move (missing the inverse of {}).",
+                    phase_name, r#move
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+// The indices below are all plain `usize`s underneath. Wrapping them makes it
+// a compile error to pass (say) a move index where a coordinate value is
+// expected, or to index `values` with a value instead of a coordinate index.
+
+/// The position of a coordinate in the list passed to `CoordinatePhaseSearch::new(…)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct CoordinateIndex(pub usize);
+
+/// The value of a coordinate for some pattern, always in `0..size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct CoordinateValue(usize);
+
+/// The index of a move in `CoordinatePhaseSearch::moves` (one per move, including each amount).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CoordinateMoveIndex(usize);
+
+impl Coordinate {
+    fn value(&self, pattern: &KPattern) -> CoordinateValue {
+        let value = (self.encode)(pattern);
+        assert!(
+            value < self.size,
+            "Coordinate value {} is out of range (size {}).",
+            value,
+            self.size
+        );
+        CoordinateValue(value)
+    }
+}
+
+// The value of a coordinate after each move, for each value of the coordinate.
+struct CoordinateMoveTable {
+    num_moves: usize,
+    next_values: Vec<u32>,
+}
+
+impl CoordinateMoveTable {
+    // Values that aren't reachable from the start pattern are left uninitialized.
+    fn new(
+        start_pattern: &KPattern,
+        move_transformations: &[KTransformation],
+        coordinate: &Coordinate,
+    ) -> Self {
+        let num_moves = move_transformations.len();
+        let mut next_values = vec![u32::MAX; coordinate.size * num_moves];
+        // We only need one representative pattern for each value.
+        breadth_first_search(
+            (coordinate.value(start_pattern), start_pattern.clone()),
+            |(value, _)| *value,
+            |(value, pattern)| {
+                move_transformations
+                    .iter()
+                    .enumerate()
+                    .map(|(move_index, move_transformation)| {
+                        let next_pattern = pattern.apply_transformation(move_transformation);
+                        let next_value = coordinate.value(&next_pattern);
+                        next_values[value.0 * num_moves + move_index] = next_value.0 as u32;
+                        (next_value, next_pattern)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |_, _, _| BreadthFirstSearchControl::Continue,
+        );
+        Self {
+            num_moves,
+            next_values,
+        }
+    }
+
+    fn apply(&self, value: CoordinateValue, move_index: CoordinateMoveIndex) -> CoordinateValue {
+        debug_assert!(move_index.0 < self.num_moves);
+        let next_value = self.next_values[value.0 * self.num_moves + move_index.0];
+        debug_assert_ne!(
+            next_value,
+            u32::MAX,
+            "Coordinate value {} is not reachable from the start pattern.",
+            value.0
+        );
+        CoordinateValue(next_value as usize)
+    }
+}
+
+// Exact distances for a pair of coordinates.
+struct CoordinatePruneTable {
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    size2: usize,
+    depths: Vec<u8>,
+}
+
+impl CoordinatePruneTable {
+    fn new(
+        coordinate_indices: (CoordinateIndex, CoordinateIndex),
+        move_tables: (&CoordinateMoveTable, &CoordinateMoveTable),
+        sizes: (usize, usize),
+        solved_values: (CoordinateValue, CoordinateValue),
+    ) -> Self {
+        // Both tables are indexed by the same moves.
+        assert_eq!(move_tables.0.num_moves, move_tables.1.num_moves);
+        let (size1, size2) = sizes;
+        let mut depths = vec![u8::MAX; size1 * size2];
+        depths[solved_values.0 .0 * size2 + solved_values.1 .0] = 0;
+        let mut depth = 0;
+        loop {
+            // Each layer only reads the previous ones, so it can be computed in parallel.
+            let next_indices = |index: usize| {
+                let (value1, value2) = (
+                    CoordinateValue(index / size2),
+                    CoordinateValue(index % size2),
+                );
+                (0..move_tables.0.num_moves)
+                    .map(CoordinateMoveIndex)
+                    .map(move |move_index| {
+                        move_tables.0.apply(value1, move_index).0 * size2
+                            + move_tables.1.apply(value2, move_index).0
+                    })
+            };
+            #[cfg(feature = "parallel")]
+            let new_indices: Vec<usize> = {
+                use rayon::prelude::*;
+                (0..depths.len())
+                    .into_par_iter()
+                    .filter(|index| depths[*index] == depth)
+                    .flat_map_iter(next_indices)
+                    .filter(|next_index| depths[*next_index] == u8::MAX)
+                    .collect()
+            };
+            #[cfg(not(feature = "parallel"))]
+            let new_indices: Vec<usize> = (0..depths.len())
+                .filter(|index| depths[*index] == depth)
+                .flat_map(next_indices)
+                .filter(|next_index| depths[*next_index] == u8::MAX)
+                .collect();
+            if new_indices.is_empty() {
+                break;
+            }
+            for next_index in new_indices {
+                depths[next_index] = depth + 1;
+            }
+            depth += 1;
+        }
+        Self {
+            coordinate_indices,
+            size2,
+            depths,
+        }
+    }
+
+    fn membership_within(&self, max_depth: usize) -> CoordinateMembershipSet {
+        let mut bits = vec![0u8; self.depths.len().div_ceil(8)];
+        for (index, depth) in self.depths.iter().enumerate() {
+            if (*depth as usize) <= max_depth {
+                bits[index / 8] |= 1 << (index % 8);
+            }
+        }
+        CoordinateMembershipSet {
+            coordinate_indices: self.coordinate_indices,
+            size2: self.size2,
+            num_entries: self.depths.len(),
+            max_depth: max_depth.min(u8::MAX as usize) as u8,
+            bits,
+        }
+    }
+
+    #[cfg(test)]
+    fn to_bytes(&self) -> Vec<u8> {
+        CoordinateTableHeader {
+            kind: CoordinateTableKind::PruneTable,
+            coordinate_indices: self.coordinate_indices,
+            sizes: (self.depths.len() / self.size2, self.size2),
+            max_depth: u8::MAX,
+        }
+        .write_with_entries(&self.depths)
+    }
+
+    #[cfg(test)]
+    fn from_bytes(bytes: &[u8], search: &CoordinatePhaseSearch) -> Result<Self, PuzzleError> {
+        let (header, entries) = CoordinateTableHeader::read_with_entries(
+            bytes,
+            CoordinateTableKind::PruneTable,
+            search,
+            |num_entries| num_entries,
+        )?;
+        Ok(Self {
+            coordinate_indices: header.coordinate_indices,
+            size2: header.sizes.1,
+            depths: entries.to_vec(),
+        })
+    }
+}
+
+// A lower bound on the number of moves from coordinate values to the target.
+// Each pruning table of a `CoordinatePhaseSearch` is a heuristic, and the
+// search uses the max of the ones that are loaded.
+trait Heuristic: Send + Sync {
+    fn lookup(&self, values: &[CoordinateValue]) -> usize;
+
+    fn approximate_num_bytes(&self) -> usize;
+
+    // Only heuristics with an exact distance stored for every pair can provide this.
+    fn membership_within(&self, _max_depth: usize) -> Option<CoordinateMembershipSet> {
+        None
+    }
+}
+
+impl Heuristic for CoordinatePruneTable {
+    fn lookup(&self, values: &[CoordinateValue]) -> usize {
+        let (value1, value2) = (
+            values[self.coordinate_indices.0 .0],
+            values[self.coordinate_indices.1 .0],
+        );
+        self.depths[value1.0 * self.size2 + value2.0] as usize
+    }
+
+    fn approximate_num_bytes(&self) -> usize {
+        self.depths.len()
+    }
+
+    fn membership_within(&self, max_depth: usize) -> Option<CoordinateMembershipSet> {
+        Some(CoordinatePruneTable::membership_within(self, max_depth))
+    }
+}
+
+// Each slot of a sparse pruning table stores its pair index along with its depth.
+const SPARSE_PRUNE_TABLE_SLOT_NUM_BYTES: usize = std::mem::size_of::<u64>() + 1;
+const SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET: usize = 4;
+// With two buckets of 4 slots per entry, cuckoo hashing reliably reaches a
+// load factor of about 95%, so we aim a little lower.
+const SPARSE_PRUNE_TABLE_MAX_LOAD_PERCENT: usize = 90;
+const SPARSE_PRUNE_TABLE_MAX_NUM_EVICTIONS: usize = 500;
+const SPARSE_PRUNE_TABLE_EMPTY_SLOT: u64 = u64::MAX;
+const SPARSE_PRUNE_TABLE_HASH_SEEDS: [u64; 2] = [0x9E37_79B9_7F4A_7C15, 0xC2B2_AE3D_27D4_EB4F];
+
+// The finalizer of SplitMix64, which mixes every input bit into every output bit.
+fn mix_bits(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+// Distances for a pair of coordinates whose pruning table is too large to
+// store densely. Only the pairs closest to the target are stored (every pair
+// up to `complete_depth`), and every other pair is reported as one move
+// further, which is always a valid lower bound.
+//
+// The stored pairs are kept in a (bucketized) cuckoo hash table, so each
+// lookup reads at most two buckets. Every slot stores its full pair index, so
+// a lookup never returns the depth of a different pair.
+struct SparseCoordinatePruneTable {
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    size2: usize,
+    num_buckets: usize,
+    // `SPARSE_PRUNE_TABLE_EMPTY_SLOT` for empty slots.
+    slot_indices: Vec<u64>,
+    slot_depths: Vec<u8>,
+    complete_depth: u8,
+}
+
+impl SparseCoordinatePruneTable {
+    fn new(
+        coordinate_indices: (CoordinateIndex, CoordinateIndex),
+        move_tables: (&CoordinateMoveTable, &CoordinateMoveTable),
+        size2: usize,
+        solved_values: (CoordinateValue, CoordinateValue),
+        max_num_entries: usize,
+    ) -> Self {
+        // Both tables are indexed by the same moves.
+        assert_eq!(move_tables.0.num_moves, move_tables.1.num_moves);
+        let solved_index = solved_values.0 .0 * size2 + solved_values.1 .0;
+        let mut depth_by_index = HashMap::<usize, u8>::from([(solved_index, 0)]);
+        let mut frontier = vec![solved_index];
+        let mut complete_depth: u8 = 0;
+        // Stop before `u8::MAX`, since lookups return one more than the complete depth.
+        while complete_depth < u8::MAX - 1 {
+            let mut next_frontier = HashSet::<usize>::new();
+            for index in frontier {
+                let (value1, value2) = (
+                    CoordinateValue(index / size2),
+                    CoordinateValue(index % size2),
+                );
+                for move_index in (0..move_tables.0.num_moves).map(CoordinateMoveIndex) {
+                    let next_index = move_tables.0.apply(value1, move_index).0 * size2
+                        + move_tables.1.apply(value2, move_index).0;
+                    if !depth_by_index.contains_key(&next_index) {
+                        next_frontier.insert(next_index);
+                    }
+                }
+            }
+            // Only complete layers are stored, so that missing pairs are known to be further away.
+            if next_frontier.is_empty()
+                || depth_by_index.len() + next_frontier.len() > max_num_entries
+            {
+                break;
+            }
+            complete_depth += 1;
+            for next_index in &next_frontier {
+                depth_by_index.insert(*next_index, complete_depth);
+            }
+            frontier = next_frontier.into_iter().collect();
+        }
+
+        let mut num_buckets = (depth_by_index.len() * 100)
+            .div_ceil(SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET * SPARSE_PRUNE_TABLE_MAX_LOAD_PERCENT)
+            .max(1);
+        loop {
+            let mut prune_table = Self {
+                coordinate_indices,
+                size2,
+                num_buckets,
+                slot_indices: vec![
+                    SPARSE_PRUNE_TABLE_EMPTY_SLOT;
+                    num_buckets * SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET
+                ],
+                slot_depths: vec![0; num_buckets * SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET],
+                complete_depth,
+            };
+            if depth_by_index
+                .iter()
+                .all(|(index, depth)| prune_table.insert(*index as u64, *depth))
+            {
+                return prune_table;
+            }
+            // This is very unlikely at our load factor, but a slightly larger table always helps.
+            num_buckets += num_buckets.div_ceil(8);
+        }
+    }
+
+    fn bucket(&self, index: u64, seed: u64) -> usize {
+        // Maps the hash to `0..num_buckets` without a division.
+        ((mix_bits(index ^ seed) as u128 * self.num_buckets as u128) >> 64) as usize
+    }
+
+    fn slots(&self, bucket: usize) -> std::ops::Range<usize> {
+        bucket * SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET
+            ..(bucket + 1) * SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET
+    }
+
+    // Returns `false` if the table is too full (in which case an entry that
+    // was already in the table may have been evicted).
+    fn insert(&mut self, mut index: u64, mut depth: u8) -> bool {
+        for num_evictions in 0..SPARSE_PRUNE_TABLE_MAX_NUM_EVICTIONS {
+            for seed in SPARSE_PRUNE_TABLE_HASH_SEEDS {
+                let bucket = self.bucket(index, seed);
+                if let Some(slot) = self
+                    .slots(bucket)
+                    .find(|slot| self.slot_indices[*slot] == SPARSE_PRUNE_TABLE_EMPTY_SLOT)
+                {
+                    self.slot_indices[slot] = index;
+                    self.slot_depths[slot] = depth;
+                    return true;
+                }
+            }
+            // Both buckets are full, so we move an entry out of the way (to
+            // its other bucket) and place this one in its slot.
+            let bucket = self.bucket(
+                index,
+                SPARSE_PRUNE_TABLE_HASH_SEEDS[num_evictions % SPARSE_PRUNE_TABLE_HASH_SEEDS.len()],
+            );
+            let slot = self.slots(bucket).start
+                + (num_evictions / SPARSE_PRUNE_TABLE_HASH_SEEDS.len())
+                    % SPARSE_PRUNE_TABLE_NUM_SLOTS_PER_BUCKET;
+            std::mem::swap(&mut self.slot_indices[slot], &mut index);
+            std::mem::swap(&mut self.slot_depths[slot], &mut depth);
+        }
+        false
+    }
+
+    fn get(&self, index: u64) -> Option<u8> {
+        SPARSE_PRUNE_TABLE_HASH_SEEDS.iter().find_map(|seed| {
+            self.slots(self.bucket(index, *seed))
+                .find(|slot| self.slot_indices[*slot] == index)
+                .map(|slot| self.slot_depths[slot])
+        })
+    }
+}
+
+impl Heuristic for SparseCoordinatePruneTable {
+    fn lookup(&self, values: &[CoordinateValue]) -> usize {
+        let (value1, value2) = (
+            values[self.coordinate_indices.0 .0],
+            values[self.coordinate_indices.1 .0],
+        );
+        match self.get((value1.0 * self.size2 + value2.0) as u64) {
+            Some(depth) => depth as usize,
+            None => self.complete_depth as usize + 1,
+        }
+    }
+
+    fn approximate_num_bytes(&self) -> usize {
+        self.slot_indices.len() * SPARSE_PRUNE_TABLE_SLOT_NUM_BYTES
+    }
+}
+
+// The value of a coordinate after conjugating by each symmetry of the phase,
+// for each value of the coordinate.
+struct CoordinateSymmetryTable {
+    num_symmetries: usize,
+    conjugated_values: Vec<u32>,
+}
+
+impl CoordinateSymmetryTable {
+    // Values that aren't reachable from the start pattern are left uninitialized.
+    fn new(
+        kpuzzle: &KPuzzle,
+        move_transformations: &[KTransformation],
+        symmetry_transformations: &[KTransformation],
+        coordinate: &Coordinate,
+    ) -> Self {
+        let num_symmetries = symmetry_transformations.len();
+        let inverse_symmetry_transformations: Vec<KTransformation> = symmetry_transformations
+            .iter()
+            .map(|symmetry_transformation| symmetry_transformation.invert())
+            .collect();
+        let start_pattern = kpuzzle.default_pattern();
+        let mut conjugated_values = vec![u32::MAX; coordinate.size * num_symmetries];
+        // Conjugating needs a transformation, so we keep one (instead of a
+        // pattern) as the representative for each value.
+        breadth_first_search(
+            (
+                coordinate.value(&start_pattern),
+                kpuzzle.identity_transformation(),
+            ),
+            |(value, _)| *value,
+            |(_, transformation)| {
+                move_transformations
+                    .iter()
+                    .map(|move_transformation| {
+                        let next_transformation =
+                            transformation.apply_transformation(move_transformation);
+                        let next_value = coordinate
+                            .value(&start_pattern.apply_transformation(&next_transformation));
+                        (next_value, next_transformation)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |(value, transformation), _, _| {
+                for (symmetry_index, (symmetry_transformation, inverse_symmetry_transformation)) in
+                    symmetry_transformations
+                        .iter()
+                        .zip(&inverse_symmetry_transformations)
+                        .enumerate()
+                {
+                    let conjugated_pattern = start_pattern
+                        .apply_transformation(inverse_symmetry_transformation)
+                        .apply_transformation(transformation)
+                        .apply_transformation(symmetry_transformation);
+                    conjugated_values[value.0 * num_symmetries + symmetry_index] =
+                        coordinate.value(&conjugated_pattern).0 as u32;
+                }
+                BreadthFirstSearchControl::Continue
+            },
+        );
+        Self {
+            num_symmetries,
+            conjugated_values,
+        }
+    }
+
+    fn is_reachable(&self, value: CoordinateValue) -> bool {
+        // The first symmetry is the identity.
+        self.conjugated_values[value.0 * self.num_symmetries] != u32::MAX
+    }
+
+    fn conjugate(&self, value: CoordinateValue, symmetry_index: usize) -> CoordinateValue {
+        debug_assert!(symmetry_index < self.num_symmetries);
+        let conjugated_value =
+            self.conjugated_values[value.0 * self.num_symmetries + symmetry_index];
+        debug_assert_ne!(
+            conjugated_value,
+            u32::MAX,
+            "Coordinate value {} is not reachable from the start pattern.",
+            value.0
+        );
+        CoordinateValue(conjugated_value as usize)
+    }
+}
+
+// Exact distances for a pair of coordinates, with one entry per symmetry
+// class of the first coordinate (instead of one per value).
+//
+// Conjugating a pattern by a symmetry of the phase keeps its distance to the
+// target, so a pair is looked up by conjugating both values by the symmetry
+// that takes the first value to the representative of its class.
+struct SymmetryReducedCoordinatePruneTable {
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    size2: usize,
+    // Indexed by the value of the first coordinate (`u32::MAX` if unreachable).
+    class_indices: Vec<u32>,
+    // The symmetry that takes each value of the first coordinate to the representative of its class.
+    class_symmetry_indices: Vec<u8>,
+    second_symmetry_table: CoordinateSymmetryTable,
+    // Indexed by class, then by the (conjugated) value of the second coordinate.
+    depths: Vec<u8>,
+}
+
+impl SymmetryReducedCoordinatePruneTable {
+    fn new(
+        coordinate_indices: (CoordinateIndex, CoordinateIndex),
+        move_tables: (&CoordinateMoveTable, &CoordinateMoveTable),
+        symmetry_tables: (&CoordinateSymmetryTable, CoordinateSymmetryTable),
+        sizes: (usize, usize),
+        solved_values: (CoordinateValue, CoordinateValue),
+    ) -> Self {
+        // Both tables are indexed by the same moves and symmetries.
+        assert_eq!(move_tables.0.num_moves, move_tables.1.num_moves);
+        let (first_symmetry_table, second_symmetry_table) = symmetry_tables;
+        let num_symmetries = first_symmetry_table.num_symmetries;
+        assert_eq!(num_symmetries, second_symmetry_table.num_symmetries);
+        assert!(num_symmetries <= u8::MAX as usize + 1);
+        let (size1, size2) = sizes;
+
+        let mut class_indices = vec![u32::MAX; size1];
+        let mut class_symmetry_indices = vec![0u8; size1];
+        let mut representatives = Vec::<CoordinateValue>::new();
+        for value in (0..size1).map(CoordinateValue) {
+            if class_indices[value.0] != u32::MAX || !first_symmetry_table.is_reachable(value) {
+                continue;
+            }
+            let class_index = representatives.len() as u32;
+            representatives.push(value);
+            for symmetry_index in 0..num_symmetries {
+                let conjugated_value = first_symmetry_table.conjugate(value, symmetry_index);
+                if class_indices[conjugated_value.0] != u32::MAX {
+                    continue;
+                }
+                class_indices[conjugated_value.0] = class_index;
+                // The symmetries form a group, so this is the inverse of `symmetry_index`.
+                class_symmetry_indices[conjugated_value.0] = (0..num_symmetries)
+                    .find(|inverse_symmetry_index| {
+                        first_symmetry_table.conjugate(conjugated_value, *inverse_symmetry_index)
+                            == value
+                    })
+                    .expect("Coordinate symmetries must form a group.")
+                    as u8;
+            }
+        }
+
+        let mut prune_table = Self {
+            coordinate_indices,
+            size2,
+            class_indices,
+            class_symmetry_indices,
+            second_symmetry_table,
+            depths: vec![u8::MAX; representatives.len() * size2],
+        };
+        let solved_index = prune_table.reduced_index(solved_values.0, solved_values.1);
+        prune_table.depths[solved_index] = 0;
+        let mut depth = 0;
+        loop {
+            let new_indices: Vec<usize> = (0..prune_table.depths.len())
+                .filter(|index| prune_table.depths[*index] == depth)
+                .flat_map(|index| {
+                    let (value1, value2) = (
+                        representatives[index / size2],
+                        CoordinateValue(index % size2),
+                    );
+                    let prune_table = &prune_table;
+                    (0..move_tables.0.num_moves)
+                        .map(CoordinateMoveIndex)
+                        .map(move |move_index| {
+                            prune_table.reduced_index(
+                                move_tables.0.apply(value1, move_index),
+                                move_tables.1.apply(value2, move_index),
+                            )
+                        })
+                })
+                .filter(|next_index| prune_table.depths[*next_index] == u8::MAX)
+                .collect();
+            if new_indices.is_empty() {
+                break;
+            }
+            for next_index in new_indices {
+                prune_table.depths[next_index] = depth + 1;
+            }
+            depth += 1;
+        }
+        prune_table
+    }
+
+    fn reduced_index(&self, value1: CoordinateValue, value2: CoordinateValue) -> usize {
+        let class_index = self.class_indices[value1.0] as usize;
+        let symmetry_index = self.class_symmetry_indices[value1.0] as usize;
+        class_index * self.size2
+            + self
+                .second_symmetry_table
+                .conjugate(value2, symmetry_index)
+                .0
+    }
+}
+
+impl Heuristic for SymmetryReducedCoordinatePruneTable {
+    fn lookup(&self, values: &[CoordinateValue]) -> usize {
+        self.depths[self.reduced_index(
+            values[self.coordinate_indices.0 .0],
+            values[self.coordinate_indices.1 .0],
+        )] as usize
+    }
+
+    fn approximate_num_bytes(&self) -> usize {
+        self.depths.len()
+            + self.class_indices.len() * std::mem::size_of::<u32>()
+            + self.class_symmetry_indices.len()
+            + self.second_symmetry_table.conjugated_values.len() * std::mem::size_of::<u32>()
+    }
+}
+
+// A pruning table for a pair of coordinates, stored densely if it fits in
+// `max_num_bytes` (see `TableMemoryMode`) and sparsely otherwise.
+fn coordinate_pair_prune_table(
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    move_tables: (&CoordinateMoveTable, &CoordinateMoveTable),
+    sizes: (usize, usize),
+    solved_values: (CoordinateValue, CoordinateValue),
+    max_num_bytes: usize,
+) -> Box<dyn Heuristic> {
+    match sizes.0.checked_mul(sizes.1) {
+        Some(num_entries) if num_entries <= max_num_bytes => Box::new(CoordinatePruneTable::new(
+            coordinate_indices,
+            move_tables,
+            sizes,
+            solved_values,
+        )),
+        _ => Box::new(SparseCoordinatePruneTable::new(
+            coordinate_indices,
+            move_tables,
+            sizes.1,
+            solved_values,
+            max_num_bytes * SPARSE_PRUNE_TABLE_MAX_LOAD_PERCENT
+                / 100
+                / SPARSE_PRUNE_TABLE_SLOT_NUM_BYTES,
+        )),
+    }
+}
+
+// Coordinate tables are stored as a small header followed by the raw
+// entries (one byte per depth, or one bit per member), so that pruning tables
+// and membership sets share one format. Nothing persists tables yet, so this
+// is only built for tests.
+#[cfg(test)]
+const COORDINATE_TABLE_MAGIC: &[u8; 4] = b"TWCT";
+#[cfg(test)]
+const COORDINATE_TABLE_FORMAT_VERSION: u8 = 1;
+#[cfg(test)]
+const COORDINATE_TABLE_HEADER_NUM_BYTES: usize = 4 + 3 + 4 * 4;
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CoordinateTableKind {
+    PruneTable = 0,
+    MembershipSet = 1,
+}
+
+#[cfg(test)]
+struct CoordinateTableHeader {
+    kind: CoordinateTableKind,
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    sizes: (usize, usize),
+    // Only meaningful for membership sets.
+    max_depth: u8,
+}
+
+#[cfg(test)]
+impl CoordinateTableHeader {
+    fn write_with_entries(&self, entries: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::with_capacity(COORDINATE_TABLE_HEADER_NUM_BYTES + entries.len());
+        bytes.extend_from_slice(COORDINATE_TABLE_MAGIC);
+        bytes.push(COORDINATE_TABLE_FORMAT_VERSION);
+        bytes.push(self.kind as u8);
+        bytes.push(self.max_depth);
+        for value in [
+            self.coordinate_indices.0 .0,
+            self.coordinate_indices.1 .0,
+            self.sizes.0,
+            self.sizes.1,
+        ] {
+            bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(entries);
+        bytes
+    }
+
+    // Checks the header against the search that the table will be used with,
+    // so that lookups can't index out of bounds.
+    fn read_with_entries<'a>(
+        bytes: &'a [u8],
+        expected_kind: CoordinateTableKind,
+        search: &CoordinatePhaseSearch,
+        expected_num_entry_bytes: impl FnOnce(usize) -> usize,
+    ) -> Result<(Self, &'a [u8]), PuzzleError> {
+        if bytes.len() < COORDINATE_TABLE_HEADER_NUM_BYTES || &bytes[0..4] != COORDINATE_TABLE_MAGIC
+        {
+            return Err("Not a coordinate table.".into());
+        }
+        if bytes[4] != COORDINATE_TABLE_FORMAT_VERSION {
+            return Err("Unsupported coordinate table format version.".into());
+        }
+        if bytes[5] != expected_kind as u8 {
+            return Err("Unexpected coordinate table kind.".into());
+        }
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let header = Self {
+            kind: expected_kind,
+            coordinate_indices: (CoordinateIndex(read_u32(7)), CoordinateIndex(read_u32(11))),
+            sizes: (read_u32(15), read_u32(19)),
+            max_depth: bytes[6],
+        };
+        let (i, j) = header.coordinate_indices;
+        if i.0 >= search.coordinates.len() || j.0 >= search.coordinates.len() {
+            return Err("Coordinate table index out of range for the search.".into());
+        }
+        if header.sizes != (search.coordinates[i.0].size, search.coordinates[j.0].size) {
+            return Err("Coordinate table sizes don't match the search.".into());
+        }
+        let entries = &bytes[COORDINATE_TABLE_HEADER_NUM_BYTES..];
+        if entries.len() != expected_num_entry_bytes(header.sizes.0 * header.sizes.1) {
+            return Err("Coordinate table has the wrong number of entries.".into());
+        }
+        Ok((header, entries))
+    }
+}
+
+/// The pairs of values of two coordinates that are within `max_depth` moves
+/// of the target, as a bitset with the same shape as a pruning table.
+///
+/// A pattern that is within `max_depth` moves is always a member, so this is
+/// a fast filter (with false positives) for e.g. rejecting scrambles that are
+/// too short.
+pub(crate) struct CoordinateMembershipSet {
+    coordinate_indices: (CoordinateIndex, CoordinateIndex),
+    size2: usize,
+    num_entries: usize,
+    max_depth: u8,
+    bits: Vec<u8>,
+}
+
+impl CoordinateMembershipSet {
+    pub fn approximate_num_bytes(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// The max depth of the members.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth as usize
+    }
+
+    fn contains(&self, values: &[CoordinateValue]) -> bool {
+        let index = values[self.coordinate_indices.0 .0].0 * self.size2
+            + values[self.coordinate_indices.1 .0].0;
+        debug_assert!(index < self.num_entries);
+        self.bits[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    #[cfg(test)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        CoordinateTableHeader {
+            kind: CoordinateTableKind::MembershipSet,
+            coordinate_indices: self.coordinate_indices,
+            sizes: (self.num_entries / self.size2, self.size2),
+            max_depth: self.max_depth,
+        }
+        .write_with_entries(&self.bits)
+    }
+
+    /// Reads a membership set for the coordinates of `search`.
+    #[cfg(test)]
+    pub fn from_bytes(bytes: &[u8], search: &CoordinatePhaseSearch) -> Result<Self, PuzzleError> {
+        let (header, entries) = CoordinateTableHeader::read_with_entries(
+            bytes,
+            CoordinateTableKind::MembershipSet,
+            search,
+            |num_entries| num_entries.div_ceil(8),
+        )?;
+        Ok(Self {
+            coordinate_indices: header.coordinate_indices,
+            size2: header.sizes.1,
+            num_entries: header.sizes.0 * header.sizes.1,
+            max_depth: header.max_depth,
+            bits: entries.to_vec(),
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CoordinateSearchOptions {
+    pub max_depth: Option<usize>,
+    pub disallowed_initial_quanta: Option<Vec<QuantumMove>>,
+    pub disallowed_final_quanta: Option<Vec<QuantumMove>>,
+    /// Try the first move with the lowest heuristic (i.e. closest to the
+    /// target) first at each depth, instead of the order of the moves. This
+    /// finds solutions of each length sooner, but in a different order.
+    pub order_first_moves_by_heuristic: bool,
+    /// Stops the search (as if `visit` asked to stop) once this has passed.
+    pub deadline: Option<instant::Instant>,
+}
+
+fn is_deadline_passed(options: &CoordinateSearchOptions) -> bool {
+    options
+        .deadline
+        .is_some_and(|deadline| instant::Instant::now() >= deadline)
+}
+
+// The first ply of the search, which is the same for every depth of the
+// iterative deepening, so we only compute it once.
+struct FirstMove {
+    move_index: CoordinateMoveIndex,
+    values: Vec<CoordinateValue>,
+    heuristic: usize,
+}
+
+fn is_quantum_disallowed(r#move: &Move, disallowed_quanta: &Option<Vec<QuantumMove>>) -> bool {
+    match disallowed_quanta {
+        Some(disallowed_quanta) => disallowed_quanta.contains(r#move.quantum.as_ref()),
+        None => false,
+    }
+}
+
+/// An optimal (IDA*) search for a single phase, using move tables and pruning
+/// tables for coordinates instead of searching patterns directly. The target
+/// is the default pattern of the puzzle (as seen by the coordinates).
+///
+/// Only the first pruning table is built up front. Searches use whichever
+/// pruning tables are loaded (a weaker bound is still a valid lower bound, so
+/// solutions are the same but take longer to find), and the rest can be
+/// built later with `load_next_prune_table(…)` (including from another
+/// thread while searches are running).
+pub(crate) struct CoordinatePhaseSearch {
+    moves: Vec<Move>,
+    // The index of each move's quantum among the distinct quanta.
+    move_quantum_indices: Vec<usize>,
+    quanta_commute: Vec<Vec<bool>>,
+    // Used to apply solutions to patterns without looking up each move again.
+    move_transformations: Vec<KTransformation>,
+    kpuzzle: KPuzzle,
+    // The identity first, followed by the other symmetries that the moves are closed under.
+    symmetry_transformations: Vec<KTransformation>,
+    coordinates: Vec<Coordinate>,
+    move_tables: Vec<CoordinateMoveTable>,
+    solved_values: Vec<CoordinateValue>,
+    prune_table_coordinate_pairs: Vec<(CoordinateIndex, CoordinateIndex)>,
+    prune_tables: Vec<OnceLock<Box<dyn Heuristic>>>,
+}
+
+impl CoordinatePhaseSearch {
+    /// `symmetries` are transformations (e.g. rotations) to conjugate
+    /// patterns by, and must form a group. The ones that map the moves to
+    /// moves are used to store the pruning tables with one entry per symmetry
+    /// class of the first coordinate of each pair, so conjugating by them must
+    /// keep the target and give well-defined coordinate values.
+    pub fn new(
+        kpuzzle: &KPuzzle,
+        moves: Vec<Move>,
+        coordinates: Vec<Coordinate>,
+        prune_table_coordinate_pairs: &[(CoordinateIndex, CoordinateIndex)],
+        symmetries: &[KTransformation],
+    ) -> Self {
+        let mut trace_span =
+            TraceSpan::start(TraceCategory::TableBuild, || "Coordinate tables".to_owned());
+        if let Some(trace_span) = &mut trace_span {
+            trace_span.set_arg("num_moves", moves.len());
+            trace_span.set_arg("num_coordinates", coordinates.len());
+        }
+        let mut quanta = Vec::<QuantumMove>::new();
+        let mut move_quantum_indices = Vec::<usize>::new();
+        for r#move in &moves {
+            let quantum = r#move.quantum.as_ref();
+            let quantum_index = match quanta.iter().position(|q| q == quantum) {
+                Some(quantum_index) => quantum_index,
+                None => {
+                    quanta.push(quantum.clone());
+                    quanta.len() - 1
+                }
+            };
+            move_quantum_indices.push(quantum_index);
+        }
+        let quantum_transformations: Vec<KTransformation> = quanta
+            .iter()
+            .map(|quantum| {
+                kpuzzle
+                    .transformation_from_move(&Move {
+                        quantum: Arc::new(quantum.clone()),
+                        amount: 1,
+                    })
+                    .unwrap()
+            })
+            .collect();
+        let quanta_commute = quantum_transformations
+            .iter()
+            .map(|t1| {
+                quantum_transformations
+                    .iter()
+                    .map(|t2| do_transformations_commute(t1, t2))
+                    .collect()
+            })
+            .collect();
+
+        let move_transformations: Vec<KTransformation> = moves
+            .iter()
+            .map(|r#move| kpuzzle.transformation_from_move(r#move).unwrap())
+            .collect();
+        let identity_transformation = kpuzzle.identity_transformation();
+        let symmetry_transformations: Vec<KTransformation> =
+            std::iter::once(identity_transformation.clone())
+                .chain(
+                    symmetries
+                        .iter()
+                        .filter(|symmetry_transformation| {
+                            **symmetry_transformation != identity_transformation
+                                && move_transformations.iter().all(|move_transformation| {
+                                    move_transformations.contains(
+                                        &symmetry_transformation
+                                            .invert()
+                                            .apply_transformation(move_transformation)
+                                            .apply_transformation(symmetry_transformation),
+                                    )
+                                })
+                        })
+                        .cloned(),
+                )
+                .collect();
+        let default_pattern = kpuzzle.default_pattern();
+        let move_tables: Vec<CoordinateMoveTable> = coordinates
+            .iter()
+            .map(|coordinate| {
+                CoordinateMoveTable::new(&default_pattern, &move_transformations, coordinate)
+            })
+            .collect();
+        // The tables are indexed per move (not per quantum), so their shape must match the move list exactly.
+        for move_table in &move_tables {
+            assert_eq!(move_table.num_moves, moves.len());
+        }
+        let solved_values: Vec<CoordinateValue> = coordinates
+            .iter()
+            .map(|coordinate| coordinate.value(&default_pattern))
+            .collect();
+        for (i, j) in prune_table_coordinate_pairs {
+            assert!(
+                i.0 < coordinates.len() && j.0 < coordinates.len(),
+                "Prune table coordinate index out of range."
+            );
+        }
+        let prune_tables = prune_table_coordinate_pairs
+            .iter()
+            .map(|_| OnceLock::new())
+            .collect();
+
+        let search = Self {
+            moves,
+            move_quantum_indices,
+            quanta_commute,
+            move_transformations,
+            kpuzzle: kpuzzle.clone(),
+            symmetry_transformations,
+            coordinates,
+            move_tables,
+            solved_values,
+            prune_table_coordinate_pairs: prune_table_coordinate_pairs.to_vec(),
+            prune_tables,
+        };
+        // Without any pruning table, searches of a realistic depth would never finish.
+        search.load_next_prune_table();
+        search
+    }
+
+    /// Builds the first pruning table that isn't loaded yet, and returns
+    /// `false` if all of them were already loaded.
+    pub fn load_next_prune_table(&self) -> bool {
+        let Some(index) = self
+            .prune_tables
+            .iter()
+            .position(|prune_table| prune_table.get().is_none())
+        else {
+            return false;
+        };
+        // If another thread is building the same table, this waits for it.
+        self.prune_tables[index].get_or_init(|| {
+            let (i, j) = self.prune_table_coordinate_pairs[index];
+            let _trace_span = TraceSpan::start(TraceCategory::TableBuild, || {
+                format!("Coordinate pruning table {}", index)
+            });
+            let max_num_bytes = table_memory_mode().max_prune_table_size();
+            if self.symmetry_transformations.len() > 1 {
+                let prune_table = self.symmetry_reduced_prune_table((i, j));
+                // Otherwise, a sparse table is smaller.
+                if prune_table.approximate_num_bytes() <= max_num_bytes {
+                    return Box::new(prune_table);
+                }
+            }
+            coordinate_pair_prune_table(
+                (i, j),
+                (&self.move_tables[i.0], &self.move_tables[j.0]),
+                (self.coordinates[i.0].size, self.coordinates[j.0].size),
+                (self.solved_values[i.0], self.solved_values[j.0]),
+                max_num_bytes,
+            )
+        });
+        true
+    }
+
+    fn symmetry_reduced_prune_table(
+        &self,
+        (i, j): (CoordinateIndex, CoordinateIndex),
+    ) -> SymmetryReducedCoordinatePruneTable {
+        let symmetry_table = |coordinate_index: CoordinateIndex| {
+            CoordinateSymmetryTable::new(
+                &self.kpuzzle,
+                &self.move_transformations,
+                &self.symmetry_transformations,
+                &self.coordinates[coordinate_index.0],
+            )
+        };
+        SymmetryReducedCoordinatePruneTable::new(
+            (i, j),
+            (&self.move_tables[i.0], &self.move_tables[j.0]),
+            (&symmetry_table(i), symmetry_table(j)),
+            (self.coordinates[i.0].size, self.coordinates[j.0].size),
+            (self.solved_values[i.0], self.solved_values[j.0]),
+        )
+    }
+
+    pub fn load_all_prune_tables(&self) {
+        while self.load_next_prune_table() {}
+    }
+
+    /// The approximate memory used by the move and pruning tables.
+    pub fn approximate_num_bytes(&self) -> usize {
+        self.move_tables
+            .iter()
+            .map(|move_table| move_table.next_values.len() * std::mem::size_of::<u32>())
+            .sum::<usize>()
+            + self
+                .prune_tables
+                .iter()
+                .filter_map(OnceLock::get)
+                .map(|prune_table| prune_table.approximate_num_bytes())
+                .sum::<usize>()
+    }
+
+    /// Returns an error if some value of a coordinate can't be reached from
+    /// the target with the moves of this phase. The move tables don't cover
+    /// such values, so patterns with them can't be searched.
+    pub fn check_every_value_is_reachable(&self, phase_name: &str) -> Result<(), PuzzleError> {
+        for (coordinate_index, move_table) in self.move_tables.iter().enumerate() {
+            if move_table.next_values.contains(&u32::MAX) {
+                return Err(PuzzleError {
+                    description: format!(
+                        "{} moves can't reach every value of coordinate {}.",
+                        phase_name, coordinate_index
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of moves that the tables are indexed by (one per move, including each amount).
+    pub fn num_moves(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Whether the pattern is at the target of this phase (as seen by the coordinates).
+    pub fn is_solved(&self, pattern: &KPattern) -> bool {
+        self.coordinates
+            .iter()
+            .zip(&self.solved_values)
+            .all(|(coordinate, solved_value)| coordinate.value(pattern) == *solved_value)
+    }
+
+    /// Membership sets for being within `max_depth` moves of the target (one
+    /// for each dense pruning table that is loaded). Use with `may_be_within(…)`.
+    pub fn membership_sets_within(&self, max_depth: usize) -> Vec<CoordinateMembershipSet> {
+        self.prune_tables
+            .iter()
+            .filter_map(OnceLock::get)
+            // Membership sets are as large as a dense table, so sparse tables don't have them.
+            .filter_map(|prune_table| prune_table.membership_within(max_depth))
+            .collect()
+    }
+
+    /// Returns `false` if the pattern is definitely not within the depth of
+    /// the membership sets (using the moves of this phase).
+    pub fn may_be_within(
+        &self,
+        membership_sets: &[CoordinateMembershipSet],
+        pattern: &KPattern,
+    ) -> bool {
+        let values: Vec<CoordinateValue> = self
+            .coordinates
+            .iter()
+            .map(|coordinate| coordinate.value(pattern))
+            .collect();
+        membership_sets
+            .iter()
+            .all(|membership_set| membership_set.contains(&values))
+    }
+
+    fn heuristic(&self, values: &[CoordinateValue]) -> usize {
+        self.prune_tables
+            .iter()
+            .filter_map(OnceLock::get)
+            .map(|prune_table| prune_table.lookup(values))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns an optimal solution (for the phase) that respects the options,
+    /// if there is one.
+    pub fn search(&self, pattern: &KPattern, options: &CoordinateSearchOptions) -> Option<Alg> {
+        let mut solution: Option<Alg> = None;
+        self.search_each(pattern, options, |alg| {
+            solution = Some(alg);
+            true
+        });
+        solution
+    }
+
+    /// Calls `visit` with each solution (for the phase) that respects the
+    /// options, in order of increasing length, until it returns `true`.
+    pub fn search_each(
+        &self,
+        pattern: &KPattern,
+        options: &CoordinateSearchOptions,
+        mut visit: impl FnMut(Alg) -> bool,
+    ) {
+        self.search_each_move_indices(pattern, options, &mut |move_indices| {
+            visit(self.alg_for_move_indices(move_indices))
+        });
+    }
+
+    /// Like `search_each(…)`, but also passes the pattern that each solution
+    /// leads to (e.g. to seed the next phase). This is cheaper than applying
+    /// the solution alg to the pattern afterwards.
+    pub fn search_each_with_patterns(
+        &self,
+        pattern: &KPattern,
+        options: &CoordinateSearchOptions,
+        mut visit: impl FnMut(Alg, KPattern) -> bool,
+    ) {
+        self.search_each_move_indices(pattern, options, &mut |move_indices| {
+            let mut solution_pattern = pattern.clone();
+            for move_index in move_indices {
+                solution_pattern =
+                    solution_pattern.apply_transformation(&self.move_transformations[move_index.0]);
+            }
+            visit(self.alg_for_move_indices(move_indices), solution_pattern)
+        });
+    }
+
+    fn alg_for_move_indices(&self, move_indices: &[CoordinateMoveIndex]) -> Alg {
+        Alg {
+            nodes: move_indices
+                .iter()
+                .map(|move_index| self.moves[move_index.0].clone().into())
+                .collect(),
+        }
+    }
+
+    fn search_each_move_indices(
+        &self,
+        pattern: &KPattern,
+        options: &CoordinateSearchOptions,
+        visit: &mut dyn FnMut(&[CoordinateMoveIndex]) -> bool,
+    ) {
+        let values: Vec<CoordinateValue> = self
+            .coordinates
+            .iter()
+            .map(|coordinate| coordinate.value(pattern))
+            .collect();
+        let mut first_moves: Vec<FirstMove> = (0..self.moves.len())
+            .map(CoordinateMoveIndex)
+            .filter(|move_index| {
+                !is_quantum_disallowed(
+                    &self.moves[move_index.0],
+                    &options.disallowed_initial_quanta,
+                )
+            })
+            .map(|move_index| {
+                let values = self.apply_move(&values, move_index);
+                let heuristic = self.heuristic(&values);
+                FirstMove {
+                    move_index,
+                    values,
+                    heuristic,
+                }
+            })
+            .collect();
+        if options.order_first_moves_by_heuristic {
+            // Stable, so ties keep the order of the moves.
+            first_moves.sort_by_key(|first_move| first_move.heuristic);
+        }
+
+        let mut move_indices = Vec::<CoordinateMoveIndex>::new();
+        if self.recurse(&values, 0, None, options, &mut move_indices, visit) {
+            return;
+        }
+        let root_heuristic = self.heuristic(&values);
+        for depth in 1..=options.max_depth.unwrap_or(MAX_COORDINATE_SEARCH_DEPTH) {
+            if root_heuristic > depth {
+                continue;
+            }
+            for first_move in &first_moves {
+                if first_move.heuristic > depth - 1 {
+                    continue;
+                }
+                if is_deadline_passed(options) {
+                    return;
+                }
+                move_indices.push(first_move.move_index);
+                let done = self.recurse(
+                    &first_move.values,
+                    depth - 1,
+                    Some(first_move.move_index),
+                    options,
+                    &mut move_indices,
+                    visit,
+                );
+                move_indices.pop();
+                if done {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn apply_move(
+        &self,
+        values: &[CoordinateValue],
+        move_index: CoordinateMoveIndex,
+    ) -> Vec<CoordinateValue> {
+        values
+            .iter()
+            .zip(&self.move_tables)
+            .map(|(value, move_table)| move_table.apply(*value, move_index))
+            .collect()
+    }
+
+    // Returns `true` when `visit` asks to stop (or the deadline has passed).
+    fn recurse(
+        &self,
+        values: &[CoordinateValue],
+        remaining_depth: usize,
+        previous_move_index: Option<CoordinateMoveIndex>,
+        options: &CoordinateSearchOptions,
+        move_indices: &mut Vec<CoordinateMoveIndex>,
+        visit: &mut dyn FnMut(&[CoordinateMoveIndex]) -> bool,
+    ) -> bool {
+        if remaining_depth == 0 {
+            if values != self.solved_values {
+                return false;
+            }
+            if let Some(previous_move_index) = previous_move_index {
+                if is_quantum_disallowed(
+                    &self.moves[previous_move_index.0],
+                    &options.disallowed_final_quanta,
+                ) {
+                    return false;
+                }
+            }
+            return visit(move_indices);
+        }
+        if self.heuristic(values) > remaining_depth {
+            return false;
+        }
+        if remaining_depth >= MIN_REMAINING_DEPTH_FOR_DEADLINE_CHECK && is_deadline_passed(options)
+        {
+            return true;
+        }
+        for move_index in (0..self.moves.len()).map(CoordinateMoveIndex) {
+            let quantum_index = self.move_quantum_indices[move_index.0];
+            match previous_move_index {
+                Some(previous_move_index) => {
+                    let previous_quantum_index = self.move_quantum_indices[previous_move_index.0];
+                    // Only allow commuting moves in one order (e.g. `U D` but not `D U`).
+                    if quantum_index == previous_quantum_index
+                        || (self.quanta_commute[previous_quantum_index][quantum_index]
+                            && quantum_index < previous_quantum_index)
+                    {
+                        continue;
+                    }
+                }
+                None => {
+                    if is_quantum_disallowed(
+                        &self.moves[move_index.0],
+                        &options.disallowed_initial_quanta,
+                    ) {
+                        continue;
+                    }
+                }
+            }
+            let next_values = self.apply_move(values, move_index);
+            move_indices.push(move_index);
+            let done = self.recurse(
+                &next_values,
+                remaining_depth - 1,
+                Some(move_index),
+                options,
+                move_indices,
+                visit,
+            );
+            move_indices.pop();
+            if done {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[test]
+fn coordinate_prune_table_bytes_test() {
+    use super::cube3x3x3_coordinates::{
+        cube3x3x3_phase1_search, cube3x3x3_phase2_search, DEFAULT_PHASE1_MOVES,
+        DEFAULT_PHASE2_MOVES,
+    };
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let search = cube3x3x3_phase2_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE2_MOVES));
+    let (i, j) = search.prune_table_coordinate_pairs[0];
+    let prune_table = CoordinatePruneTable::new(
+        (i, j),
+        (&search.move_tables[i.0], &search.move_tables[j.0]),
+        (search.coordinates[i.0].size, search.coordinates[j.0].size),
+        (search.solved_values[i.0], search.solved_values[j.0]),
+    );
+    let bytes = prune_table.to_bytes();
+    let read_prune_table = CoordinatePruneTable::from_bytes(&bytes, &search).unwrap();
+    assert_eq!(
+        read_prune_table.coordinate_indices,
+        prune_table.coordinate_indices
+    );
+    assert_eq!(read_prune_table.size2, prune_table.size2);
+    assert!(read_prune_table.depths == prune_table.depths);
+
+    // Membership sets use the same format, but aren't interchangeable with pruning tables.
+    assert!(CoordinateMembershipSet::from_bytes(&bytes, &search).is_err());
+    assert!(CoordinatePruneTable::from_bytes(&bytes[..bytes.len() - 1], &search).is_err());
+    // The coordinates of phase 1 have different sizes.
+    let phase1_search = cube3x3x3_phase1_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE1_MOVES));
+    assert!(CoordinatePruneTable::from_bytes(&bytes, &phase1_search).is_err());
+}
+
+#[test]
+fn sparse_coordinate_prune_table_test() {
+    use super::cube3x3x3_coordinates::{cube3x3x3_phase2_search, DEFAULT_PHASE2_MOVES};
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let search = cube3x3x3_phase2_search(
+        cube3x3x3_centerless_kpuzzle(),
+        static_parsed_list(&DEFAULT_PHASE2_MOVES),
+    );
+    let (i, j) = search.prune_table_coordinate_pairs[1];
+    let move_tables = (&search.move_tables[i.0], &search.move_tables[j.0]);
+    let solved_values = (search.solved_values[i.0], search.solved_values[j.0]);
+    let dense_prune_table = CoordinatePruneTable::new(
+        (i, j),
+        move_tables,
+        (search.coordinates[i.0].size, search.coordinates[j.0].size),
+        solved_values,
+    );
+    let max_num_entries = 10_000;
+    let sparse_prune_table = SparseCoordinatePruneTable::new(
+        (i, j),
+        move_tables,
+        dense_prune_table.size2,
+        solved_values,
+        max_num_entries,
+    );
+    let num_sparse_entries = sparse_prune_table
+        .slot_indices
+        .iter()
+        .filter(|index| **index != SPARSE_PRUNE_TABLE_EMPTY_SLOT)
+        .count();
+    assert!(num_sparse_entries <= max_num_entries);
+    assert!(sparse_prune_table.complete_depth > 0);
+
+    let mut values = vec![CoordinateValue(0); search.coordinates.len()];
+    for (index, depth) in dense_prune_table.depths.iter().enumerate() {
+        if *depth == u8::MAX {
+            continue; // Unreachable
+        }
+        values[i.0] = CoordinateValue(index / dense_prune_table.size2);
+        values[j.0] = CoordinateValue(index % dense_prune_table.size2);
+        let sparse_depth = sparse_prune_table.lookup(&values);
+        if *depth <= sparse_prune_table.complete_depth {
+            assert_eq!(sparse_depth, *depth as usize);
+        } else {
+            assert_eq!(sparse_depth, sparse_prune_table.complete_depth as usize + 1);
+        }
+    }
+}
+
+#[test]
+fn symmetry_reduced_prune_table_test() {
+    use super::cube3x3x3_coordinates::{cube3x3x3_phase2_search, DEFAULT_PHASE2_MOVES};
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let search = cube3x3x3_phase2_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE2_MOVES));
+    assert_eq!(search.symmetry_transformations.len(), 8);
+    for (i, j) in search.prune_table_coordinate_pairs.clone() {
+        let dense_prune_table = CoordinatePruneTable::new(
+            (i, j),
+            (&search.move_tables[i.0], &search.move_tables[j.0]),
+            (search.coordinates[i.0].size, search.coordinates[j.0].size),
+            (search.solved_values[i.0], search.solved_values[j.0]),
+        );
+        let symmetry_reduced_prune_table = search.symmetry_reduced_prune_table((i, j));
+        assert!(
+            symmetry_reduced_prune_table.approximate_num_bytes() * 2
+                < dense_prune_table.approximate_num_bytes()
+        );
+        let mut values = vec![CoordinateValue(0); search.coordinates.len()];
+        for (index, depth) in dense_prune_table.depths.iter().enumerate() {
+            values[i.0] = CoordinateValue(index / dense_prune_table.size2);
+            values[j.0] = CoordinateValue(index % dense_prune_table.size2);
+            assert_eq!(
+                symmetry_reduced_prune_table.lookup(&values),
+                *depth as usize
+            );
+        }
+    }
+
+    // Without `D`, the rotations that swap `U` and `D` aren't symmetries of the moves.
+    let search = cube3x3x3_phase2_search(
+        kpuzzle,
+        static_parsed_list(&["U", "U2", "U'", "L2", "F2", "R2", "B2"]),
+    );
+    assert_eq!(search.symmetry_transformations.len(), 4);
+}
+
+// The corner orientation and E-slice pruning table of 3x3x3 phase 1 has
+// 1_082_565 entries, so it is stored sparsely in the minimal memory mode.
+#[test]
+fn minimal_memory_phase1_prune_table_test() {
+    use crate::_internal::TableMemoryMode;
+
+    use super::cube3x3x3_coordinates::{cube3x3x3_phase1_search, DEFAULT_PHASE1_MOVES};
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let search = cube3x3x3_phase1_search(
+        cube3x3x3_centerless_kpuzzle(),
+        static_parsed_list(&DEFAULT_PHASE1_MOVES),
+    );
+    let (i, j) = search.prune_table_coordinate_pairs[0];
+    let max_num_bytes = TableMemoryMode::Minimal.max_prune_table_size();
+    let sizes = (search.coordinates[i.0].size, search.coordinates[j.0].size);
+    assert!(sizes.0 * sizes.1 > max_num_bytes);
+    let prune_table = coordinate_pair_prune_table(
+        (i, j),
+        (&search.move_tables[i.0], &search.move_tables[j.0]),
+        sizes,
+        (search.solved_values[i.0], search.solved_values[j.0]),
+        max_num_bytes,
+    );
+    assert!(prune_table.approximate_num_bytes() <= max_num_bytes);
+    // Sparse tables can't be used for membership sets.
+    assert!(prune_table.membership_within(1).is_none());
+
+    // The bound is a lower bound on the actual phase 1 solution length.
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    for scramble in [
+        "R U F",
+        "R U2 F' L D B2 R' F U'",
+        "F R' B L2 D' R U F2 L' B",
+    ] {
+        let pattern = kpuzzle.default_pattern().apply_transformation(
+            &kpuzzle
+                .transformation_from_alg(&scramble.parse().unwrap())
+                .unwrap(),
+        );
+        let values: Vec<CoordinateValue> = search
+            .coordinates
+            .iter()
+            .map(|coordinate| coordinate.value(&pattern))
+            .collect();
+        let solution = search
+            .search(&pattern, &CoordinateSearchOptions::default())
+            .unwrap();
+        assert!(prune_table.lookup(&values) <= solution.nodes.len());
+    }
+}
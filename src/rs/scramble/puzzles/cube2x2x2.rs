@@ -1,26 +1,42 @@
 use cubing::{alg::Alg, puzzles::cube2x2x2_kpuzzle};
 
+use crate::_internal::PuzzleError;
+
 use super::{
     super::randomize::{
-        randomize_orbit_naïve, OrbitOrientationConstraint, OrbitPermutationConstraint,
+        randomize_pattern, OrbitOrientationConstraint, OrbitPermutationConstraint,
+        OrbitRandomization, PatternRandomization,
+    },
+    super::rejection_tracker::RejectionTracker,
+    super::scramble_search::{
+        filtered_search, filtering_profile_for_event, generators_from_vec_str,
     },
-    super::scramble_search::{filtered_search, generators_from_vec_str},
+    super::Event,
+};
+
+pub(crate) const CUBE2X2X2_RANDOMIZATION: PatternRandomization = PatternRandomization {
+    orbits: &[OrbitRandomization {
+        orbit_name: "CORNERS",
+        permutation_constraint: OrbitPermutationConstraint::AnyPermutation,
+        orientation_constraint: OrbitOrientationConstraint::OrientationsMustSumToZero,
+    }],
+    cross_orbit_constraints: &[],
 };
 
-pub fn scramble_2x2x2() -> Alg {
+pub fn scramble_2x2x2() -> Result<Alg, PuzzleError> {
     let kpuzzle = cube2x2x2_kpuzzle();
+    let mut rejection_tracker = RejectionTracker::new("2x2x2");
     loop {
-        let mut scramble_pattern = kpuzzle.default_pattern();
-        let orbit_info = &kpuzzle.data.ordered_orbit_info[0];
-        randomize_orbit_naïve(
-            &mut scramble_pattern,
-            orbit_info,
-            OrbitPermutationConstraint::AnyPermutation,
-            OrbitOrientationConstraint::OrientationsMustSumToZero,
-        );
+        let scramble_pattern = randomize_pattern(kpuzzle, &CUBE2X2X2_RANDOMIZATION)?;
         let generators = generators_from_vec_str(vec!["U", "L", "F", "R"]);
-        if let Some(scramble) = filtered_search(&scramble_pattern, generators, Some(4), Some(11)) {
-            return scramble;
+        match filtered_search(
+            &scramble_pattern,
+            generators,
+            &filtering_profile_for_event(Event::Cube2x2x2Speedsolving).unwrap(),
+            Some(11),
+        ) {
+            Ok(scramble) => return Ok(scramble),
+            Err(reason) => rejection_tracker.reject(reason)?,
         }
     }
 }
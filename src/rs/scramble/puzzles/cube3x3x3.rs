@@ -1,241 +1,906 @@
-use std::sync::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
 
 use cubing::{
     alg::{Alg, AlgNode, Move, QuantumMove},
     kpuzzle::{KPattern, KPuzzle},
 };
+use instant::Instant;
 use lazy_static::lazy_static;
 
 use crate::{
-    _internal::{IDFSearch, IndividualSearchOptions},
+    _internal::{
+        options::{CustomGenerators, Generators, MetricEnum},
+        CanonicalFSM, IDFSearch, PuzzleError, SearchGenerators, TraceCategory, TraceSpan,
+    },
     scramble::{
         collapse::collapse_adjacent_moves,
-        randomize::{basic_parity, BasicParity},
-        scramble_search::{basic_idfs, idfs_with_target_pattern},
+        rejection_tracker::{RejectionReason, RejectionTracker},
+        scramble_options::{forced_scramble_pattern, is_scramble_filtering_skipped},
+        scramble_search::{
+            check_scramble_distance_range, filtering_idfs, filtering_profile_for_event,
+            random_canonical_moves, FilteringProfile,
+        },
+        Event,
     },
 };
 
 use super::{
     super::randomize::{
-        randomize_orbit_naïve, OrbitOrientationConstraint, OrbitPermutationConstraint,
+        is_scramble_seeded, randomize_pattern, CrossOrbitConstraint, OrbitOrientationConstraint,
+        OrbitPermutationConstraint, OrbitRandomization, PatternRandomization,
     },
     super::scramble_search::generators_from_vec_str,
-    definitions::{cube3x3x3_centerless_g1_target_kpattern, cube3x3x3_centerless_kpuzzle},
-    static_move_list::{add_random_suffixes_from, static_parsed_list, static_parsed_opt_list},
+    coordinate_search::{
+        check_phase_moves_closed_under_inverses, check_phase_moves_preserve_target,
+        CoordinateMembershipSet, CoordinatePhaseSearch, CoordinateSearchOptions,
+    },
+    cube3x3x3_coordinates::{
+        cube3x3x3_phase1_coordinates, cube3x3x3_phase1_search, cube3x3x3_phase2_search,
+        DEFAULT_PHASE1_MOVES, DEFAULT_PHASE2_MOVES,
+    },
+    definitions::cube3x3x3_centerless_kpuzzle,
+    static_move_list::{
+        choose_random_suffixes_from, static_parsed_list, static_parsed_opt_list, try_parsed_list,
+    },
 };
 
-pub struct Scramble3x3x3TwoPhase {
+// Random-state scrambles are very rarely this short, but a scramble that is
+// much shorter than usual looks like a mistake to competitors, so shorter
+// ones are padded (see `pad_short_scramble(…)`).
+const MIN_3X3X3_SCRAMBLE_LENGTH: usize = 15;
+
+/// The moves used by each phase of the two-phase algorithm. These can be
+/// restricted (e.g. to `<U, L, F, R, B>` for phase 1), at the cost of longer
+/// (or no) solutions for some patterns.
+///
+/// The moves of each phase must include the inverse of each move, and must be
+/// able to reach every value of that phase's coordinates. For example, phase
+/// 1 can't use `<U, R>`, which never moves the `BL` and `FL` edges.
+#[derive(Clone)]
+pub struct Cube3x3x3PhaseGenerators {
+    pub phase1: Vec<Move>,
+    /// Every move must stay within the phase 1 target `<U, D, L2, F2, R2, B2>`.
+    pub phase2: Vec<Move>,
+}
+
+impl Default for Cube3x3x3PhaseGenerators {
+    fn default() -> Self {
+        Self {
+            phase1: static_parsed_list(&DEFAULT_PHASE1_MOVES),
+            phase2: static_parsed_list(&DEFAULT_PHASE2_MOVES),
+        }
+    }
+}
+
+impl Cube3x3x3PhaseGenerators {
+    fn validate(&self, kpuzzle: &KPuzzle) -> Result<(), PuzzleError> {
+        for r#move in self.phase1.iter().chain(&self.phase2) {
+            kpuzzle
+                .transformation_from_move(r#move)
+                .map_err(|e| PuzzleError {
+                    description: format!("Invalid phase move {}: {}", r#move, e),
+                })?;
+        }
+        check_phase_moves_closed_under_inverses(kpuzzle, "Phase 1", &self.phase1)?;
+        check_phase_moves_closed_under_inverses(kpuzzle, "Phase 2", &self.phase2)?;
+        check_phase_moves_preserve_target(
+            kpuzzle,
+            "Phase 2",
+            &self.phase2,
+            "phase 1",
+            &cube3x3x3_phase1_coordinates(),
+        )
+    }
+}
+
+// The parts of the solver that are only read during a search, so that they
+// can be shared by all contexts (see `Scramble3x3x3TwoPhasePool`).
+struct Scramble3x3x3TwoPhaseTables {
     kpuzzle: KPuzzle,
 
-    filtering_idfs: IDFSearch,
+    filtering_profile: FilteringProfile,
+    // Whether phase 1 can use every move that the filtering can (see `load_next_table(…)`).
+    phase1_covers_filtering_moves: bool,
+    // Patterns that might be too short, as a fast path for filtering. These
+    // are built from the phase 1 pruning tables once they are all loaded.
+    filtering_membership_sets: OnceLock<Option<Vec<CoordinateMembershipSet>>>,
+
+    phase1_search: CoordinatePhaseSearch,
 
-    phase1_target_pattern: KPattern,
-    phase1_idfs: IDFSearch,
+    phase2_search: CoordinatePhaseSearch,
 
-    phase2_idfs: IDFSearch,
+    // For padding short scrambles (see `pad_short_scramble(…)`).
+    filler_generators: SearchGenerators,
+    filler_fsm: CanonicalFSM,
+}
+
+pub struct Scramble3x3x3TwoPhase {
+    tables: Arc<Scramble3x3x3TwoPhaseTables>,
+    // The filtering search grows its prune table as it goes, so each context has its own.
+    filtering_idfs: IDFSearch,
 }
 
 impl Default for Scramble3x3x3TwoPhase {
     fn default() -> Self {
+        Self::try_new(&Cube3x3x3PhaseGenerators::default()).unwrap()
+    }
+}
+
+impl Scramble3x3x3TwoPhaseTables {
+    // Builds the next table that isn't loaded yet (pruning tables first,
+    // since they help searches the most), and returns `false` if all of them
+    // were already loaded.
+    fn load_next_table(&self) -> bool {
+        if self.phase1_search.load_next_prune_table() || self.phase2_search.load_next_prune_table()
+        {
+            return true;
+        }
+        if self.filtering_membership_sets.get().is_some() {
+            return false;
+        }
+        self.filtering_membership_sets.get_or_init(|| {
+            // Reaching the phase 1 target never takes more moves than solving,
+            // as long as phase 1 can use every move that the filtering can.
+            if !self.phase1_covers_filtering_moves {
+                return None;
+            }
+            self.filtering_profile
+                .max_rejected_distance
+                .map(|max_distance| self.phase1_search.membership_sets_within(max_distance))
+        });
+        true
+    }
+
+    fn load_all_tables(&self) {
+        while self.load_next_table() {}
+    }
+}
+
+impl Scramble3x3x3TwoPhase {
+    pub fn try_new(phase_generators: &Cube3x3x3PhaseGenerators) -> Result<Self, PuzzleError> {
+        let two_phase = Self::try_new_partially_loaded(phase_generators)?;
+        two_phase.tables.load_all_tables();
+        Ok(two_phase)
+    }
+
+    // Only builds the tables that searches can't do without. The rest are
+    // built by `load_next_table(…)`, and seeded solutions don't depend on
+    // which ones are loaded (see `solve_3x3x3_pattern(…)`).
+    fn try_new_partially_loaded(
+        phase_generators: &Cube3x3x3PhaseGenerators,
+    ) -> Result<Self, PuzzleError> {
+        let _trace_span = TraceSpan::start(TraceCategory::TableBuild, || {
+            "3x3x3 two-phase tables".to_owned()
+        });
         let kpuzzle = cube3x3x3_centerless_kpuzzle().clone();
-        let generators = generators_from_vec_str(vec!["U", "L", "F", "R", "B", "D"]);
-        let filtering_idfs = basic_idfs(&kpuzzle, generators.clone(), Some(32));
+        phase_generators.validate(&kpuzzle)?;
 
-        let phase1_target_pattern = cube3x3x3_centerless_g1_target_kpattern().clone();
-        let phase1_idfs = idfs_with_target_pattern(
-            &kpuzzle,
-            generators.clone(),
-            phase1_target_pattern.clone(),
-            Some(1 << 24),
-        );
+        // All 3x3x3 events use the same filtering.
+        let filtering_profile = filtering_profile_for_event(Event::Cube3x3x3Speedsolving).unwrap();
+
+        // Reachability is cheapest to check with the move tables, so it's
+        // checked here rather than in `validate(…)`.
+        let phase1_search = cube3x3x3_phase1_search(&kpuzzle, phase_generators.phase1.clone());
+        phase1_search.check_every_value_is_reachable("Phase 1")?;
+
+        let phase2_search = cube3x3x3_phase2_search(&kpuzzle, phase_generators.phase2.clone());
+        phase2_search.check_every_value_is_reachable("Phase 2")?;
+
+        let default_phase1_moves: Vec<Move> = static_parsed_list(&DEFAULT_PHASE1_MOVES);
+        let phase1_covers_filtering_moves = default_phase1_moves
+            .iter()
+            .all(|r#move| phase_generators.phase1.contains(r#move));
 
-        let phase2_generators = generators_from_vec_str(vec!["U", "L2", "F2", "R2", "B2", "D"]);
-        let phase2_idfs = idfs_with_target_pattern(
+        // Filler moves use the phase 1 moves (one per quantum), so that they
+        // are allowed wherever the rest of the scramble is.
+        let mut filler_moves = Vec::<Move>::new();
+        for r#move in &phase_generators.phase1 {
+            if !filler_moves
+                .iter()
+                .any(|filler_move| filler_move.quantum == r#move.quantum)
+            {
+                filler_moves.push(Move {
+                    quantum: r#move.quantum.clone(),
+                    amount: 1,
+                });
+            }
+        }
+        let filler_generators = SearchGenerators::try_new(
             &kpuzzle,
-            phase2_generators.clone(),
-            kpuzzle.default_pattern(),
-            Some(1 << 24),
-        );
+            &Generators::Custom(CustomGenerators {
+                moves: filler_moves,
+                algs: vec![],
+            }),
+            &MetricEnum::Hand,
+            false,
+        )?;
+        let filler_fsm = CanonicalFSM::try_new(filler_generators.clone())?;
 
-        Self {
+        Ok(Self::from_tables(Arc::new(Scramble3x3x3TwoPhaseTables {
             kpuzzle,
-            filtering_idfs,
+            filtering_profile,
+            phase1_covers_filtering_moves,
+            filtering_membership_sets: OnceLock::new(),
+
+            phase1_search,
 
-            phase1_target_pattern,
-            phase1_idfs,
+            phase2_search,
 
-            phase2_idfs,
+            filler_generators,
+            filler_fsm,
+        })))
+    }
+
+    fn from_tables(tables: Arc<Scramble3x3x3TwoPhaseTables>) -> Self {
+        let filtering_idfs = filtering_idfs(
+            &tables.kpuzzle,
+            generators_from_vec_str(vec!["U", "L", "F", "R", "B", "D"]),
+            &tables.filtering_profile,
+        );
+        Self {
+            tables,
+            filtering_idfs,
         }
     }
+
+    /// The approximate memory used by the tables of this solver.
+    pub fn approximate_table_num_bytes(&self) -> usize {
+        self.filtering_idfs.approximate_prune_table_num_bytes()
+            + self
+                .tables
+                .filtering_membership_sets
+                .get()
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|membership_set| membership_set.approximate_num_bytes())
+                .sum::<usize>()
+            + self.tables.phase1_search.approximate_num_bytes()
+            + self.tables.phase2_search.approximate_num_bytes()
+    }
+
+    /// The optimal number of moves to reach the phase 1 target (`<U, D, L2, F2, R2, B2>`).
+    pub(crate) fn phase1_depth(&self, pattern: &KPattern) -> Option<usize> {
+        self.tables
+            .phase1_search
+            .search(pattern, &CoordinateSearchOptions::default())
+            .map(|alg| alg.nodes.len())
+    }
+
+    /// Solves a pattern of the centerless 3x3x3 using the phase generators of this solver.
+    pub fn solve(&mut self, pattern: &KPattern) -> Result<Alg, PuzzleError> {
+        self.solve_3x3x3_pattern(pattern, PrefixOrSuffixConstraints::None, None)
+            .map_err(|_| "No solution found using the phase generators.".into())
+    }
 }
 
-pub fn random_3x3x3_pattern() -> KPattern {
-    let kpuzzle = cube3x3x3_centerless_kpuzzle();
-    let mut scramble_pattern = kpuzzle.default_pattern();
-    let orbit_info = &kpuzzle.data.ordered_orbit_info[0];
-    assert_eq!(orbit_info.name.0, "EDGES");
-    let edge_order = randomize_orbit_naïve(
-        &mut scramble_pattern,
-        orbit_info,
-        OrbitPermutationConstraint::AnyPermutation,
-        OrbitOrientationConstraint::OrientationsMustSumToZero,
-    );
-    let each_orbit_parity = basic_parity(&edge_order);
-    let orbit_info = &kpuzzle.data.ordered_orbit_info[1];
-    assert_eq!(orbit_info.name.0, "CORNERS");
-    randomize_orbit_naïve(
-        &mut scramble_pattern,
-        orbit_info,
-        match each_orbit_parity {
-            BasicParity::Even => OrbitPermutationConstraint::SingleOrbitEvenParity,
-            BasicParity::Odd => OrbitPermutationConstraint::SingleOrbitOddParity,
+const CUBE3X3X3_RANDOMIZATION: PatternRandomization = PatternRandomization {
+    orbits: &[
+        OrbitRandomization {
+            orbit_name: "EDGES",
+            permutation_constraint: OrbitPermutationConstraint::AnyPermutation,
+            orientation_constraint: OrbitOrientationConstraint::OrientationsMustSumToZero,
         },
-        OrbitOrientationConstraint::OrientationsMustSumToZero,
-    );
-    scramble_pattern
+        OrbitRandomization {
+            orbit_name: "CORNERS",
+            permutation_constraint: OrbitPermutationConstraint::AnyPermutation,
+            orientation_constraint: OrbitOrientationConstraint::OrientationsMustSumToZero,
+        },
+    ],
+    cross_orbit_constraints: &[CrossOrbitConstraint::EqualParity("EDGES", "CORNERS")],
+};
+
+pub fn random_3x3x3_pattern() -> KPattern {
+    randomize_pattern(cube3x3x3_centerless_kpuzzle(), &CUBE3X3X3_RANDOMIZATION).unwrap()
 }
 
+#[derive(Clone)]
 pub(crate) enum PrefixOrSuffixConstraints {
     None,
     ForFMC,
+    DisallowedFinalQuanta(Vec<QuantumMove>),
+    DisallowedQuanta {
+        initial_quanta: Option<Vec<QuantumMove>>,
+        final_quanta: Option<Vec<QuantumMove>>,
+    },
 }
 
-impl Scramble3x3x3TwoPhase {
-    pub(crate) fn solve_3x3x3_pattern(
-        &mut self,
-        pattern: &KPattern,
-        constraints: PrefixOrSuffixConstraints,
-    ) -> Alg {
-        // TODO: once perf is good enough, use `F`` as "required first move" and `R'` as "required last move" in the search (overlapping with the affixes).
-        let (phase1_disallowed_initial_quanta, disallowed_final_quanta) = match constraints {
+impl PrefixOrSuffixConstraints {
+    // The quanta that the first and last moves can't use (if any).
+    fn disallowed_initial_and_final_quanta(
+        self,
+    ) -> (Option<Vec<QuantumMove>>, Option<Vec<QuantumMove>>) {
+        match self {
             PrefixOrSuffixConstraints::None => (None, None),
             PrefixOrSuffixConstraints::ForFMC => (
                 Some(static_parsed_list::<QuantumMove>(&["F", "B"])),
                 Some(static_parsed_list::<QuantumMove>(&["R", "L"])),
             ),
-        };
-
-        let phase1_alg = {
-            let mut phase1_search_pattern = self.phase1_target_pattern.clone();
-            for orbit_info in self.kpuzzle.orbit_info_iter() {
-                for i in 0..orbit_info.num_pieces {
-                    let old_piece = pattern.get_piece(orbit_info, i);
-                    let old_piece_mapped =
-                        self.phase1_target_pattern.get_piece(orbit_info, old_piece);
-                    phase1_search_pattern.set_piece(orbit_info, i, old_piece_mapped);
-                    let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
-                    phase1_search_pattern.set_orientation_with_mod(
-                        orbit_info,
-                        i,
-                        orientation_with_mod,
-                    );
-                }
+            PrefixOrSuffixConstraints::DisallowedFinalQuanta(disallowed_final_quanta) => {
+                (None, Some(disallowed_final_quanta))
             }
+            PrefixOrSuffixConstraints::DisallowedQuanta {
+                initial_quanta,
+                final_quanta,
+            } => (initial_quanta, final_quanta),
+        }
+    }
+}
 
-            self.phase1_idfs
-                .search(
-                    &phase1_search_pattern,
-                    IndividualSearchOptions {
-                        min_num_solutions: Some(1),
-                        min_depth: None,
-                        max_depth: None,
-                        disallowed_initial_quanta: phase1_disallowed_initial_quanta,
-                        disallowed_final_quanta: disallowed_final_quanta.clone(), // TODO: We currently need to pass this in case phase 2 return the empty alg. Can we handle this in another way?
+impl Scramble3x3x3TwoPhase {
+    pub(crate) fn solve_3x3x3_pattern(
+        &mut self,
+        pattern: &KPattern,
+        constraints: PrefixOrSuffixConstraints,
+        max_total_length: Option<usize>,
+    ) -> Result<Alg, RejectionReason> {
+        // TODO: once perf is good enough, use `F`` as "required first move" and `R'` as "required last move" in the search (overlapping with the affixes).
+        let (phase1_disallowed_initial_quanta, disallowed_final_quanta) =
+            constraints.disallowed_initial_and_final_quanta();
+        let mut trace_span = TraceSpan::start(TraceCategory::SearchPhase, || {
+            "3x3x3 two-phase solve".to_owned()
+        });
+        let mut num_phase1_solutions: u64 = 0;
+
+        // If phase 2 can't finish within the total length (or the
+        // constraints), we backtrack and try the next phase 1 solution.
+        let mut solution: Option<Alg> = None;
+        self.tables.phase1_search.search_each_with_patterns(
+            pattern,
+            &CoordinateSearchOptions {
+                max_depth: max_total_length,
+                disallowed_initial_quanta: phase1_disallowed_initial_quanta,
+                disallowed_final_quanta: disallowed_final_quanta.clone(), // TODO: We currently need to pass this in case phase 2 return the empty alg. Can we handle this in another way?
+                // We go through phase 1 solutions until phase 2 succeeds, so
+                // finding them sooner at each depth saves the most work here.
+                // That order depends on which pruning tables are loaded, so
+                // seeded scrambles keep the order of the moves instead.
+                order_first_moves_by_heuristic: !is_scramble_seeded(),
+                deadline: None,
+            },
+            |phase1_alg, phase2_search_pattern| {
+                num_phase1_solutions += 1;
+                let phase2_alg = self.tables.phase2_search.search(
+                    &phase2_search_pattern,
+                    &CoordinateSearchOptions {
+                        max_depth: max_total_length
+                            .map(|max_total_length| max_total_length - phase1_alg.nodes.len()),
+                        disallowed_final_quanta: disallowed_final_quanta.clone(),
+                        ..Default::default()
                     },
-                )
-                .next()
-                .unwrap()
-        };
+                );
+                match phase2_alg {
+                    Some(mut phase2_alg) => {
+                        if cfg!(debug_assertions) {
+                            self.check_phase1_invariants(&phase2_search_pattern, &phase2_alg);
+                        }
+                        let mut nodes = phase1_alg.nodes;
+                        nodes.append(&mut phase2_alg.nodes);
+                        solution = Some(Alg { nodes });
+                        true
+                    }
+                    None => false,
+                }
+            },
+        );
+        if let Some(trace_span) = &mut trace_span {
+            trace_span.set_arg("num_phase1_solutions", num_phase1_solutions);
+        }
+        solution.ok_or(RejectionReason::NoPhaseSolution)
+    }
 
-        let mut phase2_alg = {
-            let phase2_search_pattern = pattern
-                .apply_transformation(&self.kpuzzle.transformation_from_alg(&phase1_alg).unwrap());
-            self.phase2_idfs
-                .search(
+    /// Solves the pattern, and then keeps looking for shorter solutions (using
+    /// longer phase 1 solutions) until `deadline`. Each solution that is
+    /// shorter than the previous ones is passed to `on_improved_solution` as
+    /// soon as it is found, and the last one is returned.
+    ///
+    /// The deadline is checked inside both phase searches, so this returns an
+    /// error if no solution was found by then.
+    pub fn solve_until(
+        &mut self,
+        pattern: &KPattern,
+        deadline: Instant,
+        mut on_improved_solution: impl FnMut(&Alg),
+    ) -> Result<Alg, PuzzleError> {
+        let mut best_solution: Option<Alg> = None;
+        self.tables.phase1_search.search_each_with_patterns(
+            pattern,
+            &CoordinateSearchOptions {
+                order_first_moves_by_heuristic: true,
+                deadline: Some(deadline),
+                ..Default::default()
+            },
+            |phase1_alg, phase2_search_pattern| {
+                // Phase 1 solutions come in order of length, so once they are
+                // as long as the best solution, none of the rest can improve on it.
+                if let Some(best_solution) = &best_solution {
+                    if phase1_alg.nodes.len() >= best_solution.nodes.len() {
+                        return true;
+                    }
+                }
+                let phase2_alg = self.tables.phase2_search.search(
                     &phase2_search_pattern,
-                    IndividualSearchOptions {
-                        min_num_solutions: Some(1),
-                        min_depth: None,
-                        max_depth: None,
-                        disallowed_initial_quanta: None,
-                        disallowed_final_quanta,
+                    &CoordinateSearchOptions {
+                        max_depth: best_solution.as_ref().map(|best_solution| {
+                            best_solution.nodes.len() - 1 - phase1_alg.nodes.len()
+                        }),
+                        deadline: Some(deadline),
+                        ..Default::default()
                     },
-                )
-                .next()
-                .unwrap()
-        };
+                );
+                if let Some(mut phase2_alg) = phase2_alg {
+                    let mut nodes = phase1_alg.nodes;
+                    nodes.append(&mut phase2_alg.nodes);
+                    let solution = Alg { nodes };
+                    on_improved_solution(&solution);
+                    best_solution = Some(solution);
+                }
+                Instant::now() >= deadline
+            },
+        );
+        best_solution.ok_or_else(|| {
+            if Instant::now() >= deadline {
+                "No solution found before the deadline.".into()
+            } else {
+                "No solution found using the phase generators.".into()
+            }
+        })
+    }
+
+    // Catches generator bugs (e.g. a phase 2 move that leaves phase 1's
+    // subgroup) that would otherwise only show up as a wrong final scramble.
+    fn check_phase1_invariants(&self, phase2_search_pattern: &KPattern, phase2_alg: &Alg) {
+        let mut pattern = phase2_search_pattern.clone();
+        assert!(
+            self.tables.phase1_search.is_solved(&pattern),
+            "Phase 1 solution does not reach the phase 1 target."
+        );
+        for node in &phase2_alg.nodes {
+            let AlgNode::MoveNode(r#move) = node else {
+                continue;
+            };
+            pattern = pattern.apply_transformation(
+                &self
+                    .tables
+                    .kpuzzle
+                    .transformation_from_move(r#move)
+                    .unwrap(),
+            );
+            assert!(
+                self.tables.phase1_search.is_solved(&pattern),
+                "Phase 2 move {} breaks the phase 1 invariants.",
+                r#move
+            );
+        }
+    }
 
-        let mut nodes = phase1_alg.nodes;
-        nodes.append(&mut phase2_alg.nodes);
-        Alg { nodes }
+    /// Returns the optimal solution length (HTM) of the pattern if it is at
+    /// most `max_distance`, or `None` if it is longer. This is fast for small
+    /// distances (e.g. to check whether a scramble is too short).
+    pub fn distance_at_most(&mut self, pattern: &KPattern, max_distance: usize) -> Option<u8> {
+        // Until the membership sets are loaded, the filtering search does all the work.
+        if let Some(Some(filtering_membership_sets)) = self.tables.filtering_membership_sets.get() {
+            let covers_max_distance = filtering_membership_sets
+                .iter()
+                .all(|membership_set| membership_set.max_depth() >= max_distance);
+            if covers_max_distance
+                && !self
+                    .tables
+                    .phase1_search
+                    .may_be_within(filtering_membership_sets, pattern)
+            {
+                return None;
+            }
+        }
+        self.filtering_idfs.distance_at_most(pattern, max_distance)
+    }
+
+    /// The max distance of patterns that scramble filtering rejects, if any are.
+    pub(crate) fn max_rejected_distance(&self) -> Option<usize> {
+        self.tables.filtering_profile.max_rejected_distance
     }
 
     // TODO: rely on the main search to find patterns at a low depth?
     pub fn is_valid_scramble_pattern(&mut self, pattern: &KPattern) -> bool {
-        self.filtering_idfs
-            .search(
-                pattern,
-                IndividualSearchOptions {
-                    min_num_solutions: Some(1),
-                    min_depth: Some(0),
-                    max_depth: Some(2),
-                    disallowed_initial_quanta: None,
-                    disallowed_final_quanta: None,
-                },
-            )
-            .next()
-            .is_none()
+        match self.max_rejected_distance() {
+            Some(max_rejected_distance) => self
+                .distance_at_most(pattern, max_rejected_distance)
+                .is_none(),
+            None => true,
+        }
     }
 
-    pub(crate) fn scramble_3x3x3(&mut self, constraints: PrefixOrSuffixConstraints) -> Alg {
+    pub(crate) fn scramble_3x3x3(
+        &mut self,
+        constraints: PrefixOrSuffixConstraints,
+    ) -> Result<Alg, PuzzleError> {
+        if let Some(forced_pattern) = forced_scramble_pattern() {
+            return self.scramble_forced_3x3x3_pattern(&forced_pattern, constraints);
+        }
+        let mut rejection_tracker = RejectionTracker::new("3x3x3");
+        loop {
+            let scramble_pattern = self.random_scramble_pattern(&mut rejection_tracker)?;
+            match self
+                .solve_3x3x3_pattern(&scramble_pattern, constraints.clone(), None)
+                .and_then(|scramble| {
+                    self.pad_short_scramble(&scramble_pattern, scramble, constraints.clone())
+                }) {
+                Ok(scramble) => return Ok(scramble),
+                Err(reason) => rejection_tracker.reject(reason)?,
+            }
+        }
+    }
+
+    // A random pattern that passes scramble filtering (and the distance range, if any).
+    fn random_scramble_pattern(
+        &mut self,
+        rejection_tracker: &mut RejectionTracker,
+    ) -> Result<KPattern, PuzzleError> {
         loop {
             let scramble_pattern = random_3x3x3_pattern();
-            if !self.is_valid_scramble_pattern(&scramble_pattern) {
+            if !is_scramble_filtering_skipped()
+                && !self.is_valid_scramble_pattern(&scramble_pattern)
+            {
+                rejection_tracker.reject(RejectionReason::OptimalSolutionTooShort)?;
                 continue;
             }
-            return self.solve_3x3x3_pattern(&scramble_pattern, constraints);
+            if let Err(reason) = check_scramble_distance_range(|max_distance| {
+                self.distance_at_most(&scramble_pattern, max_distance)
+            }) {
+                rejection_tracker.reject(reason)?;
+                continue;
+            }
+            return Ok(scramble_pattern);
+        }
+    }
+
+    /// Like `scramble_3x3x3(…)` (without constraints), but returns the
+    /// shortest scramble that `solve_until(…)` finds by `deadline`, or `None`
+    /// if it didn't find one in time.
+    fn scramble_3x3x3_until(&mut self, deadline: Instant) -> Result<Option<Alg>, PuzzleError> {
+        let scramble_pattern = self.random_scramble_pattern(&mut RejectionTracker::new("3x3x3"))?;
+        let Ok(scramble) = self.solve_until(&scramble_pattern, deadline, |_| {}) else {
+            return Ok(None);
+        };
+        Ok(self
+            .pad_short_scramble(&scramble_pattern, scramble, PrefixOrSuffixConstraints::None)
+            .ok())
+    }
+
+    // Lengthens a scramble for `pattern` that is shorter than
+    // `MIN_3X3X3_SCRAMBLE_LENGTH`, by starting with canonical filler moves `F`
+    // and then solving the pattern after `F` (which together still solve
+    // `pattern`). The solution after `F` doesn't start on the same axis as the
+    // last filler move, so nothing cancels.
+    fn pad_short_scramble(
+        &mut self,
+        pattern: &KPattern,
+        scramble: Alg,
+        constraints: PrefixOrSuffixConstraints,
+    ) -> Result<Alg, RejectionReason> {
+        if scramble.nodes.len() >= MIN_3X3X3_SCRAMBLE_LENGTH {
+            return Ok(scramble);
+        }
+        let (disallowed_initial_quanta, disallowed_final_quanta) =
+            constraints.disallowed_initial_and_final_quanta();
+        let mut num_filler_moves = MIN_3X3X3_SCRAMBLE_LENGTH - scramble.nodes.len();
+        let mut padded_scramble = scramble;
+        // Each attempt has more filler moves, so this always finishes.
+        while padded_scramble.nodes.len() < MIN_3X3X3_SCRAMBLE_LENGTH {
+            let filler_moves = random_canonical_moves(
+                &self.tables.filler_generators,
+                &self.tables.filler_fsm,
+                num_filler_moves,
+                disallowed_initial_quanta.as_deref().unwrap_or_default(),
+            );
+            let Some(last_filler_move) = filler_moves.last() else {
+                return Err(RejectionReason::NoPhaseSolution);
+            };
+            let filler_generators = &self.tables.filler_generators;
+            let last_filler_axis_quanta: Vec<QuantumMove> = filler_generators
+                .axis_for_move(last_filler_move)
+                .map(|axis| {
+                    filler_generators.axes[axis.0]
+                        .iter()
+                        .map(|move_class_index| {
+                            filler_generators.grouped[move_class_index.0][0]
+                                .r#move
+                                .quantum
+                                .as_ref()
+                                .clone()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut filler_pattern = pattern.clone();
+            for r#move in &filler_moves {
+                filler_pattern = filler_pattern.apply_transformation(
+                    &self
+                        .tables
+                        .kpuzzle
+                        .transformation_from_move(r#move)
+                        .unwrap(),
+                );
+            }
+            let remainder = self.solve_3x3x3_pattern(
+                &filler_pattern,
+                PrefixOrSuffixConstraints::DisallowedQuanta {
+                    initial_quanta: Some(last_filler_axis_quanta),
+                    final_quanta: disallowed_final_quanta.clone(),
+                },
+                None,
+            )?;
+            let mut nodes: Vec<AlgNode> = filler_moves
+                .into_iter()
+                .map(|r#move| r#move.into())
+                .collect();
+            nodes.extend(remainder.nodes);
+            padded_scramble = Alg { nodes };
+            num_filler_moves += 1;
+        }
+        Ok(padded_scramble)
+    }
+
+    // See `ScrambleOptions::forced_pattern`.
+    fn scramble_forced_3x3x3_pattern(
+        &mut self,
+        forced_pattern: &KPattern,
+        constraints: PrefixOrSuffixConstraints,
+    ) -> Result<Alg, PuzzleError> {
+        if !has_centerless_3x3x3_orbits(forced_pattern.kpuzzle()) {
+            return Err(
+                "The forced scramble pattern must be a pattern of the centerless 3x3x3.".into(),
+            );
+        }
+        let could_not_scramble = |reason: RejectionReason| PuzzleError {
+            description: format!("Could not scramble the forced pattern ({}).", reason),
+        };
+        // A scramble is a solution for the inverse of the pattern that it
+        // produces, and applying any solution of the forced pattern to the
+        // solved pattern gives that inverse.
+        let solution = self
+            .solve_3x3x3_pattern(forced_pattern, PrefixOrSuffixConstraints::None, None)
+            .map_err(could_not_scramble)?;
+        let inverse_pattern = self.tables.kpuzzle.default_pattern().apply_transformation(
+            &self
+                .tables
+                .kpuzzle
+                .transformation_from_alg(&solution)
+                .unwrap(),
+        );
+        if !is_scramble_filtering_skipped() && !self.is_valid_scramble_pattern(&inverse_pattern) {
+            return Err(could_not_scramble(RejectionReason::OptimalSolutionTooShort));
+        }
+        self.solve_3x3x3_pattern(&inverse_pattern, constraints, None)
+            .map_err(could_not_scramble)
+    }
+}
+
+/// Whether patterns of the puzzle can be passed to the two-phase solver (i.e.
+/// it has the same orbits as the centerless 3x3x3, even if it is a different
+/// `KPuzzle` instance).
+pub(crate) fn has_centerless_3x3x3_orbits(kpuzzle: &KPuzzle) -> bool {
+    let orbit_shapes = |kpuzzle: &KPuzzle| {
+        kpuzzle
+            .orbit_info_iter()
+            .map(|orbit_info| {
+                (
+                    orbit_info.name.0.clone(),
+                    orbit_info.num_pieces,
+                    orbit_info.num_orientations,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    orbit_shapes(kpuzzle) == orbit_shapes(cube3x3x3_centerless_kpuzzle())
+}
+
+// Set once the shared tables have been fully built, so that callers can prefer
+// other solvers when building them would take longer than the solve itself.
+static TWO_PHASE_TABLES_LOADED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn are_3x3x3_two_phase_tables_loaded() -> bool {
+    TWO_PHASE_TABLES_LOADED.load(Ordering::Relaxed)
+}
+
+// Set once the shared solver exists (with the tables that searches can't do
+// without), so that time-budgeted scrambles don't have to wait for it.
+static TWO_PHASE_POOL_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Two-phase solver contexts that share a single set of tables. Each thread
+/// checks out its own context (with its own scratch data), so threads only
+/// wait for each other to check contexts in and out rather than for whole
+/// searches (e.g. in a multi-threaded scramble server).
+pub(crate) struct Scramble3x3x3TwoPhasePool {
+    tables: Arc<Scramble3x3x3TwoPhaseTables>,
+    idle_contexts: Mutex<Vec<Scramble3x3x3TwoPhase>>,
+}
+
+impl Scramble3x3x3TwoPhasePool {
+    // The first scramble only waits for the tables that searches can't do
+    // without, and the rest are loaded in the background.
+    fn new() -> Self {
+        let context =
+            Scramble3x3x3TwoPhase::try_new_partially_loaded(&Cube3x3x3PhaseGenerators::default())
+                .unwrap();
+        let tables = context.tables.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let tables = tables.clone();
+            std::thread::spawn(move || Self::finish_loading_tables(&tables));
+        }
+        TWO_PHASE_POOL_INITIALIZED.store(true, Ordering::Relaxed);
+        Self {
+            tables,
+            idle_contexts: Mutex::new(vec![context]),
+        }
+    }
+
+    fn finish_loading_tables(tables: &Scramble3x3x3TwoPhaseTables) {
+        tables.load_all_tables();
+        TWO_PHASE_TABLES_LOADED.store(true, Ordering::Relaxed);
+    }
+
+    /// Runs `f` with a context that no other thread is using, creating a new
+    /// one (for the same tables) if all of them are in use.
+    pub(crate) fn with_context<T>(&self, f: impl FnOnce(&mut Scramble3x3x3TwoPhase) -> T) -> T {
+        let idle_context = self.idle_contexts.lock().unwrap().pop();
+        let mut context =
+            idle_context.unwrap_or_else(|| Scramble3x3x3TwoPhase::from_tables(self.tables.clone()));
+        let result = f(&mut context);
+        self.idle_contexts.lock().unwrap().push(context);
+        // There are no background threads in WASM, so we load one more table
+        // after each call instead.
+        #[cfg(target_arch = "wasm32")]
+        {
+            if !self.tables.load_next_table() {
+                TWO_PHASE_TABLES_LOADED.store(true, Ordering::Relaxed);
+            }
         }
+        result
+    }
+
+    /// Waits until all the tables are loaded (building any that are left on
+    /// the current thread).
+    fn wait_for_all_tables(&self) {
+        Self::finish_loading_tables(&self.tables);
     }
 }
 
 // TODO: switch to `LazyLock` once that's stable: https://doc.rust-lang.org/nightly/std/cell/struct.LazyCell.html
 lazy_static! {
-    static ref SCRAMBLE3X3X3_TWO_PHASE: Mutex<Scramble3x3x3TwoPhase> =
-        Mutex::new(Scramble3x3x3TwoPhase::default());
+    pub(crate) static ref SCRAMBLE3X3X3_TWO_PHASE: Scramble3x3x3TwoPhasePool =
+        Scramble3x3x3TwoPhasePool::new();
 }
 
-pub fn scramble_3x3x3() -> Alg {
+/// Initializes the shared two-phase solver (if needed), waits for all of its
+/// tables to load, and returns the approximate memory used by them.
+pub(crate) fn warm_up_3x3x3_two_phase() -> usize {
+    SCRAMBLE3X3X3_TWO_PHASE.wait_for_all_tables();
+    SCRAMBLE3X3X3_TWO_PHASE.with_context(|two_phase| two_phase.approximate_table_num_bytes())
+}
+
+pub fn scramble_3x3x3() -> Result<Alg, PuzzleError> {
     SCRAMBLE3X3X3_TWO_PHASE
-        .lock()
-        .unwrap()
-        .scramble_3x3x3(PrefixOrSuffixConstraints::None)
+        .with_context(|two_phase| two_phase.scramble_3x3x3(PrefixOrSuffixConstraints::None))
 }
 
-pub fn scramble_3x3x3_bld() -> Alg {
+/// Returns whether the shared two-phase solver can be used without waiting
+/// for it to be built. If it can't, this starts building it on a background
+/// thread (only once, no matter how often it's called). WASM has no
+/// background threads, so this always returns `true` there, and the first
+/// use builds the solver.
+pub(crate) fn prepare_3x3x3_two_phase_without_waiting() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START_BUILDING: std::sync::Once = std::sync::Once::new();
+        if !TWO_PHASE_POOL_INITIALIZED.load(Ordering::Relaxed) {
+            START_BUILDING.call_once(|| {
+                std::thread::spawn(|| lazy_static::initialize(&SCRAMBLE3X3X3_TWO_PHASE));
+            });
+            return false;
+        }
+    }
+    true
+}
+
+/// Like `scramble_3x3x3()`, but returns the shortest scramble found by
+/// `deadline`, or `None` if none was found in time.
+pub(crate) fn scramble_3x3x3_until(deadline: Instant) -> Result<Option<Alg>, PuzzleError> {
+    SCRAMBLE3X3X3_TWO_PHASE.with_context(|two_phase| two_phase.scramble_3x3x3_until(deadline))
+}
+
+/// Solves a pattern of the centerless 3x3x3 using the two-phase algorithm,
+/// with at most `max_total_length` moves (HTM). Bounds close to optimal can
+/// take a very long time.
+pub fn solve_3x3x3_with_max_total_length(
+    pattern: &KPattern,
+    max_total_length: usize,
+) -> Result<Alg, PuzzleError> {
+    SCRAMBLE3X3X3_TWO_PHASE
+        .with_context(|two_phase| {
+            two_phase.solve_3x3x3_pattern(
+                pattern,
+                PrefixOrSuffixConstraints::None,
+                Some(max_total_length),
+            )
+        })
+        .map_err(|_| PuzzleError {
+            description: format!("No solution found with at most {} moves.", max_total_length),
+        })
+}
+
+/// Like `solve_3x3x3_with_max_total_length(…)`, but returns the shortest
+/// solution that it finds within (roughly) the time budget instead of
+/// requiring a bound. Each improvement is passed to `on_improved_solution` as
+/// soon as it is found (e.g. to show the best solution so far while analyzing
+/// an FMC attempt).
+pub fn solve_3x3x3_within_time_budget(
+    pattern: &KPattern,
+    time_budget: Duration,
+    on_improved_solution: impl FnMut(&Alg),
+) -> Result<Alg, PuzzleError> {
+    let deadline = Instant::now() + time_budget;
+    SCRAMBLE3X3X3_TWO_PHASE
+        .with_context(|two_phase| two_phase.solve_until(pattern, deadline, on_improved_solution))
+}
+
+// The outer faces that a wide move turns (directly or through its rotation).
+fn bld_suffix_axis_quanta(r#move: &Move) -> Vec<QuantumMove> {
+    match r#move.quantum.family.as_str() {
+        "Rw" => static_parsed_list(&["R", "L"]),
+        "Fw" => static_parsed_list(&["F", "B"]),
+        "Uw" => static_parsed_list(&["U", "D"]),
+        _ => vec![],
+    }
+}
+
+pub fn scramble_3x3x3_bld() -> Result<Alg, PuzzleError> {
     let s1 = static_parsed_opt_list(&["", "Rw", "Rw2", "Rw'", "Fw", "Fw'"]);
     let s2 = static_parsed_opt_list(&["", "Uw", "Uw2", "Uw'"]);
-    add_random_suffixes_from(scramble_3x3x3(), [s1, s2])
+    let suffixes = choose_random_suffixes_from([s1, s2]);
+    // A final move on the same axis as the first suffix (e.g. `L` before `Rw`)
+    // would cancel with it, so we ask for a scramble that doesn't end with
+    // one. Re-drawing the suffixes instead would skew the final orientation.
+    let constraints = match suffixes.first() {
+        Some(first_suffix) => {
+            PrefixOrSuffixConstraints::DisallowedFinalQuanta(bld_suffix_axis_quanta(first_suffix))
+        }
+        None => PrefixOrSuffixConstraints::None,
+    };
+    let mut nodes = SCRAMBLE3X3X3_TWO_PHASE
+        .with_context(|two_phase| two_phase.scramble_3x3x3(constraints))?
+        .nodes;
+    for r#move in suffixes {
+        nodes.push(r#move.into());
+    }
+    Ok(Alg { nodes })
 }
 
 const FMC_AFFIX: [&str; 3] = ["R'", "U'", "F"];
 
-pub fn scramble_3x3x3_fmc() -> Alg {
+pub fn scramble_3x3x3_fmc() -> Result<Alg, PuzzleError> {
     let mut nodes = Vec::<AlgNode>::new();
 
-    let prefix_and_suffix: Vec<Move> = static_parsed_list(&FMC_AFFIX);
+    let prefix_and_suffix: Vec<Move> = try_parsed_list(&FMC_AFFIX)?;
     for r#move in prefix_and_suffix {
         nodes.push(r#move.into());
     }
 
     nodes.append(
         &mut SCRAMBLE3X3X3_TWO_PHASE
-            .lock()
-            .unwrap()
-            .scramble_3x3x3(PrefixOrSuffixConstraints::ForFMC)
+            .with_context(|two_phase| two_phase.scramble_3x3x3(PrefixOrSuffixConstraints::ForFMC))?
             .nodes,
     );
 
-    let affix: Vec<Move> = static_parsed_list(&FMC_AFFIX);
+    let affix: Vec<Move> = try_parsed_list(&FMC_AFFIX)?;
     for r#move in affix {
         nodes.push(r#move.into());
     }
@@ -243,5 +908,70 @@ pub fn scramble_3x3x3_fmc() -> Alg {
     // Note: `collapse_adjacent_moves(…)` is technically overkill, as it's only
     // possible for a single move to overlap without completely cancelling.
     // However, it's safer to use a common function for this instead of a one-off implementation.
-    collapse_adjacent_moves(Alg { nodes }, 4, -1)
+    Ok(collapse_adjacent_moves(Alg { nodes }, 4, -1))
+}
+
+// Which pruning tables are loaded changes the order in which phase 1
+// solutions are found, so this checks that seeded scrambles don't depend on it.
+#[test]
+fn seeded_scramble_with_partially_loaded_tables_test() -> Result<(), PuzzleError> {
+    use super::super::randomize::{with_scramble_seed, ScrambleSeed};
+
+    let phase_generators = Cube3x3x3PhaseGenerators::default();
+    let mut fresh_two_phase = Scramble3x3x3TwoPhase::try_new_partially_loaded(&phase_generators)?;
+    let mut warm_two_phase = Scramble3x3x3TwoPhase::try_new(&phase_generators)?;
+    for seed_byte in 0..4 {
+        let seed = ScrambleSeed::from_bytes([seed_byte; 32]);
+        let mut scramble = |two_phase: &mut Scramble3x3x3TwoPhase| {
+            with_scramble_seed(&seed, || {
+                two_phase.scramble_3x3x3(PrefixOrSuffixConstraints::None)
+            })
+        };
+        assert_eq!(
+            scramble(&mut fresh_two_phase)?.to_string(),
+            scramble(&mut warm_two_phase)?.to_string(),
+            "{}",
+            seed
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn restricted_phase_generators_test() -> Result<(), PuzzleError> {
+    let phase_generators = |phase1: &[&str]| Cube3x3x3PhaseGenerators {
+        phase1: try_parsed_list(phase1).unwrap(),
+        ..Default::default()
+    };
+
+    // Any five faces generate the whole cube group.
+    let mut two_phase = Scramble3x3x3TwoPhase::try_new(&phase_generators(&[
+        "U", "U2", "U'", "L", "L2", "L'", "F", "F2", "F'", "R", "R2", "R'", "B", "B2", "B'",
+    ]))?;
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let pattern = kpuzzle.default_pattern().apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&"D R2 F' D2 L B' U D' F2 R".parse().unwrap())
+            .unwrap(),
+    );
+    let solution = two_phase
+        .solve_3x3x3_pattern(&pattern, PrefixOrSuffixConstraints::None, None)
+        .unwrap();
+    assert_eq!(
+        pattern.apply_transformation(&kpuzzle.transformation_from_alg(&solution).unwrap()),
+        kpuzzle.default_pattern()
+    );
+
+    // `<U, R>` never moves the `BL` and `FL` edges, so it can't reach every E-slice position.
+    assert!(
+        Scramble3x3x3TwoPhase::try_new(&phase_generators(&["U", "U2", "U'", "R", "R2", "R'"]))
+            .is_err()
+    );
+    // `U'` is missing.
+    assert!(Scramble3x3x3TwoPhase::try_new(&phase_generators(&[
+        "U", "U2", "L", "L2", "L'", "F", "F2", "F'", "R", "R2", "R'", "B", "B2", "B'", "D", "D2",
+        "D'",
+    ]))
+    .is_err());
+    Ok(())
 }
@@ -0,0 +1,296 @@
+use cubing::{
+    alg::{Alg, Move},
+    kpuzzle::{KPattern, KPuzzle},
+};
+
+use crate::_internal::PatternOrbits;
+
+use super::coordinate_search::{Coordinate, CoordinateIndex, CoordinatePhaseSearch};
+
+// These assume the piece order of `cube3x3x3_centerless_kpuzzle()`, where
+// edges 8 to 11 are the E-slice edges.
+const EDGES_ORBIT_NAME: &str = "EDGES";
+const CORNERS_ORBIT_NAME: &str = "CORNERS";
+const NUM_UD_EDGES: u8 = 8;
+const NUM_EDGES: u8 = 12;
+const NUM_CORNERS: u8 = 8;
+
+// The index of the permutation among all permutations of the same values, in lexicographic order.
+fn permutation_rank(pieces: &[u8]) -> usize {
+    let mut rank = 0;
+    for (i, piece) in pieces.iter().enumerate() {
+        let num_smaller_later = pieces[i + 1..]
+            .iter()
+            .filter(|later_piece| *later_piece < piece)
+            .count();
+        rank = rank * (pieces.len() - i) + num_smaller_later;
+    }
+    rank
+}
+
+fn orbit_pieces(pattern: &KPattern, orbit_name: &str, positions: std::ops::Range<u8>) -> Vec<u8> {
+    let orbit = pattern.orbit(orbit_name).unwrap();
+    positions.map(|i| orbit.piece(i)).collect()
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |product, i| product * (n - i) / (i + 1))
+}
+
+// The orientation of all but the last piece (which is determined by the others).
+fn orientation_coordinate(pattern: &KPattern, orbit_name: &str, num_pieces: u8) -> usize {
+    let orbit = pattern.orbit(orbit_name).unwrap();
+    (0..num_pieces - 1).fold(0, |coordinate, i| {
+        coordinate * (orbit.num_orientations() as usize) + (orbit.orientation(i) as usize)
+    })
+}
+
+fn corner_orientation(pattern: &KPattern) -> usize {
+    orientation_coordinate(pattern, CORNERS_ORBIT_NAME, NUM_CORNERS)
+}
+
+fn edge_orientation(pattern: &KPattern) -> usize {
+    orientation_coordinate(pattern, EDGES_ORBIT_NAME, NUM_EDGES)
+}
+
+// Which positions hold the E-slice edges (but not their order), using the combinatorial number system.
+fn e_slice_edge_positions(pattern: &KPattern) -> usize {
+    let mut coordinate = 0;
+    let mut num_e_slice_edges_sofar = 0;
+    for (position, piece) in orbit_pieces(pattern, EDGES_ORBIT_NAME, 0..NUM_EDGES)
+        .into_iter()
+        .enumerate()
+    {
+        if piece >= NUM_UD_EDGES {
+            num_e_slice_edges_sofar += 1;
+            coordinate += binomial(position, num_e_slice_edges_sofar);
+        }
+    }
+    coordinate
+}
+
+fn corner_permutation(pattern: &KPattern) -> usize {
+    permutation_rank(&orbit_pieces(pattern, CORNERS_ORBIT_NAME, 0..NUM_CORNERS))
+}
+
+// Only valid in phase 2, where the U and D layer edges stay in the U and D layers.
+fn ud_edge_permutation(pattern: &KPattern) -> usize {
+    permutation_rank(&orbit_pieces(pattern, EDGES_ORBIT_NAME, 0..NUM_UD_EDGES))
+}
+
+// Only valid in phase 2, where the E-slice edges stay in the E slice.
+fn e_slice_edge_permutation(pattern: &KPattern) -> usize {
+    permutation_rank(&orbit_pieces(
+        pattern,
+        EDGES_ORBIT_NAME,
+        NUM_UD_EDGES..NUM_EDGES,
+    ))
+}
+
+pub(crate) const DEFAULT_PHASE1_MOVES: [&str; 18] = [
+    "U", "U2", "U'", "L", "L2", "L'", "F", "F2", "F'", "R", "R2", "R'", "B", "B2", "B'", "D", "D2",
+    "D'",
+];
+pub(crate) const DEFAULT_PHASE2_MOVES: [&str; 10] =
+    ["U", "U2", "U'", "D", "D2", "D'", "L2", "F2", "R2", "B2"];
+
+// The rotations that keep the U-D axis, which map `<U, D, L2, F2, R2, B2>` to
+// itself. (The reflections would halve the tables again, but they aren't
+// transformations of the puzzle.)
+const UD_AXIS_ROTATIONS: [&str; 8] = ["", "y", "y2", "y'", "x2", "x2 y", "x2 y2", "x2 y'"];
+
+/// The coordinates of phase 1, which are all solved exactly in `<U, D, L2, F2, R2, B2>`.
+pub(crate) fn cube3x3x3_phase1_coordinates() -> Vec<Coordinate> {
+    vec![
+        Coordinate {
+            encode: corner_orientation,
+            size: 2187,
+        },
+        Coordinate {
+            encode: edge_orientation,
+            size: 2048,
+        },
+        Coordinate {
+            encode: e_slice_edge_positions,
+            size: 495,
+        },
+    ]
+}
+
+/// Phase 1 of the two-phase algorithm (reaching `<U, D, L2, F2, R2, B2>`).
+pub(crate) fn cube3x3x3_phase1_search(
+    kpuzzle: &KPuzzle,
+    moves: Vec<Move>,
+) -> CoordinatePhaseSearch {
+    CoordinatePhaseSearch::new(
+        kpuzzle,
+        moves,
+        cube3x3x3_phase1_coordinates(),
+        &[
+            (CoordinateIndex(0), CoordinateIndex(2)),
+            (CoordinateIndex(1), CoordinateIndex(2)),
+        ],
+        &[],
+    )
+}
+
+/// Phase 2 of the two-phase algorithm (solving from `<U, D, L2, F2, R2, B2>`).
+///
+/// The pruning tables are reduced by the rotations that keep the U-D axis
+/// (the ones that the moves are closed under), so they store about 1/8 as
+/// many corner and U-D edge permutations.
+pub(crate) fn cube3x3x3_phase2_search(
+    kpuzzle: &KPuzzle,
+    moves: Vec<Move>,
+) -> CoordinatePhaseSearch {
+    CoordinatePhaseSearch::new(
+        kpuzzle,
+        moves,
+        vec![
+            Coordinate {
+                encode: corner_permutation,
+                size: 40320,
+            },
+            Coordinate {
+                encode: ud_edge_permutation,
+                size: 40320,
+            },
+            Coordinate {
+                encode: e_slice_edge_permutation,
+                size: 24,
+            },
+        ],
+        &[
+            (CoordinateIndex(0), CoordinateIndex(2)),
+            (CoordinateIndex(1), CoordinateIndex(2)),
+        ],
+        &UD_AXIS_ROTATIONS.map(|rotation| {
+            kpuzzle
+                .transformation_from_alg(&rotation.parse::<Alg>().unwrap())
+                .unwrap()
+        }),
+    )
+}
+
+// There is no 4x4x4 solver in this tree yet, so these vectors cover the
+// 3x3x3 two-phase pipeline, checking each phase's invariants by coordinate.
+#[test]
+fn cube3x3x3_phase_invariants_test() {
+    use super::coordinate_search::CoordinateSearchOptions;
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let test_scrambles = [
+        // Solved, and already in phase 2.
+        "",
+        "U D2 R2 F2",
+        // A single move out of phase 2.
+        "R",
+        // Superflip.
+        "U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2",
+        // The hardcoded test alg for move collapsing.
+        "R' U' F R2 D U B D U' L' B2 U' F' D' B2 D' L2 D' R2 B2 R2 F2 U' B2 D' R' U' F",
+        // The FMC prefix and suffix on their own.
+        "R' U' F R' U' F",
+    ];
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let phase1_search = cube3x3x3_phase1_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE1_MOVES));
+    let phase2_search = cube3x3x3_phase2_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE2_MOVES));
+    let default_pattern = kpuzzle.default_pattern();
+    let apply_alg = |pattern: &KPattern, alg: &cubing::alg::Alg| {
+        pattern.apply_transformation(&kpuzzle.transformation_from_alg(alg).unwrap())
+    };
+    for scramble in test_scrambles {
+        let pattern = apply_alg(&default_pattern, &scramble.parse().unwrap());
+
+        let phase1_alg = phase1_search
+            .search(&pattern, &CoordinateSearchOptions::default())
+            .unwrap();
+        let phase2_pattern = apply_alg(&pattern, &phase1_alg);
+        assert_eq!(corner_orientation(&phase2_pattern), 0, "{}", scramble);
+        assert_eq!(edge_orientation(&phase2_pattern), 0, "{}", scramble);
+        assert_eq!(
+            e_slice_edge_positions(&phase2_pattern),
+            e_slice_edge_positions(&default_pattern),
+            "{}",
+            scramble
+        );
+        assert!(phase1_search.is_solved(&phase2_pattern), "{}", scramble);
+
+        let phase2_alg = phase2_search
+            .search(&phase2_pattern, &CoordinateSearchOptions::default())
+            .unwrap();
+        let solved_pattern = apply_alg(&phase2_pattern, &phase2_alg);
+        assert_eq!(corner_permutation(&solved_pattern), 0, "{}", scramble);
+        assert_eq!(ud_edge_permutation(&solved_pattern), 0, "{}", scramble);
+        assert_eq!(e_slice_edge_permutation(&solved_pattern), 0, "{}", scramble);
+        assert!(solved_pattern == default_pattern, "{}", scramble);
+    }
+}
+
+#[test]
+fn cube3x3x3_phase1_membership_sets_test() {
+    use super::coordinate_search::CoordinateMembershipSet;
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let phase1_search = cube3x3x3_phase1_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE1_MOVES));
+    let membership_sets: Vec<CoordinateMembershipSet> = phase1_search
+        .membership_sets_within(1)
+        .iter()
+        .map(|membership_set| {
+            CoordinateMembershipSet::from_bytes(&membership_set.to_bytes(), &phase1_search).unwrap()
+        })
+        .collect();
+    let pattern_from_alg = |alg: &str| {
+        kpuzzle.default_pattern().apply_transformation(
+            &kpuzzle
+                .transformation_from_alg(&alg.parse().unwrap())
+                .unwrap(),
+        )
+    };
+    for alg in ["", "U", "R", "F2"] {
+        assert!(
+            phase1_search.may_be_within(&membership_sets, &pattern_from_alg(alg)),
+            "{}",
+            alg
+        );
+    }
+    // Superflip flips every edge, which takes more than 1 move.
+    assert!(!phase1_search.may_be_within(
+        &membership_sets,
+        &pattern_from_alg("U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2")
+    ));
+    assert!(CoordinateMembershipSet::from_bytes(b"TWCT", &phase1_search).is_err());
+    // The coordinates of phase 2 have different sizes.
+    let phase2_search = cube3x3x3_phase2_search(kpuzzle, static_parsed_list(&DEFAULT_PHASE2_MOVES));
+    assert!(
+        CoordinateMembershipSet::from_bytes(&membership_sets[0].to_bytes(), &phase2_search)
+            .is_err()
+    );
+}
+
+#[test]
+fn cube3x3x3_phase2_moves_preserve_phase1_target_test() {
+    use super::coordinate_search::check_phase_moves_preserve_target;
+    use super::definitions::cube3x3x3_centerless_kpuzzle;
+    use super::static_move_list::static_parsed_list;
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let check = |moves: &[Move]| {
+        check_phase_moves_preserve_target(
+            kpuzzle,
+            "Phase 2",
+            moves,
+            "phase 1",
+            &cube3x3x3_phase1_coordinates(),
+        )
+    };
+    assert!(check(&static_parsed_list::<Move>(&DEFAULT_PHASE2_MOVES)).is_ok());
+    assert!(check(&["R".parse::<Move>().unwrap()]).is_err());
+}
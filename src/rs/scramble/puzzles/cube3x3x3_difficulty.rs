@@ -0,0 +1,143 @@
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KTransformation},
+};
+
+use crate::_internal::{SearchError, CUBE_ORIENTATION_ROTATIONS};
+
+use super::{
+    cube3x3x3::{PrefixOrSuffixConstraints, SCRAMBLE3X3X3_TWO_PHASE},
+    cube3x3x3_neutrality::analyze_3x3x3_neutrality,
+    definitions::cube3x3x3_centerless_kpuzzle,
+};
+
+// One rotation for each axis, to bring that axis to U/D.
+const DOMINO_AXIS_ROTATIONS: [&str; 3] = ["", "x", "z"];
+
+/// Difficulty-related metrics for a 3x3x3 scramble (in HTM), e.g. for
+/// trainers that bucket scrambles by difficulty. The phase depths are the
+/// best over all orientations.
+#[derive(Clone, Debug)]
+pub struct Cube3x3x3DifficultyMetrics {
+    /// Edge orientation on any axis.
+    pub eo_depth: usize,
+    /// Cross on any face.
+    pub cross_depth: usize,
+    /// Domino reduction (reaching `<U, D, L2, F2, R2, B2>`) on any axis.
+    pub dr_depth: usize,
+    /// The length of a two-phase solution. This is an upper bound for the
+    /// optimal solution length (which would be too slow to compute).
+    pub two_phase_solution_length: usize,
+    /// The optimal solution length, if the scramble is short enough that
+    /// scramble filtering would reject it.
+    pub short_optimal_solution_length: Option<usize>,
+    /// The most pieces that are already solved relative to each other (i.e.
+    /// ignoring the orientation of the whole cube). This is a measure of how
+    /// "blocky" the scramble is.
+    pub max_num_solved_pieces: usize,
+}
+
+fn num_solved_pieces(pattern: &KPattern) -> usize {
+    let default_pattern = pattern.kpuzzle().default_pattern();
+    pattern
+        .kpuzzle()
+        .orbit_info_iter()
+        .map(|orbit_info| {
+            (0..orbit_info.num_pieces)
+                .filter(|i| {
+                    pattern.get_piece(orbit_info, *i) == default_pattern.get_piece(orbit_info, *i)
+                        && pattern.get_orientation_with_mod(orbit_info, *i).orientation
+                            == default_pattern
+                                .get_orientation_with_mod(orbit_info, *i)
+                                .orientation
+                })
+                .count()
+        })
+        .sum()
+}
+
+fn rotation_transformation(rotation: &str) -> KTransformation {
+    cube3x3x3_centerless_kpuzzle()
+        .transformation_from_alg(&rotation.parse::<Alg>().unwrap())
+        .unwrap()
+}
+
+/// Reports difficulty metrics for the scramble, using the same analysis as
+/// `analyze_3x3x3_neutrality(…)` and the phase 1 search of the two-phase solver.
+pub fn analyze_3x3x3_difficulty(scramble: &Alg) -> Result<Cube3x3x3DifficultyMetrics, SearchError> {
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    let scramble_transformation =
+        kpuzzle
+            .transformation_from_alg(scramble)
+            .map_err(|e| SearchError {
+                description: e.to_string(),
+            })?;
+    let pattern = kpuzzle
+        .default_pattern()
+        .apply_transformation(&scramble_transformation);
+
+    let orientation_analyses = analyze_3x3x3_neutrality(scramble)?;
+    let eo_depth = orientation_analyses
+        .iter()
+        .map(|orientation_analysis| orientation_analysis.eo_depth)
+        .min()
+        .unwrap();
+    let cross_depth = orientation_analyses
+        .iter()
+        .map(|orientation_analysis| orientation_analysis.cross_depth)
+        .min()
+        .unwrap();
+
+    let (dr_depth, two_phase_solution_length, short_optimal_solution_length) =
+        SCRAMBLE3X3X3_TWO_PHASE.with_context(
+            |two_phase| -> Result<(usize, usize, Option<usize>), SearchError> {
+                let mut dr_depth = usize::MAX;
+                for rotation in DOMINO_AXIS_ROTATIONS {
+                    // See `Cube3x3x3NeutralityAnalyzer::analyze(…)` for why we conjugate by the rotation.
+                    let rotation_transformation = rotation_transformation(rotation);
+                    let rotated_pattern = kpuzzle
+                        .default_pattern()
+                        .apply_transformation(&rotation_transformation.invert())
+                        .apply_transformation(&scramble_transformation)
+                        .apply_transformation(&rotation_transformation);
+                    let depth = two_phase
+                        .phase1_depth(&rotated_pattern)
+                        .ok_or("No domino reduction found.")?;
+                    dr_depth = dr_depth.min(depth);
+                }
+                let two_phase_solution_length = two_phase
+                    .solve_3x3x3_pattern(&pattern, PrefixOrSuffixConstraints::None, None)
+                    .map_err(|_| "No two-phase solution found.")?
+                    .nodes
+                    .len();
+                let short_optimal_solution_length = two_phase
+                    .max_rejected_distance()
+                    .and_then(|max_rejected_distance| {
+                        two_phase.distance_at_most(&pattern, max_rejected_distance)
+                    })
+                    .map(|distance| distance as usize);
+                Ok((
+                    dr_depth,
+                    two_phase_solution_length,
+                    short_optimal_solution_length,
+                ))
+            },
+        )?;
+
+    let max_num_solved_pieces = CUBE_ORIENTATION_ROTATIONS
+        .iter()
+        .map(|rotation| {
+            num_solved_pieces(&pattern.apply_transformation(&rotation_transformation(rotation)))
+        })
+        .max()
+        .unwrap();
+
+    Ok(Cube3x3x3DifficultyMetrics {
+        eo_depth,
+        cross_depth,
+        dr_depth,
+        two_phase_solution_length,
+        short_optimal_solution_length,
+        max_num_solved_pieces,
+    })
+}
@@ -0,0 +1,299 @@
+use std::sync::Mutex;
+
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo},
+};
+use lazy_static::lazy_static;
+
+use crate::{
+    _internal::{IDFSearch, IndividualSearchOptions, OrbitInfoByName, SearchError},
+    scramble::scramble_search::{generators_from_vec_str, idfs_with_target_pattern},
+};
+
+use super::{definitions::cube3x3x3_centerless_kpuzzle, mask_pattern::mask_pattern};
+
+// In `cube3x3x3_centerless_kpuzzle()`, the U layer pieces are at positions 0
+// to 3 in each orbit, in the order that `U` cycles them.
+const NUM_LAST_LAYER_PIECES: u8 = 4;
+
+// Edges, then corners (positions 0 to 3 of each).
+type LastLayerKey = [u8; 8];
+
+/// A last layer case, with the cross on D. Cases that differ only by `U`
+/// moves before (or, for PLL, after) the alg are the same case.
+///
+/// Each ID is the index of the case in an enumeration of all the cases
+/// (including the solved case, which is always 0). These are stable, but are
+/// not the conventional OLL/PLL numbering or names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cube3x3x3LastLayerCase {
+    Oll(usize),
+    Pll(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Cube3x3x3LastLayerSolutions {
+    pub case: Cube3x3x3LastLayerCase,
+    /// All optimal (HTM) algs for the step (orienting the last layer for
+    /// OLL, or solving the cube for PLL).
+    pub algs: Vec<Alg>,
+}
+
+fn rotate(values: &[u8], amount: usize) -> impl Iterator<Item = u8> + '_ {
+    let n = values.len();
+    (0..n).map(move |i| values[(i + amount) % n])
+}
+
+// The smallest key among all `U` moves before the alg.
+fn canonical_oll_key(edge_orientations: &[u8], corner_orientations: &[u8]) -> LastLayerKey {
+    (0..(NUM_LAST_LAYER_PIECES as usize))
+        .map(|amount| {
+            let mut key = LastLayerKey::default();
+            for (k, value) in rotate(edge_orientations, amount)
+                .chain(rotate(corner_orientations, amount))
+                .enumerate()
+            {
+                key[k] = value;
+            }
+            key
+        })
+        .min()
+        .unwrap()
+}
+
+// The smallest key among all `U` moves before and after the alg. A `U` move
+// before the alg rotates the positions, and a `U` move after the alg
+// relabels the pieces.
+fn canonical_pll_key(edge_pieces: &[u8], corner_pieces: &[u8]) -> LastLayerKey {
+    let n = NUM_LAST_LAYER_PIECES as usize;
+    (0..n)
+        .flat_map(|before| (0..n).map(move |after| (before, after)))
+        .map(|(before, after)| {
+            let mut key = LastLayerKey::default();
+            for (k, value) in rotate(edge_pieces, before)
+                .chain(rotate(corner_pieces, before))
+                .enumerate()
+            {
+                key[k] = (value + after as u8) % NUM_LAST_LAYER_PIECES;
+            }
+            key
+        })
+        .min()
+        .unwrap()
+}
+
+fn permutations(values: Vec<u8>) -> Vec<Vec<u8>> {
+    if values.is_empty() {
+        return vec![vec![]];
+    }
+    let mut result = Vec::<Vec<u8>>::new();
+    for i in 0..values.len() {
+        let mut rest = values.clone();
+        let first = rest.remove(i);
+        for mut permutation in permutations(rest) {
+            permutation.insert(0, first);
+            result.push(permutation);
+        }
+    }
+    result
+}
+
+fn is_even_permutation(values: &[u8]) -> bool {
+    let num_inversions = (0..values.len())
+        .flat_map(|i| ((i + 1)..values.len()).map(move |j| (i, j)))
+        .filter(|(i, j)| values[*i] > values[*j])
+        .count();
+    num_inversions % 2 == 0
+}
+
+// Returns the distinct canonical keys, sorted, so that the index of a key is its case ID.
+fn sorted_distinct(mut keys: Vec<LastLayerKey>) -> Vec<LastLayerKey> {
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn all_oll_case_keys() -> Vec<LastLayerKey> {
+    let mut keys = Vec::<LastLayerKey>::new();
+    for edge_bits in 0..16u8 {
+        let edge_orientations: Vec<u8> = (0..4).map(|i| (edge_bits >> i) & 1).collect();
+        if edge_orientations.iter().sum::<u8>() % 2 != 0 {
+            continue;
+        }
+        for corner_index in 0..81u8 {
+            let corner_orientations: Vec<u8> = [1, 3, 9, 27]
+                .map(|place| (corner_index / place) % 3)
+                .to_vec();
+            if corner_orientations.iter().sum::<u8>() % 3 != 0 {
+                continue;
+            }
+            keys.push(canonical_oll_key(&edge_orientations, &corner_orientations));
+        }
+    }
+    sorted_distinct(keys)
+}
+
+fn all_pll_case_keys() -> Vec<LastLayerKey> {
+    let mut keys = Vec::<LastLayerKey>::new();
+    for edge_pieces in permutations(vec![0, 1, 2, 3]) {
+        for corner_pieces in permutations(vec![0, 1, 2, 3]) {
+            if is_even_permutation(&edge_pieces) == is_even_permutation(&corner_pieces) {
+                keys.push(canonical_pll_key(&edge_pieces, &corner_pieces));
+            }
+        }
+    }
+    sorted_distinct(keys)
+}
+
+// Everything except the last layer is solved, and the last layer pieces are
+// identical (but keep their orientation).
+fn oll_target_pattern(kpuzzle: &KPuzzle) -> KPattern {
+    let mut target_pattern = kpuzzle.default_pattern();
+    for orbit_info in kpuzzle.orbit_info_iter() {
+        for i in 1..NUM_LAST_LAYER_PIECES {
+            target_pattern.set_piece(orbit_info, i, 0);
+        }
+    }
+    target_pattern
+}
+
+/// Recognizes last layer cases, and finds optimal algs for them.
+pub struct Cube3x3x3LastLayerSolver {
+    kpuzzle: KPuzzle,
+
+    oll_case_keys: Vec<LastLayerKey>,
+    oll_target_pattern: KPattern,
+    oll_idfs: IDFSearch,
+
+    pll_case_keys: Vec<LastLayerKey>,
+    pll_idfs: IDFSearch,
+}
+
+impl Default for Cube3x3x3LastLayerSolver {
+    fn default() -> Self {
+        let kpuzzle = cube3x3x3_centerless_kpuzzle().clone();
+        let generators = generators_from_vec_str(vec!["U", "L", "F", "R", "B", "D"]);
+
+        let oll_target_pattern = oll_target_pattern(&kpuzzle);
+        let oll_idfs = idfs_with_target_pattern(
+            &kpuzzle,
+            generators.clone(),
+            oll_target_pattern.clone(),
+            None,
+        );
+        let pll_idfs =
+            idfs_with_target_pattern(&kpuzzle, generators, kpuzzle.default_pattern(), None);
+
+        Self {
+            kpuzzle,
+
+            oll_case_keys: all_oll_case_keys(),
+            oll_target_pattern,
+            oll_idfs,
+
+            pll_case_keys: all_pll_case_keys(),
+            pll_idfs,
+        }
+    }
+}
+
+// Returns all the optimal solutions.
+fn optimal_solutions(idfs: &mut IDFSearch, pattern: &KPattern) -> Result<Vec<Alg>, SearchError> {
+    Ok(idfs
+        .try_search(
+            pattern,
+            IndividualSearchOptions {
+                min_num_solutions: Some(1),
+                finish_solution_depth: Some(true),
+                ..Default::default()
+            },
+        )?
+        .collect())
+}
+
+impl Cube3x3x3LastLayerSolver {
+    fn is_solved_except_last_layer(&self, pattern: &KPattern) -> bool {
+        self.kpuzzle.orbit_info_iter().all(|orbit_info| {
+            (NUM_LAST_LAYER_PIECES..orbit_info.num_pieces).all(|i| {
+                pattern.get_piece(orbit_info, i) == i
+                    && pattern.get_orientation_with_mod(orbit_info, i).orientation == 0
+            })
+        })
+    }
+
+    /// Recognizes the case of a pattern of `cube3x3x3_centerless_kpuzzle()`
+    /// with everything except the last layer solved: an OLL case if the last
+    /// layer isn't oriented, and a PLL case otherwise.
+    pub fn recognize(&self, pattern: &KPattern) -> Result<Cube3x3x3LastLayerCase, SearchError> {
+        if !self.is_solved_except_last_layer(pattern) {
+            return Err("Only the last layer (U) may be unsolved.".into());
+        }
+        let edges_orbit_info = self.kpuzzle.orbit_info_by_name("EDGES").unwrap();
+        let corners_orbit_info = self.kpuzzle.orbit_info_by_name("CORNERS").unwrap();
+        let orientations = |orbit_info: &KPuzzleOrbitInfo| -> Vec<u8> {
+            (0..NUM_LAST_LAYER_PIECES)
+                .map(|i| pattern.get_orientation_with_mod(orbit_info, i).orientation)
+                .collect()
+        };
+        let edge_orientations = orientations(edges_orbit_info);
+        let corner_orientations = orientations(corners_orbit_info);
+        let is_oriented = edge_orientations
+            .iter()
+            .chain(&corner_orientations)
+            .all(|orientation| *orientation == 0);
+        if !is_oriented {
+            let key = canonical_oll_key(&edge_orientations, &corner_orientations);
+            return Ok(Cube3x3x3LastLayerCase::Oll(
+                self.oll_case_keys.binary_search(&key).unwrap(),
+            ));
+        }
+        let pieces = |orbit_info: &KPuzzleOrbitInfo| -> Vec<u8> {
+            (0..NUM_LAST_LAYER_PIECES)
+                .map(|i| pattern.get_piece(orbit_info, i))
+                .collect()
+        };
+        let key = canonical_pll_key(&pieces(edges_orbit_info), &pieces(corners_orbit_info));
+        Ok(Cube3x3x3LastLayerCase::Pll(
+            self.pll_case_keys.binary_search(&key).unwrap(),
+        ))
+    }
+
+    /// Recognizes the case (see `recognize(…)`) and finds all optimal algs
+    /// for it. Long cases can take a while.
+    pub fn solve(
+        &mut self,
+        pattern: &KPattern,
+    ) -> Result<Cube3x3x3LastLayerSolutions, SearchError> {
+        let case = self.recognize(pattern)?;
+        let algs = match case {
+            Cube3x3x3LastLayerCase::Oll(_) => optimal_solutions(
+                &mut self.oll_idfs,
+                &mask_pattern(pattern, &self.oll_target_pattern),
+            )?,
+            Cube3x3x3LastLayerCase::Pll(_) => optimal_solutions(&mut self.pll_idfs, pattern)?,
+        };
+        Ok(Cube3x3x3LastLayerSolutions { case, algs })
+    }
+}
+
+// TODO: switch to `LazyLock` once that's stable: https://doc.rust-lang.org/nightly/std/cell/struct.LazyCell.html
+lazy_static! {
+    static ref CUBE3X3X3_LAST_LAYER_SOLVER: Mutex<Cube3x3x3LastLayerSolver> =
+        Mutex::new(Cube3x3x3LastLayerSolver::default());
+}
+
+pub fn recognize_3x3x3_last_layer(
+    pattern: &KPattern,
+) -> Result<Cube3x3x3LastLayerCase, SearchError> {
+    CUBE3X3X3_LAST_LAYER_SOLVER
+        .lock()
+        .unwrap()
+        .recognize(pattern)
+}
+
+pub fn solve_3x3x3_last_layer(
+    pattern: &KPattern,
+) -> Result<Cube3x3x3LastLayerSolutions, SearchError> {
+    CUBE3X3X3_LAST_LAYER_SOLVER.lock().unwrap().solve(pattern)
+}
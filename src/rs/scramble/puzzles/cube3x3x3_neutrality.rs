@@ -0,0 +1,198 @@
+use std::sync::Mutex;
+
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle},
+};
+use lazy_static::lazy_static;
+
+use crate::{
+    _internal::{IDFSearch, IndividualSearchOptions, SearchError, CUBE_ORIENTATION_ROTATIONS},
+    scramble::scramble_search::{generators_from_vec_str, idfs_with_target_pattern},
+};
+
+use super::{
+    definitions::{
+        cube3x3x3_centerless_cross_target_kpattern, cube3x3x3_centerless_eo_target_kpattern,
+        cube3x3x3_centerless_kpuzzle,
+    },
+    mask_pattern::mask_pattern,
+};
+
+/// The best phase 1 depths (in HTM) when solving a scramble in a given orientation.
+#[derive(Clone, Debug)]
+pub struct Cube3x3x3OrientationAnalysis {
+    /// The rotation performed after the scramble (before solving).
+    pub rotation: Alg,
+    /// Edge orientation with respect to the F/B axis.
+    pub eo_depth: usize,
+    /// Cross on the D face.
+    pub cross_depth: usize,
+}
+
+pub struct Cube3x3x3NeutralityAnalyzer {
+    kpuzzle: KPuzzle,
+    rotations: Vec<Alg>,
+
+    eo_target_pattern: KPattern,
+    eo_idfs: IDFSearch,
+
+    cross_target_pattern: KPattern,
+    cross_idfs: IDFSearch,
+}
+
+impl Default for Cube3x3x3NeutralityAnalyzer {
+    fn default() -> Self {
+        let kpuzzle = cube3x3x3_centerless_kpuzzle().clone();
+        let generators = generators_from_vec_str(vec!["U", "L", "F", "R", "B", "D"]);
+        let rotations = CUBE_ORIENTATION_ROTATIONS
+            .iter()
+            .map(|rotation| rotation.parse::<Alg>().unwrap())
+            .collect();
+
+        let eo_target_pattern = cube3x3x3_centerless_eo_target_kpattern().clone();
+        let eo_idfs = idfs_with_target_pattern(
+            &kpuzzle,
+            generators.clone(),
+            eo_target_pattern.clone(),
+            None,
+        );
+
+        let cross_target_pattern = cube3x3x3_centerless_cross_target_kpattern().clone();
+        let cross_idfs =
+            idfs_with_target_pattern(&kpuzzle, generators, cross_target_pattern.clone(), None);
+
+        Self {
+            kpuzzle,
+            rotations,
+
+            eo_target_pattern,
+            eo_idfs,
+
+            cross_target_pattern,
+            cross_idfs,
+        }
+    }
+}
+
+// Returns the optimal depth for each of the patterns, using a single
+// multi-pattern search for each distinct depth.
+fn best_depths(
+    idfs: &mut IDFSearch,
+    search_patterns: &[KPattern],
+) -> Result<Vec<usize>, SearchError> {
+    // Many orientations are equivalent for a given phase (e.g. `y2` for EO), so we only search each distinct pattern once.
+    let mut distinct_patterns = Vec::<KPattern>::new();
+    let mut distinct_pattern_indices = Vec::<usize>::new();
+    for search_pattern in search_patterns {
+        let index = match distinct_patterns
+            .iter()
+            .position(|distinct_pattern| distinct_pattern == search_pattern)
+        {
+            Some(index) => index,
+            None => {
+                distinct_patterns.push(search_pattern.clone());
+                distinct_patterns.len() - 1
+            }
+        };
+        distinct_pattern_indices.push(index);
+    }
+
+    let mut distinct_depths: Vec<Option<usize>> = vec![None; distinct_patterns.len()];
+    let mut min_depth = 0;
+    loop {
+        let remaining_indices: Vec<usize> = (0..distinct_patterns.len())
+            .filter(|index| distinct_depths[*index].is_none())
+            .collect();
+        if remaining_indices.is_empty() {
+            break;
+        }
+        let remaining_patterns: Vec<KPattern> = remaining_indices
+            .iter()
+            .map(|index| distinct_patterns[*index].clone())
+            .collect();
+        let (remaining_index, alg) = idfs
+            .search_from_multiple_patterns(
+                &remaining_patterns,
+                IndividualSearchOptions {
+                    min_num_solutions: Some(1),
+                    min_depth: Some(min_depth),
+                    ..Default::default()
+                },
+            )?
+            .with_search_pattern_indices()
+            .next()
+            .ok_or("No solution found.")?;
+        // Every remaining pattern is at least as deep as this one, so we can continue from this depth.
+        min_depth = alg.nodes.len();
+        distinct_depths[remaining_indices[remaining_index]] = Some(min_depth);
+    }
+
+    Ok(distinct_pattern_indices
+        .into_iter()
+        .map(|index| distinct_depths[index].unwrap())
+        .collect())
+}
+
+impl Cube3x3x3NeutralityAnalyzer {
+    /// Reports the best EO and cross depths for the scramble in each of the 24 orientations.
+    pub fn analyze(
+        &mut self,
+        scramble: &Alg,
+    ) -> Result<Vec<Cube3x3x3OrientationAnalysis>, SearchError> {
+        let scramble_transformation =
+            self.kpuzzle
+                .transformation_from_alg(scramble)
+                .map_err(|e| SearchError {
+                    description: e.to_string(),
+                })?;
+
+        // Solving with moves `M` after performing rotation `r` means that
+        // `r' S r M` is solved (for centerless patterns), so we search from `r' S r`.
+        let mut eo_search_patterns = Vec::<KPattern>::new();
+        let mut cross_search_patterns = Vec::<KPattern>::new();
+        for rotation in &self.rotations {
+            let rotation_transformation = self.kpuzzle.transformation_from_alg(rotation).unwrap();
+            let pattern = self
+                .kpuzzle
+                .default_pattern()
+                .apply_transformation(&rotation_transformation.invert())
+                .apply_transformation(&scramble_transformation)
+                .apply_transformation(&rotation_transformation);
+            eo_search_patterns.push(mask_pattern(&pattern, &self.eo_target_pattern));
+            cross_search_patterns.push(mask_pattern(&pattern, &self.cross_target_pattern));
+        }
+
+        let eo_depths = best_depths(&mut self.eo_idfs, &eo_search_patterns)?;
+        let cross_depths = best_depths(&mut self.cross_idfs, &cross_search_patterns)?;
+
+        Ok(self
+            .rotations
+            .iter()
+            .zip(eo_depths)
+            .zip(cross_depths)
+            .map(
+                |((rotation, eo_depth), cross_depth)| Cube3x3x3OrientationAnalysis {
+                    rotation: rotation.clone(),
+                    eo_depth,
+                    cross_depth,
+                },
+            )
+            .collect())
+    }
+}
+
+// TODO: switch to `LazyLock` once that's stable: https://doc.rust-lang.org/nightly/std/cell/struct.LazyCell.html
+lazy_static! {
+    static ref CUBE3X3X3_NEUTRALITY_ANALYZER: Mutex<Cube3x3x3NeutralityAnalyzer> =
+        Mutex::new(Cube3x3x3NeutralityAnalyzer::default());
+}
+
+pub fn analyze_3x3x3_neutrality(
+    scramble: &Alg,
+) -> Result<Vec<Cube3x3x3OrientationAnalysis>, SearchError> {
+    CUBE3X3X3_NEUTRALITY_ANALYZER
+        .lock()
+        .unwrap()
+        .analyze(scramble)
+}
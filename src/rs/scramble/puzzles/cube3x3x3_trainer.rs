@@ -0,0 +1,248 @@
+use std::sync::Mutex;
+
+use cubing::{
+    alg::Alg,
+    kpuzzle::{KPattern, KPuzzle, OrientationWithMod},
+};
+use lazy_static::lazy_static;
+
+use crate::{
+    _internal::{IDFSearch, IndividualSearchOptions, PatternAlgebra, PatternOrbits, SearchError},
+    scramble::scramble_search::{generators_from_vec_str, idfs_with_target_pattern},
+};
+
+use super::{
+    definitions::{cube3x3x3_centerless_cross_target_kpattern, cube3x3x3_centerless_kpuzzle},
+    mask_pattern::mask_pattern,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cube3x3x3Face {
+    U,
+    L,
+    F,
+    R,
+    B,
+    D,
+}
+
+impl Cube3x3x3Face {
+    // The rotation (performed after the scramble) that brings this face to D.
+    fn rotation_to_d(&self) -> &'static str {
+        match self {
+            Cube3x3x3Face::U => "x2",
+            Cube3x3x3Face::L => "z'",
+            Cube3x3x3Face::F => "x'",
+            Cube3x3x3Face::R => "z",
+            Cube3x3x3Face::B => "x",
+            Cube3x3x3Face::D => "",
+        }
+    }
+}
+
+/// An F2L slot, with the cross on D.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cube3x3x3F2LSlot {
+    FrontRight,
+    FrontLeft,
+    BackLeft,
+    BackRight,
+}
+
+const ALL_F2L_SLOTS: [Cube3x3x3F2LSlot; 4] = [
+    Cube3x3x3F2LSlot::FrontRight,
+    Cube3x3x3F2LSlot::FrontLeft,
+    Cube3x3x3F2LSlot::BackLeft,
+    Cube3x3x3F2LSlot::BackRight,
+];
+
+impl Cube3x3x3F2LSlot {
+    // The positions of the corner and edge of the slot in `cube3x3x3_centerless_kpuzzle()`.
+    fn corner_and_edge_positions(&self) -> (u8, u8) {
+        match self {
+            Cube3x3x3F2LSlot::FrontRight => (4, 8),
+            Cube3x3x3F2LSlot::FrontLeft => (5, 9),
+            Cube3x3x3F2LSlot::BackLeft => (6, 11),
+            Cube3x3x3F2LSlot::BackRight => (7, 10),
+        }
+    }
+}
+
+/// Optimal solutions (in HTM) for a step, performed after the rotation.
+#[derive(Clone, Debug)]
+pub struct Cube3x3x3TrainerSolutions {
+    pub rotation: Alg,
+    pub solutions: Vec<Alg>,
+}
+
+// The cross target, with the corner and edge of the slot also solved.
+fn cross_and_pair_target_pattern(slot: Cube3x3x3F2LSlot) -> KPattern {
+    let mut target_pattern = cube3x3x3_centerless_cross_target_kpattern().clone();
+    let (corner_position, edge_position) = slot.corner_and_edge_positions();
+    let solved_with_orientation = OrientationWithMod {
+        orientation: 0,
+        orientation_mod: 0,
+    };
+    // Cross edges use pieces 1 to 4, and unimportant pieces use 0.
+    let mut edges = target_pattern.orbit_mut("EDGES").unwrap();
+    edges.set_piece(edge_position, 5);
+    edges.set_orientation_with_mod(edge_position, &solved_with_orientation);
+    let mut corners = target_pattern.orbit_mut("CORNERS").unwrap();
+    corners.set_piece(corner_position, 1);
+    corners.set_orientation_with_mod(corner_position, &solved_with_orientation);
+    target_pattern
+}
+
+/// Solvers for the first steps of CFOP, for trainer apps.
+pub struct Cube3x3x3TrainerSolver {
+    kpuzzle: KPuzzle,
+
+    cross_target_pattern: KPattern,
+    cross_idfs: IDFSearch,
+
+    // Indexed in the order of `ALL_F2L_SLOTS`.
+    pair_target_patterns: Vec<KPattern>,
+    pair_idfs: Vec<IDFSearch>,
+}
+
+impl Default for Cube3x3x3TrainerSolver {
+    fn default() -> Self {
+        let kpuzzle = cube3x3x3_centerless_kpuzzle().clone();
+        let generators = generators_from_vec_str(vec!["U", "L", "F", "R", "B", "D"]);
+
+        let cross_target_pattern = cube3x3x3_centerless_cross_target_kpattern().clone();
+        let cross_idfs = idfs_with_target_pattern(
+            &kpuzzle,
+            generators.clone(),
+            cross_target_pattern.clone(),
+            None,
+        );
+
+        let pair_target_patterns: Vec<KPattern> = ALL_F2L_SLOTS
+            .iter()
+            .map(|slot| cross_and_pair_target_pattern(*slot))
+            .collect();
+        let pair_idfs = pair_target_patterns
+            .iter()
+            .map(|target_pattern| {
+                idfs_with_target_pattern(&kpuzzle, generators.clone(), target_pattern.clone(), None)
+            })
+            .collect();
+
+        Self {
+            kpuzzle,
+
+            cross_target_pattern,
+            cross_idfs,
+
+            pair_target_patterns,
+            pair_idfs,
+        }
+    }
+}
+
+// Returns all the optimal solutions.
+fn optimal_solutions(idfs: &mut IDFSearch, pattern: &KPattern) -> Result<Vec<Alg>, SearchError> {
+    Ok(idfs
+        .try_search(
+            pattern,
+            IndividualSearchOptions {
+                min_num_solutions: Some(1),
+                finish_solution_depth: Some(true),
+                ..Default::default()
+            },
+        )?
+        .collect())
+}
+
+impl Cube3x3x3TrainerSolver {
+    // Solving with moves `M` after performing rotation `r` means that `r' S r M`
+    // is solved (for centerless patterns), so we search from `r' S r`.
+    fn rotated_pattern(
+        &self,
+        scramble: &Alg,
+        face: Cube3x3x3Face,
+    ) -> Result<KPattern, SearchError> {
+        let scramble_transformation =
+            self.kpuzzle
+                .transformation_from_alg(scramble)
+                .map_err(|e| SearchError {
+                    description: e.to_string(),
+                })?;
+        self.kpuzzle
+            .default_pattern()
+            .apply_transformation(&scramble_transformation)
+            .conjugate_by_alg(&face.rotation_to_d().parse::<Alg>().unwrap())
+            .map_err(|e| SearchError {
+                description: e.description,
+            })
+    }
+
+    /// Returns all optimal solutions for the cross on the given face.
+    pub fn solve_cross(
+        &mut self,
+        scramble: &Alg,
+        face: Cube3x3x3Face,
+    ) -> Result<Cube3x3x3TrainerSolutions, SearchError> {
+        let pattern = self.rotated_pattern(scramble, face)?;
+        Ok(Cube3x3x3TrainerSolutions {
+            rotation: face.rotation_to_d().parse::<Alg>().unwrap(),
+            solutions: optimal_solutions(
+                &mut self.cross_idfs,
+                &mask_pattern(&pattern, &self.cross_target_pattern),
+            )?,
+        })
+    }
+
+    /// Returns all optimal solutions for the cross (on the given face) plus
+    /// each F2L pair. `scramble` can include moves that were already done
+    /// (e.g. a cross solution, after the rotation).
+    pub fn solve_f2l_pairs(
+        &mut self,
+        scramble: &Alg,
+        cross_face: Cube3x3x3Face,
+    ) -> Result<Vec<(Cube3x3x3F2LSlot, Cube3x3x3TrainerSolutions)>, SearchError> {
+        let pattern = self.rotated_pattern(scramble, cross_face)?;
+        let mut slot_solutions = Vec::new();
+        for (slot_index, slot) in ALL_F2L_SLOTS.iter().enumerate() {
+            let solutions = optimal_solutions(
+                &mut self.pair_idfs[slot_index],
+                &mask_pattern(&pattern, &self.pair_target_patterns[slot_index]),
+            )?;
+            slot_solutions.push((
+                *slot,
+                Cube3x3x3TrainerSolutions {
+                    rotation: cross_face.rotation_to_d().parse::<Alg>().unwrap(),
+                    solutions,
+                },
+            ));
+        }
+        Ok(slot_solutions)
+    }
+}
+
+// TODO: switch to `LazyLock` once that's stable: https://doc.rust-lang.org/nightly/std/cell/struct.LazyCell.html
+lazy_static! {
+    static ref CUBE3X3X3_TRAINER_SOLVER: Mutex<Cube3x3x3TrainerSolver> =
+        Mutex::new(Cube3x3x3TrainerSolver::default());
+}
+
+pub fn solve_3x3x3_cross(
+    scramble: &Alg,
+    face: Cube3x3x3Face,
+) -> Result<Cube3x3x3TrainerSolutions, SearchError> {
+    CUBE3X3X3_TRAINER_SOLVER
+        .lock()
+        .unwrap()
+        .solve_cross(scramble, face)
+}
+
+pub fn solve_3x3x3_f2l_pairs(
+    scramble: &Alg,
+    cross_face: Cube3x3x3Face,
+) -> Result<Vec<(Cube3x3x3F2LSlot, Cube3x3x3TrainerSolutions)>, SearchError> {
+    CUBE3X3X3_TRAINER_SOLVER
+        .lock()
+        .unwrap()
+        .solve_f2l_pairs(scramble, cross_face)
+}
@@ -0,0 +1,162 @@
+use cubing::{
+    alg::{Alg, Move},
+    kpuzzle::{KPattern, KPuzzle},
+};
+
+use crate::_internal::{basic_parity, PatternOrbits, PuzzleError};
+
+use super::{
+    cube3x3x3::SCRAMBLE3X3X3_TWO_PHASE,
+    definitions::{cube3x3x3_centerless_kpuzzle, cube4x4x4_kpuzzle},
+};
+
+const FACES: &str = "ULFRBD";
+const NUM_CENTERS_PER_FACE: usize = 4;
+
+/// A 4x4x4 state described as a reduction to 3x3x3: each pair of wings (a
+/// dedge) moves like a 3x3x3 edge, and the centers only matter by face.
+#[derive(Clone, Debug)]
+pub struct Cube4x4x4Reduction {
+    /// The corners and dedges, as a 3x3x3 pattern (using the `EDGES` and
+    /// `CORNERS` orbits in the standard 3x3x3 piece order). Any centers are
+    /// ignored.
+    pub cube3x3x3_pattern: KPattern,
+    /// The face of each center piece, as 24 letters from `ULFRBD`: 4 for the
+    /// centers on each face, with the faces in `ULFRBD` order. For example,
+    /// solved centers are `UUUULLLLFFFFRRRRBBBBDDDD`.
+    pub center_faces: String,
+}
+
+// The `CENTERS` positions on each face (in `FACES` order), found by which
+// centers the outer turn of that face moves.
+fn center_positions_by_face(kpuzzle: &KPuzzle) -> Vec<Vec<u8>> {
+    let default_pattern = kpuzzle.default_pattern();
+    let default_centers = default_pattern.orbit("CENTERS").unwrap();
+    FACES
+        .chars()
+        .map(|face| {
+            let face_turn = kpuzzle
+                .transformation_from_move(&face.to_string().parse::<Move>().unwrap())
+                .unwrap();
+            let turned_pattern = default_pattern.apply_transformation(&face_turn);
+            let turned_centers = turned_pattern.orbit("CENTERS").unwrap();
+            (0..default_centers.num_pieces())
+                .filter(|i| turned_centers.piece(*i) != default_centers.piece(*i))
+                .collect()
+        })
+        .collect()
+}
+
+fn cube3x3x3_centerless_pattern(cube3x3x3_pattern: &KPattern) -> Result<KPattern, PuzzleError> {
+    let mut centerless_pattern = cube3x3x3_centerless_kpuzzle().default_pattern();
+    for (orbit_name, num_orientations) in [("EDGES", 2), ("CORNERS", 3)] {
+        let orbit = cube3x3x3_pattern.orbit(orbit_name)?;
+        let mut centerless_orbit = centerless_pattern.orbit_mut(orbit_name)?;
+        if orbit.num_pieces() != centerless_orbit.num_pieces()
+            || orbit.num_orientations() != num_orientations
+        {
+            return Err(PuzzleError {
+                description: format!("The 3x3x3 pattern has an unexpected {} orbit.", orbit_name),
+            });
+        }
+        let pieces: Vec<u8> = (0..orbit.num_pieces()).map(|i| orbit.piece(i)).collect();
+        let mut sorted_pieces = pieces.clone();
+        sorted_pieces.sort();
+        if sorted_pieces != (0..orbit.num_pieces()).collect::<Vec<u8>>() {
+            return Err(PuzzleError {
+                description: format!("The 3x3x3 {} are not a permutation.", orbit_name),
+            });
+        }
+        let mut orientation_sum = 0;
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let i = i as u8;
+            centerless_orbit.set_piece(i, piece);
+            centerless_orbit.set_orientation(i, orbit.orientation(i));
+            orientation_sum += orbit.orientation(i) as usize;
+        }
+        if orientation_sum % (num_orientations as usize) != 0 {
+            return Err(PuzzleError {
+                description: format!(
+                    "The 3x3x3 {} have an invalid total orientation.",
+                    orbit_name
+                ),
+            });
+        }
+    }
+    if centerless_pattern.orbit("EDGES")?.parity() != centerless_pattern.orbit("CORNERS")?.parity()
+    {
+        return Err("The 3x3x3 edges and corners have different permutation parity.".into());
+    }
+    Ok(centerless_pattern)
+}
+
+/// Constructs a 4x4x4 pattern from a reduction-style description, for
+/// interop with tools that model big cubes as reductions. The description is
+/// validated (as a solvable 3x3x3 with four centers of each face) first.
+///
+/// The dedges are always paired in the result, with no OLL or PLL parity.
+pub fn cube4x4x4_pattern_from_reduction(
+    reduction: &Cube4x4x4Reduction,
+) -> Result<KPattern, PuzzleError> {
+    let center_faces: Vec<char> = reduction.center_faces.chars().collect();
+    if center_faces.len() != FACES.len() * NUM_CENTERS_PER_FACE {
+        return Err("There must be exactly 24 center faces.".into());
+    }
+    for face in FACES.chars() {
+        if center_faces.iter().filter(|c| **c == face).count() != NUM_CENTERS_PER_FACE {
+            return Err(PuzzleError {
+                description: format!("There must be exactly 4 centers of face: {}", face),
+            });
+        }
+    }
+    if let Some(c) = center_faces.iter().find(|c| !FACES.contains(**c)) {
+        return Err(PuzzleError {
+            description: format!("Invalid center face: {}", c),
+        });
+    }
+
+    // Outer turns keep the dedges paired, so performing the 3x3x3 state with
+    // them gives the corners and wings of the 4x4x4.
+    let centerless_pattern = cube3x3x3_centerless_pattern(&reduction.cube3x3x3_pattern)?;
+    let solution =
+        SCRAMBLE3X3X3_TWO_PHASE.with_context(|two_phase| two_phase.solve(&centerless_pattern))?;
+    let kpuzzle = cube4x4x4_kpuzzle();
+    let generating_alg: Alg = solution.invert();
+    let mut pattern = kpuzzle.default_pattern().apply_transformation(
+        &kpuzzle
+            .transformation_from_alg(&generating_alg)
+            .map_err(|e| PuzzleError {
+                description: e.to_string(),
+            })?,
+    );
+
+    // Each center gets the next unused piece from the face it belongs to. In
+    // the default pattern, each center piece is at its own position.
+    let center_positions_by_face = center_positions_by_face(kpuzzle);
+    let mut unused_pieces_by_face = center_positions_by_face.clone();
+    let mut center_pieces = vec![0u8; center_faces.len()];
+    for (face_index, positions) in center_positions_by_face.iter().enumerate() {
+        for (k, position) in positions.iter().enumerate() {
+            let piece_face = center_faces[face_index * NUM_CENTERS_PER_FACE + k];
+            let piece_face_index = FACES.find(piece_face).unwrap();
+            center_pieces[*position as usize] = unused_pieces_by_face[piece_face_index].remove(0);
+        }
+    }
+    // Every move changes the center permutation parity together with the
+    // other pieces, so we keep the parity that the moves above produced. Swapping
+    // two centers of the same face doesn't change how the centers look.
+    let mut centers = pattern.orbit_mut("CENTERS")?;
+    if basic_parity(&center_pieces) != centers.parity() {
+        let same_face_positions: Vec<usize> = center_pieces
+            .iter()
+            .enumerate()
+            .filter(|(_, piece)| center_positions_by_face[0].contains(*piece))
+            .map(|(position, _)| position)
+            .collect();
+        center_pieces.swap(same_face_positions[0], same_face_positions[1]);
+    }
+    for (position, piece) in center_pieces.into_iter().enumerate() {
+        centers.set_piece(position as u8, piece);
+    }
+    Ok(pattern)
+}
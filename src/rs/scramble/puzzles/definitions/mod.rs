@@ -1,8 +1,16 @@
 use cubing::kpuzzle::{kpattern_from_json_file, kpuzzle_from_json_file};
 
+// Each definition is embedded as JSON and parsed once, on first use. `KPuzzle`
+// and `KPattern` hold reference-counted data owned by `cubing`, so they can't
+// be constructed in a `const` context (or generated by a build script) from
+// this crate. In practice, parsing is much cheaper than building the search
+// tables that use these definitions.
+
 kpuzzle_from_json_file!(pub(crate), cube3x3x3_centerless, "3x3x3-centerless.kpuzzle.json");
-kpattern_from_json_file!(pub(crate), cube3x3x3_centerless_g1_target, "3x3x3-G1-centerless.target-pattern.json", cube3x3x3_centerless_kpuzzle());
+kpattern_from_json_file!(pub(crate), cube3x3x3_centerless_eo_target, "3x3x3-EO-centerless.target-pattern.json", cube3x3x3_centerless_kpuzzle());
+kpattern_from_json_file!(pub(crate), cube3x3x3_centerless_cross_target, "3x3x3-cross-centerless.target-pattern.json", cube3x3x3_centerless_kpuzzle());
 
+kpuzzle_from_json_file!(pub(crate), cube4x4x4, "4x4x4.kpuzzle.json");
 kpuzzle_from_json_file!(pub(crate), cube5x5x5, "5x5x5.kpuzzle.json");
 kpuzzle_from_json_file!(pub(crate), cube6x6x6, "6x6x6.kpuzzle.json");
 kpuzzle_from_json_file!(pub(crate), cube7x7x7, "7x7x7.kpuzzle.json");
@@ -0,0 +1,39 @@
+use cubing::kpuzzle::{KPattern, OrientationWithMod};
+
+// Maps each piece of `pattern` to the piece at its solved position in
+// `target_pattern`, so that pieces which are identical in the target become
+// identical in the result. Orientations are kept, except that they are reduced
+// by the target's orientation mod for that piece (e.g. a mod of 1 means that
+// the piece's orientation is ignored).
+//
+// This assumes that `target_pattern` has every piece at its own solved
+// position (i.e. it is the default pattern with some pieces/orientations
+// identified).
+pub(crate) fn mask_pattern(pattern: &KPattern, target_pattern: &KPattern) -> KPattern {
+    let mut masked_pattern = target_pattern.clone();
+    for orbit_info in pattern.kpuzzle().orbit_info_iter() {
+        for i in 0..orbit_info.num_pieces {
+            let old_piece = pattern.get_piece(orbit_info, i);
+            let old_piece_mapped = target_pattern.get_piece(orbit_info, old_piece);
+            masked_pattern.set_piece(orbit_info, i, old_piece_mapped);
+
+            let orientation_with_mod = pattern.get_orientation_with_mod(orbit_info, i);
+            let orientation_mod = target_pattern
+                .get_orientation_with_mod(orbit_info, old_piece)
+                .orientation_mod;
+            masked_pattern.set_orientation_with_mod(
+                orbit_info,
+                i,
+                &OrientationWithMod {
+                    orientation: if orientation_mod == 0 {
+                        orientation_with_mod.orientation
+                    } else {
+                        orientation_with_mod.orientation % orientation_mod
+                    },
+                    orientation_mod,
+                },
+            );
+        }
+    }
+    masked_pattern
+}
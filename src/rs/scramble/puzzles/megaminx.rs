@@ -1,20 +1,25 @@
 use cubing::alg::{parse_move, Alg, AlgNode, Move, Newline};
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-const NUM_LINES: usize = 7;
-const NUM_RANDOM_MOVE_PAIRS: usize = 5;
+use super::super::{randomize::ScrambleRng, scramble_length::RandomMoveScrambleLength, Event};
 
 pub fn scramble_megaminx() -> Alg {
-    let mut rng = thread_rng();
+    scramble_megaminx_with_length(
+        &RandomMoveScrambleLength::default_for_event(Event::MegaminxSpeedsolving).unwrap(),
+    )
+}
+
+pub fn scramble_megaminx_with_length(length: &RandomMoveScrambleLength) -> Alg {
+    let mut rng = ScrambleRng;
     let mut alg_nodes = Vec::<AlgNode>::new();
 
     let r_array: [Move; 2] = [parse_move!("R++"), parse_move!("R--")];
     let d_array: [Move; 2] = [parse_move!("D++"), parse_move!("D--")];
     let u_array: [Move; 2] = [parse_move!("U"), parse_move!("U'")];
 
-    for _ in 0..NUM_LINES {
+    for _ in 0..length.num_blocks {
         let mut random_choice: usize = 0;
-        for _ in 0..NUM_RANDOM_MOVE_PAIRS {
+        for _ in 0..(length.num_random_moves_per_block / 2) {
             for arr in [&r_array, &d_array] {
                 random_choice = rng.gen_range(0..=1);
                 alg_nodes.push(arr[random_choice].clone().into());
@@ -2,8 +2,18 @@ pub mod big_cubes;
 pub mod clock;
 pub mod cube2x2x2;
 pub mod cube3x3x3;
+pub mod cube3x3x3_difficulty;
+pub mod cube3x3x3_last_layer;
+pub mod cube3x3x3_neutrality;
+pub mod cube3x3x3_trainer;
+pub mod cube4x4x4_reduction;
 pub mod megaminx;
 pub mod pyraminx;
 
-mod definitions;
+mod coordinate_search;
+mod cube3x3x3_coordinates;
+pub(crate) mod definitions;
+mod mask_pattern;
+pub(crate) mod random_move_fallback;
 mod static_move_list;
+mod trivial_orbit;
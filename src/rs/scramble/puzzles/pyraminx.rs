@@ -1,58 +1,64 @@
-use cubing::alg::{Alg, AlgNode, Move};
-use rand::{thread_rng, Rng};
+use cubing::alg::{Alg, QuantumMove};
 
-use crate::scramble::scramble_search::move_list_from_vec;
+use crate::_internal::PuzzleError;
 
 use super::{
     super::randomize::{
-        randomize_orbit_naïve, OrbitOrientationConstraint, OrbitPermutationConstraint,
+        randomize_pattern, OrbitOrientationConstraint, OrbitPermutationConstraint,
+        OrbitRandomization, PatternRandomization, ScrambleRng,
     },
-    super::scramble_search::{filtered_search, generators_from_vec_str},
+    super::rejection_tracker::RejectionTracker,
+    super::scramble_search::{
+        filtered_search, filtering_profile_for_event, generators_from_vec_str,
+    },
+    super::Event,
     definitions::tetraminx_kpuzzle,
+    static_move_list::static_parsed_list,
+    trivial_orbit::TrivialOrbit,
 };
 
-pub fn scramble_pyraminx() -> Alg {
-    let kpuzzle = tetraminx_kpuzzle();
-    loop {
-        let mut scramble_pattern = kpuzzle.default_pattern();
+const TIP_QUANTA: [&str; 4] = ["u", "l", "r", "b"];
 
-        let orbit_info = &kpuzzle.data.ordered_orbit_info[0];
-        assert_eq!(orbit_info.name.0, "EDGES");
-        randomize_orbit_naïve(
-            &mut scramble_pattern,
-            orbit_info,
-            OrbitPermutationConstraint::SingleOrbitEvenParity,
-            OrbitOrientationConstraint::OrientationsMustSumToZero,
-        );
+pub(crate) const PYRAMINX_RANDOMIZATION: PatternRandomization = PatternRandomization {
+    orbits: &[
+        OrbitRandomization {
+            orbit_name: "EDGES",
+            permutation_constraint: OrbitPermutationConstraint::SingleOrbitEvenParity,
+            orientation_constraint: OrbitOrientationConstraint::OrientationsMustSumToZero,
+        },
+        OrbitRandomization {
+            orbit_name: "CORNERS",
+            permutation_constraint: OrbitPermutationConstraint::IdentityPermutation,
+            orientation_constraint: OrbitOrientationConstraint::AnySum,
+        },
+    ],
+    cross_orbit_constraints: &[],
+};
 
-        let orbit_info = &kpuzzle.data.ordered_orbit_info[1];
-        assert_eq!(orbit_info.name.0, "CORNERS");
-        randomize_orbit_naïve(
-            &mut scramble_pattern,
-            orbit_info,
-            OrbitPermutationConstraint::IdentityPermutation,
-            OrbitOrientationConstraint::AnySum,
-        );
+pub fn scramble_pyraminx() -> Result<Alg, PuzzleError> {
+    let kpuzzle = tetraminx_kpuzzle();
+    let mut rejection_tracker = RejectionTracker::new("Pyraminx");
+    loop {
+        let scramble_pattern = randomize_pattern(kpuzzle, &PYRAMINX_RANDOMIZATION)?;
 
-        let tip_moves = move_list_from_vec(vec!["u", "l", "r", "b"]); // TODO: cache
+        // The tips aren't part of the puzzle definition, since they can be scrambled independently.
+        let tips = TrivialOrbit::new(static_parsed_list::<QuantumMove>(&TIP_QUANTA), 3); // TODO: cache
 
-        let mut rng = thread_rng();
         let generators = generators_from_vec_str(vec!["U", "L", "R", "B"]); // TODO: cache
-        if let Some(scramble) = filtered_search(&scramble_pattern, generators, Some(4), Some(11)) {
-            let mut alg_nodes: Vec<AlgNode> = vec![];
-            for tip_move in tip_moves {
-                let amount = rng.gen_range(-1..=1);
-                if amount == 0 {
-                    continue;
-                }
-                alg_nodes.push(cubing::alg::AlgNode::MoveNode(Move {
-                    quantum: tip_move.quantum.clone(),
-                    amount,
-                }))
+        let scramble = match filtered_search(
+            &scramble_pattern,
+            generators,
+            &filtering_profile_for_event(Event::PyraminxSpeedsolving).unwrap(),
+            Some(11),
+        ) {
+            Ok(scramble) => scramble,
+            Err(reason) => {
+                rejection_tracker.reject(reason)?;
+                continue;
             }
-            let mut nodes = scramble.nodes;
-            nodes.append(&mut alg_nodes);
-            return Alg { nodes };
-        }
+        };
+        let mut nodes = scramble.nodes;
+        nodes.append(&mut tips.random_alg(&mut ScrambleRng).nodes);
+        return Ok(Alg { nodes });
     }
 }
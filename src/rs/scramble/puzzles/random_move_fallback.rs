@@ -0,0 +1,57 @@
+use std::sync::OnceLock;
+
+use cubing::{alg::Alg, puzzles::cube2x2x2_kpuzzle};
+
+use super::{
+    super::{scramble_length::RandomMoveScrambleLength, Event},
+    big_cubes::{scramble_with_random_moves, ScrambleInfo},
+    definitions::{cube3x3x3_centerless_kpuzzle, tetraminx_kpuzzle},
+    static_move_list::static_parsed_list,
+};
+
+static CUBE2X2X2_FALLBACK_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
+static CUBE3X3X3_FALLBACK_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
+static PYRAMINX_FALLBACK_SCRAMBLE_INFO_CELL: OnceLock<ScrambleInfo> = OnceLock::new();
+
+fn single_block(num_random_moves: usize) -> RandomMoveScrambleLength {
+    RandomMoveScrambleLength {
+        num_blocks: 1,
+        num_random_moves_per_block: num_random_moves,
+    }
+}
+
+/// A random-move scramble for events that are normally scrambled using
+/// random-state search, for use when the search takes too long. Returns `None`
+/// for events without a fallback (e.g. FMC, which has additional constraints).
+pub(crate) fn random_move_fallback_scramble(event: Event) -> Option<Alg> {
+    match event {
+        Event::Cube2x2x2Speedsolving => {
+            let scramble_info = CUBE2X2X2_FALLBACK_SCRAMBLE_INFO_CELL.get_or_init(|| {
+                ScrambleInfo::new(cube2x2x2_kpuzzle(), static_parsed_list(&["U", "F", "R"]))
+            });
+            Some(scramble_with_random_moves(scramble_info, &single_block(11)))
+        }
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3MultiBlind => {
+            let scramble_info = CUBE3X3X3_FALLBACK_SCRAMBLE_INFO_CELL.get_or_init(|| {
+                ScrambleInfo::new(
+                    cube3x3x3_centerless_kpuzzle(),
+                    static_parsed_list(&["U", "L", "F", "R", "B", "D"]),
+                )
+            });
+            Some(scramble_with_random_moves(scramble_info, &single_block(25)))
+        }
+        Event::PyraminxSpeedsolving => {
+            let scramble_info = PYRAMINX_FALLBACK_SCRAMBLE_INFO_CELL.get_or_init(|| {
+                ScrambleInfo::new(
+                    tetraminx_kpuzzle(),
+                    static_parsed_list(&["U", "L", "R", "B"]),
+                )
+            });
+            Some(scramble_with_random_moves(scramble_info, &single_block(11)))
+        }
+        _ => None,
+    }
+}
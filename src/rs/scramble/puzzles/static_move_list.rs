@@ -1,17 +1,39 @@
 use std::str::FromStr;
 
 use cubing::alg::{Alg, Move};
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
+
+use crate::_internal::PuzzleError;
+
+use super::super::randomize::ScrambleRng;
 
 // Hardcoded to 2 because we only need this for BLD right now.
 const NUM_RANDOM_SUFFIX_CHOICES: usize = 2;
 
+/// Parses each string, returning an error for the first one that is invalid.
+pub(crate) fn try_parsed_list<T: FromStr>(strings: &[&str]) -> Result<Vec<T>, PuzzleError>
+where
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    strings
+        .iter()
+        .map(|s| {
+            s.parse::<T>().map_err(|e| PuzzleError {
+                description: format!("Invalid entry in static list ({:?}): {}", e, s),
+            })
+        })
+        .collect()
+}
+
+// For lists that are hardcoded in the scramblers, so that an invalid entry is
+// a bug rather than a runtime error. `static_lists_used_by_scramblers_test`
+// checks every one of them.
 // TODO: figure out how to make these actually static
 pub(crate) fn static_parsed_list<T: FromStr>(strings: &[&str]) -> Vec<T>
 where
     <T as std::str::FromStr>::Err: std::fmt::Debug,
 {
-    strings.iter().map(|s| s.parse::<T>().unwrap()).collect()
+    try_parsed_list(strings).unwrap_or_else(|e| panic!("{}", e.description))
 }
 
 // An empty input string corresponds to `None`.
@@ -24,21 +46,31 @@ where
         .iter()
         .map(|s| match s {
             &"" => None,
-            s => Some(s.parse::<T>().unwrap()),
+            s => Some(static_parsed_list(&[s]).remove(0)),
         })
         .collect()
 }
 
+pub(crate) fn choose_random_suffixes_from(
+    suffixes_from: [Vec<Option<Move>>; NUM_RANDOM_SUFFIX_CHOICES],
+) -> Vec<Move> {
+    let mut rng = ScrambleRng;
+    let mut suffixes = Vec::<Move>::new();
+    for suffix_from in &suffixes_from {
+        if let Some(Some(r#move)) = suffix_from.choose(&mut rng) {
+            suffixes.push(r#move.clone())
+        }
+    }
+    suffixes
+}
+
 pub(crate) fn add_random_suffixes_from(
     alg: Alg,
     suffixes_from: [Vec<Option<Move>>; NUM_RANDOM_SUFFIX_CHOICES],
 ) -> Alg {
-    let mut rng = thread_rng();
     let mut nodes = alg.nodes;
-    for suffix_from in &suffixes_from {
-        if let Some(Some(r#move)) = suffix_from.choose(&mut rng) {
-            nodes.push(r#move.clone().into())
-        }
+    for r#move in choose_random_suffixes_from(suffixes_from) {
+        nodes.push(r#move.into())
     }
     Alg { nodes }
 }
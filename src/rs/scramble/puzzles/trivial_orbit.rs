@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use cubing::alg::{Alg, AlgNode, Move, QuantumMove};
+use rand::Rng;
+
+/// An orbit where each piece only has an orientation, and is twisted by its
+/// own move that doesn't affect anything else (e.g. Pyraminx tips). These are
+/// solved (or scrambled) directly, and appended to the alg for the rest of the
+/// puzzle, instead of being part of a search.
+pub(crate) struct TrivialOrbit {
+    // One per piece.
+    quanta: Vec<Arc<QuantumMove>>,
+    num_orientations: u8,
+}
+
+impl TrivialOrbit {
+    pub fn new(quanta: Vec<QuantumMove>, num_orientations: u8) -> Self {
+        Self {
+            quanta: quanta.into_iter().map(Arc::new).collect(),
+            num_orientations,
+        }
+    }
+
+    // The amount closest to 0 that twists a piece by `orientation` (e.g. `-1` instead of `2`).
+    fn amount_for_orientation(&self, orientation: u8) -> i32 {
+        let num_orientations = self.num_orientations as i32;
+        let offset = (num_orientations - 1) / 2;
+        (orientation as i32 + offset).rem_euclid(num_orientations) - offset
+    }
+
+    /// The moves that twist each piece by the corresponding orientation,
+    /// skipping pieces with an orientation of 0.
+    pub fn alg_for_orientations(&self, orientations: &[u8]) -> Alg {
+        assert_eq!(orientations.len(), self.quanta.len());
+        let nodes: Vec<AlgNode> = self
+            .quanta
+            .iter()
+            .zip(orientations)
+            .filter(|(_, orientation)| *orientation % self.num_orientations != 0)
+            .map(|(quantum, orientation)| {
+                AlgNode::MoveNode(Move {
+                    quantum: quantum.clone(),
+                    amount: self.amount_for_orientation(*orientation),
+                })
+            })
+            .collect();
+        Alg { nodes }
+    }
+
+    /// Twists each piece to a uniformly random orientation.
+    pub fn random_alg(&self, rng: &mut impl Rng) -> Alg {
+        let orientations: Vec<u8> = self
+            .quanta
+            .iter()
+            .map(|_| rng.gen_range(0..self.num_orientations))
+            .collect();
+        self.alg_for_orientations(&orientations)
+    }
+}
+
+#[test]
+fn trivial_orbit_test() {
+    let tips = TrivialOrbit::new(
+        vec!["u", "l", "r", "b"]
+            .into_iter()
+            .map(|quantum| quantum.parse().unwrap())
+            .collect(),
+        3,
+    );
+    assert_eq!(tips.alg_for_orientations(&[1, 0, 2, 0]).to_string(), "u r'");
+    assert_eq!(tips.alg_for_orientations(&[0, 0, 0, 0]).to_string(), "");
+}
@@ -1,44 +1,345 @@
+use std::time::Duration;
+
 use cubing::alg::Alg;
 
-use crate::_internal::PuzzleError;
+use crate::_internal::{NotationDialect, ParallelOptions, PuzzleError, TraceCategory, TraceSpan};
 
 use super::{
     puzzles::{
-        big_cubes::{scramble_5x5x5, scramble_5x5x5_bld, scramble_6x6x6, scramble_7x7x7},
+        big_cubes::{
+            big_cube_kpuzzle_for_event, scramble_5x5x5, scramble_5x5x5_bld,
+            scramble_5x5x5_with_length, scramble_6x6x6, scramble_6x6x6_with_length, scramble_7x7x7,
+            scramble_7x7x7_with_length,
+        },
         clock::scramble_clock,
         cube2x2x2::scramble_2x2x2,
-        cube3x3x3::{scramble_3x3x3, scramble_3x3x3_bld, scramble_3x3x3_fmc},
-        megaminx::scramble_megaminx,
+        cube3x3x3::{
+            prepare_3x3x3_two_phase_without_waiting, scramble_3x3x3, scramble_3x3x3_bld,
+            scramble_3x3x3_fmc, scramble_3x3x3_until, warm_up_3x3x3_two_phase,
+        },
+        megaminx::{scramble_megaminx, scramble_megaminx_with_length},
         pyraminx::scramble_pyraminx,
+        random_move_fallback::random_move_fallback_scramble,
     },
-    Event,
+    randomize::{with_scramble_seed, ScrambleRandomSource, ScrambleSeed},
+    scramble_options::{with_scramble_options, ScrambleOptions},
+    scramble_search::filtering_profile_for_event,
+    scramble_session::uses_3x3x3_two_phase_tables,
+    wide_move_compression::compress_wide_moves,
+    Event, RandomMoveScrambleLength,
 };
 
 pub fn random_scramble_for_event(event: Event) -> Result<Alg, PuzzleError> {
+    let _trace_span = TraceSpan::start(TraceCategory::Scramble, || format!("Scramble {}", event));
     let err = Err(PuzzleError {
         description: format!("Scrambles are not implement for this event yet: {}", event),
     });
     match event {
-        Event::Cube3x3x3Speedsolving => Ok(scramble_3x3x3()),
-        Event::Cube2x2x2Speedsolving => Ok(scramble_2x2x2()),
+        Event::Cube3x3x3Speedsolving => scramble_3x3x3(),
+        Event::Cube2x2x2Speedsolving => scramble_2x2x2(),
         Event::Cube4x4x4Speedsolving => err,
         Event::Cube5x5x5Speedsolving => Ok(scramble_5x5x5()),
         Event::Cube6x6x6Speedsolving => Ok(scramble_6x6x6()),
         Event::Cube7x7x7Speedsolving => Ok(scramble_7x7x7()),
-        Event::Cube3x3x3Blindfolded => Ok(scramble_3x3x3_bld()),
-        Event::Cube3x3x3FewestMoves => Ok(scramble_3x3x3_fmc()),
-        Event::Cube3x3x3OneHanded => Ok(scramble_3x3x3()),
+        Event::Cube3x3x3Blindfolded => scramble_3x3x3_bld(),
+        Event::Cube3x3x3FewestMoves => scramble_3x3x3_fmc(),
+        Event::Cube3x3x3OneHanded => scramble_3x3x3(),
         Event::ClockSpeedsolving => Ok(scramble_clock()),
         Event::MegaminxSpeedsolving => Ok(scramble_megaminx()),
-        Event::PyraminxSpeedsolving => Ok(scramble_pyraminx()),
+        Event::PyraminxSpeedsolving => scramble_pyraminx(),
         Event::SkewbSpeedsolving => err,
         Event::Square1Speedsolving => err,
         Event::Cube4x4x4Blindfolded => err,
         Event::Cube5x5x5Blindfolded => Ok(scramble_5x5x5_bld()),
-        Event::Cube3x3x3MultiBlind => Ok(scramble_3x3x3_bld()), // TODO: represent multiple returned scrambles without affecting ergonomics for other events.
+        Event::Cube3x3x3MultiBlind => scramble_3x3x3_bld(), // TODO: represent multiple returned scrambles without affecting ergonomics for other events.
         Event::FTOSpeedsolving => err,
         Event::MasterTetraminxSpeedsolving => err,
         Event::KilominxSpeedsolving => err,
         Event::RediCubeSpeedsolving => err,
     }
 }
+
+/// Like `random_scramble_for_event(…)`, but with wide moves written in the given notation dialect.
+pub fn random_scramble_for_event_with_notation(
+    event: Event,
+    notation: NotationDialect,
+) -> Result<Alg, PuzzleError> {
+    Ok(notation.convert_alg(&random_scramble_for_event(event)?))
+}
+
+/// Like `random_scramble_for_event_with_notation(…)`, but also combines
+/// adjacent moves on the same face of big cubes where possible (see
+/// `compress_wide_moves(…)`).
+pub fn random_scramble_for_event_with_compressed_notation(
+    event: Event,
+    notation: NotationDialect,
+) -> Result<Alg, PuzzleError> {
+    let alg = random_scramble_for_event(event)?;
+    Ok(match big_cube_kpuzzle_for_event(event) {
+        Some(kpuzzle) => compress_wide_moves(kpuzzle, &alg, notation),
+        None => notation.convert_alg(&alg),
+    })
+}
+
+/// Like `random_scramble_for_event(…)`, but with a custom length for events that are scrambled using random moves.
+pub fn random_scramble_for_event_with_length(
+    event: Event,
+    length: &RandomMoveScrambleLength,
+) -> Result<Alg, PuzzleError> {
+    length.validate_for_event(event)?;
+    match event {
+        Event::Cube5x5x5Speedsolving => Ok(scramble_5x5x5_with_length(length)),
+        Event::Cube6x6x6Speedsolving => Ok(scramble_6x6x6_with_length(length)),
+        Event::Cube7x7x7Speedsolving => Ok(scramble_7x7x7_with_length(length)),
+        Event::MegaminxSpeedsolving => Ok(scramble_megaminx_with_length(length)),
+        // TODO: support custom lengths for BLD events (which add random rotations).
+        _ => Err(PuzzleError {
+            description: format!(
+                "Custom scramble lengths are not implemented for this event yet: {}",
+                event
+            ),
+        }),
+    }
+}
+
+pub struct SeededScramble {
+    pub alg: Alg,
+    /// The seed that all the randomness for this scramble came from.
+    pub seed: ScrambleSeed,
+}
+
+/// Like `random_scramble_for_event(…)`, but with all the randomness coming
+/// from the given source (e.g. for competitions that need to audit how
+/// scrambles were generated). The seed used is returned with the scramble,
+/// and passing it back as `ScrambleRandomSource::Seed(…)` gives the same
+/// scramble again.
+pub fn random_scramble_for_event_with_random_source(
+    event: Event,
+    random_source: &ScrambleRandomSource,
+) -> Result<SeededScramble, PuzzleError> {
+    // Initializing the shared tables doesn't use the scramble RNG, but we do
+    // it before seeding so that the seed only covers the scramble itself.
+    if uses_3x3x3_two_phase_tables(event) {
+        warm_up_3x3x3_two_phase();
+    }
+    let seed = random_source.seed();
+    let alg = with_scramble_seed(&seed, || random_scramble_for_event(event))?;
+    Ok(SeededScramble { alg, seed })
+}
+
+/// Like `random_scramble_for_event_with_random_source(…)`, but with the
+/// testing and research options in `options` (see `ScrambleOptions`).
+pub fn random_scramble_for_event_with_options(
+    event: Event,
+    options: &ScrambleOptions,
+) -> Result<SeededScramble, PuzzleError> {
+    if options.forced_pattern.is_some() && !uses_3x3x3_two_phase_tables(event) {
+        return Err(PuzzleError {
+            description: format!(
+                "Forced scramble patterns are not supported for this event: {}",
+                event
+            ),
+        });
+    }
+    if let Some(distance_range) = options.distance_range {
+        if filtering_profile_for_event(event).is_none() {
+            return Err(PuzzleError {
+                description: format!(
+                    "Scramble distance ranges are not supported for this event: {}",
+                    event
+                ),
+            });
+        }
+        if let (Some(min_distance), Some(max_distance)) =
+            (distance_range.min_distance, distance_range.max_distance)
+        {
+            if min_distance > max_distance {
+                return Err(PuzzleError {
+                    description: format!(
+                        "Invalid scramble distance range: the min ({}) is greater than the max ({}).",
+                        min_distance, max_distance
+                    ),
+                });
+            }
+        }
+    }
+    with_scramble_options(options, || {
+        random_scramble_for_event_with_random_source(event, &options.random_source)
+    })
+}
+
+pub struct TimeBoundedScramble {
+    pub alg: Alg,
+    /// Set if the random-state scramble did not finish in time, and a random-move scramble was returned instead.
+    pub is_random_move_fallback: bool,
+}
+
+/// Like `random_scramble_for_event(…)`, but returns within (roughly) the given
+/// time budget. For 3x3x3 speedsolving and one-handed, this uses the whole
+/// budget to look for shorter scrambles, and returns the shortest one found.
+///
+/// If no scramble is found in time (e.g. while the 3x3x3 tables are still
+/// being built in the background), this returns a random-move scramble
+/// (flagged as such) for events that support it, and an error otherwise.
+/// Other events are scrambled directly, since their scramblers are fast once
+/// their (much smaller) tables are built.
+pub fn random_scramble_for_event_with_time_budget(
+    event: Event,
+    time_budget: Duration,
+) -> Result<TimeBoundedScramble, PuzzleError> {
+    let deadline = instant::Instant::now() + time_budget;
+    let scramble =
+        if uses_3x3x3_two_phase_tables(event) && !prepare_3x3x3_two_phase_without_waiting() {
+            None
+        } else {
+            match event {
+                Event::Cube3x3x3Speedsolving | Event::Cube3x3x3OneHanded => {
+                    scramble_3x3x3_until(deadline)?
+                }
+                _ => Some(random_scramble_for_event(event)?),
+            }
+        };
+    if let Some(alg) = scramble {
+        return Ok(TimeBoundedScramble {
+            alg,
+            is_random_move_fallback: false,
+        });
+    }
+    match random_move_fallback_scramble(event) {
+        Some(alg) => Ok(TimeBoundedScramble {
+            alg,
+            is_random_move_fallback: true,
+        }),
+        None => Err(PuzzleError {
+            description: format!(
+                "Scramble did not finish within the time budget, and there is no random-move fallback for this event: {}",
+                event
+            ),
+        }),
+    }
+}
+
+/// Generates `num_scrambles` scrambles for the event (e.g. for a competition
+/// round). With the `parallel` feature, they are generated on multiple
+/// threads. Each thread gets its own solver context for events with shared
+/// tables (like 3x3x3), so the tables are built only once.
+pub fn random_scrambles_for_event(
+    event: Event,
+    num_scrambles: usize,
+    parallel_options: &ParallelOptions,
+) -> Result<Vec<Alg>, PuzzleError> {
+    parallel_options.install(|| random_scrambles_for_event_in_current_pool(event, num_scrambles))
+}
+
+// Uses the thread pool that the caller has installed (if any).
+pub(crate) fn random_scrambles_for_event_in_current_pool(
+    event: Event,
+    num_scrambles: usize,
+) -> Result<Vec<Alg>, PuzzleError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        (0..num_scrambles)
+            .into_par_iter()
+            .map(|_| random_scramble_for_event(event))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..num_scrambles)
+            .map(|_| random_scramble_for_event(event))
+            .collect()
+    }
+}
+
+#[test]
+fn random_scramble_for_event_with_random_source_test() -> Result<(), PuzzleError> {
+    for event in [Event::ClockSpeedsolving, Event::Cube2x2x2Speedsolving] {
+        let scramble =
+            random_scramble_for_event_with_random_source(event, &ScrambleRandomSource::OsEntropy)?;
+        let reproduced = random_scramble_for_event_with_random_source(
+            event,
+            &ScrambleRandomSource::Seed(scramble.seed),
+        )?;
+        assert_eq!(reproduced.seed, scramble.seed);
+        assert_eq!(
+            reproduced.alg.to_string(),
+            scramble.alg.to_string(),
+            "{}",
+            event
+        );
+    }
+    Ok(())
+}
+
+// The scramblers parse hardcoded move lists (see `static_parsed_list(…)`),
+// which panic if they are invalid. This runs every scrambler (including
+// custom lengths and fallbacks), so that each list is parsed at least once.
+#[test]
+fn static_lists_used_by_scramblers_test() -> Result<(), PuzzleError> {
+    for event_capabilities in super::supported_events() {
+        let event = event_capabilities.event;
+        random_scramble_for_event(event)?;
+        if event_capabilities.supports_custom_length {
+            random_scramble_for_event_with_length(
+                event,
+                &RandomMoveScrambleLength::default_for_event(event).unwrap(),
+            )?;
+        }
+        assert_eq!(
+            random_move_fallback_scramble(event).is_some(),
+            event_capabilities.has_random_move_fallback,
+            "{}",
+            event
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn scramble_distance_range_test() -> Result<(), PuzzleError> {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    use super::{
+        scramble_options::ScrambleDistanceRange,
+        solve_pattern::{solve_pattern, SolveConstraints},
+    };
+
+    let options = ScrambleOptions {
+        distance_range: Some(ScrambleDistanceRange {
+            min_distance: Some(9),
+            max_distance: Some(9),
+        }),
+        ..Default::default()
+    };
+    let scramble = random_scramble_for_event_with_options(Event::Cube2x2x2Speedsolving, &options)?;
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let pattern = kpuzzle
+        .default_pattern()
+        .apply_transformation(&kpuzzle.transformation_from_alg(&scramble.alg).unwrap());
+    let solution = solve_pattern(
+        &pattern,
+        &SolveConstraints {
+            max_length: None,
+            require_optimal: true,
+        },
+    )?;
+    assert_eq!(solution.alg.nodes.len(), 9);
+
+    assert!(random_scramble_for_event_with_options(Event::ClockSpeedsolving, &options).is_err());
+    Ok(())
+}
+
+#[test]
+fn random_scramble_for_event_with_time_budget_test() -> Result<(), PuzzleError> {
+    // There's no time to find a 3x3x3 scramble (or to build the tables), so it falls back to random moves.
+    let scramble =
+        random_scramble_for_event_with_time_budget(Event::Cube3x3x3Speedsolving, Duration::ZERO)?;
+    assert!(scramble.is_random_move_fallback);
+
+    // Other events are scrambled directly.
+    let scramble =
+        random_scramble_for_event_with_time_budget(Event::Cube2x2x2Speedsolving, Duration::ZERO)?;
+    assert!(!scramble.is_random_move_fallback);
+    Ok(())
+}
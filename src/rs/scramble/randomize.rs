@@ -1,6 +1,121 @@
-use cubing::kpuzzle::{KPattern, KPuzzleOrbitInfo, OrientationWithMod};
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use std::{cell::RefCell, collections::HashMap, fmt::Display};
 
+use cubing::kpuzzle::{KPattern, KPuzzle, KPuzzleOrbitInfo, OrientationWithMod};
+use rand::{
+    rngs::{OsRng, StdRng},
+    seq::SliceRandom,
+    thread_rng, Rng, RngCore, SeedableRng,
+};
+
+use crate::_internal::{basic_parity, set_parity, BasicParity, OrbitInfoByName, PuzzleError};
+
+/// The seed for the RNG used to generate a scramble, which can be recorded
+/// (e.g. for auditing competition scrambles) and used to generate the same
+/// scramble again.
+///
+/// Note that scrambles are only reproducible with the same version of
+/// `twsearch`, since both the scramble code and the underlying RNG
+/// algorithm may change between versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrambleSeed([u8; 32]);
+
+impl ScrambleSeed {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// A seed from the OS's cryptographically secure random number generator.
+    pub fn from_os_entropy() -> Self {
+        let mut bytes = [0; 32];
+        OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+}
+
+impl Display for ScrambleSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the randomness for a scramble comes from.
+#[derive(Clone, Copy, Debug)]
+pub enum ScrambleRandomSource {
+    /// A new seed from the OS's cryptographically secure random number generator for each scramble.
+    OsEntropy,
+    /// A fixed seed (e.g. to reproduce a recorded scramble).
+    Seed(ScrambleSeed),
+}
+
+impl ScrambleRandomSource {
+    pub(crate) fn seed(&self) -> ScrambleSeed {
+        match self {
+            ScrambleRandomSource::OsEntropy => ScrambleSeed::from_os_entropy(),
+            ScrambleRandomSource::Seed(seed) => *seed,
+        }
+    }
+}
+
+thread_local! {
+    static SEEDED_SCRAMBLE_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with all scramble randomness on the current thread coming from
+/// the given seed.
+pub(crate) fn with_scramble_seed<T>(seed: &ScrambleSeed, f: impl FnOnce() -> T) -> T {
+    let previous_rng = SEEDED_SCRAMBLE_RNG.with(|rng| rng.replace(Some(StdRng::from_seed(seed.0))));
+    let result = f();
+    SEEDED_SCRAMBLE_RNG.with(|rng| rng.replace(previous_rng));
+    result
+}
+
+/// Whether scramble randomness on the current thread comes from a seed (see
+/// `with_scramble_seed(…)`), in which case scrambles must be reproducible.
+pub(crate) fn is_scramble_seeded() -> bool {
+    SEEDED_SCRAMBLE_RNG.with(|rng| rng.borrow().is_some())
+}
+
+/// The RNG that scramble code should use instead of `thread_rng()`. This uses
+/// the seed from `with_scramble_seed(…)` if there is one, and `thread_rng()`
+/// otherwise.
+pub(crate) struct ScrambleRng;
+
+impl ScrambleRng {
+    // Each call only borrows the thread-local RNG briefly, so `ScrambleRng` values can be nested freely.
+    fn with_rng<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        SEEDED_SCRAMBLE_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+            Some(rng) => f(rng),
+            None => f(&mut thread_rng()),
+        })
+    }
+}
+
+impl RngCore for ScrambleRng {
+    fn next_u32(&mut self) -> u32 {
+        Self::with_rng(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Self::with_rng(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Self::with_rng(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        Self::with_rng(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum OrbitPermutationConstraint {
     AnyPermutation,
     SingleOrbitEvenParity,
@@ -13,21 +128,107 @@ impl Default for OrbitPermutationConstraint {
         Self::SingleOrbitOddParity
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum OrbitOrientationConstraint {
     AnySum,
     OrientationsMustSumToZero,
 }
 
+/// A combination of constraints that `randomize_orbit_naïve(…)` can't
+/// satisfy for an orbit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum OrbitRandomizationError {
+    /// An odd permutation needs at least two pieces to swap.
+    OddParityWithFewerThanTwoPieces { orbit_name: String, num_pieces: u8 },
+    /// The randomization places pieces `0..num_pieces`, which only describes a
+    /// valid pattern if every piece of the orbit is distinct (and in order)
+    /// in the default pattern.
+    NonIdentityDefaultPieces { orbit_name: String },
+    /// A `PatternRandomization` refers to an orbit that the puzzle doesn't have.
+    UnknownOrbit { orbit_name: String },
+    /// An `EqualParity` constraint for an orbit that is randomized before the
+    /// orbit it depends on, or that has its own permutation constraint.
+    UnsatisfiableEqualParity { orbit_name: String },
+}
+
+impl Display for OrbitRandomizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OddParityWithFewerThanTwoPieces {
+                orbit_name,
+                num_pieces,
+            } => write!(
+                f,
+                "Cannot randomize orbit {} with odd parity, since it only has {} piece(s).",
+                orbit_name, num_pieces
+            ),
+            Self::NonIdentityDefaultPieces { orbit_name } => write!(
+                f,
+                "Cannot randomize orbit {}, since its pieces are not distinct and in order in the default pattern.",
+                orbit_name
+            ),
+            Self::UnknownOrbit { orbit_name } => write!(
+                f,
+                "Cannot randomize orbit {}, since it does not exist in the puzzle definition.",
+                orbit_name
+            ),
+            Self::UnsatisfiableEqualParity { orbit_name } => write!(
+                f,
+                "Cannot match the parity of orbit {}, which must use `AnyPermutation` and come after the orbit it matches.",
+                orbit_name
+            ),
+        }
+    }
+}
+
+impl From<OrbitRandomizationError> for PuzzleError {
+    fn from(e: OrbitRandomizationError) -> Self {
+        PuzzleError {
+            description: e.to_string(),
+        }
+    }
+}
+
+/// Checks that `randomize_orbit_naïve(…)` can satisfy the constraints for the
+/// orbit of the pattern's puzzle.
+pub(crate) fn validate_orbit_constraints(
+    pattern: &KPattern,
+    orbit_info: &KPuzzleOrbitInfo,
+    permutation_constraints: OrbitPermutationConstraint,
+) -> Result<(), OrbitRandomizationError> {
+    let orbit_name = || orbit_info.name.0.clone();
+    if permutation_constraints == OrbitPermutationConstraint::SingleOrbitOddParity
+        && orbit_info.num_pieces < 2
+    {
+        return Err(OrbitRandomizationError::OddParityWithFewerThanTwoPieces {
+            orbit_name: orbit_name(),
+            num_pieces: orbit_info.num_pieces,
+        });
+    }
+    // Any orientation sum is reachable (e.g. by twisting only the last
+    // piece), so the orientation constraints can always be satisfied.
+    let default_pattern = pattern.kpuzzle().default_pattern();
+    if (0..orbit_info.num_pieces).any(|i| default_pattern.get_piece(orbit_info, i) != i) {
+        return Err(OrbitRandomizationError::NonIdentityDefaultPieces {
+            orbit_name: orbit_name(),
+        });
+    }
+    Ok(())
+}
+
 // Selects a random permutation (ignoring parity).
 // Applies a random orientation to each piece (ensuring the total is 0).
-// Returns the piece order
+// Returns the piece order, or an error (without changing the pattern) if the
+// constraints can't be satisfied (see `validate_orbit_constraints(…)`).
 pub(crate) fn randomize_orbit_naïve(
     pattern: &mut KPattern,
     orbit_info: &KPuzzleOrbitInfo,
     permutation_constraints: OrbitPermutationConstraint,
     orientation_constraints: OrbitOrientationConstraint,
-) -> Vec<u8> {
-    let mut rng = thread_rng();
+) -> Result<Vec<u8>, OrbitRandomizationError> {
+    validate_orbit_constraints(pattern, orbit_info, permutation_constraints)?;
+    let mut rng = ScrambleRng;
     let mut piece_order: Vec<u8> = (0..orbit_info.num_pieces).collect();
     match permutation_constraints {
         OrbitPermutationConstraint::AnyPermutation => {
@@ -72,7 +273,81 @@ pub(crate) fn randomize_orbit_naïve(
             },
         );
     }
-    piece_order
+    Ok(piece_order)
+}
+
+/// How to randomize a single orbit (see `PatternRandomization`).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OrbitRandomization {
+    pub orbit_name: &'static str,
+    pub permutation_constraint: OrbitPermutationConstraint,
+    pub orientation_constraint: OrbitOrientationConstraint,
+}
+
+/// A constraint that links orbits which are otherwise randomized independently.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum CrossOrbitConstraint {
+    /// The permutation parity of the second orbit must equal that of the first
+    /// (e.g. corners and edges of the 3x3x3). The second orbit must come
+    /// later in `PatternRandomization::orbits`, and use `AnyPermutation`.
+    EqualParity(&'static str, &'static str),
+}
+
+/// A declarative description of the random-state patterns of a puzzle:
+/// each orbit is randomized in order, subject to its own constraints and the
+/// cross-orbit constraints. Orbits that aren't listed stay solved.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PatternRandomization {
+    pub orbits: &'static [OrbitRandomization],
+    pub cross_orbit_constraints: &'static [CrossOrbitConstraint],
+}
+
+/// A uniformly random pattern (starting from the default pattern) that
+/// satisfies the randomization.
+pub(crate) fn randomize_pattern(
+    kpuzzle: &KPuzzle,
+    randomization: &PatternRandomization,
+) -> Result<KPattern, OrbitRandomizationError> {
+    let mut pattern = kpuzzle.default_pattern();
+    let mut parities = HashMap::<&str, BasicParity>::new();
+    for orbit_randomization in randomization.orbits {
+        let orbit_name = orbit_randomization.orbit_name;
+        let orbit_info = kpuzzle.orbit_info_by_name(orbit_name).map_err(|_| {
+            OrbitRandomizationError::UnknownOrbit {
+                orbit_name: orbit_name.to_owned(),
+            }
+        })?;
+        let mut permutation_constraint = orbit_randomization.permutation_constraint;
+        for cross_orbit_constraint in randomization.cross_orbit_constraints {
+            match cross_orbit_constraint {
+                CrossOrbitConstraint::EqualParity(source_orbit_name, target_orbit_name) => {
+                    if *target_orbit_name != orbit_name {
+                        continue;
+                    }
+                    let (Some(source_parity), OrbitPermutationConstraint::AnyPermutation) = (
+                        parities.get(source_orbit_name),
+                        orbit_randomization.permutation_constraint,
+                    ) else {
+                        return Err(OrbitRandomizationError::UnsatisfiableEqualParity {
+                            orbit_name: orbit_name.to_owned(),
+                        });
+                    };
+                    permutation_constraint = match source_parity {
+                        BasicParity::Even => OrbitPermutationConstraint::SingleOrbitEvenParity,
+                        BasicParity::Odd => OrbitPermutationConstraint::SingleOrbitOddParity,
+                    };
+                }
+            }
+        }
+        let piece_order = randomize_orbit_naïve(
+            &mut pattern,
+            orbit_info,
+            permutation_constraint,
+            orbit_randomization.orientation_constraint,
+        )?;
+        parities.insert(orbit_name, basic_parity(&piece_order));
+    }
+    Ok(pattern)
 }
 
 // Adds without overflow.
@@ -84,39 +359,141 @@ fn subtract_u8_mod(v1: u8, v2: u8, modulus: u8) -> u8 {
     ((v1 as i32) - (v2 as i32)).rem_euclid(modulus as i32) as u8
 }
 
-fn set_parity(permutation: &mut [u8], target_parity: BasicParity) {
-    let parity = basic_parity(permutation);
-    if parity != target_parity {
-        // Since odd parity is only possible with more than 1 element in the permutation, we can safely swap the first two elements.
-        permutation.swap(0, 1);
+#[test]
+fn randomize_orbit_constraints_test() {
+    use cubing::kpuzzle::{KPuzzle, KPuzzleDefinition};
+
+    let kpuzzle_with_orbit = |num_pieces: u8, num_orientations: u8, default_pieces: Vec<u8>| {
+        let definition: KPuzzleDefinition = serde_json::from_value(serde_json::json!({
+            "name": "Test",
+            "orbits": [{
+                "orbitName": "PIECES",
+                "numPieces": num_pieces,
+                "numOrientations": num_orientations,
+            }],
+            "defaultPattern": {
+                "PIECES": {
+                    "pieces": default_pieces,
+                    "orientation": vec![0; num_pieces as usize],
+                },
+            },
+            "moves": {}
+        }))
+        .unwrap();
+        KPuzzle::try_new(definition).unwrap()
     };
-}
 
-#[derive(PartialEq)]
-pub(crate) enum BasicParity {
-    Even,
-    Odd,
-}
+    for num_pieces in 1..=4 {
+        for num_orientations in 1..=3 {
+            let kpuzzle =
+                kpuzzle_with_orbit(num_pieces, num_orientations, (0..num_pieces).collect());
+            let orbit_info = kpuzzle.orbit_info_iter().next().unwrap();
+            for permutation_constraints in [
+                OrbitPermutationConstraint::AnyPermutation,
+                OrbitPermutationConstraint::SingleOrbitEvenParity,
+                OrbitPermutationConstraint::SingleOrbitOddParity,
+                OrbitPermutationConstraint::IdentityPermutation,
+            ] {
+                for orientation_constraints in [
+                    OrbitOrientationConstraint::AnySum,
+                    OrbitOrientationConstraint::OrientationsMustSumToZero,
+                ] {
+                    let mut pattern = kpuzzle.default_pattern();
+                    let result = randomize_orbit_naïve(
+                        &mut pattern,
+                        orbit_info,
+                        permutation_constraints,
+                        orientation_constraints,
+                    );
+                    let is_satisfiable = !(permutation_constraints
+                        == OrbitPermutationConstraint::SingleOrbitOddParity
+                        && num_pieces < 2);
+                    let Ok(piece_order) = result else {
+                        assert!(!is_satisfiable, "{:?}", permutation_constraints);
+                        assert_eq!(pattern, kpuzzle.default_pattern());
+                        continue;
+                    };
+                    assert!(is_satisfiable, "{:?}", permutation_constraints);
 
-impl BasicParity {
-    pub fn flip(&mut self) {
-        let new_value = match self {
-            BasicParity::Even => BasicParity::Odd,
-            BasicParity::Odd => BasicParity::Even,
-        };
-        *self = new_value
+                    let mut sorted_pieces = piece_order.clone();
+                    sorted_pieces.sort();
+                    assert_eq!(sorted_pieces, (0..num_pieces).collect::<Vec<u8>>());
+                    match permutation_constraints {
+                        OrbitPermutationConstraint::AnyPermutation => {}
+                        OrbitPermutationConstraint::SingleOrbitEvenParity => {
+                            assert_eq!(basic_parity(&piece_order), BasicParity::Even)
+                        }
+                        OrbitPermutationConstraint::SingleOrbitOddParity => {
+                            assert_eq!(basic_parity(&piece_order), BasicParity::Odd)
+                        }
+                        OrbitPermutationConstraint::IdentityPermutation => {
+                            assert_eq!(piece_order, sorted_pieces)
+                        }
+                    }
+                    if orientation_constraints
+                        == OrbitOrientationConstraint::OrientationsMustSumToZero
+                    {
+                        let total_orientation: u32 = (0..num_pieces)
+                            .map(|i| {
+                                pattern.get_orientation_with_mod(orbit_info, i).orientation as u32
+                            })
+                            .sum();
+                        assert_eq!(total_orientation % (num_orientations as u32), 0);
+                    }
+                }
+            }
+        }
     }
+
+    // Identical pieces (e.g. centers of big cubes) can't be randomized this way.
+    let kpuzzle = kpuzzle_with_orbit(4, 1, vec![0, 0, 1, 1]);
+    let orbit_info = kpuzzle.orbit_info_iter().next().unwrap();
+    assert!(matches!(
+        validate_orbit_constraints(
+            &kpuzzle.default_pattern(),
+            orbit_info,
+            OrbitPermutationConstraint::AnyPermutation
+        ),
+        Err(OrbitRandomizationError::NonIdentityDefaultPieces { .. })
+    ));
 }
 
-pub(crate) fn basic_parity(permutation: &[u8]) -> BasicParity {
-    let mut parity = BasicParity::Even;
-    // TODO: we can save a tiny bit of speed by avoid iterating over the last element for `p1`.
-    for (i, p2) in permutation.iter().enumerate().skip(1) {
-        for p1 in &permutation[0..i] {
-            if p1 > p2 {
-                parity.flip();
-            }
-        }
+#[test]
+fn randomize_pattern_test() {
+    use super::puzzles::{
+        cube3x3x3::random_3x3x3_pattern, definitions::cube3x3x3_centerless_kpuzzle,
+    };
+    use crate::_internal::orbit_parity;
+
+    let kpuzzle = cube3x3x3_centerless_kpuzzle();
+    for _ in 0..10 {
+        let pattern = random_3x3x3_pattern();
+        assert_eq!(
+            orbit_parity(&pattern, kpuzzle.orbit_info_by_name("EDGES").unwrap()),
+            orbit_parity(&pattern, kpuzzle.orbit_info_by_name("CORNERS").unwrap()),
+        );
     }
-    parity
+
+    // The corners would have to be randomized before the edges.
+    let randomization = PatternRandomization {
+        orbits: &[
+            OrbitRandomization {
+                orbit_name: "EDGES",
+                permutation_constraint: OrbitPermutationConstraint::AnyPermutation,
+                orientation_constraint: OrbitOrientationConstraint::AnySum,
+            },
+            OrbitRandomization {
+                orbit_name: "CORNERS",
+                permutation_constraint: OrbitPermutationConstraint::AnyPermutation,
+                orientation_constraint: OrbitOrientationConstraint::AnySum,
+            },
+        ],
+        cross_orbit_constraints: &[CrossOrbitConstraint::EqualParity("CORNERS", "EDGES")],
+    };
+    assert_eq!(
+        randomize_pattern(kpuzzle, &randomization),
+        Err(OrbitRandomizationError::UnsatisfiableEqualParity {
+            orbit_name: "EDGES".to_owned()
+        })
+    );
 }
@@ -0,0 +1,140 @@
+use std::{
+    sync::mpsc::{channel, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use cubing::{kpuzzle::KPattern, puzzles::cube2x2x2_kpuzzle};
+
+use crate::_internal::{KPatternDisplay, PuzzleError};
+
+use super::{
+    puzzles::{
+        cube2x2x2::CUBE2X2X2_RANDOMIZATION, cube3x3x3::random_3x3x3_pattern,
+        definitions::tetraminx_kpuzzle, pyraminx::PYRAMINX_RANDOMIZATION,
+    },
+    randomize::randomize_pattern,
+    solve_pattern::{solve_pattern, SolveConstraints},
+    Event,
+};
+
+// Patterns from a correct randomizer are solved in well under a second, while
+// a search for an unreachable pattern never finishes.
+const PATTERN_SOLVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The result of a successful `verify_randomizer_for_event(…)`.
+#[derive(Debug, Clone)]
+pub struct RandomizerVerificationReport {
+    pub num_patterns: usize,
+    /// The longest solution found, as a sanity check for the solver.
+    pub max_solution_length: usize,
+    pub duration: Duration,
+}
+
+// The random-state pattern that the scrambler for the event starts from
+// (before any filtering), or `None` for events that are scrambled using
+// random moves.
+fn random_pattern_for_event(event: Event) -> Option<Result<KPattern, PuzzleError>> {
+    match event {
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3FewestMoves
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3MultiBlind => Some(Ok(random_3x3x3_pattern())),
+        Event::Cube2x2x2Speedsolving => Some(
+            randomize_pattern(cube2x2x2_kpuzzle(), &CUBE2X2X2_RANDOMIZATION).map_err(|e| e.into()),
+        ),
+        Event::PyraminxSpeedsolving => Some(
+            randomize_pattern(tetraminx_kpuzzle(), &PYRAMINX_RANDOMIZATION).map_err(|e| e.into()),
+        ),
+        _ => None,
+    }
+}
+
+// Generates a random pattern and solves it using all the moves of the
+// puzzle, returning the solution length.
+fn verify_random_pattern(event: Event) -> Result<usize, PuzzleError> {
+    let pattern = random_pattern_for_event(event).unwrap()?;
+    let unreachable = |description: String| PuzzleError {
+        description: format!(
+            "Random pattern for {} is not reachable ({}):\n{}",
+            event,
+            description,
+            KPatternDisplay(&pattern)
+        ),
+    };
+    let solution = solve_pattern(&pattern, &SolveConstraints::default())
+        .map_err(|e| unreachable(e.description))?;
+    let kpuzzle = pattern.kpuzzle();
+    let solution_transformation = kpuzzle
+        .transformation_from_alg(&solution.alg)
+        .map_err(|e| unreachable(format!("invalid solution: {}", e)))?;
+    if pattern.apply_transformation(&solution_transformation) != kpuzzle.default_pattern() {
+        return Err(unreachable(format!(
+            "the solution does not solve the pattern: {}",
+            solution.alg
+        )));
+    }
+    Ok(solution.alg.nodes.len())
+}
+
+/// Samples `num_patterns` patterns from the randomizer for the event, and
+/// checks that each one can be solved using all the moves of the puzzle. This
+/// catches constraint bugs (e.g. a missing parity link between orbits) that
+/// would otherwise produce impossible scrambles.
+///
+/// This is slow, since it solves every pattern without any filtering. A
+/// pattern that is not solved within a minute is reported as unreachable
+/// (and its search keeps running in the background).
+pub fn verify_randomizer_for_event(
+    event: Event,
+    num_patterns: usize,
+) -> Result<RandomizerVerificationReport, PuzzleError> {
+    if random_pattern_for_event(event).is_none() {
+        return Err(PuzzleError {
+            description: format!(
+                "This event is scrambled using random moves, so there is no randomizer to verify: {}",
+                event
+            ),
+        });
+    }
+    let start_time = instant::Instant::now();
+    let mut max_solution_length = 0;
+    for _ in 0..num_patterns {
+        let (sender, receiver) = channel();
+        // The receiver may be gone if we timed out, which is fine.
+        thread::spawn(move || {
+            let _ = sender.send(verify_random_pattern(event));
+        });
+        let solution_length = match receiver.recv_timeout(PATTERN_SOLVE_TIMEOUT) {
+            Ok(result) => result?,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(PuzzleError {
+                    description: format!(
+                    "Random pattern for {} was not solved within {:?}, so it may not be reachable.",
+                    event, PATTERN_SOLVE_TIMEOUT
+                ),
+                })
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err("Randomizer verification failed.".into())
+            }
+        };
+        max_solution_length = max_solution_length.max(solution_length);
+    }
+    Ok(RandomizerVerificationReport {
+        num_patterns,
+        max_solution_length,
+        duration: instant::Instant::now() - start_time,
+    })
+}
+
+#[test]
+fn verify_randomizer_for_event_test() -> Result<(), PuzzleError> {
+    for event in [Event::Cube2x2x2Speedsolving, Event::PyraminxSpeedsolving] {
+        let report = verify_randomizer_for_event(event, 5)?;
+        assert_eq!(report.num_patterns, 5);
+    }
+    assert!(verify_randomizer_for_event(Event::Cube5x5x5Speedsolving, 1).is_err());
+    Ok(())
+}
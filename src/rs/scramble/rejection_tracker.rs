@@ -0,0 +1,71 @@
+use std::fmt::Display;
+
+use crate::_internal::PuzzleError;
+
+use super::scramble_audit_log::record_rejection_for_audit;
+
+// Random-state scrambles are rejected very rarely for official puzzles, so
+// this many consecutive rejections means that something is misconfigured.
+const MAX_CONSECUTIVE_REJECTIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RejectionReason {
+    OptimalSolutionTooShort,
+    NoPhaseSolution,
+    OutsideDistanceRange,
+}
+
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::OptimalSolutionTooShort => "optimal solution too short",
+            Self::NoPhaseSolution => "no solution found for a phase",
+            Self::OutsideDistanceRange => "optimal solution outside the requested range",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Counts why candidate scrambles were rejected, so that a scramble loop can
+/// give up with a useful error instead of looping forever.
+pub(crate) struct RejectionTracker {
+    scramble_name: &'static str,
+    counts: Vec<(RejectionReason, usize)>,
+    num_rejections: usize,
+}
+
+impl RejectionTracker {
+    pub fn new(scramble_name: &'static str) -> Self {
+        Self {
+            scramble_name,
+            counts: vec![],
+            num_rejections: 0,
+        }
+    }
+
+    pub fn reject(&mut self, reason: RejectionReason) -> Result<(), PuzzleError> {
+        record_rejection_for_audit(reason);
+        match self.counts.iter_mut().find(|(other, _)| *other == reason) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((reason, 1)),
+        }
+        self.num_rejections += 1;
+        if self.num_rejections < MAX_CONSECUTIVE_REJECTIONS {
+            return Ok(());
+        }
+
+        let (dominant_reason, _) = self.counts.iter().max_by_key(|(_, count)| *count).unwrap();
+        let counts_str = self
+            .counts
+            .iter()
+            .map(|(reason, count)| format!("{}: {}", reason, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        Err(PuzzleError {
+            description: format!(
+                "Gave up generating a {} scramble after {} consecutive rejections (dominant reason: {}; counts: {}).",
+                self.scramble_name, self.num_rejections, dominant_reason, counts_str
+            ),
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use cubing::{
+    kpuzzle::KPuzzle,
+    puzzles::{cube2x2x2_kpuzzle, cube3x3x3_kpuzzle},
+};
+use serde::Serialize;
+
+use crate::_internal::{KPatternDisplay, PuzzleError};
+
+use super::{
+    puzzles::big_cubes::big_cube_kpuzzle_for_event,
+    random_scramble_for_event::random_scramble_for_event_with_random_source,
+    rejection_tracker::RejectionReason, scramble_options::is_scramble_filtering_skipped, Event,
+    ScrambleRandomSource, SeededScramble,
+};
+
+/// A record of how a single scramble was generated, written as one line of
+/// JSON to a `ScrambleAuditLog`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrambleAuditRecord {
+    pub event: String,
+    /// The seed as hex (see `ScrambleSeed`), which reproduces the scramble.
+    pub seed: String,
+    pub scramble: String,
+    /// The pattern that the scramble produces from the solved pattern (one
+    /// line per orbit, as in `KPatternDisplay`), for events where we know the
+    /// puzzle definition.
+    pub pattern: Option<Vec<String>>,
+    pub filtering_skipped: bool,
+    /// Why each candidate was rejected before the scramble was accepted, in order.
+    pub rejections: Vec<String>,
+    pub duration_milliseconds: f64,
+}
+
+/// An audit log for a set of scrambles (e.g. a competition round), with one
+/// JSON record per line. This lets organizers show how each scramble was
+/// generated, and helps with debugging reports of bad scrambles.
+pub struct ScrambleAuditLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+fn audit_log_error(e: std::io::Error) -> PuzzleError {
+    PuzzleError {
+        description: format!("Could not write the scramble audit log: {}", e),
+    }
+}
+
+impl ScrambleAuditLog {
+    /// Creates (or truncates) the file at `path`.
+    pub fn create(path: &Path) -> Result<Self, PuzzleError> {
+        let file = File::create(path).map_err(audit_log_error)?;
+        Ok(Self::from_writer(BufWriter::new(file)))
+    }
+
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    pub fn write_record(&self, record: &ScrambleAuditRecord) -> Result<(), PuzzleError> {
+        let line = serde_json::to_string(record).map_err(|e| PuzzleError {
+            description: format!("Could not serialize a scramble audit record: {}", e),
+        })?;
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", line).map_err(audit_log_error)?;
+        // Flush each record, so that the log is complete even if the process
+        // stops in the middle of a set.
+        writer.flush().map_err(audit_log_error)
+    }
+}
+
+thread_local! {
+    static AUDITED_REJECTIONS: RefCell<Option<Vec<RejectionReason>>> = const { RefCell::new(None) };
+}
+
+/// Records a rejection for the audit of the scramble that is currently being
+/// generated on this thread (if any).
+pub(crate) fn record_rejection_for_audit(reason: RejectionReason) {
+    AUDITED_REJECTIONS.with(|rejections| {
+        if let Some(rejections) = rejections.borrow_mut().as_mut() {
+            rejections.push(reason);
+        }
+    });
+}
+
+fn with_audited_rejections<T>(f: impl FnOnce() -> T) -> (T, Vec<RejectionReason>) {
+    let previous_rejections =
+        AUDITED_REJECTIONS.with(|rejections| rejections.replace(Some(vec![])));
+    let result = f();
+    let rejections = AUDITED_REJECTIONS
+        .with(|rejections| rejections.replace(previous_rejections))
+        .unwrap_or_default();
+    (result, rejections)
+}
+
+fn audit_kpuzzle_for_event(event: Event) -> Option<&'static KPuzzle> {
+    match event {
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3FewestMoves
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3MultiBlind => Some(cube3x3x3_kpuzzle()),
+        Event::Cube2x2x2Speedsolving => Some(cube2x2x2_kpuzzle()),
+        _ => big_cube_kpuzzle_for_event(event),
+    }
+}
+
+/// Like `random_scramble_for_event_with_random_source(…)`, but also writes a
+/// record of how the scramble was generated to `audit_log`.
+pub fn random_scramble_for_event_with_audit_log(
+    event: Event,
+    random_source: &ScrambleRandomSource,
+    audit_log: &ScrambleAuditLog,
+) -> Result<SeededScramble, PuzzleError> {
+    let start_time = instant::Instant::now();
+    let (seeded_scramble, rejections) = with_audited_rejections(|| {
+        random_scramble_for_event_with_random_source(event, random_source)
+    });
+    let seeded_scramble = seeded_scramble?;
+    let duration = instant::Instant::now() - start_time;
+
+    let pattern = audit_kpuzzle_for_event(event).and_then(|kpuzzle| {
+        let transformation = kpuzzle.transformation_from_alg(&seeded_scramble.alg).ok()?;
+        let pattern = kpuzzle
+            .default_pattern()
+            .apply_transformation(&transformation);
+        Some(
+            KPatternDisplay(&pattern)
+                .to_string()
+                .lines()
+                .map(|line| line.to_owned())
+                .collect(),
+        )
+    });
+    audit_log.write_record(&ScrambleAuditRecord {
+        event: event.id().to_owned(),
+        seed: seeded_scramble.seed.to_string(),
+        scramble: seeded_scramble.alg.to_string(),
+        pattern,
+        filtering_skipped: is_scramble_filtering_skipped(),
+        rejections: rejections.iter().map(|reason| reason.to_string()).collect(),
+        duration_milliseconds: duration.as_secs_f64() * 1000.0,
+    })?;
+    Ok(seeded_scramble)
+}
+
+#[test]
+fn scramble_audit_log_test() -> Result<(), PuzzleError> {
+    let path = std::env::temp_dir().join("twsearch-scramble-audit-log-test.jsonl");
+    let audit_log = ScrambleAuditLog::create(&path)?;
+    let seeded_scramble = random_scramble_for_event_with_audit_log(
+        Event::Cube2x2x2Speedsolving,
+        &ScrambleRandomSource::OsEntropy,
+        &audit_log,
+    )?;
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let record: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+    assert_eq!(record["event"], "222");
+    assert_eq!(record["seed"], seeded_scramble.seed.to_string());
+    assert_eq!(record["scramble"], seeded_scramble.alg.to_string());
+    assert!(record["pattern"].is_array());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::_internal::PuzzleError;
+
+use super::Event;
+
+// Keeps scrambles (and the time to generate them) reasonable even for unofficial events.
+const MAX_TOTAL_RANDOM_MOVES: usize = 1000;
+
+/// The length of a scramble for an event that is scrambled using random moves.
+/// Blocks are separated by newlines in the scramble (e.g. the lines of a
+/// Megaminx scramble).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RandomMoveScrambleLength {
+    pub num_blocks: usize,
+    /// For Megaminx, this does not include the `U` move at the end of each line.
+    pub num_random_moves_per_block: usize,
+}
+
+impl RandomMoveScrambleLength {
+    /// The official scramble length for the event, or `None` if the event is not scrambled using random moves.
+    pub fn default_for_event(event: Event) -> Option<Self> {
+        let (num_blocks, num_random_moves_per_block) = match event {
+            Event::Cube5x5x5Speedsolving | Event::Cube5x5x5Blindfolded => (1, 60),
+            Event::Cube6x6x6Speedsolving => (1, 80),
+            Event::Cube7x7x7Speedsolving => (1, 100),
+            Event::MegaminxSpeedsolving => (7, 10),
+            _ => return None,
+        };
+        Some(Self {
+            num_blocks,
+            num_random_moves_per_block,
+        })
+    }
+
+    pub fn validate_for_event(&self, event: Event) -> Result<(), PuzzleError> {
+        if Self::default_for_event(event).is_none() {
+            return Err(PuzzleError {
+                description: format!(
+                    "Scramble lengths can only be configured for events scrambled using random moves: {}",
+                    event
+                ),
+            });
+        }
+        if self.num_blocks == 0 || self.num_random_moves_per_block == 0 {
+            return Err(
+                "Scramble lengths must have at least one block and one move per block.".into(),
+            );
+        }
+        if self
+            .num_blocks
+            .checked_mul(self.num_random_moves_per_block)
+            .map_or(true, |total| total > MAX_TOTAL_RANDOM_MOVES)
+        {
+            return Err(PuzzleError {
+                description: format!(
+                    "Scramble lengths are limited to {} random moves in total.",
+                    MAX_TOTAL_RANDOM_MOVES
+                ),
+            });
+        }
+        if event == Event::MegaminxSpeedsolving && self.num_random_moves_per_block % 2 != 0 {
+            return Err(
+                "Megaminx lines must have an even number of random moves (alternating `R` and `D` moves).".into(),
+            );
+        }
+        Ok(())
+    }
+}
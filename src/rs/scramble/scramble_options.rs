@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+
+use cubing::kpuzzle::KPattern;
+
+use super::randomize::ScrambleRandomSource;
+
+/// A range of optimal solution lengths (HTM), for practicing with scrambles
+/// of a particular difficulty. Both bounds are inclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrambleDistanceRange {
+    pub min_distance: Option<usize>,
+    pub max_distance: Option<usize>,
+}
+
+/// Options for generating scrambles in tests and research, e.g. to get the
+/// scramble for a specific pattern, or to study scrambles without filtering.
+/// Scrambles for competitions should always use the defaults.
+#[derive(Clone)]
+pub struct ScrambleOptions {
+    /// Keeps patterns that filtering would reject (e.g. because they can be
+    /// solved in too few moves).
+    pub skip_filtering: bool,
+    /// Returns a scramble that produces this pattern (when applied to the
+    /// solved pattern) instead of a random one. This is only supported for
+    /// events that use the 3x3x3 two-phase solver, and must be a pattern of
+    /// the centerless 3x3x3. Events that add moves around the solver's
+    /// output (e.g. the FMC prefix and suffix, or BLD orientation moves)
+    /// still add them.
+    pub forced_pattern: Option<KPattern>,
+    /// Only returns scrambles for patterns whose optimal solution length is
+    /// (not counting Pyraminx tips) is in this range, by generating new random patterns until one matches.
+    /// This is only supported for events with filtering (see
+    /// `EventCapabilities::is_filtered`), and is ignored for `forced_pattern`. Checking
+    /// large distances for 3x3x3 (more than about 12 moves) can take a very
+    /// long time.
+    pub distance_range: Option<ScrambleDistanceRange>,
+    pub random_source: ScrambleRandomSource,
+}
+
+impl Default for ScrambleOptions {
+    fn default() -> Self {
+        Self {
+            skip_filtering: false,
+            forced_pattern: None,
+            distance_range: None,
+            random_source: ScrambleRandomSource::OsEntropy,
+        }
+    }
+}
+
+// The parts of `ScrambleOptions` that the scramblers read while running.
+#[derive(Clone, Default)]
+struct ActiveScrambleOptions {
+    skip_filtering: bool,
+    forced_pattern: Option<KPattern>,
+    distance_range: Option<ScrambleDistanceRange>,
+}
+
+thread_local! {
+    static ACTIVE_SCRAMBLE_OPTIONS: RefCell<ActiveScrambleOptions> =
+        RefCell::new(ActiveScrambleOptions::default());
+}
+
+/// Runs `f` with the given options applying to all scrambles on the current
+/// thread (like `with_scramble_seed(…)`, which handles the random source).
+pub(crate) fn with_scramble_options<T>(options: &ScrambleOptions, f: impl FnOnce() -> T) -> T {
+    let previous_options = ACTIVE_SCRAMBLE_OPTIONS.with(|active_options| {
+        active_options.replace(ActiveScrambleOptions {
+            skip_filtering: options.skip_filtering,
+            forced_pattern: options.forced_pattern.clone(),
+            distance_range: options.distance_range,
+        })
+    });
+    let result = f();
+    ACTIVE_SCRAMBLE_OPTIONS.with(|active_options| active_options.replace(previous_options));
+    result
+}
+
+pub(crate) fn is_scramble_filtering_skipped() -> bool {
+    ACTIVE_SCRAMBLE_OPTIONS.with(|active_options| active_options.borrow().skip_filtering)
+}
+
+pub(crate) fn forced_scramble_pattern() -> Option<KPattern> {
+    ACTIVE_SCRAMBLE_OPTIONS.with(|active_options| active_options.borrow().forced_pattern.clone())
+}
+
+pub(crate) fn scramble_distance_range() -> Option<ScrambleDistanceRange> {
+    ACTIVE_SCRAMBLE_OPTIONS.with(|active_options| active_options.borrow().distance_range)
+}
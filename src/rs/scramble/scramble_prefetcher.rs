@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{sync_channel, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use cubing::alg::Alg;
+
+use super::{random_scramble_for_event, Event, PuzzleError};
+
+type ScrambleReceiver = Arc<Mutex<Receiver<Result<Alg, PuzzleError>>>>;
+
+/// Keeps a small queue of ready scrambles for each event, generated on a
+/// background thread, so that interactive callers (e.g. timers) get scrambles
+/// with very little latency.
+///
+/// Prefetching for an event starts on the first call to `prefetch(…)` or
+/// `next_scramble(…)` for that event. The background threads stop when the
+/// prefetcher is dropped.
+///
+/// This is not available in WASM, which has no background threads.
+pub struct ScramblePrefetcher {
+    queue_depth: usize,
+    receivers: Mutex<HashMap<Event, ScrambleReceiver>>,
+}
+
+impl ScramblePrefetcher {
+    /// Keeps up to `queue_depth` scrambles ready for each event (in addition
+    /// to one that is being generated).
+    pub fn new(queue_depth: usize) -> Self {
+        Self {
+            queue_depth,
+            receivers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn receiver(&self, event: Event) -> ScrambleReceiver {
+        self.receivers
+            .lock()
+            .unwrap()
+            .entry(event)
+            .or_insert_with(|| {
+                let (sender, receiver) = sync_channel(self.queue_depth);
+                thread::spawn(move || loop {
+                    let result = random_scramble_for_event(event);
+                    let is_err = result.is_err();
+                    // Stop if the prefetcher is gone, or if the event can't be scrambled.
+                    if sender.send(result).is_err() || is_err {
+                        break;
+                    }
+                });
+                Arc::new(Mutex::new(receiver))
+            })
+            .clone()
+    }
+
+    /// Starts generating scrambles for the event in the background.
+    pub fn prefetch(&self, event: Event) {
+        self.receiver(event);
+    }
+
+    /// Returns a scramble from the queue for the event, waiting for one to be
+    /// generated if the queue is empty.
+    pub fn next_scramble(&self, event: Event) -> Result<Alg, PuzzleError> {
+        let receiver = self.receiver(event);
+        let result = receiver.lock().unwrap().recv();
+        match result {
+            Ok(result) => result,
+            // The background thread has stopped after an error, so report the error again.
+            Err(_) => random_scramble_for_event(event),
+        }
+    }
+}
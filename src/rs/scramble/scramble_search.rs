@@ -1,20 +1,33 @@
 use std::sync::Arc;
 
 use cubing::{
-    alg::{Alg, Move},
+    alg::{Alg, Move, QuantumMove},
     kpuzzle::{KPattern, KPuzzle},
 };
 
+use rand::seq::SliceRandom;
+
+use super::{
+    randomize::ScrambleRng,
+    rejection_tracker::RejectionReason,
+    scramble_options::{is_scramble_filtering_skipped, scramble_distance_range},
+    Event,
+};
 use crate::_internal::{
     options::{CustomGenerators, VerbosityLevel},
     options::{Generators, MetricEnum},
-    IDFSearch, IndividualSearchOptions, SearchLogger,
+    CanonicalFSM, IDFSearch, IndividualSearchOptions, MoveClassIndex, SearchDirection,
+    SearchGenerators, SearchLogger, CANONICAL_FSM_START_STATE,
 };
 
 pub fn move_list_from_vec(move_str_list: Vec<&str>) -> Vec<Move> {
     move_str_list
         .iter()
-        .map(|move_str| move_str.parse::<Move>().unwrap())
+        .map(|move_str| {
+            move_str
+                .parse::<Move>()
+                .unwrap_or_else(|e| panic!("Invalid move ({:?}): {}", e, move_str))
+        })
         .collect()
 }
 
@@ -25,12 +38,61 @@ pub fn generators_from_vec_str(move_str_list: Vec<&str>) -> Generators {
     })
 }
 
+/// Random moves that form a canonical sequence for the FSM (e.g. never `R R'`
+/// or `R L R`), so that filler moves don't cancel each other. The first move
+/// avoids `disallowed_initial_quanta`.
+pub(crate) fn random_canonical_moves(
+    search_generators: &SearchGenerators,
+    canonical_fsm: &CanonicalFSM,
+    num_moves: usize,
+    disallowed_initial_quanta: &[QuantumMove],
+) -> Vec<Move> {
+    let mut rng = ScrambleRng;
+    let mut moves = Vec::<Move>::with_capacity(num_moves);
+    let mut fsm_state = CANONICAL_FSM_START_STATE;
+    for _ in 0..num_moves {
+        let allowed_move_classes: Vec<(MoveClassIndex, _)> = (0..search_generators.grouped.len())
+            .map(MoveClassIndex)
+            .filter(|move_class_index| {
+                !(moves.is_empty()
+                    && disallowed_initial_quanta.contains(
+                        search_generators.grouped[move_class_index.0][0]
+                            .r#move
+                            .quantum
+                            .as_ref(),
+                    ))
+            })
+            .filter_map(|move_class_index| {
+                canonical_fsm
+                    .next_state(fsm_state, move_class_index)
+                    .map(|next_fsm_state| (move_class_index, next_fsm_state))
+            })
+            .collect();
+        let Some((move_class_index, next_fsm_state)) = allowed_move_classes.choose(&mut rng) else {
+            break;
+        };
+        let multiples = &search_generators.grouped[move_class_index.0];
+        moves.push(multiples.choose(&mut rng).unwrap().r#move.clone());
+        fsm_state = *next_fsm_state;
+    }
+    moves
+}
+
 pub(crate) fn idfs_with_target_pattern(
     kpuzzle: &KPuzzle,
     generators: Generators,
     target_pattern: KPattern,
     min_size: Option<usize>,
 ) -> IDFSearch {
+    // Custom moves are shuffled using the scramble RNG (rather than
+    // `random_start`) so that scrambles can be reproduced from their seed.
+    let (generators, random_start) = match generators {
+        Generators::Default => (Generators::Default, true),
+        Generators::Custom(mut custom_generators) => {
+            custom_generators.moves.shuffle(&mut ScrambleRng);
+            (Generators::Custom(custom_generators), false)
+        }
+    };
     IDFSearch::try_new(
         kpuzzle.clone(),
         target_pattern,
@@ -39,45 +101,119 @@ pub(crate) fn idfs_with_target_pattern(
             verbosity: VerbosityLevel::Silent,
         }),
         &MetricEnum::Hand,
-        true,
+        random_start,
         min_size,
     )
     .unwrap()
 }
 
+/// How to reject random-state scrambles that are too easy.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FilteringProfile {
+    /// Patterns at this distance from solved (or closer) are rejected. `None`
+    /// means that no patterns are rejected for being too close to solved.
+    pub max_rejected_distance: Option<usize>,
+    /// The minimum prune table size for the filtering search (which only
+    /// needs to search a few moves deep).
+    pub min_prune_table_size: Option<usize>,
+}
+
+/// Returns `None` for events that are not filtered.
+pub(crate) fn filtering_profile_for_event(event: Event) -> Option<FilteringProfile> {
+    match event {
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3FewestMoves
+        | Event::Cube3x3x3MultiBlind => Some(FilteringProfile {
+            max_rejected_distance: Some(1),
+            min_prune_table_size: Some(32),
+        }),
+        Event::Cube2x2x2Speedsolving | Event::PyraminxSpeedsolving => Some(FilteringProfile {
+            max_rejected_distance: Some(2),
+            min_prune_table_size: None,
+        }),
+        _ => None,
+    }
+}
+
 pub(crate) fn basic_idfs(
     kpuzzle: &KPuzzle,
     generators: Generators,
-    min_size: Option<usize>,
+    filtering_profile: &FilteringProfile,
 ) -> IDFSearch {
-    idfs_with_target_pattern(kpuzzle, generators, kpuzzle.default_pattern(), min_size)
+    idfs_with_target_pattern(
+        kpuzzle,
+        generators,
+        kpuzzle.default_pattern(),
+        filtering_profile.min_prune_table_size,
+    )
+}
+
+/// Like `basic_idfs(…)`, but for searches that only check distances (where
+/// the generator order doesn't matter), so it doesn't use the scramble RNG.
+pub(crate) fn filtering_idfs(
+    kpuzzle: &KPuzzle,
+    generators: Generators,
+    filtering_profile: &FilteringProfile,
+) -> IDFSearch {
+    IDFSearch::try_new(
+        kpuzzle.clone(),
+        kpuzzle.default_pattern(),
+        generators,
+        Arc::new(SearchLogger {
+            verbosity: VerbosityLevel::Silent,
+        }),
+        &MetricEnum::Hand,
+        false,
+        filtering_profile.min_prune_table_size,
+    )
+    .unwrap()
+}
+
+/// Checks the requested distance range (see `ScrambleOptions::distance_range`)
+/// for a scramble pattern, where `distance_at_most` returns the distance of
+/// the pattern if it is at most the given value.
+pub(crate) fn check_scramble_distance_range(
+    mut distance_at_most: impl FnMut(usize) -> Option<u8>,
+) -> Result<(), RejectionReason> {
+    let Some(distance_range) = scramble_distance_range() else {
+        return Ok(());
+    };
+    if let Some(min_distance) = distance_range.min_distance {
+        if min_distance > 0 && distance_at_most(min_distance - 1).is_some() {
+            return Err(RejectionReason::OutsideDistanceRange);
+        }
+    }
+    if let Some(max_distance) = distance_range.max_distance {
+        if distance_at_most(max_distance).is_none() {
+            return Err(RejectionReason::OutsideDistanceRange);
+        }
+    }
+    Ok(())
 }
 
 pub(crate) fn filtered_search(
     scramble_pattern: &KPattern,
     generators: Generators,
-    min_optimal_moves: Option<usize>,
+    filtering_profile: &FilteringProfile,
     min_scramble_moves: Option<usize>,
-) -> Option<Alg> {
-    let mut idfs = basic_idfs(scramble_pattern.kpuzzle(), generators, None);
-    if idfs
-        .search(
-            scramble_pattern,
-            IndividualSearchOptions {
-                min_num_solutions: Some(1),
-                min_depth: Some(0),
-                max_depth: min_optimal_moves.map(|v| v - 1),
-                disallowed_initial_quanta: None,
-                disallowed_final_quanta: None,
-            },
-        )
-        .next()
-        .is_some()
-    {
-        return None;
+) -> Result<Alg, RejectionReason> {
+    let mut idfs = basic_idfs(scramble_pattern.kpuzzle(), generators, filtering_profile);
+    if let Some(max_rejected_distance) = filtering_profile.max_rejected_distance {
+        if !is_scramble_filtering_skipped()
+            && idfs
+                .distance_at_most(scramble_pattern, max_rejected_distance)
+                .is_some()
+        {
+            return Err(RejectionReason::OptimalSolutionTooShort);
+        }
     }
-    Some(
-        idfs.search(
+    check_scramble_distance_range(|max_distance| {
+        idfs.distance_at_most(scramble_pattern, max_distance)
+    })?;
+    Ok(idfs
+        .search(
             scramble_pattern,
             IndividualSearchOptions {
                 min_num_solutions: Some(1),
@@ -85,10 +221,10 @@ pub(crate) fn filtered_search(
                 max_depth: None,
                 disallowed_initial_quanta: None,
                 disallowed_final_quanta: None,
+                direction: Some(SearchDirection::TargetToSearchPattern),
+                ..Default::default()
             },
         )
         .next()
-        .unwrap()
-        .invert(),
-    )
+        .unwrap())
 }
@@ -0,0 +1,115 @@
+use cubing::alg::Alg;
+
+use crate::_internal::{ParallelOptions, PuzzleError};
+
+use super::{
+    puzzles::cube3x3x3::warm_up_3x3x3_two_phase, random_scramble_for_event,
+    random_scramble_for_event::random_scrambles_for_event_in_current_pool, Event,
+};
+
+// The events whose scrambles use the shared 3x3x3 two-phase tables.
+pub(super) fn uses_3x3x3_two_phase_tables(event: Event) -> bool {
+    matches!(
+        event,
+        Event::Cube3x3x3Speedsolving
+            | Event::Cube3x3x3Blindfolded
+            | Event::Cube3x3x3FewestMoves
+            | Event::Cube3x3x3OneHanded
+            | Event::Cube3x3x3MultiBlind
+    )
+}
+
+/// Scrambles for a fixed set of events (e.g. a timer that lets the user switch
+/// between 3x3x3, 2x2x2, and Pyraminx), with the tables for all of them
+/// initialized up front and a single thread pool for batch scrambles.
+///
+/// Solver tables are shared by all sessions (and by
+/// `random_scramble_for_event(…)`), so switching events or creating another
+/// session never initializes them again.
+pub struct ScrambleSession {
+    events: Vec<Event>,
+    approximate_table_num_bytes: usize,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<rayon::ThreadPool>,
+}
+
+impl ScrambleSession {
+    /// Initializes the tables for the given events, which can take a while
+    /// (e.g. a few seconds for 3x3x3).
+    pub fn new(events: &[Event], parallel_options: &ParallelOptions) -> Self {
+        let mut session = Self {
+            events: vec![],
+            approximate_table_num_bytes: 0,
+            #[cfg(feature = "parallel")]
+            thread_pool: parallel_options.num_threads.and_then(|num_threads| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_threads)
+                    .build()
+                    // Fall back to the global thread pool rather than failing the session itself.
+                    .ok()
+            }),
+        };
+        #[cfg(not(feature = "parallel"))]
+        let _ = parallel_options;
+        for event in events {
+            session.add_event(*event);
+        }
+        session
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Adds an event to the session, initializing its tables if needed.
+    pub fn add_event(&mut self, event: Event) {
+        if self.events.contains(&event) {
+            return;
+        }
+        let shares_3x3x3_two_phase_tables = self
+            .events
+            .iter()
+            .any(|event| uses_3x3x3_two_phase_tables(*event));
+        if uses_3x3x3_two_phase_tables(event) && !shares_3x3x3_two_phase_tables {
+            self.approximate_table_num_bytes += warm_up_3x3x3_two_phase();
+        }
+        // Other events build any tables they need for each scramble.
+        self.events.push(event);
+    }
+
+    /// The approximate memory used by the tables for the events of this
+    /// session (as of when they were added), counting shared tables once.
+    pub fn approximate_table_num_bytes(&self) -> usize {
+        self.approximate_table_num_bytes
+    }
+
+    fn check_event(&self, event: Event) -> Result<(), PuzzleError> {
+        if self.events.contains(&event) {
+            Ok(())
+        } else {
+            Err(PuzzleError {
+                description: format!("Event is not part of this scramble session: {}", event),
+            })
+        }
+    }
+
+    pub fn random_scramble(&self, event: Event) -> Result<Alg, PuzzleError> {
+        self.check_event(event)?;
+        random_scramble_for_event(event)
+    }
+
+    /// Like `random_scrambles_for_event(…)`, but using the thread pool of this session.
+    pub fn random_scrambles(
+        &self,
+        event: Event,
+        num_scrambles: usize,
+    ) -> Result<Vec<Alg>, PuzzleError> {
+        self.check_event(event)?;
+        #[cfg(feature = "parallel")]
+        if let Some(thread_pool) = &self.thread_pool {
+            return thread_pool
+                .install(|| random_scrambles_for_event_in_current_pool(event, num_scrambles));
+        }
+        random_scrambles_for_event_in_current_pool(event, num_scrambles)
+    }
+}
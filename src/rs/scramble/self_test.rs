@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use cubing::{alg::Alg, kpuzzle::KPuzzle, puzzles::cube2x2x2_kpuzzle};
+
+use crate::_internal::PuzzleError;
+
+use super::{
+    puzzles::definitions::{cube3x3x3_centerless_kpuzzle, tetraminx_kpuzzle},
+    solve_pattern::{solve_pattern, SolveConstraints, SolveStrategy},
+    Event,
+};
+
+/// The result of a successful `self_test(…)`.
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    /// The solver that was checked, or `None` for events that are scrambled
+    /// using random moves (which don't use any tables).
+    pub strategy: Option<SolveStrategy>,
+    pub duration: Duration,
+}
+
+// A fixed pattern (given by a scramble) for each puzzle with a solver.
+fn known_answer_test_case(event: Event) -> Option<(&'static KPuzzle, &'static str)> {
+    match event {
+        Event::Cube3x3x3Speedsolving
+        | Event::Cube3x3x3Blindfolded
+        | Event::Cube3x3x3FewestMoves
+        | Event::Cube3x3x3OneHanded
+        | Event::Cube3x3x3MultiBlind => Some((
+            cube3x3x3_centerless_kpuzzle(),
+            "R U2 F' L D' B2 R' U F2 D2 L' B",
+        )),
+        Event::Cube2x2x2Speedsolving => Some((cube2x2x2_kpuzzle(), "R U' F R2 U")),
+        Event::PyraminxSpeedsolving => Some((tetraminx_kpuzzle(), "R U' L B'")),
+        _ => None,
+    }
+}
+
+/// Solves a fixed pattern with the solver for the event (building its tables
+/// if needed), and checks that the solution actually solves the pattern. This
+/// is meant for servers to check that their tables are intact after loading
+/// them, before serving any scrambles.
+pub fn self_test(event: Event) -> Result<SelfTestReport, PuzzleError> {
+    let start_time = instant::Instant::now();
+    let Some((kpuzzle, scramble)) = known_answer_test_case(event) else {
+        return Ok(SelfTestReport {
+            strategy: None,
+            duration: instant::Instant::now() - start_time,
+        });
+    };
+    let self_test_failed = |description: String| PuzzleError {
+        description: format!("Self-test failed for {}: {}", event, description),
+    };
+
+    let scramble: Alg = scramble.parse().unwrap();
+    let pattern = kpuzzle
+        .default_pattern()
+        .apply_transformation(&kpuzzle.transformation_from_alg(&scramble).unwrap());
+    let solution = solve_pattern(&pattern, &SolveConstraints::default())
+        .map_err(|e| self_test_failed(e.description))?;
+    let solution_transformation = kpuzzle
+        .transformation_from_alg(&solution.alg)
+        .map_err(|e| self_test_failed(format!("invalid solution ({})", e)))?;
+    if pattern.apply_transformation(&solution_transformation) != kpuzzle.default_pattern() {
+        return Err(self_test_failed(format!(
+            "the solution does not solve the pattern: {}",
+            solution.alg
+        )));
+    }
+    Ok(SelfTestReport {
+        strategy: Some(solution.strategy),
+        duration: instant::Instant::now() - start_time,
+    })
+}
+
+#[test]
+fn self_test_test() -> Result<(), PuzzleError> {
+    for event in [
+        Event::Cube2x2x2Speedsolving,
+        Event::PyraminxSpeedsolving,
+        Event::Cube5x5x5Speedsolving,
+    ] {
+        self_test(event)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use cubing::{alg::Alg, kpuzzle::KPattern};
+
+use crate::_internal::{
+    options::{Generators, MetricEnum, VerbosityLevel},
+    IDFSearch, IndividualSearchOptions, PuzzleError, SearchLogger,
+};
+
+use super::puzzles::cube3x3x3::{
+    are_3x3x3_two_phase_tables_loaded, has_centerless_3x3x3_orbits, PrefixOrSuffixConstraints,
+    SCRAMBLE3X3X3_TWO_PHASE,
+};
+
+// Building the 3x3x3 two-phase tables takes a few seconds, while `IDFSearch`
+// finishes within that time for solutions up to about this length.
+const MAX_IDFS_PREFERRED_LENGTH: usize = 7;
+
+/// The solver that `solve_pattern(…)` uses for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStrategy {
+    /// An iterative deepening search over the whole puzzle (see `IDFSearch`).
+    /// Solutions are optimal (HTM), but long ones can take a very long time.
+    IDFSearch,
+    /// The 3x3x3 two-phase solver, which uses coordinate tables. Solutions
+    /// are short, but not necessarily optimal.
+    Cube3x3x3TwoPhase,
+}
+
+/// Requirements for `solve_pattern(…)`. The defaults accept any solution.
+#[derive(Debug, Clone, Default)]
+pub struct SolveConstraints {
+    /// The max number of moves (HTM).
+    pub max_length: Option<usize>,
+    /// Only return optimal solutions.
+    pub require_optimal: bool,
+}
+
+pub struct PatternSolution {
+    pub alg: Alg,
+    pub strategy: SolveStrategy,
+}
+
+/// The fastest solver that can satisfy the constraints for the pattern, given
+/// the tables that are currently loaded.
+pub fn choose_solve_strategy(pattern: &KPattern, constraints: &SolveConstraints) -> SolveStrategy {
+    if constraints.require_optimal || !has_centerless_3x3x3_orbits(pattern.kpuzzle()) {
+        return SolveStrategy::IDFSearch;
+    }
+    let is_short_bound = constraints
+        .max_length
+        .map_or(false, |max_length| max_length <= MAX_IDFS_PREFERRED_LENGTH);
+    if is_short_bound && !are_3x3x3_two_phase_tables_loaded() {
+        return SolveStrategy::IDFSearch;
+    }
+    SolveStrategy::Cube3x3x3TwoPhase
+}
+
+/// Solves the pattern (to the default pattern of its puzzle) using the
+/// strategy from `choose_solve_strategy(…)`, so callers don't need to know
+/// which solvers are available for each puzzle.
+pub fn solve_pattern(
+    pattern: &KPattern,
+    constraints: &SolveConstraints,
+) -> Result<PatternSolution, PuzzleError> {
+    let strategy = choose_solve_strategy(pattern, constraints);
+    let no_solution = || PuzzleError {
+        description: match constraints.max_length {
+            Some(max_length) => format!("No solution found with at most {} moves.", max_length),
+            None => "No solution found.".to_owned(),
+        },
+    };
+    let alg = match strategy {
+        SolveStrategy::IDFSearch => {
+            let kpuzzle = pattern.kpuzzle();
+            let mut idfs = IDFSearch::try_new(
+                kpuzzle.clone(),
+                kpuzzle.default_pattern(),
+                Generators::Default,
+                Arc::new(SearchLogger {
+                    verbosity: VerbosityLevel::Silent,
+                }),
+                &MetricEnum::Hand,
+                false,
+                None,
+            )
+            .map_err(|e| PuzzleError {
+                description: e.to_string(),
+            })?;
+            idfs.try_search(
+                pattern,
+                IndividualSearchOptions {
+                    min_num_solutions: Some(1),
+                    // The max depth is exclusive.
+                    max_depth: constraints.max_length.map(|max_length| max_length + 1),
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| PuzzleError {
+                description: e.to_string(),
+            })?
+            .next()
+            .ok_or_else(no_solution)?
+        }
+        SolveStrategy::Cube3x3x3TwoPhase => SCRAMBLE3X3X3_TWO_PHASE
+            .with_context(|two_phase| {
+                two_phase.solve_3x3x3_pattern(
+                    pattern,
+                    PrefixOrSuffixConstraints::None,
+                    constraints.max_length,
+                )
+            })
+            .map_err(|_| no_solution())?,
+    };
+    Ok(PatternSolution { alg, strategy })
+}
+
+#[test]
+fn choose_solve_strategy_test() {
+    use cubing::puzzles::cube2x2x2_kpuzzle;
+
+    let pattern = cube2x2x2_kpuzzle().default_pattern();
+    assert_eq!(
+        choose_solve_strategy(&pattern, &SolveConstraints::default()),
+        SolveStrategy::IDFSearch
+    );
+    let scramble = "R U".parse::<Alg>().unwrap();
+    let scrambled_pattern = pattern.apply_transformation(
+        &cube2x2x2_kpuzzle()
+            .transformation_from_alg(&scramble)
+            .unwrap(),
+    );
+    let solution = solve_pattern(&scrambled_pattern, &SolveConstraints::default()).unwrap();
+    assert_eq!(solution.strategy, SolveStrategy::IDFSearch);
+    assert_eq!(solution.alg.nodes.len(), 2);
+    assert_eq!(
+        scrambled_pattern.apply_transformation(
+            &cube2x2x2_kpuzzle()
+                .transformation_from_alg(&solution.alg)
+                .unwrap()
+        ),
+        pattern
+    );
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use cubing::{
+    alg::{Alg, AlgNode, Move, QuantumMove},
+    kpuzzle::{KPuzzle, KTransformation},
+};
+
+use crate::_internal::NotationDialect;
+
+const CUBE_FACES: &str = "URFDLB";
+// Enough for the center slice of a 7x7x7.
+const MAX_NUM_LAYERS: usize = 4;
+
+fn cube_face(r#move: &Move) -> Option<char> {
+    let face = r#move.quantum.family.chars().next()?.to_ascii_uppercase();
+    CUBE_FACES.contains(face).then_some(face)
+}
+
+// Returns a single move on the given face with the given transformation, if the puzzle has one.
+fn single_move_for_transformation(
+    kpuzzle: &KPuzzle,
+    face: char,
+    transformation: &KTransformation,
+) -> Option<Move> {
+    let mut quantum_strs = vec![face.to_string(), format!("{}w", face)];
+    for num_layers in 2..=MAX_NUM_LAYERS {
+        quantum_strs.push(format!("{}{}", num_layers, face));
+        quantum_strs.push(format!("{}{}w", num_layers, face));
+    }
+    for quantum_str in quantum_strs {
+        let Ok(quantum) = quantum_str.parse::<QuantumMove>() else {
+            continue;
+        };
+        let quantum = Arc::new(quantum);
+        for amount in [1, 2, -1] {
+            let r#move = Move {
+                quantum: quantum.clone(),
+                amount,
+            };
+            if kpuzzle
+                .transformation_from_move(&r#move)
+                .is_ok_and(|candidate| &candidate == transformation)
+            {
+                return Some(r#move);
+            }
+        }
+    }
+    None
+}
+
+/// Combines adjacent moves on the same face of a big cube into a single move
+/// when the puzzle has one (e.g. `Rw R'` into the slice move `2R`, or `R 2R`
+/// into `Rw`), and writes wide moves in the given notation dialect.
+///
+/// The result is checked by applying both algs to the puzzle. If they don't
+/// match (or can't be applied), the original alg is returned with only the
+/// dialect conversion.
+pub fn compress_wide_moves(kpuzzle: &KPuzzle, alg: &Alg, notation: NotationDialect) -> Alg {
+    let mut nodes = Vec::<AlgNode>::new();
+    for node in &alg.nodes {
+        let AlgNode::MoveNode(r#move) = node else {
+            nodes.push(node.clone());
+            continue;
+        };
+        let Some(AlgNode::MoveNode(previous_move)) = nodes.last() else {
+            nodes.push(node.clone());
+            continue;
+        };
+        let combined = match (cube_face(previous_move), cube_face(r#move)) {
+            (Some(previous_face), Some(face)) if previous_face == face => {
+                match (
+                    kpuzzle.transformation_from_move(previous_move),
+                    kpuzzle.transformation_from_move(r#move),
+                ) {
+                    (Ok(previous_transformation), Ok(transformation)) => Some((
+                        face,
+                        previous_transformation.apply_transformation(&transformation),
+                    )),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let Some((face, combined_transformation)) = combined else {
+            nodes.push(node.clone());
+            continue;
+        };
+        if combined_transformation == kpuzzle.identity_transformation() {
+            nodes.pop();
+            continue;
+        }
+        match single_move_for_transformation(kpuzzle, face, &combined_transformation) {
+            Some(combined_move) => {
+                nodes.pop();
+                nodes.push(combined_move.into());
+            }
+            None => nodes.push(node.clone()),
+        }
+    }
+    let compressed = Alg { nodes };
+
+    let is_verified = match (
+        kpuzzle.transformation_from_alg(alg),
+        kpuzzle.transformation_from_alg(&compressed),
+    ) {
+        (Ok(original_transformation), Ok(compressed_transformation)) => {
+            original_transformation == compressed_transformation
+        }
+        _ => false,
+    };
+    if is_verified {
+        notation.convert_alg(&compressed)
+    } else {
+        notation.convert_alg(alg)
+    }
+}
@@ -0,0 +1,209 @@
+// Integration with the rest of the crate.
+
+use cubing::kpuzzle::{KPattern, KPuzzle, KTransformation};
+
+use crate::_internal::{generic_ida_star, GenericPuzzle};
+
+use super::{PackedPattern, PackedPuzzle, PackedTransformation, PackingError};
+
+impl GenericPuzzle for PackedPuzzle {
+    type Pattern = PackedPattern;
+    type Transformation = PackedTransformation;
+
+    fn pattern_apply_transformation(
+        &self,
+        pattern: &PackedPattern,
+        transformation: &PackedTransformation,
+    ) -> PackedPattern {
+        self.apply(pattern, transformation)
+    }
+
+    fn transformation_invert(&self, transformation: &PackedTransformation) -> PackedTransformation {
+        self.invert(transformation)
+    }
+}
+
+/// A puzzle for `generic_ida_star(…)`: packed if possible, and otherwise the
+/// original (slower) `KPuzzle` along with the reason it couldn't be packed.
+pub enum PackedOrFallbackPuzzle {
+    Packed(KPuzzle, PackedPuzzle),
+    Fallback(KPuzzle, PackingError),
+}
+
+/// A pattern for a `PackedOrFallbackPuzzle` (of the same variant).
+#[derive(Clone, PartialEq)]
+pub enum PackedOrFallbackPattern {
+    Packed(PackedPattern),
+    Fallback(KPattern),
+}
+
+/// A transformation for a `PackedOrFallbackPuzzle` (of the same variant).
+#[derive(Clone)]
+pub enum PackedOrFallbackTransformation {
+    Packed(PackedTransformation),
+    Fallback(KTransformation),
+}
+
+impl PackedOrFallbackPuzzle {
+    /// Packs the puzzle if both the definition and all of the given patterns
+    /// (e.g. the start and target patterns of a search) can be packed.
+    pub fn new(kpuzzle: &KPuzzle, patterns: &[&KPattern]) -> Self {
+        let packed_puzzle = PackedPuzzle::try_from_kpuzzle(kpuzzle).and_then(|packed_puzzle| {
+            for pattern in patterns {
+                PackedPattern::try_from(*pattern)?;
+            }
+            Ok(packed_puzzle)
+        });
+        match packed_puzzle {
+            Ok(packed_puzzle) => Self::Packed(kpuzzle.clone(), packed_puzzle),
+            Err(e) => Self::Fallback(kpuzzle.clone(), e),
+        }
+    }
+
+    pub fn pattern(&self, pattern: &KPattern) -> Result<PackedOrFallbackPattern, PackingError> {
+        Ok(match self {
+            Self::Packed(_, _) => {
+                PackedOrFallbackPattern::Packed(PackedPattern::try_from(pattern)?)
+            }
+            Self::Fallback(_, _) => PackedOrFallbackPattern::Fallback(pattern.clone()),
+        })
+    }
+
+    pub fn transformation(
+        &self,
+        transformation: &KTransformation,
+    ) -> PackedOrFallbackTransformation {
+        match self {
+            Self::Packed(kpuzzle, _) => PackedOrFallbackTransformation::Packed(
+                PackedTransformation::from_ktransformation(kpuzzle, transformation),
+            ),
+            Self::Fallback(_, _) => {
+                PackedOrFallbackTransformation::Fallback(transformation.clone())
+            }
+        }
+    }
+}
+
+const MIXED_VARIANTS_ERROR: &str =
+    "Internal error: packed and fallback values can't be used with each other.";
+
+impl GenericPuzzle for PackedOrFallbackPuzzle {
+    type Pattern = PackedOrFallbackPattern;
+    type Transformation = PackedOrFallbackTransformation;
+
+    fn pattern_apply_transformation(
+        &self,
+        pattern: &PackedOrFallbackPattern,
+        transformation: &PackedOrFallbackTransformation,
+    ) -> PackedOrFallbackPattern {
+        match (self, pattern, transformation) {
+            (
+                Self::Packed(_, packed_puzzle),
+                PackedOrFallbackPattern::Packed(pattern),
+                PackedOrFallbackTransformation::Packed(transformation),
+            ) => PackedOrFallbackPattern::Packed(packed_puzzle.apply(pattern, transformation)),
+            (
+                Self::Fallback(_, _),
+                PackedOrFallbackPattern::Fallback(pattern),
+                PackedOrFallbackTransformation::Fallback(transformation),
+            ) => PackedOrFallbackPattern::Fallback(pattern.apply_transformation(transformation)),
+            _ => panic!("{}", MIXED_VARIANTS_ERROR),
+        }
+    }
+
+    fn transformation_invert(
+        &self,
+        transformation: &PackedOrFallbackTransformation,
+    ) -> PackedOrFallbackTransformation {
+        match (self, transformation) {
+            (
+                Self::Packed(_, packed_puzzle),
+                PackedOrFallbackTransformation::Packed(transformation),
+            ) => PackedOrFallbackTransformation::Packed(packed_puzzle.invert(transformation)),
+            (Self::Fallback(_, _), PackedOrFallbackTransformation::Fallback(transformation)) => {
+                PackedOrFallbackTransformation::Fallback(transformation.invert())
+            }
+            _ => panic!("{}", MIXED_VARIANTS_ERROR),
+        }
+    }
+}
+
+/// Like `generic_ida_star(…)` (without a heuristic) for a `KPuzzle`. This
+/// searches using packed patterns when the puzzle and patterns can be packed,
+/// and automatically falls back to `KPattern`s otherwise.
+pub fn packed_or_fallback_ida_star(
+    kpuzzle: &KPuzzle,
+    start_pattern: &KPattern,
+    target_pattern: &KPattern,
+    moves: &[KTransformation],
+    max_depth: usize,
+) -> Option<Vec<usize>> {
+    let puzzle = PackedOrFallbackPuzzle::new(kpuzzle, &[start_pattern, target_pattern]);
+    let start_pattern = puzzle
+        .pattern(start_pattern)
+        .expect("The start pattern was checked when choosing the puzzle.");
+    let target_pattern = puzzle
+        .pattern(target_pattern)
+        .expect("The target pattern was checked when choosing the puzzle.");
+    let moves: Vec<PackedOrFallbackTransformation> = moves
+        .iter()
+        .map(|transformation| puzzle.transformation(transformation))
+        .collect();
+    generic_ida_star(
+        &puzzle,
+        &start_pattern,
+        &target_pattern,
+        &moves,
+        max_depth,
+        |_| 0,
+    )
+}
+
+#[test]
+fn packed_or_fallback_ida_star_test() {
+    use cubing::{kpuzzle::OrientationWithMod, puzzles::cube2x2x2_kpuzzle};
+
+    use crate::_internal::OrbitInfoByName;
+
+    let kpuzzle = cube2x2x2_kpuzzle();
+    let moves: Vec<KTransformation> = ["R", "U", "F"]
+        .iter()
+        .map(|r#move| {
+            kpuzzle
+                .transformation_from_alg(&r#move.parse().unwrap())
+                .unwrap()
+        })
+        .collect();
+    let scramble = moves[0].apply_transformation(&moves[1]);
+
+    // Packed.
+    let target_pattern = kpuzzle.default_pattern();
+    let start_pattern = target_pattern.apply_transformation(&scramble);
+    assert!(matches!(
+        PackedOrFallbackPuzzle::new(kpuzzle, &[&start_pattern, &target_pattern]),
+        PackedOrFallbackPuzzle::Packed(_, _)
+    ));
+    let packed_solution =
+        packed_or_fallback_ida_star(kpuzzle, &start_pattern, &target_pattern, &moves, 6);
+
+    // Orientation mods can't be packed.
+    let mut target_pattern = kpuzzle.default_pattern();
+    target_pattern.set_orientation_with_mod(
+        kpuzzle.orbit_info_by_name("CORNERS").unwrap(),
+        0,
+        &OrientationWithMod {
+            orientation: 0,
+            orientation_mod: 1,
+        },
+    );
+    let start_pattern = target_pattern.apply_transformation(&scramble);
+    assert!(matches!(
+        PackedOrFallbackPuzzle::new(kpuzzle, &[&start_pattern, &target_pattern]),
+        PackedOrFallbackPuzzle::Fallback(_, _)
+    ));
+    let fallback_solution =
+        packed_or_fallback_ida_star(kpuzzle, &start_pattern, &target_pattern, &moves, 6);
+
+    assert!(packed_solution.is_some());
+    assert_eq!(packed_solution, fallback_solution);
+}
@@ -0,0 +1,16 @@
+//! The search kernel from the `twsearch-search-core` crate (packed patterns,
+//! transformation application, and IDA*), which only depends on `core` and
+//! `alloc` so that it can run on targets without `std` (e.g. embedded
+//! scramble displays).
+//!
+//! `KPattern` is the pattern type for the rest of the crate (`IDFSearch`,
+//! randomization, and all the scramblers), so new puzzles only need to be
+//! written against it. Packed patterns are only a representation for this
+//! kernel: convert with `PackedPattern::try_from(&KPattern)` and
+//! `PackedPattern::to_kpattern(…)` at the boundary (solutions are returned as
+//! indices into the caller's transformations, so they need no conversion).
+
+pub use twsearch_search_core::*;
+
+mod generic_puzzle;
+pub use generic_puzzle::*;